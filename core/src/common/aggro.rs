@@ -0,0 +1,288 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+
+use crate::config::FilesystemConfig;
+
+/// A JSON file that describes how a BattleNPC notices potential targets.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct AggroProfile {
+    /// How far this NPC can see in yalms, but only in front of it. Zero means it doesn't aggro by sight.
+    #[serde(default)]
+    pub sight_range: f32,
+    /// Total width of the sight cone in degrees, centered on the direction the NPC is facing.
+    #[serde(default = "AggroProfile::default_sight_angle")]
+    pub sight_angle: f32,
+    /// How far this NPC can hear in yalms, regardless of which way it's facing. Zero means it doesn't aggro by sound.
+    #[serde(default)]
+    pub sound_range: f32,
+    /// If set, targets that are at least this many levels above the NPC are ignored.
+    /// This doesn't apply in instanced content, where everything aggros regardless of level.
+    #[serde(default)]
+    pub ignore_level_difference: Option<u8>,
+    /// How far this NPC can be pulled from where it spawned in yalms, before it gives up and returns home.
+    #[serde(default = "AggroProfile::default_leash_range")]
+    pub leash_range: f32,
+}
+
+impl Default for AggroProfile {
+    /// The same as our built-in "Default.json", in case it's missing.
+    fn default() -> Self {
+        Self {
+            sight_range: 15.0,
+            sight_angle: Self::default_sight_angle(),
+            sound_range: 5.0,
+            ignore_level_difference: Some(10),
+            leash_range: Self::default_leash_range(),
+        }
+    }
+}
+
+impl AggroProfile {
+    fn default_sight_angle() -> f32 {
+        90.0
+    }
+
+    fn default_leash_range() -> f32 {
+        40.0
+    }
+
+    /// Whether an NPC at `position` facing `rotation` notices a target at `target_position`.
+    pub fn can_sense(&self, position: Vec3A, rotation: f32, target_position: Vec3A) -> bool {
+        let distance = Vec3A::distance(position, target_position);
+        if distance < self.sound_range {
+            return true;
+        }
+
+        if distance >= self.sight_range {
+            return false;
+        }
+
+        // Uses the same convention as NPC movement, where a rotation of zero faces +Z.
+        let angle_to_target = f32::atan2(
+            target_position.x - position.x,
+            target_position.z - position.z,
+        );
+        let mut difference = (angle_to_target - rotation).rem_euclid(2.0 * PI);
+        if difference > PI {
+            difference = 2.0 * PI - difference;
+        }
+
+        difference <= self.sight_angle.to_radians() / 2.0
+    }
+
+    /// Whether a target at `target_level` is too high for an NPC at `level` to bother with.
+    pub fn ignores_level(&self, level: u8, target_level: u8) -> bool {
+        self.ignore_level_difference
+            .and_then(|difference| level.checked_add(difference))
+            .is_some_and(|threshold| target_level >= threshold)
+    }
+
+    /// Whether an NPC at `position` has been pulled too far from `home_position`.
+    pub fn exceeds_leash(&self, home_position: Vec3A, position: Vec3A) -> bool {
+        Vec3A::distance(home_position, position) > self.leash_range
+    }
+}
+
+/// Every aggro profile, which is loaded once when the server starts.
+#[derive(Debug, Default, Clone)]
+pub struct AggroProfiles {
+    /// Used when there's nothing more specific for a BattleNPC.
+    default: AggroProfile,
+    /// Profiles for a single BNpcBase, e.g. "GiantClam_269.json".
+    by_base_id: HashMap<u32, AggroProfile>,
+    /// Profiles for every BNpcBase using a ModelChara, e.g. "models/Clam_123.json".
+    /// Monsters of the same kind tend to sense players the same way, so this saves writing one for each BNpcBase.
+    by_model_chara: HashMap<u32, AggroProfile>,
+}
+
+impl AggroProfiles {
+    /// Loads "Default.json" and every BNpcBase and ModelChara profile, taking into account additional search paths.
+    ///
+    /// Missing or invalid files are logged and skipped, so one bad profile can't stop the server from starting.
+    pub fn load(config: &FilesystemConfig) -> Self {
+        let default =
+            read_profile(&config.locate_aggro_file("Default.json")).unwrap_or_else(|| {
+                tracing::warn!("Couldn't load the default aggro profile, using the built-in one!");
+                AggroProfile::default()
+            });
+
+        let mut profiles = Self {
+            default,
+            ..Default::default()
+        };
+
+        // Our built-in profiles are loaded first, so anything in the additional search paths overrides them.
+        let mut search_dirs = vec!["resources/aggro".to_string()];
+        search_dirs.extend(
+            config
+                .additional_resource_paths
+                .iter()
+                .rev()
+                .map(|x| format!("{x}/aggro")),
+        );
+
+        for search_dir in search_dirs {
+            read_profiles(&search_dir, &mut profiles.by_base_id);
+            read_profiles(
+                &format!("{search_dir}/models"),
+                &mut profiles.by_model_chara,
+            );
+        }
+
+        profiles
+    }
+
+    /// Returns the profile for a BattleNPC, preferring one for its BNpcBase, then one for its ModelChara, and finally the default one.
+    pub fn get(&self, base_id: u32, model_chara: u16) -> &AggroProfile {
+        self.by_base_id
+            .get(&base_id)
+            .or_else(|| self.by_model_chara.get(&(model_chara as u32)))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Reads a single aggro profile, logging why if it can't.
+fn read_profile(path: &str) -> Option<AggroProfile> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| tracing::warn!("Failed to read aggro profile {path}: {err}"))
+        .ok()?;
+
+    serde_json::from_str(&contents)
+        .map_err(|err| tracing::warn!("Failed to parse aggro profile {path}: {err}"))
+        .ok()
+}
+
+/// Reads every profile in `directory` whose file name ends with an id, e.g. "GiantClam_269.json".
+fn read_profiles(directory: &str, profiles: &mut HashMap<u32, AggroProfile>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(id) = file_name.to_str().and_then(id_from_file_name) else {
+            continue;
+        };
+
+        if let Some(profile) = read_profile(&entry.path().to_string_lossy()) {
+            profiles.insert(id, profile);
+        }
+    }
+}
+
+/// Returns the id at the end of a file name like "GiantClam_269.json".
+fn id_from_file_name(file_name: &str) -> Option<u32> {
+    file_name
+        .strip_suffix(".json")?
+        .rsplit_once('_')?
+        .1
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> AggroProfile {
+        AggroProfile {
+            sight_range: 15.0,
+            sight_angle: 90.0,
+            sound_range: 5.0,
+            ignore_level_difference: Some(10),
+            leash_range: 40.0,
+        }
+    }
+
+    #[test]
+    fn test_simple_example() {
+        let json = std::fs::read_to_string("../resources/data/tests/example_aggro.json").unwrap();
+        let aggro: AggroProfile = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(aggro, profile());
+    }
+
+    #[test]
+    fn test_sight() {
+        let aggro = profile();
+
+        // Directly in front
+        assert!(aggro.can_sense(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, 10.0)));
+        // Just inside the cone
+        assert!(aggro.can_sense(Vec3A::ZERO, 0.0, Vec3A::new(6.0, 0.0, 7.0)));
+        // Out of range
+        assert!(!aggro.can_sense(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, 20.0)));
+        // Behind, but out of earshot
+        assert!(!aggro.can_sense(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, -10.0)));
+        // Facing the other way
+        assert!(aggro.can_sense(Vec3A::ZERO, PI, Vec3A::new(0.0, 0.0, -10.0)));
+        // Wraps around correctly
+        assert!(aggro.can_sense(Vec3A::ZERO, -PI, Vec3A::new(1.0, 0.0, -10.0)));
+    }
+
+    #[test]
+    fn test_sound() {
+        let aggro = profile();
+
+        assert!(aggro.can_sense(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, -4.0)));
+        assert!(aggro.can_sense(Vec3A::ZERO, 0.0, Vec3A::new(4.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_level_difference() {
+        let aggro = profile();
+
+        assert!(!aggro.ignores_level(10, 19));
+        assert!(aggro.ignores_level(10, 20));
+        assert!(!aggro.ignores_level(250, 255));
+
+        let aggro = AggroProfile {
+            ignore_level_difference: None,
+            ..profile()
+        };
+        assert!(!aggro.ignores_level(1, 90));
+    }
+
+    #[test]
+    fn test_id_from_file_name() {
+        assert_eq!(id_from_file_name("GiantClam_269.json"), Some(269));
+        assert_eq!(id_from_file_name("Sastasha_Orobon_1040.json"), Some(1040));
+        assert_eq!(id_from_file_name("Default.json"), None);
+        assert_eq!(id_from_file_name("GiantClam_269.txt"), None);
+    }
+
+    #[test]
+    fn test_profile_priority() {
+        let sound_only = AggroProfile {
+            sight_range: 0.0,
+            ..profile()
+        };
+        let sight_only = AggroProfile {
+            sound_range: 0.0,
+            ..profile()
+        };
+
+        let profiles = AggroProfiles {
+            default: profile(),
+            by_base_id: HashMap::from([(269, sound_only.clone())]),
+            by_model_chara: HashMap::from([(123, sight_only.clone())]),
+        };
+
+        // A BNpcBase profile wins over its model's.
+        assert_eq!(profiles.get(269, 123), &sound_only);
+        // Otherwise every BNpcBase using the model shares it.
+        assert_eq!(profiles.get(270, 123), &sight_only);
+        // And everything else falls back to the default.
+        assert_eq!(profiles.get(270, 124), &profile());
+    }
+
+    #[test]
+    fn test_leash() {
+        let aggro = profile();
+
+        assert!(!aggro.exceeds_leash(Vec3A::ZERO, Vec3A::new(0.0, 0.0, 40.0)));
+        assert!(aggro.exceeds_leash(Vec3A::ZERO, Vec3A::new(30.0, 0.0, 30.0)));
+    }
+}
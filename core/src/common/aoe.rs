@@ -0,0 +1,244 @@
+//! Geometry for area of effect actions, used to figure out who gets hit.
+
+use std::f32::consts::PI;
+
+use glam::{Vec2, Vec3A};
+
+/// Used for cones when the width can't be figured out from the omen.
+const DEFAULT_CONE_ANGLE: f32 = 90.0;
+
+/// How big a donut's safe spot is, relative to its EffectRange.
+///
+/// Neither the Action nor the Omen sheet has the inner radius, as the client only draws the omen texture, which is scaled to EffectRange.
+/// The texture's hole is about half of its width for most donut omens, so this is what we assume for all of them.
+const DONUT_INNER_RADIUS_RATIO: f32 = 0.5;
+
+/// The shape of an area of effect. Height is ignored, like retail.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AoeShape {
+    /// A circle around the origin.
+    Circle { radius: f32 },
+    /// A wedge in front of the origin, where `angle` is the total width in degrees.
+    Cone { radius: f32, angle: f32 },
+    /// A ring around the origin, with a safe spot in the middle.
+    Donut {
+        inner_radius: f32,
+        outer_radius: f32,
+    },
+    /// A rectangle that starts at the origin and extends forward.
+    Line { length: f32, width: f32 },
+}
+
+impl AoeShape {
+    /// Whether `point` is inside of this shape, when placed at `origin` and facing `rotation`.
+    ///
+    /// Uses the same convention as actor rotations, where zero faces +Z.
+    pub fn contains(&self, origin: Vec3A, rotation: f32, point: Vec3A) -> bool {
+        let offset = Vec2::new(point.x - origin.x, point.z - origin.z);
+        let distance = offset.length();
+
+        match *self {
+            AoeShape::Circle { radius } => distance <= radius,
+            AoeShape::Cone { radius, angle } => {
+                if distance > radius {
+                    return false;
+                }
+
+                // Standing right on top of the caster always counts.
+                if distance == 0.0 {
+                    return true;
+                }
+
+                let mut difference =
+                    (f32::atan2(offset.x, offset.y) - rotation).rem_euclid(2.0 * PI);
+                if difference > PI {
+                    difference = 2.0 * PI - difference;
+                }
+
+                difference <= angle.to_radians() / 2.0
+            }
+            AoeShape::Donut {
+                inner_radius,
+                outer_radius,
+            } => distance >= inner_radius && distance <= outer_radius,
+            AoeShape::Line { length, width } => {
+                let forward = Vec2::new(rotation.sin(), rotation.cos());
+                let along = offset.dot(forward);
+                let across = forward.perp_dot(offset).abs();
+
+                (0.0..=length).contains(&along) && across <= width / 2.0
+            }
+        }
+    }
+}
+
+/// Where an area of effect is placed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AoeOrigin {
+    /// Centered on whoever used the action.
+    Caster,
+    /// Centered on the target's position when the cast began.
+    Target,
+}
+
+/// Describes the area of effect of an action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActionAoe {
+    pub shape: AoeShape,
+    pub origin: AoeOrigin,
+}
+
+impl ActionAoe {
+    /// Creates the area of effect for an action, from the CastType, EffectRange and XAxisModifier columns of the Action Excel sheet and the path of its Omen.
+    ///
+    /// Returns None if the action only hits a single target.
+    pub fn from_action(
+        cast_type: u8,
+        effect_range: u8,
+        x_axis_modifier: u8,
+        omen_path: &str,
+    ) -> Option<Self> {
+        let range = effect_range as f32;
+        if range == 0.0 {
+            return None;
+        }
+
+        let (shape, origin) = match cast_type {
+            2 | 5 => (AoeShape::Circle { radius: range }, AoeOrigin::Caster),
+            3 | 13 => (
+                AoeShape::Cone {
+                    radius: range,
+                    angle: cone_angle(omen_path).unwrap_or(DEFAULT_CONE_ANGLE),
+                },
+                AoeOrigin::Caster,
+            ),
+            4 | 8 | 12 => (
+                AoeShape::Line {
+                    length: range,
+                    width: x_axis_modifier as f32,
+                },
+                AoeOrigin::Caster,
+            ),
+            7 => (AoeShape::Circle { radius: range }, AoeOrigin::Target),
+            10 => (
+                AoeShape::Donut {
+                    inner_radius: range * DONUT_INNER_RADIUS_RATIO,
+                    outer_radius: range,
+                },
+                AoeOrigin::Caster,
+            ),
+            _ => return None,
+        };
+
+        Some(Self { shape, origin })
+    }
+}
+
+/// Cone omens have their width in the name, e.g. "gl_fan120_1bf" is 120 degrees.
+fn cone_angle(omen_path: &str) -> Option<f32> {
+    let (_, rest) = omen_path.split_once("fan")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circle() {
+        let circle = AoeShape::Circle { radius: 5.0 };
+
+        assert!(circle.contains(Vec3A::ZERO, 0.0, Vec3A::new(3.0, 0.0, 4.0)));
+        assert!(!circle.contains(Vec3A::ZERO, 0.0, Vec3A::new(4.0, 0.0, 4.0)));
+        // Height doesn't matter
+        assert!(circle.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 50.0, 1.0)));
+    }
+
+    #[test]
+    fn test_donut() {
+        let donut = AoeShape::Donut {
+            inner_radius: 5.0,
+            outer_radius: 10.0,
+        };
+
+        assert!(!donut.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, 2.0)));
+        assert!(donut.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, 7.0)));
+        assert!(!donut.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, 12.0)));
+    }
+
+    #[test]
+    fn test_cone() {
+        let cone = AoeShape::Cone {
+            radius: 10.0,
+            angle: 90.0,
+        };
+
+        assert!(cone.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, 5.0)));
+        assert!(cone.contains(Vec3A::ZERO, 0.0, Vec3A::new(3.0, 0.0, 4.0)));
+        assert!(!cone.contains(Vec3A::ZERO, 0.0, Vec3A::new(5.0, 0.0, 1.0)));
+        assert!(!cone.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, -5.0)));
+        // Turned around to face -Z
+        assert!(cone.contains(Vec3A::ZERO, PI, Vec3A::new(0.0, 0.0, -5.0)));
+        assert!(cone.contains(Vec3A::ZERO, 0.0, Vec3A::ZERO));
+    }
+
+    #[test]
+    fn test_line() {
+        let line = AoeShape::Line {
+            length: 20.0,
+            width: 4.0,
+        };
+
+        assert!(line.contains(Vec3A::ZERO, 0.0, Vec3A::new(1.5, 0.0, 15.0)));
+        assert!(!line.contains(Vec3A::ZERO, 0.0, Vec3A::new(2.5, 0.0, 15.0)));
+        assert!(!line.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, 25.0)));
+        assert!(!line.contains(Vec3A::ZERO, 0.0, Vec3A::new(0.0, 0.0, -1.0)));
+        // Facing +X
+        assert!(line.contains(Vec3A::ZERO, PI / 2.0, Vec3A::new(10.0, 0.0, 1.0)));
+        assert!(!line.contains(Vec3A::ZERO, PI / 2.0, Vec3A::new(0.0, 0.0, 10.0)));
+    }
+
+    #[test]
+    fn test_from_action() {
+        assert_eq!(ActionAoe::from_action(1, 0, 0, ""), None);
+        assert_eq!(
+            ActionAoe::from_action(7, 6, 0, "general_1bf"),
+            Some(ActionAoe {
+                shape: AoeShape::Circle { radius: 6.0 },
+                origin: AoeOrigin::Target
+            })
+        );
+        assert_eq!(
+            ActionAoe::from_action(3, 12, 0, "gl_fan120_1bf"),
+            Some(ActionAoe {
+                shape: AoeShape::Cone {
+                    radius: 12.0,
+                    angle: 120.0
+                },
+                origin: AoeOrigin::Caster
+            })
+        );
+        assert_eq!(
+            ActionAoe::from_action(3, 12, 0, "unknown"),
+            Some(ActionAoe {
+                shape: AoeShape::Cone {
+                    radius: 12.0,
+                    angle: DEFAULT_CONE_ANGLE
+                },
+                origin: AoeOrigin::Caster
+            })
+        );
+        assert_eq!(
+            ActionAoe::from_action(4, 40, 8, "gl_line_1bf"),
+            Some(ActionAoe {
+                shape: AoeShape::Line {
+                    length: 40.0,
+                    width: 8.0
+                },
+                origin: AoeOrigin::Caster
+            })
+        );
+    }
+}
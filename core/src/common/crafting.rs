@@ -0,0 +1,562 @@
+//! A simulation of crafting, used to resolve crafting actions on the server.
+
+/// The chance for an item to be HQ, indexed by its quality as a percentage of the recipe's maximum.
+const HQ_CHANCE_TABLE: [u8; 101] = [
+    1, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8,
+    9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 12, 13, 13, 13, 14, 14, 14, 15, 15, 15, 16, 16, 17,
+    17, 17, 18, 18, 18, 19, 19, 20, 20, 21, 22, 23, 24, 26, 28, 31, 34, 38, 42, 47, 52, 58, 64, 68,
+    71, 74, 76, 78, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 94, 96, 98, 100,
+];
+
+/// The most stacks of Inner Quiet that can be built up.
+pub const MAX_INNER_QUIET: u8 = 10;
+
+/// How much durability is used by synthesis and touch actions.
+const DURABILITY_COST: u32 = 10;
+
+/// How much durability Master's Mend restores.
+const MASTERS_MEND_DURABILITY: u32 = 30;
+
+/// The chance of the condition becoming Excellent after a step, if it's Normal.
+const EXCELLENT_CHANCE: u8 = 4;
+
+/// The chance of the condition becoming Good after a step, if it's Normal.
+const GOOD_CHANCE: u8 = 20;
+
+/// The condition of the item being crafted, which affects how much quality touch actions add.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u32)]
+pub enum CraftingCondition {
+    #[default]
+    Normal = 1,
+    Good = 2,
+    Excellent = 3,
+    Poor = 4,
+}
+
+impl CraftingCondition {
+    /// How much quality is multiplied by, in percent.
+    fn quality_modifier(&self) -> u32 {
+        match self {
+            CraftingCondition::Normal => 100,
+            CraftingCondition::Good => 150,
+            CraftingCondition::Excellent => 400,
+            CraftingCondition::Poor => 50,
+        }
+    }
+
+    /// Picks the condition for the next step, where `roll` is from 0 to 99.
+    pub fn next(&self, roll: u8) -> Self {
+        match self {
+            CraftingCondition::Excellent => CraftingCondition::Poor,
+            CraftingCondition::Good | CraftingCondition::Poor => CraftingCondition::Normal,
+            CraftingCondition::Normal if roll < EXCELLENT_CHANCE => CraftingCondition::Excellent,
+            CraftingCondition::Normal if roll < EXCELLENT_CHANCE + GOOD_CHANCE => {
+                CraftingCondition::Good
+            }
+            CraftingCondition::Normal => CraftingCondition::Normal,
+        }
+    }
+}
+
+/// The crafting actions we know how to resolve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CraftingAction {
+    BasicSynthesis,
+    CarefulSynthesis,
+    BasicTouch,
+    StandardTouch,
+    MastersMend,
+    Observe,
+    Veneration,
+    Innovation,
+    GreatStrides,
+    WasteNot,
+}
+
+/// Every class has their own copy of each action. These are in order of Carpenter, Blacksmith, Armorer, Goldsmith, Leatherworker, Weaver, Alchemist and Culinarian.
+const CRAFTING_ACTION_IDS: [(CraftingAction, [u32; 8]); 10] = [
+    (
+        CraftingAction::BasicSynthesis,
+        [
+            100001, 100015, 100030, 100075, 100045, 100060, 100090, 100105,
+        ],
+    ),
+    (
+        CraftingAction::CarefulSynthesis,
+        [
+            100203, 100204, 100205, 100206, 100207, 100208, 100209, 100210,
+        ],
+    ),
+    (
+        CraftingAction::BasicTouch,
+        [
+            100002, 100016, 100031, 100076, 100046, 100061, 100091, 100106,
+        ],
+    ),
+    (
+        CraftingAction::StandardTouch,
+        [
+            100004, 100018, 100034, 100078, 100048, 100064, 100093, 100109,
+        ],
+    ),
+    (
+        CraftingAction::MastersMend,
+        [
+            100003, 100017, 100032, 100077, 100047, 100062, 100092, 100107,
+        ],
+    ),
+    (
+        CraftingAction::Observe,
+        [
+            100010, 100023, 100040, 100082, 100053, 100070, 100099, 100113,
+        ],
+    ),
+    (
+        CraftingAction::Veneration,
+        [19297, 19298, 19299, 19300, 19301, 19302, 19303, 19304],
+    ),
+    (
+        CraftingAction::Innovation,
+        [19004, 19005, 19006, 19007, 19008, 19009, 19010, 19011],
+    ),
+    (
+        CraftingAction::GreatStrides,
+        [260, 261, 262, 263, 264, 265, 266, 267],
+    ),
+    (
+        CraftingAction::WasteNot,
+        [4631, 4632, 4633, 4634, 4635, 4636, 4637, 4638],
+    ),
+];
+
+impl CraftingAction {
+    /// Looks up an action by its id in either the CraftAction or Action Excel sheet, for any class.
+    pub fn from_id(id: u32) -> Option<Self> {
+        CRAFTING_ACTION_IDS
+            .iter()
+            .find(|(_, ids)| ids.contains(&id))
+            .map(|(action, _)| *action)
+    }
+
+    /// How much CP this action costs, which can depend on the action used before it.
+    pub fn cp_cost(&self, previous: Option<CraftingAction>) -> u32 {
+        match self {
+            CraftingAction::BasicSynthesis => 0,
+            CraftingAction::CarefulSynthesis => 7,
+            CraftingAction::BasicTouch => 18,
+            CraftingAction::StandardTouch if previous == Some(CraftingAction::BasicTouch) => 18,
+            CraftingAction::StandardTouch => 32,
+            CraftingAction::MastersMend => 88,
+            CraftingAction::Observe => 7,
+            CraftingAction::Veneration => 18,
+            CraftingAction::Innovation => 18,
+            CraftingAction::GreatStrides => 32,
+            CraftingAction::WasteNot => 56,
+        }
+    }
+
+    /// How much progress this action adds in percent of the base progress, at this level.
+    fn progress_efficiency(&self, level: u8) -> u32 {
+        match self {
+            CraftingAction::BasicSynthesis if level >= 31 => 120,
+            CraftingAction::BasicSynthesis => 100,
+            CraftingAction::CarefulSynthesis if level >= 82 => 180,
+            CraftingAction::CarefulSynthesis => 150,
+            _ => 0,
+        }
+    }
+
+    /// How much quality this action adds in percent of the base quality.
+    fn quality_efficiency(&self) -> u32 {
+        match self {
+            CraftingAction::BasicTouch => 100,
+            CraftingAction::StandardTouch => 125,
+            _ => 0,
+        }
+    }
+
+    /// Whether this action uses up durability.
+    fn uses_durability(&self) -> bool {
+        self.progress_efficiency(1) > 0 || self.quality_efficiency() > 0
+    }
+}
+
+/// The player's stats that are relevant to crafting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrafterStats {
+    pub level: u8,
+    pub craftsmanship: u32,
+    pub control: u32,
+    pub cp: u32,
+}
+
+/// Everything about a recipe that's needed to craft it, mostly from the Recipe and RecipeLevelTable Excel sheets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CraftingRecipe {
+    /// The recipe's class level, which the player is compared against.
+    pub level: u8,
+    /// How much progress is needed to finish the item.
+    pub progress: u32,
+    /// The highest quality the item can have.
+    pub quality: u32,
+    /// How much durability the item starts out with.
+    pub durability: u32,
+    pub progress_divider: u32,
+    pub quality_divider: u32,
+    /// Applied when the player isn't higher level than the recipe, in percent.
+    pub progress_modifier: u32,
+    /// Applied when the player isn't higher level than the recipe, in percent.
+    pub quality_modifier: u32,
+    /// Whether the result can be high quality.
+    pub can_hq: bool,
+}
+
+/// What happened after using a crafting action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CraftingStep {
+    pub progress_increase: u32,
+    pub quality_increase: u32,
+    /// Negative if durability was used up.
+    pub durability_change: i32,
+    pub cp_cost: u32,
+}
+
+/// Reasons a crafting action can't be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CraftingError {
+    /// The player doesn't have enough CP left.
+    NotEnoughCp,
+    /// The craft has already finished, either successfully or not.
+    Finished,
+}
+
+/// A single attempt at crafting a recipe.
+#[derive(Debug, Clone)]
+pub struct CraftingSession {
+    pub stats: CrafterStats,
+    pub recipe: CraftingRecipe,
+    /// How many actions have been used so far.
+    pub step: u32,
+    pub progress: u32,
+    pub quality: u32,
+    pub durability: u32,
+    pub cp: u32,
+    pub condition: CraftingCondition,
+    pub inner_quiet: u8,
+    /// How many more steps each buff lasts for.
+    veneration: u8,
+    innovation: u8,
+    great_strides: u8,
+    waste_not: u8,
+    previous_action: Option<CraftingAction>,
+}
+
+impl CraftingSession {
+    pub fn new(stats: CrafterStats, recipe: CraftingRecipe) -> Self {
+        Self {
+            stats,
+            recipe,
+            step: 0,
+            progress: 0,
+            quality: 0,
+            durability: recipe.durability,
+            cp: stats.cp,
+            condition: CraftingCondition::Normal,
+            inner_quiet: 0,
+            veneration: 0,
+            innovation: 0,
+            great_strides: 0,
+            waste_not: 0,
+            previous_action: None,
+        }
+    }
+
+    /// How much progress an action with 100% efficiency adds.
+    fn base_progress(&self) -> u32 {
+        let base = self.stats.craftsmanship * 10 / self.recipe.progress_divider.max(1) + 2;
+        if self.stats.level <= self.recipe.level {
+            base * self.recipe.progress_modifier / 100
+        } else {
+            base
+        }
+    }
+
+    /// How much quality an action with 100% efficiency adds, under Normal conditions.
+    fn base_quality(&self) -> u32 {
+        let base = self.stats.control * 10 / self.recipe.quality_divider.max(1) + 35;
+        if self.stats.level <= self.recipe.level {
+            base * self.recipe.quality_modifier / 100
+        } else {
+            base
+        }
+    }
+
+    /// Uses `action`, where `condition_roll` is from 0 to 99 and decides the condition of the next step.
+    pub fn apply(
+        &mut self,
+        action: CraftingAction,
+        condition_roll: u8,
+    ) -> Result<CraftingStep, CraftingError> {
+        if self.is_finished() {
+            return Err(CraftingError::Finished);
+        }
+
+        let cp_cost = action.cp_cost(self.previous_action);
+        if cp_cost > self.cp {
+            return Err(CraftingError::NotEnoughCp);
+        }
+        self.cp -= cp_cost;
+
+        let mut step = CraftingStep {
+            cp_cost,
+            ..Default::default()
+        };
+
+        let progress_efficiency = action.progress_efficiency(self.stats.level);
+        if progress_efficiency > 0 {
+            let bonus = if self.veneration > 0 { 50 } else { 0 };
+            let increase = self.base_progress() * progress_efficiency / 100 * (100 + bonus) / 100;
+
+            step.progress_increase = increase.min(self.recipe.progress - self.progress);
+            self.progress += step.progress_increase;
+        }
+
+        let quality_efficiency = action.quality_efficiency();
+        if quality_efficiency > 0 {
+            let mut bonus = 0;
+            if self.innovation > 0 {
+                bonus += 50;
+            }
+            if self.great_strides > 0 {
+                bonus += 100;
+                self.great_strides = 0;
+            }
+
+            let increase = self.base_quality() * quality_efficiency / 100
+                * self.condition.quality_modifier()
+                / 100
+                * (100 + self.inner_quiet as u32 * 10)
+                / 100
+                * (100 + bonus)
+                / 100;
+
+            step.quality_increase = increase.min(self.recipe.quality - self.quality);
+            self.quality += step.quality_increase;
+            self.inner_quiet = (self.inner_quiet + 1).min(MAX_INNER_QUIET);
+        }
+
+        if action.uses_durability() {
+            let cost = if self.waste_not > 0 {
+                DURABILITY_COST / 2
+            } else {
+                DURABILITY_COST
+            };
+            let cost = cost.min(self.durability);
+
+            self.durability -= cost;
+            step.durability_change = -(cost as i32);
+        }
+
+        // Buffs count down before the new ones are applied, so they last for their full duration.
+        self.veneration = self.veneration.saturating_sub(1);
+        self.innovation = self.innovation.saturating_sub(1);
+        self.great_strides = self.great_strides.saturating_sub(1);
+        self.waste_not = self.waste_not.saturating_sub(1);
+
+        match action {
+            CraftingAction::MastersMend => {
+                let restored =
+                    MASTERS_MEND_DURABILITY.min(self.recipe.durability - self.durability);
+
+                self.durability += restored;
+                step.durability_change = restored as i32;
+            }
+            CraftingAction::Veneration => self.veneration = 4,
+            CraftingAction::Innovation => self.innovation = 4,
+            CraftingAction::GreatStrides => self.great_strides = 3,
+            CraftingAction::WasteNot => self.waste_not = 4,
+            _ => {}
+        }
+
+        self.step += 1;
+        self.condition = self.condition.next(condition_roll);
+        self.previous_action = Some(action);
+
+        Ok(step)
+    }
+
+    /// Whether the item was finished, or ran out of durability.
+    pub fn is_finished(&self) -> bool {
+        self.is_successful() || self.durability == 0
+    }
+
+    /// Whether enough progress was made to finish the item.
+    pub fn is_successful(&self) -> bool {
+        self.progress >= self.recipe.progress
+    }
+
+    /// The chance for the finished item to be HQ, from 0 to 100.
+    pub fn hq_chance(&self) -> u8 {
+        if !self.recipe.can_hq || self.recipe.quality == 0 {
+            return 0;
+        }
+
+        let percent = (self.quality * 100 / self.recipe.quality).min(100);
+        HQ_CHANCE_TABLE[percent as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> CraftingSession {
+        CraftingSession::new(
+            CrafterStats {
+                level: 10,
+                craftsmanship: 100,
+                control: 100,
+                cp: 200,
+            },
+            CraftingRecipe {
+                level: 10,
+                progress: 100,
+                quality: 1000,
+                durability: 40,
+                progress_divider: 50,
+                quality_divider: 30,
+                progress_modifier: 100,
+                quality_modifier: 100,
+                can_hq: true,
+            },
+        )
+    }
+
+    #[test]
+    fn test_from_id() {
+        assert_eq!(
+            CraftingAction::from_id(100001),
+            Some(CraftingAction::BasicSynthesis)
+        );
+        assert_eq!(
+            CraftingAction::from_id(100045),
+            Some(CraftingAction::BasicSynthesis)
+        );
+        assert_eq!(
+            CraftingAction::from_id(19301),
+            Some(CraftingAction::Veneration)
+        );
+        assert_eq!(CraftingAction::from_id(1), None);
+    }
+
+    #[test]
+    fn test_synthesis() {
+        let mut session = session();
+
+        // 100 * 10 / 50 + 2 = 22
+        let step = session.apply(CraftingAction::BasicSynthesis, 99).unwrap();
+        assert_eq!(step.progress_increase, 22);
+        assert_eq!(step.durability_change, -10);
+        assert_eq!(session.durability, 30);
+
+        session.apply(CraftingAction::Veneration, 99).unwrap();
+        let step = session.apply(CraftingAction::BasicSynthesis, 99).unwrap();
+        assert_eq!(step.progress_increase, 33);
+        assert_eq!(session.progress, 55);
+    }
+
+    #[test]
+    fn test_touch() {
+        let mut session = session();
+
+        // 100 * 10 / 30 + 35 = 68
+        let step = session.apply(CraftingAction::BasicTouch, 99).unwrap();
+        assert_eq!(step.quality_increase, 68);
+        assert_eq!(step.cp_cost, 18);
+        assert_eq!(session.inner_quiet, 1);
+
+        // Combo into Standard Touch, with one stack of Inner Quiet
+        let step = session.apply(CraftingAction::StandardTouch, 99).unwrap();
+        assert_eq!(step.cp_cost, 18);
+        assert_eq!(step.quality_increase, 68 * 125 / 100 * 110 / 100);
+
+        // Great Strides is used up by the next touch
+        session.apply(CraftingAction::GreatStrides, 99).unwrap();
+        let step = session.apply(CraftingAction::BasicTouch, 99).unwrap();
+        assert_eq!(step.quality_increase, 68 * 120 / 100 * 200 / 100);
+        let step = session.apply(CraftingAction::BasicTouch, 99).unwrap();
+        assert_eq!(step.quality_increase, 68 * 130 / 100);
+    }
+
+    #[test]
+    fn test_durability() {
+        let mut session = session();
+
+        session.apply(CraftingAction::WasteNot, 99).unwrap();
+        let step = session.apply(CraftingAction::BasicTouch, 99).unwrap();
+        assert_eq!(step.durability_change, -5);
+
+        let step = session.apply(CraftingAction::MastersMend, 99).unwrap();
+        assert_eq!(step.durability_change, 5);
+        assert_eq!(session.durability, 40);
+
+        // Running out of durability ends the craft
+        let mut session = self::session();
+        for _ in 0..4 {
+            session.apply(CraftingAction::BasicTouch, 99).ok();
+        }
+        assert_eq!(session.durability, 0);
+        assert!(session.is_finished());
+        assert!(!session.is_successful());
+        assert_eq!(
+            session.apply(CraftingAction::BasicSynthesis, 99),
+            Err(CraftingError::Finished)
+        );
+    }
+
+    #[test]
+    fn test_cp() {
+        let mut session = session();
+        session.cp = 10;
+
+        assert_eq!(
+            session.apply(CraftingAction::BasicTouch, 99),
+            Err(CraftingError::NotEnoughCp)
+        );
+        assert_eq!(session.step, 0);
+    }
+
+    #[test]
+    fn test_condition() {
+        assert_eq!(
+            CraftingCondition::Normal.next(0),
+            CraftingCondition::Excellent
+        );
+        assert_eq!(CraftingCondition::Normal.next(10), CraftingCondition::Good);
+        assert_eq!(
+            CraftingCondition::Normal.next(99),
+            CraftingCondition::Normal
+        );
+        assert_eq!(
+            CraftingCondition::Excellent.next(99),
+            CraftingCondition::Poor
+        );
+        assert_eq!(CraftingCondition::Good.next(0), CraftingCondition::Normal);
+    }
+
+    #[test]
+    fn test_hq_chance() {
+        let mut session = session();
+        assert_eq!(session.hq_chance(), 1);
+
+        session.quality = 500;
+        assert_eq!(session.hq_chance(), 15);
+
+        session.quality = 1000;
+        assert_eq!(session.hq_chance(), 100);
+
+        session.recipe.can_hq = false;
+        assert_eq!(session.hq_chance(), 0);
+    }
+}
@@ -0,0 +1,439 @@
+//! Damage and healing formulas, following the ones documented by the community (see <https://www.akhmorning.com/allagan-studies/how-to-be-a-math-wizard/>.)
+//!
+//! Like the game, everything is done with integers and rounded down after each step.
+
+/// How much stronger a direct hit is, as a percentage.
+const DIRECT_HIT_BONUS_PERCENT: u64 = 25;
+
+/// How much damage and healing can vary either way, as a percentage.
+pub const DAMAGE_VARIANCE_PERCENT: u64 = 5;
+
+/// The main stat baseline at each level cap, since ParamGrow doesn't have a column for it. These are the community-documented values.
+const MAIN_STAT_BASELINES: [(u32, u32); 7] = [
+    (1, 20),
+    (50, 202),
+    (60, 218),
+    (70, 292),
+    (80, 340),
+    (90, 390),
+    (100, 440),
+];
+
+/// How steeply attack power (for non-tanks) and healing magic potency scale at each level cap, as (level, attack power slope, healing slope, healing divisor.)
+/// These are the community-documented values. Levels below 70 aren't documented, and neither is healing at 100, so they use the closest known values.
+const LEVEL_SLOPES: [(u32, u32, u32, u32); 4] = [
+    (70, 125, 100, 264),
+    (80, 165, 100, 304),
+    (90, 195, 569, 1522),
+    (100, 237, 569, 1522),
+];
+
+/// Returns the main stat (e.g. strength) a character has at this level without any gear. Levels between the caps are interpolated.
+pub fn main_stat_baseline(level: u32) -> u32 {
+    let mut previous = MAIN_STAT_BASELINES[0];
+    for (cap, baseline) in MAIN_STAT_BASELINES {
+        if level <= cap {
+            let (previous_cap, previous_baseline) = previous;
+            if cap == previous_cap {
+                return baseline;
+            }
+
+            let level = level.max(previous_cap);
+            return previous_baseline
+                + (baseline - previous_baseline) * (level - previous_cap) / (cap - previous_cap);
+        }
+        previous = (cap, baseline);
+    }
+
+    previous.1
+}
+
+/// Returns the attack power slope, healing slope and healing divisor for this level. Levels between the caps use the next cap's values.
+pub fn level_slopes(level: u32) -> (u32, u32, u32) {
+    let (_, attack_power_slope, healing_power_slope, healing_power_divisor) = LEVEL_SLOPES
+        .into_iter()
+        .find(|(cap, ..)| level <= *cap)
+        .unwrap_or(LEVEL_SLOPES[LEVEL_SLOPES.len() - 1]);
+
+    (
+        attack_power_slope,
+        healing_power_slope,
+        healing_power_divisor,
+    )
+}
+
+/// The baseline stats for a level, which every formula is relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelModifiers {
+    /// A main stat (e.g. strength) at this level, without any gear.
+    pub main: u32,
+    /// A substat (e.g. critical hit) at this level, without any gear.
+    pub sub: u32,
+    /// How much of a substat it takes to make a difference at this level.
+    pub div: u32,
+    /// How steeply attack power scales damage at this level.
+    pub attack_power_slope: u32,
+    /// How steeply healing magic potency scales healing at this level, along with `healing_power_divisor`.
+    pub healing_power_slope: u32,
+    pub healing_power_divisor: u32,
+}
+
+impl LevelModifiers {
+    /// How far `stat` is above `baseline`. Stats below the baseline count as the baseline.
+    fn above(stat: u32, baseline: u32) -> i64 {
+        stat.saturating_sub(baseline) as i64
+    }
+
+    fn main(&self) -> i64 {
+        self.main.max(1) as i64
+    }
+
+    fn div(&self) -> i64 {
+        self.div.max(1) as i64
+    }
+
+    /// The damage multiplier from attack power, as a percentage.
+    pub fn attack_power(&self, attack_power: u32) -> u64 {
+        (self.attack_power_slope as i64 * Self::above(attack_power, self.main) / self.main() + 100)
+            as u64
+    }
+
+    /// The healing multiplier from healing magic potency, as a percentage.
+    pub fn healing_power(&self, healing_magic_potency: u32) -> u64 {
+        (self.healing_power_slope as i64 * Self::above(healing_magic_potency, self.main)
+            / self.healing_power_divisor.max(1) as i64
+            + 100) as u64
+    }
+
+    /// The multiplier from determination, in thousandths.
+    pub fn determination(&self, determination: u32) -> u64 {
+        (140 * Self::above(determination, self.main) / self.div() + 1000) as u64
+    }
+
+    /// The multiplier from tenacity, in thousandths. Increases damage and healing done, and reduces damage taken.
+    pub fn tenacity(&self, tenacity: u32) -> u64 {
+        (100 * Self::above(tenacity, self.sub) / self.div() + 1000) as u64
+    }
+
+    /// The multiplier from weapon damage, as a percentage. `attribute_modifier` is the job's modifier for its primary stat, from ClassJob.
+    pub fn weapon_damage(&self, weapon_damage: u32, attribute_modifier: u32) -> u64 {
+        self.main as u64 * attribute_modifier as u64 / 1000 + weapon_damage as u64
+    }
+
+    /// The chance of a critical hit, in thousandths.
+    pub fn critical_hit_rate(&self, critical_hit: u32) -> u64 {
+        (200 * Self::above(critical_hit, self.sub) / self.div() + 50) as u64
+    }
+
+    /// The multiplier for critical hits, in thousandths.
+    pub fn critical_hit_damage(&self, critical_hit: u32) -> u64 {
+        (200 * Self::above(critical_hit, self.sub) / self.div() + 1400) as u64
+    }
+
+    /// The chance of a direct hit, in thousandths.
+    pub fn direct_hit_rate(&self, direct_hit_rate: u32) -> u64 {
+        (550 * Self::above(direct_hit_rate, self.sub) / self.div()) as u64
+    }
+
+    /// How much damage is mitigated by defense (or magic defense), as a percentage.
+    pub fn defense_mitigation(&self, defense: u32) -> u64 {
+        (15 * defense as u64 / self.div() as u64).min(100)
+    }
+}
+
+/// The stats that go into dealing or receiving damage and healing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CombatStats {
+    /// Attack power for physical damage, attack magic potency for magical damage or healing magic potency for healing.
+    pub attack_power: u32,
+    /// Physical or magic damage from the weapon, to match `attack_power`.
+    pub weapon_damage: u32,
+    pub determination: u32,
+    pub tenacity: u32,
+    pub critical_hit: u32,
+    pub direct_hit_rate: u32,
+    /// Defense for physical damage, or magic defense for magical damage.
+    pub defense: u32,
+    /// The job's modifier for its primary stat as a percentage, e.g. 115.
+    pub attribute_modifier: u32,
+}
+
+/// Random numbers from 0 to 1 that decide how a hit turns out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DamageRolls {
+    /// Decides whether it's a critical hit.
+    pub critical: f32,
+    /// Decides whether it's a direct hit.
+    pub direct_hit: f32,
+    /// Decides where it lands within the variance.
+    pub variance: f32,
+}
+
+/// The result of some damage or healing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hit {
+    pub amount: u32,
+    pub critical: bool,
+    pub direct_hit: bool,
+}
+
+/// Applies the multipliers from determination, tenacity and weapon damage to a hit that's been scaled by attack power (or healing magic potency.)
+fn apply_substats(scaled: u64, stats: &CombatStats, level: &LevelModifiers) -> u64 {
+    let amount = scaled * level.determination(stats.determination) / 1000;
+    let amount = amount * level.tenacity(stats.tenacity) / 1000;
+    amount * level.weapon_damage(stats.weapon_damage, stats.attribute_modifier) / 100
+}
+
+/// Rolls for a critical hit, a direct hit (if allowed) and the variance.
+fn roll(
+    amount: u64,
+    stats: &CombatStats,
+    level: &LevelModifiers,
+    rolls: &DamageRolls,
+    can_direct_hit: bool,
+) -> Hit {
+    let critical = ((rolls.critical * 1000.0) as u64) < level.critical_hit_rate(stats.critical_hit);
+    let direct_hit = can_direct_hit
+        && ((rolls.direct_hit * 1000.0) as u64) < level.direct_hit_rate(stats.direct_hit_rate);
+
+    let mut amount = amount;
+    if critical {
+        amount = amount * level.critical_hit_damage(stats.critical_hit) / 1000;
+    }
+    if direct_hit {
+        amount = amount * (100 + DIRECT_HIT_BONUS_PERCENT) / 100;
+    }
+
+    let variance = (100 - DAMAGE_VARIANCE_PERCENT
+        + (rolls.variance * (DAMAGE_VARIANCE_PERCENT * 2 + 1) as f32) as u64)
+        .min(100 + DAMAGE_VARIANCE_PERCENT);
+    amount = amount * variance / 100;
+
+    Hit {
+        amount: amount.min(u32::MAX as u64) as u32,
+        critical,
+        direct_hit,
+    }
+}
+
+/// Calculates the damage `attacker` deals to `target` with an attack of this potency.
+pub fn calculate_damage(
+    potency: u32,
+    attacker: &CombatStats,
+    target: &CombatStats,
+    level: &LevelModifiers,
+    rolls: &DamageRolls,
+) -> Hit {
+    let scaled = potency as u64 * level.attack_power(attacker.attack_power) / 100;
+    let amount = apply_substats(scaled, attacker, level);
+
+    let mut hit = roll(amount, attacker, level, rolls, true);
+
+    let mitigated = hit.amount as u64 * (100 - level.defense_mitigation(target.defense)) / 100;
+    let mitigated = mitigated * (2000 - level.tenacity(target.tenacity).min(2000)) / 1000;
+    hit.amount = mitigated as u32;
+
+    hit
+}
+
+/// Calculates how much `healer` heals for with a spell of this potency. Heals can be critical, but never direct hits.
+pub fn calculate_healing(
+    potency: u32,
+    healer: &CombatStats,
+    level: &LevelModifiers,
+    rolls: &DamageRolls,
+) -> Hit {
+    let scaled = potency as u64 * level.healing_power(healer.attack_power) / 100;
+    let amount = apply_substats(scaled, healer, level);
+
+    roll(amount, healer, level, rolls, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The baseline stats at level 90.
+    const LEVEL_90: LevelModifiers = LevelModifiers {
+        main: 390,
+        sub: 400,
+        div: 1900,
+        attack_power_slope: 195,
+        healing_power_slope: 569,
+        healing_power_divisor: 1522,
+    };
+
+    /// Rolls that land in the middle of the variance, and don't crit or direct hit.
+    const AVERAGE_ROLLS: DamageRolls = DamageRolls {
+        critical: 0.999,
+        direct_hit: 0.999,
+        variance: 0.5,
+    };
+
+    fn attacker() -> CombatStats {
+        CombatStats {
+            attack_power: 3000,
+            weapon_damage: 120,
+            determination: 2000,
+            tenacity: 400,
+            critical_hit: 2000,
+            direct_hit_rate: 2000,
+            defense: 0,
+            attribute_modifier: 100,
+        }
+    }
+
+    #[test]
+    fn test_level_modifier_formulas() {
+        assert_eq!(LEVEL_90.attack_power(390), 100);
+        assert_eq!(LEVEL_90.attack_power(3000), 1405);
+        assert_eq!(LEVEL_90.healing_power(3000), 1075);
+        assert_eq!(LEVEL_90.determination(390), 1000);
+        assert_eq!(LEVEL_90.determination(2000), 1118);
+        assert_eq!(LEVEL_90.tenacity(1000), 1031);
+        assert_eq!(LEVEL_90.weapon_damage(120, 100), 159);
+        assert_eq!(LEVEL_90.weapon_damage(120, 115), 164);
+        assert_eq!(LEVEL_90.critical_hit_rate(400), 50);
+        assert_eq!(LEVEL_90.critical_hit_rate(2000), 218);
+        assert_eq!(LEVEL_90.critical_hit_damage(400), 1400);
+        assert_eq!(LEVEL_90.critical_hit_damage(2000), 1568);
+        assert_eq!(LEVEL_90.direct_hit_rate(400), 0);
+        assert_eq!(LEVEL_90.direct_hit_rate(2000), 463);
+        assert_eq!(LEVEL_90.defense_mitigation(950), 7);
+
+        // Stats below the baseline don't hurt.
+        assert_eq!(LEVEL_90.critical_hit_rate(0), 50);
+        assert_eq!(LEVEL_90.attack_power(0), 100);
+    }
+
+    #[test]
+    fn test_main_stat_baseline() {
+        assert_eq!(main_stat_baseline(0), 20);
+        assert_eq!(main_stat_baseline(1), 20);
+        assert_eq!(main_stat_baseline(50), 202);
+        assert_eq!(main_stat_baseline(55), 210);
+        assert_eq!(main_stat_baseline(90), LEVEL_90.main);
+        assert_eq!(main_stat_baseline(100), 440);
+        assert_eq!(main_stat_baseline(120), 440);
+    }
+
+    #[test]
+    fn test_level_slopes() {
+        assert_eq!(level_slopes(1), (125, 100, 264));
+        assert_eq!(level_slopes(80), (165, 100, 304));
+        assert_eq!(level_slopes(85), (195, 569, 1522));
+        assert_eq!(
+            level_slopes(90),
+            (
+                LEVEL_90.attack_power_slope,
+                LEVEL_90.healing_power_slope,
+                LEVEL_90.healing_power_divisor
+            )
+        );
+        assert_eq!(level_slopes(100), (237, 569, 1522));
+        assert_eq!(level_slopes(120), (237, 569, 1522));
+    }
+
+    #[test]
+    fn test_damage() {
+        let target = CombatStats::default();
+        let hit = calculate_damage(300, &attacker(), &target, &LEVEL_90, &AVERAGE_ROLLS);
+        assert_eq!(
+            hit,
+            Hit {
+                amount: 7492,
+                critical: false,
+                direct_hit: false,
+            }
+        );
+
+        // Defense mitigates some of it.
+        let target = CombatStats {
+            defense: 950,
+            ..Default::default()
+        };
+        let hit = calculate_damage(300, &attacker(), &target, &LEVEL_90, &AVERAGE_ROLLS);
+        assert_eq!(hit.amount, 6967);
+
+        // So does tenacity.
+        let target = CombatStats {
+            tenacity: 1000,
+            ..Default::default()
+        };
+        let hit = calculate_damage(300, &attacker(), &target, &LEVEL_90, &AVERAGE_ROLLS);
+        assert_eq!(hit.amount, 7259);
+    }
+
+    #[test]
+    fn test_critical_direct_hit() {
+        let target = CombatStats::default();
+        let rolls = DamageRolls {
+            critical: 0.0,
+            direct_hit: 0.0,
+            variance: 0.5,
+        };
+        let hit = calculate_damage(300, &attacker(), &target, &LEVEL_90, &rolls);
+        assert_eq!(
+            hit,
+            Hit {
+                amount: 14683,
+                critical: true,
+                direct_hit: true,
+            }
+        );
+
+        // Heals can't be direct hits.
+        let hit = calculate_healing(300, &attacker(), &LEVEL_90, &rolls);
+        assert!(hit.critical);
+        assert!(!hit.direct_hit);
+    }
+
+    #[test]
+    fn test_variance() {
+        let target = CombatStats::default();
+        let lowest = DamageRolls {
+            variance: 0.0,
+            ..AVERAGE_ROLLS
+        };
+        let highest = DamageRolls {
+            variance: 0.99999,
+            ..AVERAGE_ROLLS
+        };
+
+        let average = calculate_damage(1000, &attacker(), &target, &LEVEL_90, &AVERAGE_ROLLS);
+        let lowest = calculate_damage(1000, &attacker(), &target, &LEVEL_90, &lowest);
+        let highest = calculate_damage(1000, &attacker(), &target, &LEVEL_90, &highest);
+        assert_eq!(lowest.amount, average.amount * 95 / 100);
+        assert_eq!(highest.amount, average.amount * 105 / 100);
+    }
+
+    #[test]
+    fn test_seeded_rolls() {
+        let mut rng = fastrand::Rng::with_seed(0x4b415741);
+        let target = CombatStats::default();
+        let average = calculate_damage(1000, &attacker(), &target, &LEVEL_90, &AVERAGE_ROLLS);
+
+        let mut criticals = 0;
+        let mut direct_hits = 0;
+        for _ in 0..10000 {
+            let rolls = DamageRolls {
+                critical: rng.f32(),
+                direct_hit: rng.f32(),
+                variance: rng.f32(),
+            };
+            let hit = calculate_damage(1000, &attacker(), &target, &LEVEL_90, &rolls);
+
+            criticals += hit.critical as u32;
+            direct_hits += hit.direct_hit as u32;
+
+            if !hit.critical && !hit.direct_hit {
+                assert!(hit.amount >= average.amount * 95 / 100);
+                assert!(hit.amount <= average.amount * 105 / 100 + 1);
+            }
+        }
+
+        // 21.8% and 46.3% of the time.
+        assert!((2080..2280).contains(&criticals), "{criticals}");
+        assert!((4530..4730).contains(&direct_hits), "{direct_hits}");
+    }
+}
@@ -0,0 +1,242 @@
+//! Rules for the fishing minigame: which fish can bite, when they bite and how they're hooked.
+
+use serde::{Deserialize, Serialize};
+
+use super::gathering::eorzean_hours;
+
+/// How hard the line is pulled when a fish bites, which hints at which hookset to use.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FishTug {
+    /// Shown as "!" in the client.
+    #[default]
+    Light,
+    /// Shown as "!!" in the client.
+    Medium,
+    /// Shown as "!!!" in the client.
+    Heavy,
+}
+
+/// The different ways of reeling in a fish.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Hookset {
+    /// The regular Hook action.
+    Normal,
+    /// Precision Hookset, which only works on light tugs.
+    Precision,
+    /// Powerful Hookset, which only works on medium and heavy tugs.
+    Powerful,
+}
+
+impl Hookset {
+    /// The Hook action.
+    pub const HOOK_ACTION_ID: u32 = 296;
+    /// The Precision Hookset action.
+    pub const PRECISION_HOOKSET_ACTION_ID: u32 = 4179;
+    /// The Powerful Hookset action.
+    pub const POWERFUL_HOOKSET_ACTION_ID: u32 = 4103;
+
+    /// Returns the hookset for this action, if it's one.
+    pub fn from_action_id(action_id: u32) -> Option<Self> {
+        match action_id {
+            Self::HOOK_ACTION_ID => Some(Self::Normal),
+            Self::PRECISION_HOOKSET_ACTION_ID => Some(Self::Precision),
+            Self::POWERFUL_HOOKSET_ACTION_ID => Some(Self::Powerful),
+            _ => None,
+        }
+    }
+
+    /// Returns the chance to land a fish with this `tug`, from 0 to 100.
+    pub fn hook_chance(&self, tug: FishTug) -> u8 {
+        match (self, tug) {
+            (Self::Normal, FishTug::Light) => 100,
+            (Self::Normal, FishTug::Medium) => 90,
+            (Self::Normal, FishTug::Heavy) => 60,
+            (Self::Precision, FishTug::Light) => 100,
+            (Self::Powerful, FishTug::Medium | FishTug::Heavy) => 100,
+            _ => 0,
+        }
+    }
+}
+
+/// A JSON file that describes when a fish can bite, and how.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct FishConditions {
+    /// Item IDs of the bait this fish accepts. Empty means any bait works.
+    #[serde(default)]
+    pub bait: Vec<u32>,
+    /// Weather IDs this fish bites in. Empty means any weather works.
+    #[serde(default)]
+    pub weather: Vec<i32>,
+    /// The Eorzean hour (0-23) this fish starts biting at. If unset, it bites all day.
+    #[serde(default)]
+    pub start_hour: Option<u32>,
+    /// The Eorzean hour (0-23) this fish stops biting at. This may be earlier than `start_hour` to wrap around midnight.
+    #[serde(default)]
+    pub end_hour: Option<u32>,
+    /// How hard this fish pulls on the line.
+    #[serde(default)]
+    pub tug: FishTug,
+    /// The earliest this fish bites after casting, in seconds.
+    #[serde(default = "FishConditions::default_min_bite_time")]
+    pub min_bite_time: f32,
+    /// The latest this fish bites after casting, in seconds.
+    #[serde(default = "FishConditions::default_max_bite_time")]
+    pub max_bite_time: f32,
+    /// How likely this fish is to bite compared to the others in the same spot.
+    #[serde(default = "FishConditions::default_weight")]
+    pub weight: u32,
+}
+
+impl Default for FishConditions {
+    fn default() -> Self {
+        Self {
+            bait: Vec::new(),
+            weather: Vec::new(),
+            start_hour: None,
+            end_hour: None,
+            tug: FishTug::default(),
+            min_bite_time: Self::default_min_bite_time(),
+            max_bite_time: Self::default_max_bite_time(),
+            weight: Self::default_weight(),
+        }
+    }
+}
+
+impl FishConditions {
+    fn default_min_bite_time() -> f32 {
+        8.0
+    }
+
+    fn default_max_bite_time() -> f32 {
+        20.0
+    }
+
+    fn default_weight() -> u32 {
+        1
+    }
+
+    /// Whether this fish can bite right now. `has_bait` is asked whether the player has a given bait.
+    pub fn can_bite(
+        &self,
+        has_bait: impl Fn(u32) -> bool,
+        weather_id: i32,
+        unix_secs: u32,
+    ) -> bool {
+        if !self.bait.is_empty() && !self.bait.iter().any(|bait| has_bait(*bait)) {
+            return false;
+        }
+
+        if !self.weather.is_empty() && !self.weather.contains(&weather_id) {
+            return false;
+        }
+
+        let hour = eorzean_hours(unix_secs) % 24;
+        match (self.start_hour, self.end_hour) {
+            (Some(start), Some(end)) if start <= end => (start..end).contains(&hour),
+            (Some(start), Some(end)) => hour >= start || hour < end,
+            (Some(start), None) => hour >= start,
+            (None, Some(end)) => hour < end,
+            (None, None) => true,
+        }
+    }
+
+    /// Picks a bite time in seconds, where `roll` is between 0.0 and 1.0.
+    pub fn bite_time(&self, roll: f32) -> f32 {
+        self.min_bite_time + (self.max_bite_time - self.min_bite_time).max(0.0) * roll
+    }
+}
+
+/// Picks one of the `candidates` based on their weight, where `roll` is between 0.0 and 1.0.
+pub fn choose_fish(
+    candidates: &[(u32, FishConditions)],
+    roll: f32,
+) -> Option<&(u32, FishConditions)> {
+    let total_weight: u32 = candidates.iter().map(|(_, fish)| fish.weight).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    let mut target = (total_weight as f32 * roll) as u32;
+    for candidate in candidates {
+        if target < candidate.1.weight {
+            return Some(candidate);
+        }
+        target -= candidate.1.weight;
+    }
+
+    candidates.iter().rev().find(|(_, fish)| fish.weight > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_chance() {
+        assert_eq!(Hookset::Normal.hook_chance(FishTug::Light), 100);
+        assert_eq!(Hookset::Normal.hook_chance(FishTug::Heavy), 60);
+        assert_eq!(Hookset::Precision.hook_chance(FishTug::Light), 100);
+        assert_eq!(Hookset::Precision.hook_chance(FishTug::Heavy), 0);
+        assert_eq!(Hookset::Powerful.hook_chance(FishTug::Light), 0);
+        assert_eq!(Hookset::Powerful.hook_chance(FishTug::Medium), 100);
+        assert_eq!(Hookset::from_action_id(4179), Some(Hookset::Precision));
+        assert_eq!(Hookset::from_action_id(7), None);
+    }
+
+    #[test]
+    fn test_can_bite() {
+        // One Eorzean hour lasts 175 real seconds.
+        let at_hour = |hour: u32| hour * 175;
+
+        let anything = FishConditions::default();
+        assert!(anything.can_bite(|_| false, 1, at_hour(0)));
+
+        let picky = FishConditions {
+            bait: vec![2587],
+            weather: vec![7],
+            ..Default::default()
+        };
+        assert!(picky.can_bite(|bait| bait == 2587, 7, at_hour(0)));
+        assert!(!picky.can_bite(|_| false, 7, at_hour(0)));
+        assert!(!picky.can_bite(|bait| bait == 2587, 1, at_hour(0)));
+
+        // 22:00 to 4:00, which wraps around midnight.
+        let nocturnal = FishConditions {
+            start_hour: Some(22),
+            end_hour: Some(4),
+            ..Default::default()
+        };
+        assert!(nocturnal.can_bite(|_| false, 1, at_hour(23)));
+        assert!(nocturnal.can_bite(|_| false, 1, at_hour(24 + 3)));
+        assert!(!nocturnal.can_bite(|_| false, 1, at_hour(4)));
+        assert!(!nocturnal.can_bite(|_| false, 1, at_hour(12)));
+    }
+
+    #[test]
+    fn test_choose_fish() {
+        let common = FishConditions {
+            weight: 3,
+            ..Default::default()
+        };
+        let rare = FishConditions::default();
+        let candidates = vec![(1, common), (2, rare)];
+
+        assert_eq!(choose_fish(&candidates, 0.0).unwrap().0, 1);
+        assert_eq!(choose_fish(&candidates, 0.74).unwrap().0, 1);
+        assert_eq!(choose_fish(&candidates, 0.75).unwrap().0, 2);
+        assert_eq!(choose_fish(&candidates, 1.0).unwrap().0, 2);
+        assert!(choose_fish(&[], 0.5).is_none());
+    }
+
+    #[test]
+    fn test_parse_conditions() {
+        let conditions: FishConditions =
+            serde_json::from_str(r#"{ "bait": [2587], "tug": "heavy", "start_hour": 18 }"#)
+                .unwrap();
+        assert_eq!(conditions.tug, FishTug::Heavy);
+        assert_eq!(conditions.start_hour, Some(18));
+        assert_eq!(conditions.weight, 1);
+        assert_eq!(conditions.bite_time(0.5), 14.0);
+    }
+}
@@ -81,6 +81,9 @@ pub const WARP_DELAY: Duration = Duration::from_secs(2);
 /// Since this is reflected in the client alone, I measured it from there.
 pub const COMBO_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// How often damage and healing over time is applied, which is the same on retail.
+pub const STATUS_EFFECT_TICK: Duration = Duration::from_secs(3);
+
 /// In seconds. This controls the animation lock time for *all actions* for now.
 pub const ANIMATION_LOCK_TIME: f32 = 0.6;
 
@@ -539,7 +542,10 @@ impl diesel::deserialize::FromSql<diesel::sql_types::Integer, diesel::sqlite::Sq
 // NOTE: When adding a new container type, make sure to add it to InventoryIterator!
 #[binrw]
 #[brw(repr = u16)]
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Deserialize, Serialize)]
+#[repr(u16)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Deserialize, Serialize, FromRepr,
+)]
 pub enum ContainerType {
     #[default]
     Inventory0 = 0,
@@ -0,0 +1,100 @@
+//! Formulas for gathering from mining and botany nodes.
+
+/// How many real seconds make up one Eorzean hour (also known as a "bell".)
+pub const EORZEAN_HOUR_SECS: u32 = 175;
+
+/// How many Eorzean hours a depleted gathering point takes to grow back.
+pub const GATHERING_POINT_RESPAWN_HOURS: u32 = 2;
+
+/// The gathering chance when the player has none of the recommended Gathering.
+const MIN_GATHERING_CHANCE: u32 = 30;
+
+/// The HQ chance when the player has exactly the recommended Perception.
+const BASE_HQ_CHANCE: u32 = 10;
+
+/// How many levels above an item the player can be before they start earning less EXP.
+const EXP_LEVEL_GRACE: u8 = 5;
+
+/// How much EXP is lost for every level past `EXP_LEVEL_GRACE`, in percent.
+const EXP_PENALTY_PER_LEVEL: i32 = 10;
+
+/// The least EXP you can earn from an item, in percent.
+const MIN_EXP_PERCENT: i32 = 10;
+
+/// How many times the base EXP is awarded for gathering an item for the first time.
+pub const GATHERING_LOG_EXP_MULTIPLIER: i32 = 5;
+
+/// Returns the number of Eorzean hours that have passed since the UNIX epoch.
+pub fn eorzean_hours(unix_secs: u32) -> u32 {
+    unix_secs / EORZEAN_HOUR_SECS
+}
+
+/// Returns the UNIX timestamp a gathering point depleted at `depleted_at` grows back. This is always at the start of a bell, like retail.
+pub fn gathering_point_respawn_time(depleted_at: u32) -> u32 {
+    (eorzean_hours(depleted_at) + GATHERING_POINT_RESPAWN_HOURS) * EORZEAN_HOUR_SECS
+}
+
+/// Returns the chance to successfully gather an item, from 0 to 100.
+pub fn gathering_chance(gathering: u32, recommended_gathering: u32) -> u8 {
+    if gathering >= recommended_gathering {
+        return 100;
+    }
+
+    (MIN_GATHERING_CHANCE + (100 - MIN_GATHERING_CHANCE) * gathering / recommended_gathering) as u8
+}
+
+/// Returns the chance for a gathered item to be high quality, from 0 to 100.
+pub fn hq_chance(perception: u32, recommended_perception: u32) -> u8 {
+    if recommended_perception == 0 {
+        return 100;
+    }
+
+    (BASE_HQ_CHANCE * perception / recommended_perception).min(100) as u8
+}
+
+/// Scales `base_exp` down when the player is much higher level than the item they gathered.
+pub fn gathering_exp(base_exp: i32, player_level: u8, item_level: u8) -> i32 {
+    let levels_over = player_level.saturating_sub(item_level.saturating_add(EXP_LEVEL_GRACE));
+    let percent = (100 - levels_over as i32 * EXP_PENALTY_PER_LEVEL).max(MIN_EXP_PERCENT);
+
+    base_exp * percent / 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_respawn_time() {
+        // Depleting at any point during a bell should respawn at the same time.
+        assert_eq!(gathering_point_respawn_time(175), 525);
+        assert_eq!(gathering_point_respawn_time(349), 525);
+        assert_eq!(gathering_point_respawn_time(350), 700);
+    }
+
+    #[test]
+    fn test_gathering_chance() {
+        assert_eq!(gathering_chance(500, 400), 100);
+        assert_eq!(gathering_chance(400, 400), 100);
+        assert_eq!(gathering_chance(200, 400), 65);
+        assert_eq!(gathering_chance(0, 400), MIN_GATHERING_CHANCE as u8);
+        assert_eq!(gathering_chance(0, 0), 100);
+    }
+
+    #[test]
+    fn test_hq_chance() {
+        assert_eq!(hq_chance(400, 400), BASE_HQ_CHANCE as u8);
+        assert_eq!(hq_chance(800, 400), 20);
+        assert_eq!(hq_chance(0, 400), 0);
+        assert_eq!(hq_chance(100_000, 400), 100);
+    }
+
+    #[test]
+    fn test_gathering_exp() {
+        assert_eq!(gathering_exp(100, 10, 10), 100);
+        assert_eq!(gathering_exp(100, 1, 10), 100);
+        assert_eq!(gathering_exp(100, 15, 10), 100);
+        assert_eq!(gathering_exp(100, 17, 10), 80);
+        assert_eq!(gathering_exp(100, 90, 10), MIN_EXP_PERCENT);
+    }
+}
@@ -0,0 +1,110 @@
+//! Rules for sharing treasure between party members.
+
+/// How long party members have to roll on an item before it's handed out, in seconds.
+pub const LOOT_ROLL_SECS: u64 = 300;
+
+/// The first weekly reset after the UNIX epoch, a Tuesday at 08:00 UTC.
+const FIRST_WEEKLY_RESET: u64 = 460_800;
+
+/// How long a week is, in seconds.
+const WEEK_SECS: u64 = 604_800;
+
+/// Returns the UNIX timestamp of the next weekly reset after `now`, which is when raid loot lockouts expire.
+pub fn next_weekly_reset(now: u64) -> u64 {
+    if now < FIRST_WEEKLY_RESET {
+        return FIRST_WEEKLY_RESET;
+    }
+
+    let weeks_since = (now - FIRST_WEEKLY_RESET) / WEEK_SECS;
+    FIRST_WEEKLY_RESET + (weeks_since + 1) * WEEK_SECS
+}
+
+/// How a party member chose to roll on an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LootRoll {
+    /// Only allowed if the item can be used by the player's current job.
+    Need,
+    Greed,
+    Pass,
+}
+
+impl LootRoll {
+    /// Returns the roll with this name, as typed in chat.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "need" => Some(Self::Need),
+            "greed" => Some(Self::Greed),
+            "pass" => Some(Self::Pass),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the index of whoever won the item from these `rolls`, where each roll is paired with a number from 1 to 99.
+///
+/// Need always wins over Greed, and within each the highest number wins. Ties go to whoever rolled first.
+pub fn loot_winner(rolls: &[(LootRoll, u8)]) -> Option<usize> {
+    let rank = |roll: LootRoll| match roll {
+        LootRoll::Need => 2,
+        LootRoll::Greed => 1,
+        LootRoll::Pass => 0,
+    };
+
+    let mut winner: Option<usize> = None;
+    for (i, (roll, number)) in rolls.iter().enumerate() {
+        if *roll == LootRoll::Pass {
+            continue;
+        }
+
+        let beats_winner = match winner {
+            Some(winner) => {
+                let (winner_roll, winner_number) = rolls[winner];
+                (rank(*roll), *number) > (rank(winner_roll), winner_number)
+            }
+            None => true,
+        };
+        if beats_winner {
+            winner = Some(i);
+        }
+    }
+
+    winner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_need_beats_greed() {
+        let rolls = [(LootRoll::Greed, 99), (LootRoll::Need, 1)];
+        assert_eq!(loot_winner(&rolls), Some(1));
+    }
+
+    #[test]
+    fn test_highest_number_wins() {
+        let rolls = [
+            (LootRoll::Greed, 20),
+            (LootRoll::Greed, 80),
+            (LootRoll::Greed, 80),
+        ];
+        assert_eq!(loot_winner(&rolls), Some(1));
+    }
+
+    #[test]
+    fn test_next_weekly_reset() {
+        // Tuesday, October 13th 2026 08:00 UTC
+        let reset = 1_791_878_400;
+        assert_eq!(next_weekly_reset(reset - 1), reset);
+        assert_eq!(next_weekly_reset(reset), reset + WEEK_SECS);
+        assert_eq!(next_weekly_reset(reset + 3 * 86_400), reset + WEEK_SECS);
+        assert_eq!(next_weekly_reset(0), FIRST_WEEKLY_RESET);
+    }
+
+    #[test]
+    fn test_everyone_passes() {
+        let rolls = [(LootRoll::Pass, 0), (LootRoll::Pass, 0)];
+        assert_eq!(loot_winner(&rolls), None);
+        assert_eq!(loot_winner(&[]), None);
+    }
+}
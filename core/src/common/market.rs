@@ -0,0 +1,107 @@
+//! Rules for retainers, and buying and selling items on the market board.
+
+/// How many retainers a character can hire.
+pub const MAX_RETAINERS: usize = 2;
+
+/// How many items a retainer can hold, not counting what they're selling.
+pub const MAX_RETAINER_ITEMS: usize = 175;
+
+/// How many items a retainer can sell at once.
+pub const MAX_RETAINER_LISTINGS: usize = 20;
+
+/// How much gil a player can carry.
+pub const MAX_GIL: u32 = 999_999_999;
+
+/// How much gil a retainer can hold, the same as a player.
+pub const MAX_RETAINER_GIL: u32 = MAX_GIL;
+
+/// How much of every sale is taken as tax, as a percentage.
+pub const MARKET_TAX_PERCENT: u32 = 5;
+
+/// How many past sales of an item are kept, which is as many as the market board can show.
+pub const MAX_SALE_HISTORY: usize = 20;
+
+/// The longest a retainer's name can be, in characters.
+pub const RETAINER_NAME_MAX_LENGTH: usize = 20;
+
+/// What a buyer pays for a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketPrice {
+    /// Given to the seller's retainer.
+    pub proceeds: u32,
+    /// Taken on top of the proceeds, and not given to anyone.
+    pub tax: u32,
+}
+
+impl MarketPrice {
+    /// Calculates the price of buying `quantity` items at `price_per_unit`. Returns None if it costs more gil than anyone could have.
+    pub fn new(price_per_unit: u32, quantity: u32) -> Option<Self> {
+        let proceeds = price_per_unit as u64 * quantity as u64;
+        if proceeds > MAX_RETAINER_GIL as u64 {
+            return None;
+        }
+
+        let tax = proceeds * MARKET_TAX_PERCENT as u64 / 100;
+        if proceeds + tax > MAX_RETAINER_GIL as u64 {
+            return None;
+        }
+
+        Some(Self {
+            proceeds: proceeds as u32,
+            tax: tax as u32,
+        })
+    }
+
+    /// How much gil the buyer needs.
+    pub fn total(&self) -> u32 {
+        self.proceeds + self.tax
+    }
+}
+
+/// Checks whether `name` can be given to a retainer. They have to start with a letter, and can only use letters, apostrophes and hyphens.
+pub fn is_valid_retainer_name(name: &str) -> bool {
+    let length = name.chars().count();
+    if !(2..=RETAINER_NAME_MAX_LENGTH).contains(&length) {
+        return false;
+    }
+
+    name.chars().next().is_some_and(|c| c.is_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_alphabetic() || c == '\'' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_price() {
+        let price = MarketPrice::new(1000, 3).unwrap();
+        assert_eq!(price.proceeds, 3000);
+        assert_eq!(price.tax, 150);
+        assert_eq!(price.total(), 3150);
+
+        // Tax is rounded down.
+        assert_eq!(MarketPrice::new(19, 1).unwrap().tax, 0);
+        assert_eq!(MarketPrice::new(20, 1).unwrap().tax, 1);
+
+        // Nobody can afford this.
+        assert_eq!(MarketPrice::new(MAX_RETAINER_GIL, 1), None);
+        assert_eq!(MarketPrice::new(u32::MAX, u32::MAX), None);
+    }
+
+    #[test]
+    fn test_retainer_names() {
+        assert!(is_valid_retainer_name("Wymond"));
+        assert!(is_valid_retainer_name("O'Ghomoro"));
+        assert!(is_valid_retainer_name("Ul-Zahn"));
+
+        assert!(!is_valid_retainer_name(""));
+        assert!(!is_valid_retainer_name("A"));
+        assert!(!is_valid_retainer_name("-Hyphen"));
+        assert!(!is_valid_retainer_name("Two Words"));
+        assert!(!is_valid_retainer_name("R2D2"));
+        assert!(!is_valid_retainer_name("Abcdefghijklmnopqrstu"));
+    }
+}
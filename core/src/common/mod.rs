@@ -2,6 +2,7 @@
 use binrw::BinWrite;
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use strum_macros::FromRepr;
 
 mod position;
 pub use position::Position;
@@ -22,9 +23,59 @@ mod dropin;
 pub use dropin::*;
 
 mod timeline;
-pub use timeline::{Timeline, Timepoint, TimepointData};
-
-use crate::constants::{GRIDANIA_POS_START, LIMSA_POS_START, ULDAH_POS_START};
+pub use timeline::{
+    Timeline, TimelineCondition, TimelineContext, TimelinePhase, TimelineTarget, Timepoint,
+    TimepointData,
+};
+
+mod aggro;
+pub use aggro::{AggroProfile, AggroProfiles};
+
+mod aoe;
+pub use aoe::{ActionAoe, AoeOrigin, AoeShape};
+
+mod crafting;
+pub use crafting::{
+    CrafterStats, CraftingAction, CraftingCondition, CraftingError, CraftingRecipe,
+    CraftingSession, CraftingStep,
+};
+
+mod fishing;
+pub use fishing::{FishConditions, FishTug, Hookset, choose_fish};
+
+mod gathering;
+pub use gathering::{
+    EORZEAN_HOUR_SECS, GATHERING_LOG_EXP_MULTIPLIER, eorzean_hours, gathering_chance,
+    gathering_exp, gathering_point_respawn_time, hq_chance,
+};
+
+mod loot;
+pub use loot::{LOOT_ROLL_SECS, LootRoll, loot_winner, next_weekly_reset};
+
+mod market;
+pub use market::{
+    MARKET_TAX_PERCENT, MAX_GIL, MAX_RETAINER_GIL, MAX_RETAINER_ITEMS, MAX_RETAINER_LISTINGS,
+    MAX_RETAINERS, MAX_SALE_HISTORY, MarketPrice, RETAINER_NAME_MAX_LENGTH, is_valid_retainer_name,
+};
+
+mod trade;
+pub use trade::{
+    MAX_TRADE_GIL, MAX_TRADE_ITEMS, TRADE_DISTANCE, TradeError, TradeOffer, TradeSession,
+};
+
+mod damage;
+pub use damage::{
+    CombatStats, DAMAGE_VARIANCE_PERCENT, DamageRolls, Hit, LevelModifiers, calculate_damage,
+    calculate_healing, level_slopes, main_stat_baseline,
+};
+
+mod recast;
+pub use recast::{ActionTiming, GCD_COOLDOWN_GROUP, RECAST_LENIENCY, Recasts, speed_adjusted};
+
+use crate::{
+    constants::{GRIDANIA_POS_START, LIMSA_POS_START, ULDAH_POS_START},
+    ipc::zone::GameMasterRank,
+};
 
 /// First character for all of Kawari's debug commands.
 pub const DEBUG_COMMAND_TRIGGER: char = '!';
@@ -155,6 +206,9 @@ pub const ACCOUNT_MANAGEMENT_SERVICE: &str = "Kawari: Account Management";
 /// Service name for game logins. This is used to uniquely identify sessions.
 pub const GAME_SERVICE: &str = "Kawari: Game Client";
 
+/// Service name for the admin panel. This is used to uniquely identify sessions.
+pub const ADMIN_SERVICE: &str = "Kawari: Admin Panel";
+
 /// Timeout in seconds before clients are disconnected because of idle network activity.
 pub const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
 
@@ -164,16 +218,45 @@ pub const WORLD_NAME: &str = "Kawari";
 /// Maximum amount of characters that should be allowed per-service account.
 pub const MAX_CHARACTERS: usize = 8;
 
+/// Determines what a user is allowed to do in the admin panel.
+#[repr(i32)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, FromRepr,
+)]
+pub enum UserRole {
+    /// Can't access the admin panel at all.
+    #[default]
+    User = 0,
+    /// Can view the admin panel and lift lockouts, but can't change the configuration or roles.
+    Moderator = 1,
+    /// Can do everything.
+    Admin = 2,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct User {
     pub id: u32,
     pub username: String,
+    /// Whether this user is currently locked out due to too many failed logins.
+    pub locked_out: bool,
+    pub role: UserRole,
+}
+
+/// A single change made through the admin panel.
+#[derive(Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub time: String,
+    /// Username of whoever made the change.
+    pub username: String,
+    /// Human-readable description of what changed.
+    pub action: String,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct BasicCharacterData {
     pub content_id: u64,
     pub name: String,
+    pub gm_rank: GameMasterRank,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -0,0 +1,208 @@
+//! Cast and recast timers for actions, so the server doesn't have to trust the client's timing.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use super::LevelModifiers;
+
+/// The cooldown group shared by every weaponskill and spell on the global cooldown.
+pub const GCD_COOLDOWN_GROUP: u8 = 58;
+
+/// Network latency means the client can legitimately use an action slightly before we think it's ready.
+pub const RECAST_LENIENCY: Duration = Duration::from_millis(500);
+
+/// The ActionCategory for spells, which are scaled by spell speed.
+const SPELL_ACTION_CATEGORY: u8 = 2;
+
+/// An action's timing, from the Action sheet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ActionTiming {
+    /// How long the cast bar is, before any speed is taken into account.
+    pub cast_time: Duration,
+    /// How long until the action can be used again, before any speed is taken into account.
+    pub recast_time: Duration,
+    /// Actions in the same group share a recast timer. Zero if it has none.
+    pub cooldown_group: u8,
+    /// How many times the action can be used back-to-back. Zero and one both mean it has no charges.
+    pub max_charges: u8,
+    /// Index into the ActionCategory sheet.
+    pub category: u8,
+}
+
+impl ActionTiming {
+    /// Whether this action is on the global cooldown, which is shortened by speed.
+    pub fn is_gcd(&self) -> bool {
+        self.cooldown_group == GCD_COOLDOWN_GROUP
+    }
+
+    /// Whether this is a spell rather than a weaponskill or ability, which decides which speed stat it uses.
+    ///
+    /// Enemy spells are also the only casts that can be interrupted with something like Interject.
+    pub fn is_spell(&self) -> bool {
+        self.category == SPELL_ACTION_CATEGORY
+    }
+}
+
+/// Shortens a cast or recast time by skill or spell speed, and haste.
+///
+/// `haste` is a percentage of the original time, where 100 means no haste. Like the game, the result is rounded down to the hundredth of a second.
+pub fn speed_adjusted(time: Duration, speed: u32, haste: u32, level: &LevelModifiers) -> Duration {
+    let above = speed.saturating_sub(level.sub) as u64;
+    let speed_multiplier = 1000u64.saturating_sub(130 * above / level.div.max(1) as u64);
+    let haste = if haste == 0 { 100 } else { haste as u64 };
+
+    let milliseconds = time.as_millis() as u64 * speed_multiplier / 1000;
+    let milliseconds = milliseconds * haste / 100;
+
+    Duration::from_millis(milliseconds / 10 * 10)
+}
+
+/// Recast timers for each of an actor's cooldown groups.
+#[derive(Debug, Default, Clone)]
+pub struct Recasts {
+    /// When each group has all of its charges back, along with the recast time of its last use.
+    groups: HashMap<u8, (Instant, Duration)>,
+}
+
+impl Recasts {
+    /// Whether an action in this group can be used at `now`, i.e. it has at least one charge left.
+    pub fn is_ready(&self, cooldown_group: u8, max_charges: u8, now: Instant) -> bool {
+        let Some((ready_at, recast_time)) = self.groups.get(&cooldown_group) else {
+            return true;
+        };
+
+        let remaining = ready_at
+            .saturating_duration_since(now)
+            .saturating_sub(RECAST_LENIENCY);
+        remaining <= *recast_time * (max_charges.max(1) as u32 - 1)
+    }
+
+    /// Uses up a charge in this group, which comes back after `recast_time`.
+    pub fn start(&mut self, cooldown_group: u8, recast_time: Duration, now: Instant) {
+        if cooldown_group == 0 || recast_time.is_zero() {
+            return;
+        }
+
+        let ready_at = self
+            .groups
+            .get(&cooldown_group)
+            .map(|(ready_at, _)| *ready_at)
+            .filter(|ready_at| *ready_at > now)
+            .unwrap_or(now);
+        self.groups
+            .insert(cooldown_group, (ready_at + recast_time, recast_time));
+    }
+
+    /// Gives back the charge that was last used in this group, e.g. when its cast was cancelled.
+    pub fn refund(&mut self, cooldown_group: u8) {
+        if let Some((ready_at, recast_time)) = self.groups.get_mut(&cooldown_group) {
+            *ready_at = ready_at.checked_sub(*recast_time).unwrap_or(*ready_at);
+        }
+    }
+
+    /// How long until this group has all of its charges back.
+    pub fn remaining(&self, cooldown_group: u8, now: Instant) -> Duration {
+        self.groups
+            .get(&cooldown_group)
+            .map(|(ready_at, _)| ready_at.saturating_duration_since(now))
+            .unwrap_or_default()
+    }
+
+    /// Resets every recast timer.
+    pub fn clear(&mut self) {
+        self.groups.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL_90: LevelModifiers = LevelModifiers {
+        main: 390,
+        sub: 400,
+        div: 1900,
+        attack_power_slope: 195,
+        healing_power_slope: 569,
+        healing_power_divisor: 1522,
+    };
+
+    #[test]
+    fn test_speed_adjusted() {
+        let gcd = Duration::from_millis(2500);
+
+        // No speed above the baseline changes nothing.
+        assert_eq!(speed_adjusted(gcd, 400, 100, &LEVEL_90), gcd);
+        assert_eq!(speed_adjusted(gcd, 0, 100, &LEVEL_90), gcd);
+
+        // The well-known 2.50 -> 2.40 breakpoint.
+        assert_eq!(
+            speed_adjusted(gcd, 400 + 585, 100, &LEVEL_90),
+            Duration::from_millis(2400)
+        );
+
+        // Haste is applied on top, e.g. a 13% haste buff.
+        assert_eq!(
+            speed_adjusted(gcd, 400, 87, &LEVEL_90),
+            Duration::from_millis(2170)
+        );
+
+        // Zero haste is treated as none at all, as NPCs and unset parameters don't have it.
+        assert_eq!(speed_adjusted(gcd, 400, 0, &LEVEL_90), gcd);
+    }
+
+    #[test]
+    fn test_recasts() {
+        let now = Instant::now();
+        let recast = Duration::from_secs(30);
+        let mut recasts = Recasts::default();
+
+        // Nothing has been used yet.
+        assert!(recasts.is_ready(1, 0, now));
+        assert_eq!(recasts.remaining(1, now), Duration::ZERO);
+
+        recasts.start(1, recast, now);
+        assert!(!recasts.is_ready(1, 0, now));
+        assert!(!recasts.is_ready(1, 0, now + Duration::from_secs(29)));
+        assert_eq!(recasts.remaining(1, now), recast);
+
+        // Other groups aren't affected.
+        assert!(recasts.is_ready(2, 0, now));
+
+        // A little early is fine, to account for latency.
+        assert!(recasts.is_ready(1, 0, now + recast - RECAST_LENIENCY));
+        assert!(recasts.is_ready(1, 0, now + recast));
+
+        // Cancelling the cast gives the charge back.
+        recasts.refund(1);
+        assert!(recasts.is_ready(1, 0, now));
+
+        // Actions without a group never have a recast.
+        recasts.start(0, recast, now);
+        assert!(recasts.is_ready(0, 0, now));
+
+        recasts.start(1, recast, now);
+        recasts.clear();
+        assert!(recasts.is_ready(1, 0, now));
+    }
+
+    #[test]
+    fn test_recast_charges() {
+        let now = Instant::now();
+        let recast = Duration::from_secs(60);
+        let mut recasts = Recasts::default();
+
+        // Two charges can be used back-to-back, but not a third.
+        recasts.start(1, recast, now);
+        assert!(recasts.is_ready(1, 2, now));
+        recasts.start(1, recast, now);
+        assert!(!recasts.is_ready(1, 2, now));
+        assert_eq!(recasts.remaining(1, now), recast * 2);
+
+        // One charge comes back after a single recast.
+        assert!(recasts.is_ready(1, 2, now + recast));
+        assert!(!recasts.is_ready(1, 2, now + recast / 2));
+    }
+}
@@ -1,16 +1,22 @@
 use serde::{Deserialize, Serialize};
 
-/// A JSON file that appends an existing (and usually empty) LGB.
+/// A JSON file that describes what a BattleNPC does in combat.
 ///
-/// These can only add gathering points for now.
+/// Simple enemies only need a flat list of timepoints, while bosses can be split into HP-based phases.
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
 pub struct Timeline {
     /// Which action is used for the auto-attack. Index into the Action Excel sheet.
     pub autoattack_action_id: u32,
     /// Whether the timeline always plays.
     pub timeline_always_plays: bool,
-    /// The timeline points.
+    /// The timeline points, played until the first phase begins.
     pub timepoints: Vec<Timepoint>,
+    /// The time in seconds to jump back to once the end of the timeline points is reached.
+    #[serde(default)]
+    pub loop_start: i32,
+    /// Phases that replace the timeline points once HP drops low enough.
+    #[serde(default)]
+    pub phases: Vec<TimelinePhase>,
     /// A series of actions (to play in sequence) on death.
     #[serde(default)]
     pub on_death: Vec<TimepointData>,
@@ -19,18 +25,63 @@ pub struct Timeline {
 impl Timeline {
     /// Duration of the entire timeline in seconds.
     pub fn duration(&self) -> i32 {
-        let mut duration = 0;
-        for point in &self.timepoints {
-            duration = duration.max(point.time);
-        }
-
-        duration
+        section_duration(&self.timepoints)
     }
 
     /// Returns all points happening at the specified time, if any.
     pub fn points_at(&self, point: i32) -> Vec<&Timepoint> {
         self.timepoints.iter().filter(|x| x.time == point).collect()
     }
+
+    /// Returns the index of the phase that should be playing at `hp_percent`, or None if it's still the opening timeline points.
+    ///
+    /// If multiple phases could apply, the one with the lowest HP threshold wins.
+    pub fn phase_at(&self, hp_percent: u8) -> Option<usize> {
+        self.phases
+            .iter()
+            .enumerate()
+            .filter(|(_, phase)| hp_percent < phase.hp_below)
+            .min_by_key(|(_, phase)| phase.hp_below)
+            .map(|(i, _)| i)
+    }
+
+    /// Returns all points happening `seconds` into `phase`, taking looping into account.
+    pub fn phase_points_at(&self, phase: Option<usize>, seconds: i32) -> Vec<&Timepoint> {
+        let (timepoints, loop_start) = match phase.and_then(|i| self.phases.get(i)) {
+            Some(phase) => (&phase.timepoints, phase.loop_start),
+            None => (&self.timepoints, self.loop_start),
+        };
+
+        if timepoints.is_empty() {
+            return Vec::new();
+        }
+
+        let duration = section_duration(timepoints);
+        let loop_start = loop_start.clamp(0, duration);
+        let position = if seconds < loop_start {
+            seconds
+        } else {
+            loop_start + (seconds - loop_start) % (duration - loop_start + 1)
+        };
+
+        timepoints.iter().filter(|x| x.time == position).collect()
+    }
+}
+
+fn section_duration(timepoints: &[Timepoint]) -> i32 {
+    timepoints.iter().map(|point| point.time).max().unwrap_or(0)
+}
+
+/// A section of the timeline that starts once HP drops below a certain percentage.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct TimelinePhase {
+    /// This phase begins once HP drops below this percentage.
+    pub hp_below: u8,
+    /// The timeline points, where zero is when this phase began.
+    pub timepoints: Vec<Timepoint>,
+    /// The time in seconds to jump back to once the end of the timeline points is reached.
+    #[serde(default)]
+    pub loop_start: i32,
 }
 
 /// Represents a point on the timeline.
@@ -48,6 +99,9 @@ pub enum TimepointData {
     Action {
         /// Index into the Action Excel sheet.
         action_id: u32,
+        /// Who the action is used on.
+        #[serde(default)]
+        target: TimelineTarget,
     },
     /// Animates timelines for the gimmick this actor is bound to, such as Giant Clams.
     #[serde(rename = "timeline_state")]
@@ -55,12 +109,140 @@ pub enum TimepointData {
     /// Changes the invulnerability state of this NPC.
     #[serde(rename = "invulnerability")]
     Invulnerability { invulnerable: bool },
+    /// Picks one of the groups at random, and does everything in it.
+    #[serde(rename = "random")]
+    Random { choices: Vec<Vec<TimepointData>> },
+    /// Does one group or the other, depending on the condition.
+    #[serde(rename = "branch")]
+    Branch {
+        condition: TimelineCondition,
+        then: Vec<TimepointData>,
+        #[serde(default)]
+        otherwise: Vec<TimepointData>,
+    },
+    /// Spawns additional BattleNPCs from the zone's layout, which join the fight.
+    #[serde(rename = "spawn_adds")]
+    SpawnAdds {
+        /// Instance IDs of the BattleNPCs in the zone's layout.
+        layout_ids: Vec<u32>,
+    },
+    /// Calls `onTimelineEvent` in the content's Lua director.
+    #[serde(rename = "director_event")]
+    DirectorEvent { event: String },
+}
+
+impl TimepointData {
+    /// Resolves any branches or random choices, and returns what should actually happen.
+    ///
+    /// `choose` is given the number of choices, and must return an index below that.
+    pub fn resolve<'a>(
+        &'a self,
+        context: &TimelineContext,
+        choose: &mut impl FnMut(usize) -> usize,
+    ) -> Vec<&'a TimepointData> {
+        match self {
+            TimepointData::Random { choices } => {
+                if choices.is_empty() {
+                    return Vec::new();
+                }
+
+                choices[choose(choices.len())]
+                    .iter()
+                    .flat_map(|data| data.resolve(context, choose))
+                    .collect()
+            }
+            TimepointData::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let group = if condition.is_met(context) {
+                    then
+                } else {
+                    otherwise
+                };
+
+                group
+                    .iter()
+                    .flat_map(|data| data.resolve(context, choose))
+                    .collect()
+            }
+            _ => vec![self],
+        }
+    }
+}
+
+/// Who a timeline action is used on.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineTarget {
+    /// The current target, who is the top of the enmity list.
+    #[default]
+    Tank,
+    /// Anyone on the enmity list.
+    Random,
+    /// Whoever on the enmity list is furthest away.
+    Furthest,
+}
+
+/// A condition checked by branches in the timeline.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum TimelineCondition {
+    /// HP is below this percentage.
+    #[serde(rename = "hp_below")]
+    HpBelow { percent: u8 },
+    /// HP is at or above this percentage.
+    #[serde(rename = "hp_above")]
+    HpAbove { percent: u8 },
+    /// There's at least this many actors on the enmity list.
+    #[serde(rename = "targets_at_least")]
+    TargetsAtLeast { count: usize },
+}
+
+impl TimelineCondition {
+    pub fn is_met(&self, context: &TimelineContext) -> bool {
+        match self {
+            TimelineCondition::HpBelow { percent } => context.hp_percent < *percent,
+            TimelineCondition::HpAbove { percent } => context.hp_percent >= *percent,
+            TimelineCondition::TargetsAtLeast { count } => context.num_targets >= *count,
+        }
+    }
+}
+
+/// The state of the NPC, used to check conditions.
+#[derive(Debug, Clone, Default)]
+pub struct TimelineContext {
+    /// Current HP, out of 100.
+    pub hp_percent: u8,
+    /// How many actors are on the enmity list.
+    pub num_targets: usize,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn action(time: i32, action_id: u32) -> Timepoint {
+        Timepoint {
+            time,
+            data: TimepointData::Action {
+                action_id,
+                target: TimelineTarget::Tank,
+            },
+        }
+    }
+
+    fn action_ids(points: Vec<&Timepoint>) -> Vec<u32> {
+        points
+            .iter()
+            .map(|point| match point.data {
+                TimepointData::Action { action_id, .. } => action_id,
+                _ => 0,
+            })
+            .collect()
+    }
+
     #[test]
     fn test_simple_example() {
         let json =
@@ -73,11 +255,110 @@ mod tests {
                 autoattack_action_id: 872,
                 timeline_always_plays: false,
                 on_death: Vec::default(),
-                timepoints: vec![Timepoint {
-                    time: 20,
-                    data: TimepointData::Action { action_id: 872 }
-                }]
+                loop_start: 0,
+                phases: Vec::default(),
+                timepoints: vec![action(20, 872)]
             }
         );
+
+        assert_eq!(action_ids(timeline.points_at(20)), vec![872]);
+        assert!(timeline.points_at(19).is_empty());
+    }
+
+    #[test]
+    fn test_boss_example() {
+        let json =
+            std::fs::read_to_string("../resources/data/tests/example_boss_timeline.json").unwrap();
+        let timeline: Timeline = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(timeline.phases.len(), 2);
+        assert_eq!(
+            timeline.phases[1].timepoints[1].data,
+            TimepointData::Branch {
+                condition: TimelineCondition::TargetsAtLeast { count: 2 },
+                then: vec![TimepointData::Action {
+                    action_id: 30,
+                    target: TimelineTarget::Furthest
+                }],
+                otherwise: vec![TimepointData::Action {
+                    action_id: 31,
+                    target: TimelineTarget::Tank
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn test_looping() {
+        let timeline = Timeline {
+            autoattack_action_id: 0,
+            timeline_always_plays: false,
+            on_death: Vec::default(),
+            loop_start: 5,
+            phases: Vec::default(),
+            timepoints: vec![action(2, 1), action(5, 2), action(10, 3)],
+        };
+
+        assert_eq!(action_ids(timeline.phase_points_at(None, 2)), vec![1]);
+        assert_eq!(action_ids(timeline.phase_points_at(None, 10)), vec![3]);
+        // Loops back to 5, skipping the opener
+        assert_eq!(action_ids(timeline.phase_points_at(None, 11)), vec![2]);
+        assert!(timeline.phase_points_at(None, 13).is_empty());
+        assert_eq!(action_ids(timeline.phase_points_at(None, 16)), vec![3]);
+    }
+
+    #[test]
+    fn test_phases() {
+        let json =
+            std::fs::read_to_string("../resources/data/tests/example_boss_timeline.json").unwrap();
+        let timeline: Timeline = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(timeline.phase_at(100), None);
+        assert_eq!(timeline.phase_at(75), None);
+        assert_eq!(timeline.phase_at(74), Some(0));
+        assert_eq!(timeline.phase_at(20), Some(1));
+        assert_eq!(timeline.phase_at(0), Some(1));
+
+        // Phases start over from zero
+        assert_eq!(action_ids(timeline.phase_points_at(Some(1), 0)), vec![20]);
+        // Unknown phases fall back to the opening timeline points
+        assert_eq!(action_ids(timeline.phase_points_at(Some(5), 3)), vec![1]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let data = TimepointData::Random {
+            choices: vec![
+                vec![TimepointData::Invulnerability { invulnerable: true }],
+                vec![TimepointData::Branch {
+                    condition: TimelineCondition::HpBelow { percent: 50 },
+                    then: vec![TimepointData::DirectorEvent {
+                        event: "enrage".to_string(),
+                    }],
+                    otherwise: Vec::default(),
+                }],
+            ],
+        };
+
+        let healthy = TimelineContext {
+            hp_percent: 80,
+            num_targets: 1,
+        };
+        let hurt = TimelineContext {
+            hp_percent: 30,
+            num_targets: 1,
+        };
+
+        assert_eq!(
+            data.resolve(&healthy, &mut |_| 0),
+            vec![&TimepointData::Invulnerability { invulnerable: true }]
+        );
+        assert!(data.resolve(&healthy, &mut |_| 1).is_empty());
+        assert_eq!(
+            data.resolve(&hurt, &mut |_| 1),
+            vec![&TimepointData::DirectorEvent {
+                event: "enrage".to_string()
+            }]
+        );
     }
 }
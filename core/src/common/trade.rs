@@ -0,0 +1,223 @@
+//! Rules for trading items and gil between two players.
+
+use crate::common::ObjectId;
+
+/// How many item stacks each player can offer in a trade.
+pub const MAX_TRADE_ITEMS: usize = 5;
+
+/// How much gil each player can offer in a trade.
+pub const MAX_TRADE_GIL: u32 = 1_000_000;
+
+/// How close two players have to be to trade, in yalms.
+pub const TRADE_DISTANCE: f32 = 6.0;
+
+/// Why an offer couldn't be changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeError {
+    /// There's no room for another item stack.
+    TooManyItems,
+    /// More gil than `MAX_TRADE_GIL` was offered.
+    TooMuchGil,
+    /// Both players have confirmed, so the trade can't be changed anymore.
+    Locked,
+}
+
+/// What one player is giving to the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeOffer<T> {
+    pub items: Vec<T>,
+    pub gil: u32,
+}
+
+impl<T> Default for TradeOffer<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            gil: 0,
+        }
+    }
+}
+
+impl<T> TradeOffer<T> {
+    /// Adds another item stack to the offer.
+    pub fn add_item(&mut self, item: T) -> Result<(), TradeError> {
+        if self.items.len() >= MAX_TRADE_ITEMS {
+            return Err(TradeError::TooManyItems);
+        }
+
+        self.items.push(item);
+        Ok(())
+    }
+
+    /// Changes how much gil is offered.
+    pub fn set_gil(&mut self, gil: u32) -> Result<(), TradeError> {
+        if gil > MAX_TRADE_GIL {
+            return Err(TradeError::TooMuchGil);
+        }
+
+        self.gil = gil;
+        Ok(())
+    }
+
+    /// Whether nothing is being offered.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.gil == 0
+    }
+}
+
+/// A trade between two players, from opening the window until the items are exchanged.
+///
+/// Both players have to confirm the same offers before anything is exchanged, and changing either offer takes back both confirmations.
+/// Once both have confirmed, each player has to have room for what they're getting.
+#[derive(Debug, Clone)]
+pub struct TradeSession<T> {
+    pub traders: [ObjectId; 2],
+    pub offers: [TradeOffer<T>; 2],
+    confirmed: [bool; 2],
+    prepared: [Option<bool>; 2],
+}
+
+impl<T> TradeSession<T> {
+    pub fn new(first: ObjectId, second: ObjectId) -> Self {
+        Self {
+            traders: [first, second],
+            offers: [TradeOffer::default(), TradeOffer::default()],
+            confirmed: [false; 2],
+            prepared: [None; 2],
+        }
+    }
+
+    /// Returns which side of the trade `actor_id` is on.
+    pub fn side(&self, actor_id: ObjectId) -> Option<usize> {
+        self.traders.iter().position(|id| *id == actor_id)
+    }
+
+    /// Returns who `actor_id` is trading with.
+    pub fn partner(&self, actor_id: ObjectId) -> Option<ObjectId> {
+        self.side(actor_id).map(|side| self.traders[1 - side])
+    }
+
+    /// Whether both players have confirmed, and the offers can no longer change.
+    pub fn is_locked(&self) -> bool {
+        self.confirmed.iter().all(|confirmed| *confirmed)
+    }
+
+    /// Replaces what `actor_id` is offering, and takes back both confirmations. Offers that break the rules are turned down.
+    pub fn set_offer(
+        &mut self,
+        actor_id: ObjectId,
+        offer: TradeOffer<T>,
+    ) -> Result<(), TradeError> {
+        let Some(side) = self.side(actor_id) else {
+            return Ok(());
+        };
+
+        if self.is_locked() {
+            return Err(TradeError::Locked);
+        }
+
+        if offer.items.len() > MAX_TRADE_ITEMS {
+            return Err(TradeError::TooManyItems);
+        }
+
+        if offer.gil > MAX_TRADE_GIL {
+            return Err(TradeError::TooMuchGil);
+        }
+
+        self.offers[side] = offer;
+        self.confirmed = [false; 2];
+        Ok(())
+    }
+
+    /// Confirms the trade on behalf of `actor_id`. Returns true once both players have confirmed.
+    pub fn confirm(&mut self, actor_id: ObjectId) -> bool {
+        if let Some(side) = self.side(actor_id) {
+            self.confirmed[side] = true;
+        }
+
+        self.is_locked()
+    }
+
+    /// Records whether `actor_id` was able to make the exchange. Returns whether the trade succeeded once both players have answered.
+    pub fn prepare(&mut self, actor_id: ObjectId, success: bool) -> Option<bool> {
+        let side = self.side(actor_id)?;
+        self.prepared[side] = Some(success);
+
+        match self.prepared {
+            [Some(first), Some(second)] => Some(first && second),
+            [Some(false), None] | [None, Some(false)] => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: ObjectId = ObjectId(1);
+    const SECOND: ObjectId = ObjectId(2);
+
+    #[test]
+    fn test_trade_offer() {
+        let mut offer = TradeOffer::default();
+        assert!(offer.is_empty());
+
+        for item_id in 0..MAX_TRADE_ITEMS as u32 {
+            assert_eq!(offer.add_item(item_id), Ok(()));
+        }
+        assert_eq!(offer.add_item(100), Err(TradeError::TooManyItems));
+
+        assert_eq!(offer.set_gil(MAX_TRADE_GIL), Ok(()));
+        assert_eq!(
+            offer.set_gil(MAX_TRADE_GIL + 1),
+            Err(TradeError::TooMuchGil)
+        );
+        assert_eq!(offer.gil, MAX_TRADE_GIL);
+    }
+
+    #[test]
+    fn test_trade_confirmation() {
+        let mut trade = TradeSession::new(FIRST, SECOND);
+        assert_eq!(trade.partner(FIRST), Some(SECOND));
+        assert_eq!(trade.partner(SECOND), Some(FIRST));
+        assert_eq!(trade.partner(ObjectId(3)), None);
+
+        let mut offer = TradeOffer::default();
+        offer.add_item(5).unwrap();
+        trade.set_offer(FIRST, offer.clone()).unwrap();
+
+        let too_generous = TradeOffer {
+            items: Vec::new(),
+            gil: MAX_TRADE_GIL + 1,
+        };
+        assert_eq!(
+            trade.set_offer(SECOND, too_generous),
+            Err(TradeError::TooMuchGil)
+        );
+
+        // Changing an offer takes back both confirmations.
+        assert!(!trade.confirm(FIRST));
+        trade.set_offer(SECOND, TradeOffer::default()).unwrap();
+        assert!(!trade.confirm(SECOND));
+        assert!(trade.confirm(FIRST));
+
+        // Nothing can change after both have confirmed.
+        assert_eq!(trade.set_offer(FIRST, offer), Err(TradeError::Locked));
+    }
+
+    #[test]
+    fn test_trade_preparation() {
+        let mut trade: TradeSession<u32> = TradeSession::new(FIRST, SECOND);
+        assert_eq!(trade.prepare(FIRST, true), None);
+        assert_eq!(trade.prepare(SECOND, true), Some(true));
+
+        // One side running out of space fails the whole trade, without waiting for the other.
+        let mut trade: TradeSession<u32> = TradeSession::new(FIRST, SECOND);
+        assert_eq!(trade.prepare(SECOND, false), Some(false));
+
+        let mut trade: TradeSession<u32> = TradeSession::new(FIRST, SECOND);
+        assert_eq!(trade.prepare(FIRST, true), None);
+        assert_eq!(trade.prepare(SECOND, false), Some(false));
+    }
+}
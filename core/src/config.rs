@@ -1,23 +1,32 @@
 use std::{
     net::{IpAddr, SocketAddr},
     str::FromStr,
+    sync::RwLock,
+    time::SystemTime,
 };
 
 use physis::Language;
 use serde::{Deserialize, Serialize};
 
+use crate::ipc::zone::GameMasterRank;
+
 fn default_listen_address() -> String {
     "0.0.0.0".to_string()
 }
 
 /// Configuration for the admin server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AdminConfig {
     #[serde(default = "AdminConfig::default_port")]
     pub port: u16,
 
     #[serde(default = "default_listen_address")]
     pub listen_address: String,
+
+    /// Username that is made an admin when the login server starts, but only if there are no admins yet.
+    /// Use this to give yourself access to the admin panel for the first time.
+    #[serde(default)]
+    pub bootstrap_admin: String,
 }
 
 impl Default for AdminConfig {
@@ -25,6 +34,7 @@ impl Default for AdminConfig {
         Self {
             port: Self::default_port(),
             listen_address: default_listen_address(),
+            bootstrap_admin: String::new(),
         }
     }
 }
@@ -44,7 +54,7 @@ impl AdminConfig {
 }
 
 /// Configuration for the frontier server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FrontierConfig {
     #[serde(default = "FrontierConfig::default_port")]
     pub port: u16,
@@ -101,7 +111,7 @@ impl FrontierConfig {
 }
 
 /// Configuration for the lobby server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LobbyConfig {
     #[serde(default = "LobbyConfig::default_port")]
     pub port: u16,
@@ -142,7 +152,7 @@ impl LobbyConfig {
 }
 
 /// Configuration for the login server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LoginConfig {
     #[serde(default = "LoginConfig::default_port")]
     pub port: u16,
@@ -197,7 +207,7 @@ impl LoginConfig {
 }
 
 /// Configuration for the patch server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PatchConfig {
     #[serde(default = "PatchConfig::default_port")]
     pub port: u16,
@@ -265,7 +275,7 @@ impl PatchConfig {
 }
 
 /// Configuration for the web server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WebConfig {
     #[serde(default = "WebConfig::default_port")]
     pub port: u16,
@@ -307,7 +317,7 @@ impl WebConfig {
 }
 
 /// Configuration for the world server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WorldConfig {
     #[serde(default = "WorldConfig::default_port")]
     pub port: u16,
@@ -356,6 +366,10 @@ pub struct WorldConfig {
     /// The language to read game data as, should have no effect on regular gameplay but definitely does affect a lot of debug/GM commands.
     #[serde(default = "WorldConfig::default_language")]
     pub language: String,
+
+    /// The GM rank given to newly created characters. Anything above `NormalUser` allows using debug and GM commands, so only development setups should raise this.
+    #[serde(default = "WorldConfig::default_gm_rank")]
+    pub default_gm_rank: GameMasterRank,
 }
 
 impl Default for WorldConfig {
@@ -374,6 +388,7 @@ impl Default for WorldConfig {
             accept_new_characters: Self::default_accept_new_characters(),
             exp_bonus: Self::default_exp_bonus(),
             language: Self::default_language(),
+            default_gm_rank: Self::default_gm_rank(),
         }
     }
 }
@@ -427,6 +442,10 @@ impl WorldConfig {
         "en".to_string()
     }
 
+    fn default_gm_rank() -> GameMasterRank {
+        GameMasterRank::NormalUser
+    }
+
     pub fn language(&self) -> Language {
         Language::from_shortname(self.language.as_str())
     }
@@ -440,17 +459,10 @@ impl WorldConfig {
             self.port,
         ))
     }
-
-    pub fn get_public_socketaddr(&self) -> SocketAddr {
-        SocketAddr::from((
-            IpAddr::from_str(&self.server_name).expect("Invalid IP address format in config!"),
-            self.port,
-        ))
-    }
 }
 
 /// Configuration for the launcher server.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LauncherConfig {
     #[serde(default = "LauncherConfig::default_port")]
     pub port: u16,
@@ -491,7 +503,7 @@ impl LauncherConfig {
 }
 
 /// Configuration for the game filesystem.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct FilesystemConfig {
     /// Path to the game directory. For example, "C:\Program Files (x86)\SquareEnix\FINAL FANTASY XIV - A Realm Reborn\game".
     #[serde(default)]
@@ -515,7 +527,7 @@ pub struct FilesystemConfig {
     ///
     /// These are ordered from highest-to-lowest, and these are always preferred over our own resource files.
     ///
-    /// Note that drop-ins and timelines are *not* combined. Web templates do not respect this option.
+    /// Note that drop-ins, timelines, aggro profiles and fish conditions are *not* combined. Web templates do not respect this option.
     #[serde(default)]
     pub additional_resource_paths: Vec<String>,
 }
@@ -552,10 +564,54 @@ impl FilesystemConfig {
 
         format!("resources/timelines/{path}")
     }
+
+    /// Locates an aggro profile file and returns its path, taking into account additional search paths.
+    ///
+    /// This is infallible as it will always return our built-in path.
+    pub fn locate_aggro_file(&self, path: &str) -> String {
+        for search_path in &self.additional_resource_paths {
+            let file_name = format!("{search_path}/aggro/{path}");
+            if std::fs::exists(&file_name).unwrap_or_default() {
+                return file_name;
+            }
+        }
+
+        format!("resources/aggro/{path}")
+    }
+
+    /// Locates the file in `folder` that's meant for this id, e.g. "SastashaOrobon_1040.json", taking into account additional search paths.
+    pub fn locate_id_file(&self, folder: &str, id: u32) -> Option<String> {
+        let mut search_dirs: Vec<String> = self
+            .additional_resource_paths
+            .iter()
+            .map(|x| format!("{x}/{folder}/"))
+            .collect();
+        search_dirs.push(format!("resources/{folder}/"));
+
+        let suffix = format!("_{id}.json");
+        for search_dir in search_dirs {
+            let Ok(entries) = std::fs::read_dir(search_dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                if entry
+                    .file_name()
+                    .to_str()
+                    .unwrap_or_default()
+                    .ends_with(&suffix)
+                {
+                    return Some(entry.path().to_string_lossy().to_string());
+                }
+            }
+        }
+
+        None
+    }
 }
 
 /// Configuration for various tweaks.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TweaksConfig {
     /// If true, always the player to skip cutscenes marked as unskippable.
     #[serde(default)]
@@ -583,7 +639,7 @@ impl TweaksConfig {
 
 /// Global and all-encompassing config.
 /// Settings that affect all servers belong here.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
     pub filesystem: FilesystemConfig,
@@ -616,10 +672,180 @@ pub struct Config {
     pub tweaks: TweaksConfig,
 }
 
+/// Location of the config file, relative to the working directory.
+const CONFIG_PATH: &str = "config.yaml";
+
+/// The last valid config that was loaded, along with the modification time of the file it came from.
+static CACHED_CONFIG: RwLock<Option<(Option<SystemTime>, Config)>> = RwLock::new(None);
+
+/// Returns the current config, only re-reading the config file if it changed since it was last read.
+///
+/// If the file is changed to something invalid, the last valid config stays in effect so a bad edit can't take down a running server.
 pub fn get_config() -> Config {
-    if let Ok(data) = std::fs::read_to_string("config.yaml") {
-        serde_yaml_ng::from_str(&data).expect("Failed to parse")
-    } else {
-        Config::default()
+    let modified = config_modified_time();
+    if let Some((last_modified, config)) = &*CACHED_CONFIG.read().unwrap()
+        && *last_modified == modified
+    {
+        return config.clone();
+    }
+
+    let mut cached = CACHED_CONFIG.write().unwrap();
+    match load_config() {
+        Ok(config) => {
+            *cached = Some((modified, config.clone()));
+            config
+        }
+        Err(err) => {
+            // There's nothing to fall back to when starting up.
+            let Some((last_modified, config)) = cached.as_mut() else {
+                panic!("{err}");
+            };
+
+            tracing::warn!("Ignoring changes to {CONFIG_PATH}: {err}");
+            *last_modified = modified;
+            config.clone()
+        }
+    }
+}
+
+/// Returns when the config file was last modified, if it exists.
+fn config_modified_time() -> Option<SystemTime> {
+    std::fs::metadata(CONFIG_PATH)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Reasons why a config can't be loaded or applied.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file exists, but couldn't be parsed.
+    ParseError(String),
+    /// An address field (named by the first value) has an invalid value.
+    InvalidAddress(&'static str, String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::ParseError(err) => write!(f, "Failed to parse config: {err}"),
+            ConfigError::InvalidAddress(field, value) => {
+                write!(f, "{field} has an invalid IP address: {value}")
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Checks for values that would otherwise cause a server to panic later on.
+    ///
+    /// Only the listen addresses have to be IP addresses, anything that's connected to can also be a hostname.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let addresses = [
+            ("admin.listen_address", &self.admin.listen_address),
+            ("frontier.listen_address", &self.frontier.listen_address),
+            ("lobby.listen_address", &self.lobby.listen_address),
+            ("login.listen_address", &self.login.listen_address),
+            ("patch.listen_address", &self.patch.listen_address),
+            ("web.listen_address", &self.web.listen_address),
+            ("world.listen_address", &self.world.listen_address),
+            ("launcher.listen_address", &self.launcher.listen_address),
+        ];
+
+        for (field, address) in addresses {
+            if IpAddr::from_str(address).is_err() {
+                return Err(ConfigError::InvalidAddress(field, address.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads and validates the config, without panicking if something is wrong with it.
+///
+/// Like `get_config`, the default config is returned if there is no config file.
+pub fn load_config() -> Result<Config, ConfigError> {
+    let config: Config = match std::fs::read_to_string(CONFIG_PATH) {
+        Ok(data) => serde_yaml_ng::from_str(&data)
+            .map_err(|err| ConfigError::ParseError(err.to_string()))?,
+        Err(_) => Config::default(),
+    };
+
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// Validates `config` and then writes it to the config file.
+///
+/// Servers that call `get_config` pick this up automatically, see `ConfigWatcher` for the rest.
+pub fn save_config(config: &Config) -> Result<(), ConfigError> {
+    config.validate()?;
+
+    let data =
+        serde_yaml_ng::to_string(config).map_err(|err| ConfigError::ParseError(err.to_string()))?;
+    std::fs::write(CONFIG_PATH, data).map_err(|err| ConfigError::ParseError(err.to_string()))
+}
+
+/// Watches the config file for changes, for servers that need to react to them.
+#[derive(Default)]
+pub struct ConfigWatcher {
+    last_modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    /// Creates a new watcher, which considers the current config file as already seen.
+    pub fn new() -> Self {
+        Self {
+            last_modified: config_modified_time(),
+        }
+    }
+
+    /// Returns the new config if the file changed since the last poll.
+    ///
+    /// Invalid configs are logged and ignored, so the previous config stays in effect.
+    pub fn poll(&mut self) -> Option<Config> {
+        let modified = config_modified_time();
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+
+        match load_config() {
+            Ok(config) => Some(config),
+            Err(err) => {
+                tracing::warn!("Ignoring changes to {CONFIG_PATH}: {err}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_default() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_address() {
+        let mut config = Config::default();
+        config.world.listen_address = "localhost".to_string();
+
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::InvalidAddress("world.listen_address", _))
+        ));
+    }
+
+    #[test]
+    fn validate_hostname() {
+        let mut config = Config::default();
+        config.world.server_name = "world.example.com".to_string();
+
+        assert!(config.validate().is_ok());
     }
 }
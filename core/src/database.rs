@@ -0,0 +1,116 @@
+use diesel::{
+    Connection, RunQueryDsl, SqliteConnection, migration::MigrationSource, sql_types::Text,
+    sqlite::Sqlite,
+};
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness};
+
+/// Pass this argument to a server to check its database migrations, without changing anything.
+pub const CHECK_MIGRATIONS_ARG: &str = "--check-migrations";
+
+/// Older databases were created with a single, unversioned migration that was recorded under this version.
+const LEGACY_MIGRATION_VERSION: &str = "current";
+
+/// Applies any pending migrations, and returns the new schema version.
+pub fn run_migrations(connection: &mut SqliteConnection, migrations: EmbeddedMigrations) -> String {
+    adopt_legacy_schema(connection, &migrations);
+
+    let applied = connection
+        .run_pending_migrations(migrations)
+        .expect("Failed to migrate the database!");
+    for version in applied {
+        tracing::info!("Applied database migration {version}");
+    }
+
+    let version = schema_version(connection);
+    tracing::info!("Database schema is at version {version}");
+
+    version
+}
+
+/// Tries to apply any pending migrations, but rolls everything back afterwards. Returns true if they would apply cleanly.
+pub fn check_migrations(connection: &mut SqliteConnection, migrations: EmbeddedMigrations) -> bool {
+    let mut succeeded = false;
+    let _ = connection.transaction::<(), diesel::result::Error, _>(|connection| {
+        tracing::info!(
+            "Database schema is at version {}",
+            schema_version(connection)
+        );
+
+        adopt_legacy_schema(connection, &migrations);
+
+        match connection.run_pending_migrations(migrations) {
+            Ok(versions) if versions.is_empty() => {
+                tracing::info!("The database is up to date.");
+                succeeded = true;
+            }
+            Ok(versions) => {
+                for version in versions {
+                    tracing::info!("Migration {version} would be applied");
+                }
+                succeeded = true;
+            }
+            Err(err) => tracing::error!("Migrations would fail to apply: {err}"),
+        }
+
+        // Never actually keep any of the changes.
+        Err(diesel::result::Error::RollbackTransaction)
+    });
+
+    succeeded
+}
+
+/// Like `check_migrations`, but for the database at `path`. If it doesn't exist yet, that's reported instead of creating it.
+pub fn check_database_migrations(path: &str, migrations: EmbeddedMigrations) -> bool {
+    if !std::path::Path::new(path).exists() {
+        tracing::info!(
+            "{path} doesn't exist yet, it will be created with every migration applied."
+        );
+        return true;
+    }
+
+    let mut connection = SqliteConnection::establish(path).expect("Failed to open database!");
+
+    check_migrations(&mut connection, migrations)
+}
+
+/// Returns the version of the last migration applied to this database, or an empty string if there's none.
+pub fn schema_version(connection: &mut SqliteConnection) -> String {
+    connection
+        .applied_migrations()
+        .unwrap_or_default()
+        .iter()
+        .map(ToString::to_string)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Marks databases created by the legacy migration as being at our first versioned migration, so they can be upgraded from there.
+fn adopt_legacy_schema(connection: &mut SqliteConnection, migrations: &EmbeddedMigrations) {
+    let Ok(applied) = connection.applied_migrations() else {
+        return;
+    };
+
+    if !applied
+        .iter()
+        .any(|version| version.to_string() == LEGACY_MIGRATION_VERSION)
+    {
+        return;
+    }
+
+    let Some(baseline) = MigrationSource::<Sqlite>::migrations(migrations)
+        .unwrap_or_default()
+        .iter()
+        .map(|migration| migration.name().version().to_string())
+        .min()
+    else {
+        return;
+    };
+
+    diesel::sql_query("UPDATE __diesel_schema_migrations SET version = ? WHERE version = ?")
+        .bind::<Text, _>(&baseline)
+        .bind::<Text, _>(LEGACY_MIGRATION_VERSION)
+        .execute(connection)
+        .expect("Failed to upgrade legacy database!");
+
+    tracing::info!("Upgrading legacy database, which is now at version {baseline}");
+}
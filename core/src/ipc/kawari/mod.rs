@@ -5,7 +5,7 @@ use crate::{
     common::{
         CHAR_NAME_MAX_LENGTH, ObjectId, read_bool_from, read_string, write_bool_as, write_string,
     },
-    ipc::lobby::CharacterDetails,
+    ipc::{lobby::CharacterDetails, zone::GameMasterRank},
     opcodes::CustomIpcType,
     packet::{IpcSegment, ServerlessIpcSegmentHeader},
 };
@@ -106,6 +106,20 @@ pub enum CustomIpcData {
         #[bw(map = write_string)]
         json: String,
     },
+    ReloadConfig,
+    SetGmRank {
+        #[bw(pad_size_to = CHAR_NAME_MAX_LENGTH)]
+        #[br(count = CHAR_NAME_MAX_LENGTH)]
+        #[br(map = read_string)]
+        #[bw(map = write_string)]
+        name: String,
+        gm_rank: GameMasterRank,
+    },
+    GmRankSet {
+        #[br(map = read_bool_from::<u8>)]
+        #[bw(map = write_bool_as::<u8>)]
+        found: bool,
+    },
 }
 
 #[cfg(test)]
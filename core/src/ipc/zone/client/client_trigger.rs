@@ -277,6 +277,15 @@ pub enum ClientTriggerCommand {
         id: u32,
     },
 
+    /// When the player selects which bait to fish with. This shares its id with `BeginOrEndFishing`.
+    #[brw(magic = 701u32)]
+    SelectBait {
+        #[br(assert(kind == 4))]
+        kind: u32,
+        /// The item ID of the bait.
+        item_id: u32,
+    },
+
     /// When the player starts fishing.
     #[brw(magic = 701u32)]
     BeginOrEndFishing {
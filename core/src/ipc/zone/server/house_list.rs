@@ -1,5 +1,6 @@
 use crate::{common::HousingFlag, ipc::zone::PlotSize};
 use binrw::binrw;
+use serde::{Deserialize, Serialize};
 
 #[binrw]
 #[derive(Clone, Copy, Debug, Default)]
@@ -27,7 +28,7 @@ pub enum HouseStatus {
 
 /// Represents a House's "pattern ids", or in other words, what models make up the house's exterior. All values are an index into the HousingExterior Excel sheet.
 #[binrw]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct HouseExterior {
     /// The roof's style.
     pub roof_id: u16,
@@ -51,7 +52,7 @@ pub struct HouseExterior {
 
 /// Represents a HouseExterior's dyes/stains.
 #[binrw]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct HouseExteriorColors {
     /// The roof.
     pub roof: u8,
@@ -1,5 +1,6 @@
 use crate::common::{HouseId, Position};
 use binrw::binrw;
+use serde::{Deserialize, Serialize};
 
 #[binrw]
 #[derive(Clone, Debug, Default)]
@@ -21,7 +22,7 @@ pub struct FurnitureList {
 }
 
 #[binrw]
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct HousingInteriorDetails {
     /// This interior's window style.
     pub window_style: u16,
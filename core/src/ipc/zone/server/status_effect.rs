@@ -18,6 +18,8 @@ pub struct StatusEffect {
 #[cfg(feature = "server")]
 impl mlua::UserData for StatusEffect {
     fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("id", |_, this| Ok(this.effect_id));
         fields.add_field_method_get("param", |_, this| Ok(this.param));
+        fields.add_field_method_get("duration", |_, this| Ok(this.duration));
     }
 }
@@ -7,6 +7,10 @@ pub mod common;
 #[cfg(feature = "server")]
 pub mod config;
 
+/// Shared database migration handling.
+#[cfg(feature = "server")]
+pub mod database;
+
 /// Everything packet parsing related.
 pub mod packet;
 
@@ -75,9 +75,9 @@ pub async fn send_keep_alive<T: ReadWriteIpcSegment>(
 pub async fn send_custom_world_packet(segment: CustomIpcSegment) -> Option<CustomIpcSegment> {
     let config = get_config();
 
-    let addr = config.world.get_public_socketaddr();
-
-    let mut stream = TcpStream::connect(addr).await.ok()?;
+    let mut stream = TcpStream::connect((config.world.server_name.as_str(), config.world.port))
+        .await
+        .ok()?;
 
     let mut packet_state = ConnectionState::None;
 
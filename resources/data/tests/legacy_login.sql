@@ -0,0 +1,33 @@
+-- A login database as created by the old, unversioned "current" migration.
+CREATE TABLE `__diesel_schema_migrations`(
+	`version` VARCHAR(50) PRIMARY KEY NOT NULL,
+	`run_on` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO `__diesel_schema_migrations` (`version`) VALUES ('current');
+
+CREATE TABLE `service_account`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`user_id` BIGINT NOT NULL,
+	`max_ex` INTEGER NOT NULL,
+	`legacy` INTEGER NOT NULL,
+	FOREIGN KEY (`user_id`) REFERENCES `user`(`id`)
+);
+
+CREATE TABLE `user`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`username` TEXT NOT NULL,
+	`password` TEXT NOT NULL
+);
+
+CREATE TABLE `session`(
+	`user_id` BIGINT NOT NULL,
+	`time` TEXT NOT NULL,
+	`service` TEXT NOT NULL,
+	`sid` TEXT NOT NULL,
+	PRIMARY KEY(`user_id`, `service`),
+	FOREIGN KEY (`user_id`) REFERENCES `user`(`id`)
+);
+
+INSERT INTO `user` (`id`, `username`, `password`) VALUES (1, 'test', 'test');
+INSERT INTO `service_account` (`id`, `user_id`, `max_ex`, `legacy`) VALUES (2, 1, 5, 0);
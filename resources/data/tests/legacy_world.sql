@@ -0,0 +1,207 @@
+-- A world database as created by the old, unversioned "current" migration.
+CREATE TABLE `__diesel_schema_migrations`(
+	`version` VARCHAR(50) PRIMARY KEY NOT NULL,
+	`run_on` TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO `__diesel_schema_migrations` (`version`) VALUES ('current');
+CREATE TABLE `classjob`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`current_class` INTEGER NOT NULL,
+	`levels` TEXT NOT NULL,
+	`exp` TEXT NOT NULL,
+	`first_class` INTEGER NOT NULL,
+	`rested_exp` INTEGER NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `aetheryte`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`unlocked` TEXT NOT NULL,
+	`homepoint` INTEGER NOT NULL,
+	`favorite_aetherytes` TEXT NOT NULL,
+	`free_aetheryte` INTEGER NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `linkshells`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`name` TEXT NOT NULL,
+	`creation_time` BIGINT NOT NULL,
+	`is_crossworld` BOOL NOT NULL
+);
+
+CREATE TABLE `quest`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`completed` TEXT NOT NULL,
+	`active` TEXT NOT NULL,
+	`completed_legacy` TEXT NOT NULL,
+	`unlocked_map_markers` TEXT NOT NULL,
+	`completed_levequests` TEXT NOT NULL,
+	`gathered_gathering_items` TEXT NOT NULL,
+	`shared_fates` TEXT NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `inventory`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`contents` TEXT NOT NULL,
+	`equipped_glasses_ids` TEXT NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `buddy`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`name` TEXT NOT NULL,
+	`rank` INTEGER NOT NULL,
+	`stars` INTEGER NOT NULL,
+	`levels` TEXT NOT NULL,
+	`exp` INTEGER NOT NULL,
+	`color` INTEGER NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `search_info`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`online_status` INTEGER NOT NULL,
+	`comment` TEXT NOT NULL,
+	`selected_languages` INTEGER NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `companion`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`unlocked_equip` TEXT NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `mentor`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`version` INTEGER NOT NULL,
+	`is_battle` INTEGER NOT NULL,
+	`is_trade` INTEGER NOT NULL,
+	`is_novice` INTEGER NOT NULL,
+	`is_returner` INTEGER NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `customize`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`chara_make` TEXT NOT NULL,
+	`city_state` INTEGER NOT NULL,
+	`remake_mode` INTEGER NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `content`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`unlocked_special_content` TEXT NOT NULL,
+	`unlocked_raids` TEXT NOT NULL,
+	`unlocked_dungeons` TEXT NOT NULL,
+	`unlocked_guildhests` TEXT NOT NULL,
+	`unlocked_trials` TEXT NOT NULL,
+	`unlocked_crystalline_conflicts` TEXT NOT NULL,
+	`unlocked_frontlines` TEXT NOT NULL,
+	`cleared_raids` TEXT NOT NULL,
+	`cleared_dungeons` TEXT NOT NULL,
+	`cleared_guildhests` TEXT NOT NULL,
+	`cleared_trials` TEXT NOT NULL,
+	`cleared_crystalline_conflicts` TEXT NOT NULL,
+	`cleared_frontlines` TEXT NOT NULL,
+	`cleared_masked_carnivale` TEXT NOT NULL,
+	`unlocked_misc_content` TEXT NOT NULL,
+	`cleared_misc_content` TEXT NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `volatile`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`position` TEXT NOT NULL,
+	`rotation` DOUBLE NOT NULL,
+	`zone_id` INTEGER NOT NULL,
+	`display_flags` INTEGER NOT NULL,
+	`title` INTEGER NOT NULL,
+	`is_online` BOOL NOT NULL,
+	`client_language` INTEGER NOT NULL,
+	`current_mount` INTEGER NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `character`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`service_account_id` BIGINT NOT NULL,
+	`actor_id` BIGINT NOT NULL,
+	`gm_rank` INTEGER NOT NULL,
+	`name` TEXT NOT NULL,
+	`time_played_minutes` BIGINT NOT NULL,
+	`warrior_of_light` INTEGER NOT NULL
+);
+
+CREATE TABLE `mail`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`kind` INTEGER NOT NULL,
+	`read` BOOL NOT NULL,
+	`timestamp` BIGINT NOT NULL,
+	`recipient_content_id` BIGINT NOT NULL,
+	`sender_content_id` BIGINT NOT NULL,
+	`message` TEXT NOT NULL,
+	`attached_items` TEXT NOT NULL
+);
+
+CREATE TABLE `linkshell_members`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`content_id` BIGINT NOT NULL,
+	`linkshell_id` BIGINT NOT NULL,
+	`invite_time` BIGINT NOT NULL,
+	`rank` INTEGER NOT NULL
+);
+
+CREATE TABLE `friends`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`content_id` BIGINT NOT NULL,
+	`friend_content_id` BIGINT NOT NULL,
+	`group_icon` INTEGER NOT NULL,
+	`invite_time` BIGINT NOT NULL,
+	`is_pending` INTEGER NOT NULL
+);
+
+CREATE TABLE `party`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`leader_content_id` BIGINT NOT NULL,
+	`members` TEXT NOT NULL
+);
+
+CREATE TABLE `grand_company`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`active_company` INTEGER NOT NULL,
+	`company_ranks` TEXT NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `unlock`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`unlocks` TEXT NOT NULL,
+	`seen_active_help` TEXT NOT NULL,
+	`minions` TEXT NOT NULL,
+	`mounts` TEXT NOT NULL,
+	`orchestrion_rolls` TEXT NOT NULL,
+	`cutscene_seen` TEXT NOT NULL,
+	`ornaments` TEXT NOT NULL,
+	`caught_fish` TEXT NOT NULL,
+	`caught_spearfish` TEXT NOT NULL,
+	`adventures` TEXT NOT NULL,
+	`triple_triad_cards` TEXT NOT NULL,
+	`glasses_styles` TEXT NOT NULL,
+	`chocobo_taxi_stands` TEXT NOT NULL,
+	`titles` TEXT NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `aether_current`(
+	`content_id` BIGINT NOT NULL PRIMARY KEY,
+	`comp_flg_set` TEXT NOT NULL,
+	`unlocked` TEXT NOT NULL,
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
+
+INSERT INTO `character` (`content_id`, `service_account_id`, `actor_id`, `gm_rank`, `name`, `time_played_minutes`, `warrior_of_light`) VALUES (1, 2, 3, 0, 'Test Character', 0, 0);
@@ -2,7 +2,7 @@ POTENCY = 160
 
 function doAction(player, in_combo)
     effects = EffectsBuilder()
-    effects:damage(DAMAGE_TYPE_SLASHING, player.parameters:calc_physical_damage(POTENCY))
+    effects:physical_damage(DAMAGE_TYPE_SLASHING, POTENCY)
 
     return effects
 end
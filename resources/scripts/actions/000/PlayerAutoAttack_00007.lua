@@ -2,7 +2,7 @@ POTENCY = 100
 
 function doAction(player, in_combo)
     effects = EffectsBuilder()
-    effects:damage(DAMAGE_TYPE_PHYSICAL, player.parameters:calc_physical_damage(POTENCY))
+    effects:physical_damage(DAMAGE_TYPE_PHYSICAL, POTENCY)
 
     return effects
 end
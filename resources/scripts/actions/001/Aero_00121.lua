@@ -0,0 +1,13 @@
+POTENCY = 50
+DOT_POTENCY = 30
+DOT_DURATION = 30.0
+
+EFFECT_AERO = 143
+
+function doAction(player, in_combo)
+    effects = EffectsBuilder()
+    effects:magical_damage(DAMAGE_TYPE_MAGIC, POTENCY)
+    effects:magical_damage_over_time(EFFECT_AERO, 0, DOT_DURATION, DOT_POTENCY)
+
+    return effects
+end
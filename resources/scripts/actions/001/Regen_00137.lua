@@ -0,0 +1,11 @@
+HOT_POTENCY = 250
+HOT_DURATION = 18.0
+
+EFFECT_REGEN = 158
+
+function doAction(player, in_combo)
+    effects = EffectsBuilder()
+    effects:heal_over_time(EFFECT_REGEN, 0, HOT_DURATION, HOT_POTENCY)
+
+    return effects
+end
@@ -2,7 +2,7 @@ POTENCY = 140
 
 function doAction(player, in_combo)
     effects = EffectsBuilder()
-    effects:damage(DAMAGE_TYPE_MAGIC, player.parameters:calc_magical_damage(POTENCY))
+    effects:magical_damage(DAMAGE_TYPE_MAGIC, POTENCY)
 
     return effects
 end
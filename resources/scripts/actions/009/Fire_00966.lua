@@ -1,6 +1,6 @@
 function doAction(player, in_combo)
     effects = EffectsBuilder()
-    effects:damage(DAMAGE_TYPE_MAGIC, 1000) -- TODO: placeholder
+    effects:magical_damage(DAMAGE_TYPE_MAGIC, 150) -- TODO: placeholder potency
 
     return effects
 end
@@ -1,6 +1,6 @@
 function doAction(player, in_combo)
     effects = EffectsBuilder()
-    effects:damage(DAMAGE_TYPE_BLUNT, 1000) -- TODO: placeholder
+    effects:physical_damage(DAMAGE_TYPE_BLUNT, 150) -- TODO: placeholder potency
 
     return effects
 end
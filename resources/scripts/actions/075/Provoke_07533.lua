@@ -0,0 +1,6 @@
+function doAction(player, in_combo)
+    effects = EffectsBuilder()
+    effects:provoke()
+
+    return effects
+end
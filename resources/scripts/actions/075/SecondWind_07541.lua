@@ -2,7 +2,7 @@ POTENCY = 800
 
 function doAction(player, in_combo)
     effects = EffectsBuilder()
-    effects:heal(player.parameters:calc_heal_amount(POTENCY))
+    effects:heal_potency(POTENCY)
 
     return effects
 end
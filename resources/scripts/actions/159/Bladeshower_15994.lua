@@ -9,7 +9,7 @@ function doAction(player, in_combo)
         potency = COMBO_POTENCY
     end
 
-    effects:damage(DAMAGE_TYPE_SLASHING, player.parameters:calc_physical_damage(potency))
+    effects:physical_damage(DAMAGE_TYPE_SLASHING, potency)
 
     if in_combo then
         -- Silken Flow has a 50% chance
@@ -2,7 +2,7 @@ POTENCY = 220
 
 function doAction(player, in_combo)
     effects = EffectsBuilder()
-    effects:damage(DAMAGE_TYPE_SLASHING, player.parameters:calc_physical_damage(POTENCY))
+    effects:physical_damage(DAMAGE_TYPE_SLASHING, POTENCY)
 
     -- Silken Symmetry has a 50% chance
     local gain_silken_symmetry = math.random(0, 1)
@@ -1,5 +1,6 @@
 DBG_DIR = "commands/debug/"
 GM_DIR = "commands/gm/"
+PLAYER_DIR = "commands/player/"
 
 -- GM commands
 -- Please keep these IDs sorted!
@@ -88,6 +89,7 @@ registerGMCommand(GM_IMMEDIATELY_ACTION, GM_DIR.."ImmediatelyAction.lua")
 registerCommand("cf",                               DBG_DIR.."JoinContent.lua")
 registerCommand("classjob",                         DBG_DIR.."ClassJob.lua")
 registerCommand("festival",                         DBG_DIR.."Festival.lua")
+registerCommand("gmrank",                           DBG_DIR.."GmRank.lua")
 registerCommand("inspect",                          GM_DIR.."InspectPlayer.lua") -- TODO: remove this once we figure out the GMInspect IPC opcode
 registerCommand("itemlevel",                        DBG_DIR.."SetItemLevel.lua")
 registerCommand("monies",                           DBG_DIR.."Monies.lua")
@@ -110,3 +112,12 @@ registerCommand("unlock",                           DBG_DIR.."Unlock.lua")
 registerCommand("unlockbuddyequip",                 DBG_DIR.."UnlockBuddyEquip.lua")
 registerCommand("unlockcontent",                    DBG_DIR.."UnlockContent.lua")
 registerCommand("skipintro",                        DBG_DIR.."SkipIntro.lua")
+
+-- Player commands
+-- These stand in for features that don't have their own UI yet, so anyone can use them.
+-- Please keep these in alphabetical order!
+
+registerCommand("house",                            PLAYER_DIR.."House.lua")
+registerCommand("loot",                             PLAYER_DIR.."Loot.lua")
+registerCommand("market",                           PLAYER_DIR.."Market.lua")
+registerCommand("trade",                            PLAYER_DIR.."Trade.lua")
@@ -0,0 +1,22 @@
+required_rank = GM_RANK_DEBUG
+command_sender = "[gmrank] "
+
+function onCommand(player, args, name)
+    local usage = "\nUsage: !gmrank <rank> <first name> <last name>\nRanks can be given by number or by name, e.g. normal_user or debug."
+
+    if #args ~= 3 then
+        printf(player, "This command requires 3 parameters."..usage)
+        return
+    end
+
+    local rank = tonumber(args[1]) or _G["GM_RANK_"..string.upper(args[1])]
+    if not rank then
+        printf(player, "Unknown GM rank %s."..usage, args[1])
+        return
+    end
+
+    local target = args[2].." "..args[3]
+    if not player:set_gm_rank(target, rank) then
+        printf(player, "Unknown GM rank %s."..usage, args[1])
+    end
+end
@@ -0,0 +1,56 @@
+required_rank = GM_RANK_NORMAL_USER
+command_sender = "[house] "
+
+function onCommand(player, args, name)
+    local usage = "\nUsage: !house ward <ward>\n!house enter <plot>\n!house buy <plot>\n!house exterior <roof> <walls> <windows> <door>\n!house relinquish"
+
+    if args[1] == "ward" then
+        local ward = tonumber(args[2])
+
+        if not ward then
+            printf(player, "Error parsing ward! Make sure it's an integer, starting from 0."..usage)
+            return
+        end
+
+        player:change_ward(ward)
+        printf(player, "Moving to ward %s.", ward)
+    elseif args[1] == "enter" then
+        local plot = tonumber(args[2])
+
+        if not plot then
+            printf(player, "Error parsing plot! Make sure it's an integer, starting from 0."..usage)
+            return
+        end
+
+        player:enter_house(plot)
+        printf(player, "Trying to enter the house on plot %s.", plot)
+    elseif args[1] == "buy" then
+        local plot = tonumber(args[2])
+
+        if not plot then
+            printf(player, "Error parsing plot! Make sure it's an integer, starting from 0."..usage)
+            return
+        end
+
+        player:purchase_plot(plot)
+        printf(player, "Trying to buy plot %s.", plot)
+    elseif args[1] == "exterior" then
+        local roof = tonumber(args[2])
+        local walls = tonumber(args[3])
+        local windows = tonumber(args[4])
+        local door = tonumber(args[5])
+
+        if not roof or not walls or not windows or not door then
+            printf(player, "This command requires 4 integer parameters."..usage)
+            return
+        end
+
+        player:set_house_exterior(roof, walls, windows, door)
+        printf(player, "Changing your house's exterior.")
+    elseif args[1] == "relinquish" then
+        player:relinquish_house()
+        printf(player, "Relinquishing your house.")
+    else
+        printf(player, "Unknown subcommand."..usage)
+    end
+end
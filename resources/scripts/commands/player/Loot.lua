@@ -0,0 +1,16 @@
+required_rank = GM_RANK_NORMAL_USER
+command_sender = "[loot] "
+
+function onCommand(player, args, name)
+    local usage = "\nUsage: !loot need|greed|pass <id>"
+
+    local id = tonumber(args[2])
+    if not id then
+        printf(player, "Error parsing loot id! Make sure it's an integer."..usage)
+        return
+    end
+
+    if not player:roll_loot(id, args[1]) then
+        printf(player, "Unknown roll."..usage)
+    end
+end
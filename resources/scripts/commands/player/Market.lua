@@ -0,0 +1,68 @@
+required_rank = GM_RANK_NORMAL_USER
+command_sender = "[market] "
+
+function onCommand(player, args, name)
+    local usage = "\nUsage: !market retainers\n!market hire|dismiss <retainer>\n!market entrust <retainer> <item id> <quantity>\n!market withdraw <retainer> <item id>\n!market gil <retainer>\n!market sell <retainer> <item id> <quantity> <price>\n!market unlist <listing id>\n!market offers <item id>\n!market buy <listing id>"
+
+    local subcommand = args[1]
+    if subcommand == "retainers" then
+        player:list_retainers()
+    elseif subcommand == "hire" or subcommand == "dismiss" or subcommand == "gil" then
+        local retainer = args[2]
+        if not retainer then
+            printf(player, "This command requires a retainer's name."..usage)
+            return
+        end
+
+        if subcommand == "hire" then
+            player:hire_retainer(retainer)
+        elseif subcommand == "dismiss" then
+            player:dismiss_retainer(retainer)
+        else
+            player:withdraw_retainer_gil(retainer)
+        end
+    elseif subcommand == "entrust" then
+        local item_id = tonumber(args[3])
+        local quantity = tonumber(args[4])
+        if not args[2] or not item_id or not quantity then
+            printf(player, "This command requires a retainer's name, and 2 integer parameters."..usage)
+            return
+        end
+
+        player:entrust_item(args[2], item_id, quantity)
+    elseif subcommand == "withdraw" then
+        local item_id = tonumber(args[3])
+        if not args[2] or not item_id then
+            printf(player, "This command requires a retainer's name, and an item id."..usage)
+            return
+        end
+
+        player:withdraw_item(args[2], item_id)
+    elseif subcommand == "sell" then
+        local item_id = tonumber(args[3])
+        local quantity = tonumber(args[4])
+        local price = tonumber(args[5])
+        if not args[2] or not item_id or not quantity or not price then
+            printf(player, "This command requires a retainer's name, and 3 integer parameters."..usage)
+            return
+        end
+
+        player:sell_item(args[2], item_id, quantity, price)
+    elseif subcommand == "unlist" or subcommand == "buy" or subcommand == "offers" then
+        local id = tonumber(args[2])
+        if not id then
+            printf(player, "Error parsing id! Make sure it's an integer."..usage)
+            return
+        end
+
+        if subcommand == "unlist" then
+            player:unlist_item(id)
+        elseif subcommand == "buy" then
+            player:buy_item(id)
+        else
+            player:show_market_offerings(id)
+        end
+    else
+        printf(player, "Unknown subcommand."..usage)
+    end
+end
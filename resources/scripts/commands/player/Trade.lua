@@ -0,0 +1,38 @@
+required_rank = GM_RANK_NORMAL_USER
+command_sender = "[trade] "
+
+function onCommand(player, args, name)
+    local usage = "\nUsage: !trade request|accept|cancel|clear|confirm\n!trade item <item id> <quantity>\n!trade gil <amount>"
+
+    local subcommand = args[1]
+    if subcommand == "request" then
+        player:request_trade()
+    elseif subcommand == "accept" then
+        player:accept_trade()
+    elseif subcommand == "cancel" then
+        player:cancel_trade()
+    elseif subcommand == "clear" then
+        player:clear_trade_offer()
+    elseif subcommand == "confirm" then
+        player:confirm_trade()
+    elseif subcommand == "item" then
+        local item_id = tonumber(args[2])
+        local quantity = tonumber(args[3])
+        if not item_id or not quantity then
+            printf(player, "This command requires 2 integer parameters."..usage)
+            return
+        end
+
+        player:offer_trade_item(item_id, quantity)
+    elseif subcommand == "gil" then
+        local amount = tonumber(args[2])
+        if not amount then
+            printf(player, "Error parsing amount! Make sure it's an integer."..usage)
+            return
+        end
+
+        player:offer_trade_gil(amount)
+    else
+        printf(player, "Unknown subcommand."..usage)
+    end
+end
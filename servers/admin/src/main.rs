@@ -1,9 +1,14 @@
-use axum::response::{Html, Redirect};
+use axum::body::Body;
+use axum::http::{Response, StatusCode};
+use axum::response::{Html, IntoResponse, Redirect};
 use axum::routing::post;
 use axum::{Router, extract::Form, routing::get};
-use kawari::common::{BasicCharacterData, User};
-use kawari::config::get_config;
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::{Cookie, Expiration};
+use kawari::common::{AuditLogEntry, BasicCharacterData, User, UserRole};
+use kawari::config::{Config, get_config, save_config};
 use kawari::ipc::kawari::{CustomIpcData, CustomIpcSegment};
+use kawari::ipc::zone::GameMasterRank;
 use kawari::packet::send_custom_world_packet;
 use kawari::web_static_dir;
 use minijinja::context;
@@ -11,6 +16,9 @@ use minijinja::{Environment, path_loader};
 use serde::Deserialize;
 use tower_http::services::ServeDir;
 
+/// Name of the cookie that holds the admin panel session.
+const SESSION_COOKIE: &str = "kawari_admin_sid";
+
 fn setup_default_environment() -> Environment<'static> {
     let mut env = Environment::new();
     env.set_loader(path_loader("resources/web/templates"));
@@ -18,39 +26,156 @@ fn setup_default_environment() -> Environment<'static> {
     env
 }
 
-async fn root() -> Html<String> {
+/// Makes a GET request to the login server's private API, and returns the response body.
+fn login_server_get(path: &str, query: &[(&str, &str)]) -> Option<String> {
     let config = get_config();
 
-    let environment = setup_default_environment();
-    let template = environment.get_template("admin_general.html").unwrap();
-    Html(template.render(context! { config }).unwrap())
+    let Ok(mut login_reply) = ureq::get(&*format!("{}{path}", config.login.server_name))
+        .query_pairs(query.iter().copied())
+        .call()
+    else {
+        tracing::warn!("Failed to contact login server, is it running?");
+        return None;
+    };
+
+    login_reply.body_mut().read_to_string().ok()
 }
 
-async fn users() -> Html<String> {
-    let environment = setup_default_environment();
-    let template = environment.get_template("admin_users.html").unwrap();
+/// Makes a POST request to the login server's private API, and returns the response body.
+fn login_server_post(path: &str, form: &[(&str, &str)]) -> Option<String> {
     let config = get_config();
 
     let Ok(mut login_reply) =
-        ureq::get(&*format!("{}/_private/users", config.login.server_name)).call()
+        ureq::post(&*format!("{}{path}", config.login.server_name)).send_form(form.iter().copied())
     else {
-        // TODO: add a better error message here
         tracing::warn!("Failed to contact login server, is it running?");
-        return Html(template.render(context! {}).unwrap());
+        return None;
     };
 
-    let Ok(body) = login_reply.body_mut().read_to_string() else {
-        // TODO: add a better error message here
-        tracing::warn!("Failed to contact login server, is it running?");
-        return Html(template.render(context! {}).unwrap());
+    login_reply.body_mut().read_to_string().ok()
+}
+
+/// Returns the admin panel session ID from the cookies, which the login server needs for any privileged request.
+fn session_id(jar: &CookieJar) -> &str {
+    jar.get(SESSION_COOKIE)
+        .map(|sid| sid.value())
+        .unwrap_or_default()
+}
+
+/// Ensures whoever sent this request is logged in with at least `role`.
+///
+/// Returns the user if they are, otherwise a response that sends them away.
+fn authenticate(jar: &CookieJar, role: UserRole) -> Result<User, Response<Body>> {
+    let user: Option<User> = jar.get(SESSION_COOKIE).and_then(|sid| {
+        let body = login_server_get("/_private/admin_session", &[("sid", sid.value())])?;
+        serde_json::from_str(&body).ok().flatten()
+    });
+
+    let Some(user) = user else {
+        return Err(Redirect::to("/login").into_response());
+    };
+
+    if user.role < role {
+        tracing::warn!(
+            "{} tried to access a page that requires {role:?}!",
+            user.username
+        );
+        return Err((
+            StatusCode::FORBIDDEN,
+            Html("You don't have permission to do that!".to_string()),
+        )
+            .into_response());
+    }
+
+    Ok(user)
+}
+
+/// Records `action` as being done by `user` in the audit log.
+fn audit(jar: &CookieJar, user: &User, action: &str) {
+    tracing::info!("{} {action}", user.username);
+
+    login_server_post(
+        "/_private/audit_log",
+        &[("sid", session_id(jar)), ("action", action)],
+    );
+}
+
+async fn login_page() -> Html<String> {
+    let environment = setup_default_environment();
+    let template = environment.get_template("admin_login.html").unwrap();
+    Html(template.render(context! {}).unwrap())
+}
+
+#[derive(Deserialize, Debug)]
+struct LoginInput {
+    username: String,
+    password: String,
+}
+
+async fn do_login(jar: CookieJar, Form(input): Form<LoginInput>) -> (CookieJar, Redirect) {
+    let sid: Option<String> = login_server_post(
+        "/_private/admin_login",
+        &[("username", &input.username), ("password", &input.password)],
+    )
+    .and_then(|body| serde_json::from_str(&body).ok().flatten());
+
+    let Some(sid) = sid else {
+        return (jar, Redirect::to("/login"));
     };
 
-    let users: Option<Vec<User>> = serde_json::from_str(&body).ok();
+    let cookie = Cookie::build((SESSION_COOKIE, sid))
+        .path("/")
+        .secure(false)
+        .expires(Expiration::Session)
+        .http_only(true);
+
+    (jar.add(cookie), Redirect::to("/"))
+}
+
+async fn logout(jar: CookieJar) -> (CookieJar, Redirect) {
+    if let Some(sid) = jar.get(SESSION_COOKIE) {
+        login_server_post("/_private/admin_logout", &[("sid", sid.value())]);
+    }
 
-    Html(template.render(context! { users }).unwrap())
+    (jar.remove(SESSION_COOKIE), Redirect::to("/login"))
 }
 
-async fn characters() -> Html<String> {
+async fn root(jar: CookieJar) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Moderator) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let config = get_config();
+
+    let environment = setup_default_environment();
+    let template = environment.get_template("admin_general.html").unwrap();
+    Html(template.render(context! { config, current_user }).unwrap()).into_response()
+}
+
+async fn users(jar: CookieJar) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Moderator) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let environment = setup_default_environment();
+    let template = environment.get_template("admin_users.html").unwrap();
+
+    // TODO: add a better error message here
+    let users: Option<Vec<User>> =
+        login_server_get("/_private/users", &[("sid", session_id(&jar))])
+            .and_then(|body| serde_json::from_str(&body).ok());
+
+    Html(template.render(context! { users, current_user }).unwrap()).into_response()
+}
+
+async fn characters(jar: CookieJar) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Moderator) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
     let environment = setup_default_environment();
     let template = environment.get_template("admin_characters.html").unwrap();
 
@@ -60,11 +185,131 @@ async fn characters() -> Html<String> {
         && let CustomIpcData::FullCharacterListResponse { json } = response.data
     {
         let characters: Option<Vec<BasicCharacterData>> = serde_json::from_str(&json).ok();
-        Html(template.render(context! { characters }).unwrap())
+        Html(
+            template
+                .render(context! { characters, current_user })
+                .unwrap(),
+        )
+        .into_response()
     } else {
         // error out better than this
-        Html(template.render(context! {}).unwrap())
+        Html(template.render(context! { current_user }).unwrap()).into_response()
+    }
+}
+
+async fn audit_log(jar: CookieJar) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Admin) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let environment = setup_default_environment();
+    let template = environment.get_template("admin_audit.html").unwrap();
+
+    let entries: Option<Vec<AuditLogEntry>> =
+        login_server_get("/_private/audit_log", &[("sid", session_id(&jar))])
+            .and_then(|body| serde_json::from_str(&body).ok());
+
+    Html(template.render(context! { entries, current_user }).unwrap()).into_response()
+}
+
+#[derive(Deserialize, Debug)]
+struct ClearLockoutInput {
+    username: String,
+}
+
+async fn clear_lockout(jar: CookieJar, Form(input): Form<ClearLockoutInput>) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Moderator) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    if login_server_post(
+        "/_private/clear_lockout",
+        &[("sid", session_id(&jar)), ("username", &input.username)],
+    )
+    .is_some()
+    {
+        audit(
+            &jar,
+            &current_user,
+            &format!("cleared the lockout for {}", input.username),
+        );
+    }
+
+    Redirect::to("/users").into_response()
+}
+
+#[derive(Deserialize, Debug)]
+struct SetRoleInput {
+    username: String,
+    role: i32,
+}
+
+async fn set_role(jar: CookieJar, Form(input): Form<SetRoleInput>) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Admin) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let Some(role) = UserRole::from_repr(input.role) else {
+        return (StatusCode::BAD_REQUEST, "Unknown role").into_response();
+    };
+
+    let success: bool = login_server_post(
+        "/_private/set_role",
+        &[
+            ("sid", session_id(&jar)),
+            ("username", &input.username),
+            ("role", &input.role.to_string()),
+        ],
+    )
+    .and_then(|body| serde_json::from_str(&body).ok())
+    .unwrap_or_default();
+
+    if success {
+        audit(
+            &jar,
+            &current_user,
+            &format!("changed the role of {} to {role:?}", input.username),
+        );
     }
+
+    Redirect::to("/users").into_response()
+}
+
+#[derive(Deserialize, Debug)]
+struct SetGmRankInput {
+    name: String,
+    gm_rank: u8,
+}
+
+async fn set_gm_rank(jar: CookieJar, Form(input): Form<SetGmRankInput>) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Admin) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let Some(gm_rank) = GameMasterRank::from_repr(input.gm_rank) else {
+        return (StatusCode::BAD_REQUEST, "Unknown GM rank").into_response();
+    };
+
+    let ipc_segment = CustomIpcSegment::new(CustomIpcData::SetGmRank {
+        name: input.name.clone(),
+        gm_rank,
+    });
+
+    if let Some(response) = send_custom_world_packet(ipc_segment).await
+        && let CustomIpcData::GmRankSet { found: true } = response.data
+    {
+        audit(
+            &jar,
+            &current_user,
+            &format!("changed the GM rank of {} to {gm_rank:?}", input.name),
+        );
+    }
+
+    Redirect::to("/characters").into_response()
 }
 
 #[derive(Deserialize, Debug)]
@@ -80,7 +325,39 @@ struct Input {
     login_message: Option<String>,
 }
 
-async fn apply(Form(input): Form<Input>) -> Redirect {
+/// Describes every setting that differs between `old` and `new`, for the audit log.
+fn describe_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    macro_rules! compare {
+        ($($field:ident).+) => {
+            if old.$($field).+ != new.$($field).+ {
+                changes.push(format!(
+                    "changed {} from {:?} to {:?}",
+                    stringify!($($field).+),
+                    old.$($field).+,
+                    new.$($field).+
+                ));
+            }
+        };
+    }
+
+    compare!(frontier.worlds_open);
+    compare!(frontier.login_open);
+    compare!(world.active_festivals);
+    compare!(world.world_id);
+    compare!(world.login_message);
+
+    changes
+}
+
+async fn apply(jar: CookieJar, Form(input): Form<Input>) -> Response<Body> {
+    let current_user = match authenticate(&jar, UserRole::Admin) {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let old_config = get_config();
     let mut config = get_config();
 
     if let Some(gate_open) = input.worlds_open {
@@ -115,10 +392,19 @@ async fn apply(Form(input): Form<Input>) -> Redirect {
         config.world.login_message = login_message;
     }
 
-    serde_yaml_ng::to_writer(&std::fs::File::create("config.yaml").unwrap(), &config)
-        .expect("TODO: panic message");
+    if let Err(err) = save_config(&config) {
+        tracing::warn!("Refusing to apply config: {err}");
+        return (StatusCode::BAD_REQUEST, Html(err.to_string())).into_response();
+    }
+
+    for change in describe_changes(&old_config, &config) {
+        audit(&jar, &current_user, &change);
+    }
+
+    // Most servers re-read the config on demand, but the world server has to push changes to connected players.
+    send_custom_world_packet(CustomIpcSegment::new(CustomIpcData::ReloadConfig)).await;
 
-    Redirect::to("/")
+    Redirect::to("/").into_response()
 }
 
 #[tokio::main]
@@ -127,9 +413,16 @@ async fn main() {
 
     let app = Router::new()
         .route("/", get(root))
+        .route("/login", get(login_page))
+        .route("/login", post(do_login))
+        .route("/logout", get(logout))
         .route("/apply", post(apply))
         .route("/users", get(users))
+        .route("/users/clear_lockout", post(clear_lockout))
+        .route("/users/set_role", post(set_role))
         .route("/characters", get(characters))
+        .route("/characters/set_gm_rank", post(set_gm_rank))
+        .route("/audit", get(audit_log))
         .nest_service("/static", ServeDir::new(web_static_dir!("")));
 
     let config = get_config();
@@ -130,11 +130,18 @@ async fn main() {
                                             account_index,
                                             ..
                                         } => {
-                                            connection.selected_service_account = Some(
-                                                connection.service_accounts
-                                                    [*account_index as usize]
-                                                    .id,
-                                            );
+                                            let Some(service_account) = connection
+                                                .service_accounts
+                                                .get(*account_index as usize)
+                                            else {
+                                                tracing::warn!(
+                                                    "Client picked service account {account_index}, which doesn't exist!"
+                                                );
+                                                continue;
+                                            };
+
+                                            connection.selected_service_account =
+                                                Some(service_account.id);
                                             connection.send_lobby_info(*sequence).await
                                         }
                                         ClientLobbyIpcData::CharaMake(character_action) => {
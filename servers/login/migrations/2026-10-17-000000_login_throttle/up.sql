@@ -0,0 +1,14 @@
+CREATE TABLE `login_throttle`(
+	`identifier` TEXT NOT NULL PRIMARY KEY,
+	`failures` INTEGER NOT NULL,
+	`locked_until` BIGINT NOT NULL
+);
+
+CREATE TABLE `failed_login`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`user_id` BIGINT NOT NULL,
+	`time` TEXT NOT NULL,
+	`service` TEXT NOT NULL,
+	`address` TEXT NOT NULL,
+	FOREIGN KEY (`user_id`) REFERENCES `user`(`id`)
+);
@@ -0,0 +1,8 @@
+ALTER TABLE `user` ADD COLUMN `role` INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE `audit_log`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`time` TEXT NOT NULL,
+	`username` TEXT NOT NULL,
+	`action` TEXT NOT NULL
+);
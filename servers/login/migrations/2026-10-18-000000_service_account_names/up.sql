@@ -0,0 +1 @@
+ALTER TABLE `service_account` ADD COLUMN `name` TEXT NOT NULL DEFAULT '';
@@ -1,12 +1,30 @@
 use crate::models::*;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Argon2, Params};
 use diesel::prelude::*;
 use diesel::{Connection, SqliteConnection};
-use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
-use kawari::common::MaxEx;
+use diesel_migrations::{EmbeddedMigrations, embed_migrations};
+use kawari::common::{ACCOUNT_MANAGEMENT_SERVICE, AuditLogEntry, MaxEx, UserRole};
 use serde::Serialize;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
+/// How many failed logins are allowed before we start delaying further attempts.
+const FREE_LOGIN_ATTEMPTS: i32 = 3;
+
+/// How many failed logins it takes before the identifier is locked out entirely.
+const LOCKOUT_LOGIN_ATTEMPTS: i32 = 10;
+
+/// How long a lockout lasts, in seconds.
+const LOCKOUT_DURATION: i64 = 15 * 60;
+
+/// How many service accounts a user can have, which is as many as the lobby can list.
+pub const MAX_SERVICE_ACCOUNTS: usize = 8;
+
+/// How long a service account's name can be, in bytes. The lobby has room for a little more, but it needs to be null-terminated.
+pub const MAX_SERVICE_ACCOUNT_NAME_LENGTH: usize = 64;
+
 pub struct LoginDatabase {
     connection: SqliteConnection,
     num_expansions: usize,
@@ -16,15 +34,35 @@ pub struct LoginDatabase {
 pub enum LoginError {
     WrongUsername,
     WrongPassword,
+    /// Too many failed attempts were made for this username or address, and they have to wait.
+    LockedOut,
     InternalError,
 }
 
+/// The result of checking a password against what's stored in the database.
+#[derive(Debug, PartialEq)]
+enum PasswordCheck {
+    /// The password is wrong.
+    Invalid,
+    /// The password matched and the stored hash is up-to-date.
+    Valid,
+    /// The password matched, but it was stored in plaintext or with outdated parameters.
+    ValidNeedsRehash,
+}
+
 #[derive(Serialize)]
 pub struct SessionInformation {
     pub time: String,
     pub service: String,
 }
 
+#[derive(Serialize)]
+pub struct FailedLoginInformation {
+    pub time: String,
+    pub service: String,
+    pub address: String,
+}
+
 impl LoginDatabase {
     /// Creates a new connection to the database, and creates tables as needed.
     pub fn new(num_expansions: usize) -> Self {
@@ -51,9 +89,14 @@ impl LoginDatabase {
         }
     }
 
-    /// Setups up the initial database schema.
+    /// Setups up the initial database schema, or upgrades an existing one.
     fn create_tables(connection: &mut SqliteConnection) {
-        connection.run_pending_migrations(MIGRATIONS).unwrap();
+        kawari::database::run_migrations(connection, MIGRATIONS);
+    }
+
+    /// Checks whether the pending migrations would apply cleanly to the database, without changing it.
+    pub fn check_migrations() -> bool {
+        kawari::database::check_database_migrations("login.db", MIGRATIONS)
     }
 
     /// Generates a random account ID.
@@ -62,17 +105,80 @@ impl LoginDatabase {
         fastrand::u32(..)
     }
 
+    /// Hashes `password` with Argon2id and a random salt.
+    ///
+    /// The returned PHC string contains the algorithm, its parameters and the salt, so it can be verified later even if our defaults change.
+    fn hash_password(password: &str) -> Option<String> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .ok()
+    }
+
+    /// Checks `password` against the `stored_password` from the database.
+    ///
+    /// Older databases stored passwords in plaintext, so anything that isn't a valid PHC string is compared as-is.
+    fn verify_password(stored_password: &str, password: &str) -> PasswordCheck {
+        let Ok(hash) = PasswordHash::new(stored_password) else {
+            return if stored_password == password {
+                PasswordCheck::ValidNeedsRehash
+            } else {
+                PasswordCheck::Invalid
+            };
+        };
+
+        if Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_err()
+        {
+            return PasswordCheck::Invalid;
+        }
+
+        // Rehash if the stored hash was created with a different algorithm or weaker parameters.
+        let defaults = Params::default();
+        let is_current = hash.algorithm == argon2::ARGON2ID_IDENT
+            && Params::try_from(&hash).is_ok_and(|params| {
+                params.m_cost() >= defaults.m_cost()
+                    && params.t_cost() >= defaults.t_cost()
+                    && params.p_cost() >= defaults.p_cost()
+            });
+        if is_current {
+            PasswordCheck::Valid
+        } else {
+            PasswordCheck::ValidNeedsRehash
+        }
+    }
+
+    /// Replaces the stored password hash for `user_id`.
+    fn set_password_hash(&mut self, for_user_id: i64, hashed_password: &str) {
+        use crate::schema::user::dsl::*;
+
+        if let Err(err) = diesel::update(user.filter(id.eq(for_user_id)))
+            .set(password.eq(hashed_password))
+            .execute(&mut self.connection)
+        {
+            tracing::error!("While updating password for {for_user_id}: {err:?}");
+        }
+    }
+
     /// Adds a new user to the database with `username` and `password`.
     ///
     /// Returns false if the username was already taken.
     pub fn add_user(&mut self, username: &str, password: &str) -> bool {
-        use crate::schema::{service_account, user};
+        use crate::schema::user;
 
         if self.check_username(username) {
             tracing::error!("Username {username} already taken!");
             return false;
         }
 
+        let Some(hashed_password) = Self::hash_password(password) else {
+            tracing::error!("Failed to hash password for {username}!");
+            return false;
+        };
+
         let user_id = Self::generate_account_id();
 
         // add user
@@ -83,7 +189,8 @@ impl LoginDatabase {
                 .values(&User {
                     id: user_id as i64,
                     username: username.to_string(),
-                    password: password.to_string(),
+                    password: hashed_password,
+                    role: UserRole::User as i32,
                 })
                 .execute(&mut self.connection)
             {
@@ -92,51 +199,336 @@ impl LoginDatabase {
             }
         }
 
-        // add service account
-        {
-            diesel::insert_into(service_account::table)
-                .values(&ServiceAccount {
-                    id: Self::generate_account_id() as i64,
-                    user_id: user_id as i64,
-                    max_ex: self.num_expansions as i32,
-                    ..Default::default()
-                })
-                .execute(&mut self.connection)
-                .unwrap();
+        self.add_service_account(user_id as u64, "").is_some()
+    }
+
+    /// Checks `for_password` against the one stored for `for_user_id`.
+    pub fn check_password(&mut self, for_user_id: u64, for_password: &str) -> bool {
+        use crate::schema::user::dsl::*;
+
+        let Ok(stored_password) = user
+            .filter(id.eq(for_user_id as i64))
+            .select(password)
+            .first::<String>(&mut self.connection)
+        else {
+            return false;
+        };
+
+        Self::verify_password(&stored_password, for_password) != PasswordCheck::Invalid
+    }
+
+    /// Changes the password of `for_user_id`, but only if `old_password` is correct.
+    ///
+    /// `address` is where the request came from, wrong old passwords are throttled the same way as failed logins.
+    /// Every session is revoked afterwards, so anyone still logged in with the old password has to login again.
+    pub fn change_password(
+        &mut self,
+        for_user_id: u64,
+        old_password: &str,
+        new_password: &str,
+        address: &str,
+    ) -> Result<(), LoginError> {
+        let username_identifier = Self::username_identifier(&self.get_username(for_user_id));
+        let address_identifier = Self::address_identifier(address);
+
+        if self.is_locked_out(&username_identifier) || self.is_locked_out(&address_identifier) {
+            tracing::warn!("Rejected password change for {for_user_id} from {address}, locked out");
+            return Err(LoginError::LockedOut);
+        }
+
+        if !self.check_password(for_user_id, old_password) {
+            self.record_failure(&username_identifier);
+            self.record_failure(&address_identifier);
+            self.log_failed_login(for_user_id as i64, ACCOUNT_MANAGEMENT_SERVICE, address);
+
+            return Err(LoginError::WrongPassword);
         }
 
-        true
+        self.clear_failures(&username_identifier);
+        self.clear_failures(&address_identifier);
+
+        let Some(hashed_password) = Self::hash_password(new_password) else {
+            tracing::error!("Failed to hash new password for {for_user_id}!");
+            return Err(LoginError::InternalError);
+        };
+
+        // The new password and revoking the old sessions have to go together, otherwise we could end up with one but not the other.
+        let result = self.connection.transaction(|connection| {
+            use crate::schema::{session, user};
+
+            diesel::update(user::table.filter(user::id.eq(for_user_id as i64)))
+                .set(user::password.eq(&hashed_password))
+                .execute(connection)?;
+            diesel::delete(session::table.filter(session::user_id.eq(for_user_id as i64)))
+                .execute(connection)?;
+
+            diesel::QueryResult::Ok(())
+        });
+
+        if let Err(err) = result {
+            tracing::error!("While changing password for {for_user_id}: {err:?}");
+            return Err(LoginError::InternalError);
+        }
+
+        tracing::info!("Changed password for user {for_user_id}!");
+
+        Ok(())
     }
 
     /// Login as a user, and returns a session id if successful.
     ///
     /// `service` is the purpose of this login.
     /// `username` and `password` is the user's credentials.
+    /// `address` is where the login came from, and is used to throttle failed attempts.
     pub fn login_user(
         &mut self,
         service: &str,
         for_username: &str,
         for_password: &str,
+        address: &str,
+    ) -> Result<String, LoginError> {
+        self.login(service, for_username, for_password, Some(address))
+    }
+
+    /// Same as `login_user`, but for logins that are relayed by the admin server.
+    ///
+    /// Their address is always the admin server's, so failed attempts are only throttled by username.
+    pub fn login_admin(
+        &mut self,
+        service: &str,
+        for_username: &str,
+        for_password: &str,
+    ) -> Result<String, LoginError> {
+        self.login(service, for_username, for_password, None)
+    }
+
+    fn login(
+        &mut self,
+        service: &str,
+        for_username: &str,
+        for_password: &str,
+        address: Option<&str>,
     ) -> Result<String, LoginError> {
         use crate::schema::user::dsl::*;
 
+        let username_identifier = Self::username_identifier(for_username);
+        let address_identifier = address.map(Self::address_identifier);
+
+        if self.is_locked_out(&username_identifier)
+            || address_identifier
+                .as_ref()
+                .is_some_and(|address_identifier| self.is_locked_out(address_identifier))
+        {
+            tracing::warn!("Rejected login for {for_username} from {address:?}, locked out");
+            return Err(LoginError::LockedOut);
+        }
+
         if let Ok(selected_user) = user
             .filter(username.eq(for_username))
             .select(User::as_select())
             .first(&mut self.connection)
         {
-            if selected_user.password == for_password {
-                return self
-                    .create_session(service, selected_user.id as u64)
-                    .ok_or(LoginError::InternalError);
-            } else {
-                return Err(LoginError::WrongPassword);
+            match Self::verify_password(&selected_user.password, for_password) {
+                PasswordCheck::Invalid => {
+                    self.record_failure(&username_identifier);
+                    if let Some(address_identifier) = &address_identifier {
+                        self.record_failure(address_identifier);
+                    }
+                    self.log_failed_login(selected_user.id, service, address.unwrap_or_default());
+
+                    return Err(LoginError::WrongPassword);
+                }
+                PasswordCheck::ValidNeedsRehash => {
+                    tracing::info!("Upgrading password hash for {for_username}");
+
+                    if let Some(hashed_password) = Self::hash_password(for_password) {
+                        self.set_password_hash(selected_user.id, &hashed_password);
+                    }
+                }
+                PasswordCheck::Valid => {}
             }
+
+            self.clear_failures(&username_identifier);
+            if let Some(address_identifier) = &address_identifier {
+                self.clear_failures(address_identifier);
+            }
+
+            return self
+                .create_session(service, selected_user.id as u64)
+                .ok_or(LoginError::InternalError);
+        }
+
+        // There's no user to attach this to, so only the address is penalized.
+        if let Some(address_identifier) = &address_identifier {
+            self.record_failure(address_identifier);
         }
 
         Err(LoginError::WrongUsername)
     }
 
+    /// Returns the throttle identifier for `username`.
+    fn username_identifier(username: &str) -> String {
+        format!("username:{username}")
+    }
+
+    /// Returns the throttle identifier for `address`.
+    fn address_identifier(address: &str) -> String {
+        format!("address:{address}")
+    }
+
+    /// Returns the current UNIX timestamp, according to the database.
+    fn current_time(&mut self) -> i64 {
+        diesel::select(unixepoch())
+            .get_result::<i64>(&mut self.connection)
+            .unwrap()
+    }
+
+    /// Returns how long to wait after `failures` failed attempts, in seconds.
+    fn lockout_duration(failures: i32) -> i64 {
+        if failures >= LOCKOUT_LOGIN_ATTEMPTS {
+            LOCKOUT_DURATION
+        } else if failures > FREE_LOGIN_ATTEMPTS {
+            // Exponential back-off, starting at two seconds.
+            1 << (failures - FREE_LOGIN_ATTEMPTS)
+        } else {
+            0
+        }
+    }
+
+    /// Checks if `for_identifier` has to wait before attempting to login again.
+    fn is_locked_out(&mut self, for_identifier: &str) -> bool {
+        use crate::schema::login_throttle::dsl::*;
+
+        let Ok(until) = login_throttle
+            .filter(identifier.eq(for_identifier))
+            .select(locked_until)
+            .first::<i64>(&mut self.connection)
+        else {
+            return false;
+        };
+
+        until > self.current_time()
+    }
+
+    /// Records a failed login for `for_identifier`, and locks it out if needed.
+    fn record_failure(&mut self, for_identifier: &str) {
+        use crate::schema::login_throttle::dsl::*;
+
+        let now = self.current_time();
+        let previous_failures = login_throttle
+            .filter(identifier.eq(for_identifier))
+            .select((failures, locked_until))
+            .first::<(i32, i64)>(&mut self.connection)
+            .map(|(previous_failures, until)| {
+                // Once a full lockout has been served, start counting again from scratch.
+                if previous_failures >= LOCKOUT_LOGIN_ATTEMPTS && until <= now {
+                    0
+                } else {
+                    previous_failures
+                }
+            })
+            .unwrap_or_default();
+
+        let new_failures = previous_failures + 1;
+        let new_locked_until = now + Self::lockout_duration(new_failures);
+
+        if new_failures >= LOCKOUT_LOGIN_ATTEMPTS {
+            tracing::warn!("Locking out {for_identifier} after {new_failures} failed logins!");
+        }
+
+        if let Err(err) = diesel::replace_into(login_throttle)
+            .values(&LoginThrottle {
+                identifier: for_identifier.to_string(),
+                failures: new_failures,
+                locked_until: new_locked_until,
+            })
+            .execute(&mut self.connection)
+        {
+            tracing::error!("While recording failed login for {for_identifier}: {err:?}");
+        }
+    }
+
+    /// Forgets all failed logins for `for_identifier`.
+    fn clear_failures(&mut self, for_identifier: &str) {
+        use crate::schema::login_throttle::dsl::*;
+
+        let _ = diesel::delete(login_throttle.filter(identifier.eq(for_identifier)))
+            .execute(&mut self.connection);
+    }
+
+    /// Adds a failed login for `for_user_id` to their login history.
+    fn log_failed_login(&mut self, for_user_id: i64, for_service: &str, from_address: &str) {
+        use crate::schema::failed_login;
+
+        let time = diesel::select(datetime())
+            .get_result::<String>(&mut self.connection)
+            .unwrap();
+
+        if let Err(err) = diesel::insert_into(failed_login::table)
+            .values(&FailedLogin {
+                id: fastrand::i64(..),
+                user_id: for_user_id,
+                time,
+                service: for_service.to_string(),
+                address: from_address.to_string(),
+            })
+            .execute(&mut self.connection)
+        {
+            tracing::error!("While logging failed login for {for_user_id}: {err:?}");
+        }
+    }
+
+    /// Lifts the lockout for `for_username`, including any addresses that failed to login as them.
+    pub fn clear_lockout(&mut self, for_username: &str) {
+        let addresses = {
+            use crate::schema::failed_login::dsl::*;
+            use crate::schema::user;
+
+            failed_login
+                .inner_join(user::table)
+                .filter(user::username.eq(for_username))
+                .select(address)
+                .distinct()
+                .load::<String>(&mut self.connection)
+                .unwrap_or_default()
+        };
+
+        self.clear_failures(&Self::username_identifier(for_username));
+        for address in addresses {
+            self.clear_failures(&Self::address_identifier(&address));
+        }
+
+        tracing::info!("Cleared lockout for {for_username}!");
+    }
+
+    /// Checks if `for_username` is currently locked out.
+    pub fn is_user_locked_out(&mut self, for_username: &str) -> bool {
+        self.is_locked_out(&Self::username_identifier(for_username))
+    }
+
+    /// Gets the list of failed logins for this user, newest first.
+    pub fn get_failed_logins(&mut self, for_user_id: u64) -> Vec<FailedLoginInformation> {
+        use crate::schema::failed_login::dsl::*;
+
+        if let Ok(failed_logins) = failed_login
+            .filter(user_id.eq(for_user_id as i64))
+            .order(time.desc())
+            .select(FailedLogin::as_select())
+            .load(&mut self.connection)
+        {
+            failed_logins
+                .iter()
+                .map(|x| FailedLoginInformation {
+                    time: x.time.clone(),
+                    service: x.service.clone(),
+                    address: x.address.clone(),
+                })
+                .collect()
+        } else {
+            Vec::default()
+        }
+    }
+
     /// Generates a random session ID.
     fn generate_sid() -> String {
         let random_id: String =
@@ -194,32 +586,7 @@ impl LoginDatabase {
             return Vec::default();
         };
 
-        // service accounts
-        {
-            use crate::schema::service_account::dsl::*;
-
-            if let Ok(service_accounts) = service_account
-                .filter(user_id.eq(found_user_id))
-                .select(ServiceAccount::as_select())
-                .load(&mut self.connection)
-            {
-                service_accounts
-                    .iter()
-                    .enumerate()
-                    .map(|(i, x)| kawari::ipc::lobby::ServiceAccount {
-                        id: x.id as u64,
-                        index: i as u32,
-                        name: if service_accounts.len() == 1 {
-                            "FINAL FANTASY XIV".to_string()
-                        } else {
-                            format!("FINAL FANTASY XIV {}", i + 1)
-                        },
-                    })
-                    .collect()
-            } else {
-                Vec::default()
-            }
-        }
+        self.get_service_accounts(found_user_id as u64)
     }
 
     /// Checks if a username is taken
@@ -254,15 +621,111 @@ impl LoginDatabase {
             .unwrap_or_default()
     }
 
-    // TODO: only returns one account right now
-    pub fn get_service_account(&mut self, for_user_id: u64) -> u64 {
+    /// Returns every service account belonging to `for_user_id`, in the order they were created.
+    pub fn get_service_accounts(
+        &mut self,
+        for_user_id: u64,
+    ) -> Vec<kawari::ipc::lobby::ServiceAccount> {
+        use crate::schema::service_account::dsl::*;
+
+        let Ok(service_accounts) = service_account
+            .filter(user_id.eq(for_user_id as i64))
+            .order(diesel::dsl::sql::<diesel::sql_types::BigInt>("rowid"))
+            .select(ServiceAccount::as_select())
+            .load(&mut self.connection)
+        else {
+            return Vec::default();
+        };
+
+        service_accounts
+            .iter()
+            .enumerate()
+            .map(|(i, x)| kawari::ipc::lobby::ServiceAccount {
+                id: x.id as u64,
+                index: i as u32,
+                name: if !x.name.is_empty() {
+                    x.name.clone()
+                } else if service_accounts.len() == 1 {
+                    "FINAL FANTASY XIV".to_string()
+                } else {
+                    format!("FINAL FANTASY XIV {}", i + 1)
+                },
+            })
+            .collect()
+    }
+
+    /// Checks whether `for_service_account_id` belongs to `for_user_id`.
+    pub fn owns_service_account(&mut self, for_user_id: u64, for_service_account_id: u64) -> bool {
         use crate::schema::service_account::dsl::*;
 
         service_account
+            .filter(id.eq(for_service_account_id as i64))
             .filter(user_id.eq(for_user_id as i64))
-            .select(id)
-            .first::<i64>(&mut self.connection)
-            .unwrap_or_default() as u64
+            .count()
+            .get_result::<i64>(&mut self.connection)
+            .unwrap_or_default()
+            > 0
+    }
+
+    /// Adds a new service account to `for_user_id`, and returns its ID. An empty `for_name` gives it the usual "FINAL FANTASY XIV" name.
+    ///
+    /// Returns None if they already have the maximum number of service accounts, or the name is too long.
+    pub fn add_service_account(&mut self, for_user_id: u64, for_name: &str) -> Option<u64> {
+        use crate::schema::service_account;
+
+        if for_name.len() > MAX_SERVICE_ACCOUNT_NAME_LENGTH {
+            return None;
+        }
+
+        if self.get_service_accounts(for_user_id).len() >= MAX_SERVICE_ACCOUNTS {
+            tracing::warn!(
+                "User {for_user_id} already has the maximum number of service accounts!"
+            );
+            return None;
+        }
+
+        let new_id = Self::generate_account_id() as u64;
+        if let Err(err) = diesel::insert_into(service_account::table)
+            .values(&ServiceAccount {
+                id: new_id as i64,
+                user_id: for_user_id as i64,
+                max_ex: self.num_expansions as i32,
+                name: for_name.to_string(),
+                ..Default::default()
+            })
+            .execute(&mut self.connection)
+        {
+            tracing::error!("While adding service account: {err:?}");
+            return None;
+        }
+
+        Some(new_id)
+    }
+
+    /// Renames one of the service accounts of `for_user_id`.
+    ///
+    /// Returns false if they don't own it, or the name is too long.
+    pub fn rename_service_account(
+        &mut self,
+        for_user_id: u64,
+        for_service_account_id: u64,
+        for_name: &str,
+    ) -> bool {
+        use crate::schema::service_account::dsl::*;
+
+        if for_name.len() > MAX_SERVICE_ACCOUNT_NAME_LENGTH {
+            return false;
+        }
+
+        diesel::update(
+            service_account
+                .filter(id.eq(for_service_account_id as i64))
+                .filter(user_id.eq(for_user_id as i64)),
+        )
+        .set(name.eq(for_name))
+        .execute(&mut self.connection)
+        .unwrap_or_default()
+            > 0
     }
 
     /// Gets the current session list, at some point it will return past sessions too.
@@ -332,6 +795,14 @@ impl LoginDatabase {
                 .unwrap();
         }
 
+        // Delete login history
+        {
+            use crate::schema::failed_login::dsl::*;
+            diesel::delete(failed_login.filter(user_id.eq(for_user_id as i64)))
+                .execute(&mut self.connection)
+                .unwrap();
+        }
+
         // Delete user
         {
             use crate::schema::user::dsl::*;
@@ -353,6 +824,99 @@ impl LoginDatabase {
                 .map(|x| kawari::common::User {
                     id: x.id as u32,
                     username: x.username.clone(),
+                    locked_out: self.is_user_locked_out(&x.username),
+                    role: UserRole::from_repr(x.role).unwrap_or_default(),
+                })
+                .collect()
+        } else {
+            Vec::default()
+        }
+    }
+
+    /// Returns the role of `for_user_id`, or the default role if they don't exist.
+    pub fn get_user_role(&mut self, for_user_id: u64) -> UserRole {
+        use crate::schema::user::dsl::*;
+
+        user.filter(id.eq(for_user_id as i64))
+            .select(role)
+            .first::<i32>(&mut self.connection)
+            .ok()
+            .and_then(UserRole::from_repr)
+            .unwrap_or_default()
+    }
+
+    /// Changes the role of `for_username`.
+    ///
+    /// Returns false if the user doesn't exist.
+    pub fn set_user_role(&mut self, for_username: &str, new_role: UserRole) -> bool {
+        use crate::schema::user::dsl::*;
+
+        let updated = diesel::update(user.filter(username.eq(for_username)))
+            .set(role.eq(new_role as i32))
+            .execute(&mut self.connection)
+            .unwrap_or_default();
+
+        if updated > 0 {
+            tracing::info!("Changed role of {for_username} to {new_role:?}!");
+        }
+
+        updated > 0
+    }
+
+    /// Promotes `for_username` to an admin, but only if there are no admins yet.
+    pub fn bootstrap_admin(&mut self, for_username: &str) {
+        use crate::schema::user::dsl::*;
+
+        let admin_count = user
+            .filter(role.eq(UserRole::Admin as i32))
+            .count()
+            .get_result::<i64>(&mut self.connection)
+            .unwrap_or_default();
+        if admin_count > 0 {
+            return;
+        }
+
+        if !self.set_user_role(for_username, UserRole::Admin) {
+            tracing::warn!("Can't bootstrap admin {for_username}, they don't exist yet!");
+        }
+    }
+
+    /// Records a change made by `for_username` in the audit log.
+    pub fn add_audit_log(&mut self, for_username: &str, for_action: &str) {
+        use crate::schema::audit_log;
+
+        let time = diesel::select(datetime())
+            .get_result::<String>(&mut self.connection)
+            .unwrap();
+
+        if let Err(err) = diesel::insert_into(audit_log::table)
+            .values(&AuditLog {
+                id: fastrand::i64(..),
+                time,
+                username: for_username.to_string(),
+                action: for_action.to_string(),
+            })
+            .execute(&mut self.connection)
+        {
+            tracing::error!("While adding to the audit log: {err:?}");
+        }
+    }
+
+    /// Returns the entire audit log, newest first.
+    pub fn get_audit_log(&mut self) -> Vec<AuditLogEntry> {
+        use crate::schema::audit_log::dsl::*;
+
+        if let Ok(entries) = audit_log
+            .order(time.desc())
+            .select(AuditLog::as_select())
+            .load(&mut self.connection)
+        {
+            entries
+                .into_iter()
+                .map(|x| AuditLogEntry {
+                    time: x.time,
+                    username: x.username,
+                    action: x.action,
                 })
                 .collect()
         } else {
@@ -403,6 +967,7 @@ mod tests {
     use super::*;
 
     const SERVICE_NAME: &'static str = "Unit Test";
+    const ADDRESS: &'static str = "127.0.0.1";
 
     #[test]
     fn test_login() {
@@ -410,21 +975,417 @@ mod tests {
 
         // No users exist in the database yet.
         assert_eq!(
-            database.login_user(SERVICE_NAME, "test", "test"),
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
             Err(LoginError::WrongUsername)
         );
 
         // Now add said user, the login should now succeed.
         assert!(database.add_user("test", "test"));
-        assert!(database.login_user(SERVICE_NAME, "test", "test").is_ok());
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+                .is_ok()
+        );
 
         // But the same user with the wrong password should fail!
         assert_eq!(
-            database.login_user(SERVICE_NAME, "test", "wrong"),
+            database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+    }
+
+    /// Returns the raw value of the password column for `for_username`.
+    fn get_stored_password(database: &mut LoginDatabase, for_username: &str) -> String {
+        use crate::schema::user::dsl::*;
+
+        user.filter(username.eq(for_username))
+            .select(password)
+            .first::<String>(&mut database.connection)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_password_hashing() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        // The password should never be stored as-is.
+        let stored_password = get_stored_password(&mut database, "test");
+        assert_ne!(stored_password, "test");
+        assert!(stored_password.starts_with("$argon2id$"));
+
+        // Two users with the same password shouldn't share a hash, thanks to the salt.
+        assert!(database.add_user("test2", "test"));
+        assert_ne!(stored_password, get_stored_password(&mut database, "test2"));
+
+        // The hash itself shouldn't work as a password.
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", &stored_password, ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+    }
+
+    #[test]
+    fn test_plaintext_upgrade() {
+        use crate::schema::user;
+
+        let mut database = LoginDatabase::new_in_memory();
+
+        // Simulate a user created before passwords were hashed.
+        diesel::insert_into(user::table)
+            .values(&User {
+                id: 1,
+                username: "test".to_string(),
+                password: "test".to_string(),
+                role: UserRole::User as i32,
+            })
+            .execute(&mut database.connection)
+            .unwrap();
+
+        // A wrong password shouldn't upgrade anything.
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+        assert_eq!(get_stored_password(&mut database, "test"), "test");
+
+        // Logging in with the right password should succeed, and rehash it.
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+                .is_ok()
+        );
+        let stored_password = get_stored_password(&mut database, "test");
+        assert!(stored_password.starts_with("$argon2id$"));
+
+        // And we should still be able to login afterwards.
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+                .is_ok()
+        );
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS),
             Err(LoginError::WrongPassword)
         );
     }
 
+    #[test]
+    fn test_weak_hash_upgrade() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        // Store a hash that uses weaker parameters than our defaults.
+        let salt = SaltString::encode_b64(b"unit test salt").unwrap();
+        let weak_hash = Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            Params::new(1024, 1, 1, None).unwrap(),
+        )
+        .hash_password(b"test", &salt)
+        .unwrap()
+        .to_string();
+        let user_id = {
+            use crate::schema::user::dsl::*;
+
+            user.filter(username.eq("test"))
+                .select(id)
+                .first::<i64>(&mut database.connection)
+                .unwrap()
+        };
+        database.set_password_hash(user_id, &weak_hash);
+
+        // Logging in should upgrade it to our current parameters.
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+                .is_ok()
+        );
+        let stored_password = get_stored_password(&mut database, "test");
+        assert_ne!(stored_password, weak_hash);
+        assert_eq!(
+            LoginDatabase::verify_password(&stored_password, "test"),
+            PasswordCheck::Valid
+        );
+    }
+
+    /// Pretends that every lockout has expired.
+    fn expire_lockouts(database: &mut LoginDatabase) {
+        use crate::schema::login_throttle::dsl::*;
+
+        diesel::update(login_throttle)
+            .set(locked_until.eq(0))
+            .execute(&mut database.connection)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_lockout() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        // The first few failures shouldn't be throttled at all.
+        for _ in 0..FREE_LOGIN_ATTEMPTS {
+            assert_eq!(
+                database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS),
+                Err(LoginError::WrongPassword)
+            );
+        }
+        assert!(!database.is_user_locked_out("test"));
+
+        // But the next one should start the back-off, even for the right password.
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+        assert!(database.is_user_locked_out("test"));
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
+            Err(LoginError::LockedOut)
+        );
+
+        // Another address is still blocked, because the username is.
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "test", "127.0.0.2"),
+            Err(LoginError::LockedOut)
+        );
+
+        // Once the back-off expires, the user can login again and their failures are forgotten.
+        expire_lockouts(&mut database);
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+                .is_ok()
+        );
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+        assert!(!database.is_user_locked_out("test"));
+    }
+
+    #[test]
+    fn test_lockout_duration() {
+        assert_eq!(LoginDatabase::lockout_duration(FREE_LOGIN_ATTEMPTS), 0);
+        assert_eq!(LoginDatabase::lockout_duration(FREE_LOGIN_ATTEMPTS + 1), 2);
+        assert_eq!(LoginDatabase::lockout_duration(FREE_LOGIN_ATTEMPTS + 2), 4);
+        assert_eq!(
+            LoginDatabase::lockout_duration(LOCKOUT_LOGIN_ATTEMPTS),
+            LOCKOUT_DURATION
+        );
+        assert_eq!(
+            LoginDatabase::lockout_duration(LOCKOUT_LOGIN_ATTEMPTS + 10),
+            LOCKOUT_DURATION
+        );
+    }
+
+    #[test]
+    fn test_lockout_expiry() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        // Wait out each back-off, so every attempt counts towards a full lockout.
+        for _ in 0..LOCKOUT_LOGIN_ATTEMPTS {
+            expire_lockouts(&mut database);
+            let _ = database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS);
+        }
+        assert!(database.is_user_locked_out("test"));
+
+        // After the lockout is over, a single mistake shouldn't lock them out again.
+        expire_lockouts(&mut database);
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+        assert!(!database.is_user_locked_out("test"));
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_address_lockout() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        // Guessing usernames from one address should lock that address out.
+        for i in 0..=FREE_LOGIN_ATTEMPTS {
+            assert_eq!(
+                database.login_user(SERVICE_NAME, &format!("guess{i}"), "test", ADDRESS),
+                Err(LoginError::WrongUsername)
+            );
+        }
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
+            Err(LoginError::LockedOut)
+        );
+
+        // But other addresses shouldn't be affected.
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", "127.0.0.2")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_admin_lockout() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("admin1", "test"));
+        assert!(database.add_user("admin2", "test"));
+
+        // Admin logins all come from the admin server, so one admin's failures shouldn't lock out the others.
+        for _ in 0..=FREE_LOGIN_ATTEMPTS {
+            assert_eq!(
+                database.login_admin(SERVICE_NAME, "admin1", "wrong"),
+                Err(LoginError::WrongPassword)
+            );
+        }
+        assert_eq!(
+            database.login_admin(SERVICE_NAME, "admin1", "test"),
+            Err(LoginError::LockedOut)
+        );
+        assert!(database.login_admin(SERVICE_NAME, "admin2", "test").is_ok());
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "admin2", "test", ADDRESS)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_clear_lockout() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        for _ in 0..LOCKOUT_LOGIN_ATTEMPTS {
+            let _ = database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS);
+        }
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
+            Err(LoginError::LockedOut)
+        );
+
+        // An admin lifting the lockout should clear both the username and the address.
+        database.clear_lockout("test");
+        assert!(!database.is_user_locked_out("test"));
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_failed_login_history() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        let sid = database
+            .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+            .unwrap();
+        let user_id = database.get_user_id(&sid).unwrap();
+        assert!(database.get_failed_logins(user_id).is_empty());
+
+        // Each failure should show up in the login history.
+        let _ = database.login_user(SERVICE_NAME, "test", "wrong", ADDRESS);
+        let _ = database.login_user(SERVICE_NAME, "test", "wrong", "127.0.0.2");
+
+        let failed_logins = database.get_failed_logins(user_id);
+        assert_eq!(failed_logins.len(), 2);
+        assert!(failed_logins.iter().any(|x| x.address == ADDRESS));
+        assert!(failed_logins.iter().any(|x| x.address == "127.0.0.2"));
+        assert!(failed_logins.iter().all(|x| x.service == SERVICE_NAME));
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+        assert!(database.add_user("test2", "test"));
+
+        // New users shouldn't have any special role.
+        let sid = database
+            .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+            .unwrap();
+        let user_id = database.get_user_id(&sid).unwrap();
+        assert_eq!(database.get_user_role(user_id), UserRole::User);
+
+        // Bootstrapping should promote the first admin...
+        database.bootstrap_admin("test");
+        assert_eq!(database.get_user_role(user_id), UserRole::Admin);
+
+        // ...but not anyone else once there is one.
+        database.bootstrap_admin("test2");
+        assert!(
+            database
+                .get_users()
+                .iter()
+                .any(|x| x.username == "test2" && x.role == UserRole::User)
+        );
+
+        // Roles can be changed, as long as the user exists.
+        assert!(database.set_user_role("test", UserRole::Moderator));
+        assert_eq!(database.get_user_role(user_id), UserRole::Moderator);
+        assert!(!database.set_user_role("nobody", UserRole::Admin));
+    }
+
+    #[test]
+    fn test_legacy_migration() {
+        use diesel::connection::SimpleConnection;
+
+        let mut connection =
+            SqliteConnection::establish(":memory:").expect("Failed to open database!");
+        connection
+            .batch_execute(
+                &std::fs::read_to_string("../../resources/data/tests/legacy_login.sql").unwrap(),
+            )
+            .unwrap();
+
+        // Checking shouldn't change anything.
+        assert!(kawari::database::check_migrations(
+            &mut connection,
+            MIGRATIONS
+        ));
+        assert_eq!(kawari::database::schema_version(&mut connection), "current");
+
+        // But actually migrating should bring it up to date.
+        assert_eq!(
+            kawari::database::run_migrations(&mut connection, MIGRATIONS),
+            "20261018000000"
+        );
+
+        // Existing users should be able to login as before, and gained the default role.
+        let mut database = LoginDatabase {
+            connection,
+            num_expansions: kawari::constants::MAX_EXPANSION,
+        };
+        let sid = database
+            .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+            .unwrap();
+        let user_id = database.get_user_id(&sid).unwrap();
+        assert_eq!(database.get_user_role(user_id), UserRole::User);
+        let service_accounts = database.get_service_accounts(user_id);
+        assert_eq!(service_accounts.len(), 1);
+        assert_eq!(service_accounts[0].id, 2);
+        assert_eq!(service_accounts[0].name, "FINAL FANTASY XIV");
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.get_audit_log().is_empty());
+
+        database.add_audit_log("test", "Changed world.login_message");
+
+        let audit_log = database.get_audit_log();
+        assert_eq!(audit_log.len(), 1);
+        assert_eq!(audit_log[0].username, "test");
+        assert_eq!(audit_log[0].action, "Changed world.login_message");
+    }
+
     #[test]
     fn test_username_check() {
         let mut database = LoginDatabase::new_in_memory();
@@ -442,7 +1403,7 @@ mod tests {
         assert!(database.add_user("test", "test"));
 
         // User should be able to login.
-        let sid = database.login_user(SERVICE_NAME, "test", "test");
+        let sid = database.login_user(SERVICE_NAME, "test", "test", ADDRESS);
         assert!(sid.is_ok());
         let sid = sid.unwrap();
 
@@ -454,7 +1415,7 @@ mod tests {
         assert!(!database.is_session_valid(SERVICE_NAME, "abc"));
 
         // If we login with another session:
-        let other_sid = database.login_user("Unit Test 2", "test", "test");
+        let other_sid = database.login_user("Unit Test 2", "test", "test", ADDRESS);
         assert!(other_sid.is_ok());
         let other_sid = other_sid.unwrap();
 
@@ -463,13 +1424,129 @@ mod tests {
         assert!(database.is_session_valid("Unit Test 2", &other_sid));
     }
 
+    #[test]
+    fn test_change_password() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        let sid = database
+            .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+            .unwrap();
+        let user_id = database.get_user_id(&sid).unwrap();
+
+        // The old password has to be right.
+        assert_eq!(
+            database.change_password(user_id, "wrong", "new", ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+        assert!(database.is_session_valid(SERVICE_NAME, &sid));
+
+        // Changing it should revoke the existing session, and only the new password should work afterwards.
+        assert_eq!(
+            database.change_password(user_id, "test", "new", ADDRESS),
+            Ok(())
+        );
+        assert!(!database.is_session_valid(SERVICE_NAME, &sid));
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
+            Err(LoginError::WrongPassword)
+        );
+        assert!(
+            database
+                .login_user(SERVICE_NAME, "test", "new", ADDRESS)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_change_password_lockout() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+
+        let sid = database
+            .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+            .unwrap();
+        let user_id = database.get_user_id(&sid).unwrap();
+
+        // Guessing the old password should be throttled just like logging in.
+        for _ in 0..=FREE_LOGIN_ATTEMPTS {
+            assert_eq!(
+                database.change_password(user_id, "wrong", "new", ADDRESS),
+                Err(LoginError::WrongPassword)
+            );
+        }
+        assert_eq!(
+            database.change_password(user_id, "test", "new", ADDRESS),
+            Err(LoginError::LockedOut)
+        );
+        assert_eq!(
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
+            Err(LoginError::LockedOut)
+        );
+
+        expire_lockouts(&mut database);
+        assert_eq!(
+            database.change_password(user_id, "test", "new", ADDRESS),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_service_accounts() {
+        let mut database = LoginDatabase::new_in_memory();
+        assert!(database.add_user("test", "test"));
+        assert!(database.add_user("test2", "test"));
+
+        let sid = database
+            .login_user(SERVICE_NAME, "test", "test", ADDRESS)
+            .unwrap();
+        let user_id = database.get_user_id(&sid).unwrap();
+
+        // New users start with a single, unnumbered service account.
+        let service_accounts = database.check_session(SERVICE_NAME, &sid);
+        assert_eq!(service_accounts.len(), 1);
+        assert_eq!(service_accounts[0].name, "FINAL FANTASY XIV");
+
+        // Unnamed accounts are numbered once there's more than one, while named ones keep their name.
+        let second_id = database.add_service_account(user_id, "Alt").unwrap();
+        database.add_service_account(user_id, "").unwrap();
+        let service_accounts = database.check_session(SERVICE_NAME, &sid);
+        let names: Vec<&str> = service_accounts.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["FINAL FANTASY XIV 1", "Alt", "FINAL FANTASY XIV 3"]);
+        assert_eq!(service_accounts[1].id, second_id);
+        assert_eq!(service_accounts[1].index, 1);
+
+        // Accounts can only be renamed by their owner.
+        assert!(database.rename_service_account(user_id, second_id, "Main"));
+        assert_eq!(database.get_service_accounts(user_id)[1].name, "Main");
+        let first_id = database.get_service_accounts(user_id)[0].id;
+        assert!(database.owns_service_account(user_id, first_id));
+        let sid2 = database
+            .login_user(SERVICE_NAME, "test2", "test", ADDRESS)
+            .unwrap();
+        let user_id2 = database.get_user_id(&sid2).unwrap();
+        assert!(!database.rename_service_account(user_id2, second_id, "Stolen"));
+        assert!(!database.owns_service_account(user_id2, second_id));
+
+        // There's a limit to how many the lobby can show, and how long their names can be.
+        assert!(
+            database
+                .add_service_account(user_id, &"a".repeat(MAX_SERVICE_ACCOUNT_NAME_LENGTH + 1))
+                .is_none()
+        );
+        while database.get_service_accounts(user_id).len() < MAX_SERVICE_ACCOUNTS {
+            assert!(database.add_service_account(user_id, "").is_some());
+        }
+        assert!(database.add_service_account(user_id, "").is_none());
+    }
+
     #[test]
     fn test_delete_user() {
         let mut database = LoginDatabase::new_in_memory();
 
         // User shouldn't exist in the database yet.
         assert_eq!(
-            database.login_user(SERVICE_NAME, "test", "test"),
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
             Err(LoginError::WrongUsername)
         );
 
@@ -477,7 +1554,7 @@ mod tests {
         assert!(database.add_user("test", "test"));
 
         // User should be able to login.
-        let sid = database.login_user(SERVICE_NAME, "test", "test");
+        let sid = database.login_user(SERVICE_NAME, "test", "test", ADDRESS);
         assert!(sid.is_ok());
         let user_id = database.get_user_id(&sid.unwrap());
         assert!(user_id.is_some());
@@ -488,7 +1565,7 @@ mod tests {
 
         // And we should be denied login again.
         assert_eq!(
-            database.login_user(SERVICE_NAME, "test", "test"),
+            database.login_user(SERVICE_NAME, "test", "test", ADDRESS),
             Err(LoginError::WrongUsername)
         );
     }
@@ -1,5 +1,8 @@
 mod database;
-pub use database::{LoginDatabase, LoginError, SessionInformation};
+pub use database::{
+    LoginDatabase, LoginError, MAX_SERVICE_ACCOUNT_NAME_LENGTH, MAX_SERVICE_ACCOUNTS,
+    SessionInformation,
+};
 
 mod models;
 mod schema;
@@ -1,20 +1,24 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::body::Body;
-use axum::extract::{DefaultBodyLimit, Multipart, Query, State};
-use axum::http::Response;
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Multipart, Query, State};
+use axum::http::{Response, StatusCode};
 use axum::response::{Html, IntoResponse, Redirect};
 use axum::routing::post;
 use axum::{Form, Router, routing::get};
 use axum_extra::extract::CookieJar;
 use axum_extra::extract::cookie::{Cookie, Expiration};
-use kawari::common::{ACCOUNT_MANAGEMENT_SERVICE, GAME_SERVICE};
+use kawari::common::{ACCOUNT_MANAGEMENT_SERVICE, ADMIN_SERVICE, GAME_SERVICE, User, UserRole};
 use kawari::config::get_config;
 use kawari::constants::SUPPORTED_GAME_VERSION;
+use kawari::database::CHECK_MIGRATIONS_ARG;
 use kawari::ipc::kawari::{CustomIpcData, CustomIpcSegment};
 use kawari::packet::send_custom_world_packet;
 use kawari::web_static_dir;
-use kawari_login::{LoginDatabase, LoginError};
+use kawari_login::{
+    LoginDatabase, LoginError, MAX_SERVICE_ACCOUNT_NAME_LENGTH, MAX_SERVICE_ACCOUNTS,
+};
 use minijinja::{Environment, context, path_loader};
 use parking_lot::Mutex;
 use physis::resource::SqPackResource;
@@ -82,10 +86,16 @@ struct SapphireLoginResponse {
 
 async fn login_send(
     State(state): State<LoginServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Form(input): Form<Input>,
 ) -> Html<String> {
     let mut database = state.database.lock();
-    let user = database.login_user(GAME_SERVICE, &input.sqexid, &input.password);
+    let user = database.login_user(
+        GAME_SERVICE,
+        &input.sqexid,
+        &input.password,
+        &addr.ip().to_string(),
+    );
     match user {
         Ok(session_id) => {
             let user_id = database.get_user_id(&session_id).unwrap();
@@ -104,6 +114,10 @@ async fn login_send(
                 LoginError::WrongPassword => {
                     Html("window.external.user(\"login=auth,ng,err,Wrong Password\");".to_string())
                 }
+                LoginError::LockedOut => Html(
+                    "window.external.user(\"login=auth,ng,err,Too many failed login attempts, please try again later\");"
+                        .to_string(),
+                ),
                 LoginError::InternalError => Html(
                     "window.external.user(\"login=auth,ng,err,Internal Server Error\");"
                         .to_string(),
@@ -123,6 +137,7 @@ struct RegisterInput {
 async fn do_register(
     jar: CookieJar,
     State(state): State<LoginServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Form(input): Form<RegisterInput>,
 ) -> (CookieJar, Redirect) {
     let config = get_config();
@@ -135,11 +150,7 @@ async fn do_register(
         );
     }
 
-    tracing::info!(
-        "Registering with {:#?} and {:#?}!",
-        input.username,
-        input.password
-    );
+    tracing::info!("Registering with {:#?}!", input.username);
 
     let Some(username) = input.username else {
         panic!("Expected username!");
@@ -153,7 +164,12 @@ async fn do_register(
 
     // redirect to account management page
     let sid = database
-        .login_user(ACCOUNT_MANAGEMENT_SERVICE, &username, &password)
+        .login_user(
+            ACCOUNT_MANAGEMENT_SERVICE,
+            &username,
+            &password,
+            &addr.ip().to_string(),
+        )
         .unwrap();
 
     let cookie = Cookie::build(("cis_sessid", sid))
@@ -180,10 +196,35 @@ async fn check_session(
     serde_json::to_string(&accounts).unwrap_or(String::new())
 }
 
-async fn get_users(State(state): State<LoginServerState>) -> String {
+/// Ensures `sid` is an admin panel session for a user with at least `role`, and returns their username.
+fn authorize_admin(
+    database: &mut LoginDatabase,
+    sid: &str,
+    role: UserRole,
+) -> Result<String, StatusCode> {
+    if !database.is_session_valid(ADMIN_SERVICE, sid) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let user_id = database.get_user_id(sid).ok_or(StatusCode::UNAUTHORIZED)?;
+    let username = database.get_username(user_id);
+    if database.get_user_role(user_id) < role {
+        tracing::warn!("{username} tried to use an admin API that requires {role:?}!");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(username)
+}
+
+async fn get_users(
+    State(state): State<LoginServerState>,
+    Query(params): Query<AdminSessionParams>,
+) -> Result<String, StatusCode> {
     let mut database = state.database.lock();
+    authorize_admin(&mut database, &params.sid, UserRole::Moderator)?;
+
     let users = database.get_users();
-    serde_json::to_string(&users).unwrap_or(String::new())
+    Ok(serde_json::to_string(&users).unwrap_or(String::new()))
 }
 
 #[derive(Deserialize)]
@@ -192,6 +233,142 @@ struct MaxExParams {
     service: String,
 }
 
+#[derive(Deserialize)]
+struct ClearLockoutInput {
+    sid: String,
+    username: String,
+}
+
+async fn clear_lockout(
+    State(state): State<LoginServerState>,
+    Form(input): Form<ClearLockoutInput>,
+) -> Result<(), StatusCode> {
+    let mut database = state.database.lock();
+    authorize_admin(&mut database, &input.sid, UserRole::Moderator)?;
+
+    database.clear_lockout(&input.username);
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct AdminLoginInput {
+    username: String,
+    password: String,
+}
+
+/// Logs into the admin panel, returning a session ID if the user is allowed to use it.
+async fn admin_login(
+    State(state): State<LoginServerState>,
+    Form(input): Form<AdminLoginInput>,
+) -> String {
+    let mut database = state.database.lock();
+    let Ok(sid) = database.login_admin(ADMIN_SERVICE, &input.username, &input.password) else {
+        return serde_json::to_string(&None::<String>).unwrap();
+    };
+
+    let user_id = database.get_user_id(&sid).unwrap_or_default();
+    if database.get_user_role(user_id) < UserRole::Moderator {
+        tracing::warn!(
+            "{} tried to login to the admin panel without permission!",
+            input.username
+        );
+        database.revoke_session(user_id, ADMIN_SERVICE);
+        return serde_json::to_string(&None::<String>).unwrap();
+    }
+
+    serde_json::to_string(&Some(sid)).unwrap()
+}
+
+#[derive(Deserialize)]
+struct AdminSessionParams {
+    sid: String,
+}
+
+/// Returns the user behind an admin panel session, if it's still valid.
+async fn admin_session(
+    State(state): State<LoginServerState>,
+    Query(params): Query<AdminSessionParams>,
+) -> String {
+    let mut database = state.database.lock();
+
+    let mut user = None;
+    if database.is_session_valid(ADMIN_SERVICE, &params.sid)
+        && let Some(user_id) = database.get_user_id(&params.sid)
+    {
+        let username = database.get_username(user_id);
+        user = Some(User {
+            id: user_id as u32,
+            locked_out: database.is_user_locked_out(&username),
+            username,
+            role: database.get_user_role(user_id),
+        });
+    }
+
+    serde_json::to_string(&user).unwrap()
+}
+
+async fn admin_logout(
+    State(state): State<LoginServerState>,
+    Form(input): Form<AdminSessionParams>,
+) {
+    let mut database = state.database.lock();
+    if database.is_session_valid(ADMIN_SERVICE, &input.sid)
+        && let Some(user_id) = database.get_user_id(&input.sid)
+    {
+        database.revoke_session(user_id, ADMIN_SERVICE);
+    }
+}
+
+#[derive(Deserialize)]
+struct SetRoleInput {
+    sid: String,
+    username: String,
+    role: i32,
+}
+
+async fn set_role(
+    State(state): State<LoginServerState>,
+    Form(input): Form<SetRoleInput>,
+) -> Result<String, StatusCode> {
+    let mut database = state.database.lock();
+    authorize_admin(&mut database, &input.sid, UserRole::Admin)?;
+
+    let success = UserRole::from_repr(input.role)
+        .is_some_and(|role| database.set_user_role(&input.username, role));
+
+    Ok(serde_json::to_string(&success).unwrap())
+}
+
+async fn get_audit_log(
+    State(state): State<LoginServerState>,
+    Query(params): Query<AdminSessionParams>,
+) -> Result<String, StatusCode> {
+    let mut database = state.database.lock();
+    authorize_admin(&mut database, &params.sid, UserRole::Admin)?;
+
+    Ok(serde_json::to_string(&database.get_audit_log()).unwrap_or(String::new()))
+}
+
+#[derive(Deserialize)]
+struct AuditLogInput {
+    sid: String,
+    action: String,
+}
+
+/// Records `action` in the audit log, as being done by whoever owns the session.
+async fn add_audit_log(
+    State(state): State<LoginServerState>,
+    Form(input): Form<AuditLogInput>,
+) -> Result<(), StatusCode> {
+    let mut database = state.database.lock();
+    let username = authorize_admin(&mut database, &input.sid, UserRole::Moderator)?;
+
+    database.add_audit_log(&username, &input.action);
+
+    Ok(())
+}
+
 async fn get_max_ex(
     State(state): State<LoginServerState>,
     Query(params): Query<MaxExParams>,
@@ -240,6 +417,7 @@ struct LoginInput {
 
 async fn do_login(
     State(state): State<LoginServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     jar: CookieJar,
     Form(input): Form<LoginInput>,
 ) -> (CookieJar, Redirect) {
@@ -253,9 +431,18 @@ async fn do_login(
     };
 
     let mut database = state.database.lock();
-    let sid = database
-        .login_user(ACCOUNT_MANAGEMENT_SERVICE, &username, &password)
-        .unwrap();
+    let sid = match database.login_user(
+        ACCOUNT_MANAGEMENT_SERVICE,
+        &username,
+        &password,
+        &addr.ip().to_string(),
+    ) {
+        Ok(sid) => sid,
+        Err(err) => {
+            tracing::warn!("Failed to login {username}: {err:?}");
+            return (jar, Redirect::to("/oauth/oa/oauthlogin"));
+        }
+    };
 
     let cookie = Cookie::build(("cis_sessid", sid))
         .path("/")
@@ -287,44 +474,81 @@ async fn upload_character_backup(
     jar: CookieJar,
     mut multipart: Multipart,
 ) -> Response<Body> {
-    let Some(session_id) = jar.get("cis_sessid") else {
-        return restore_backup_with_message("Unknown Error".to_string())
-            .await
-            .into_response();
-    };
-
-    let service_account_id;
+    let user_id;
+    let service_accounts;
     {
-        let Some(user_id) = state.database.lock().get_user_id(session_id.value()) else {
-            return restore_backup_with_message("Unknown Error".to_string())
-                .await
-                .into_response();
+        let mut database = state.database.lock();
+        let Some(found_user_id) = get_managing_user(&mut database, &jar) else {
+            return Html("You need to be logged in!".to_string()).into_response();
         };
 
-        service_account_id = state.database.lock().get_service_account(user_id);
+        user_id = found_user_id;
+        service_accounts = database.get_service_accounts(user_id);
     }
 
+    // Restore into the first service account, unless they picked another one.
+    let Some(mut service_account_id) = service_accounts.first().map(|account| account.id) else {
+        return restore_backup_with_message(state, jar, Some("Unknown Error".to_string()))
+            .await
+            .into_response();
+    };
+    let mut backup = None;
+
     while let Some(field) = multipart.next_field().await.unwrap() {
         let name = field.name().unwrap().to_string();
         let data = field.bytes().await.unwrap();
 
-        std::fs::write("temp.zip", data).unwrap();
-
-        if name == "charbak" {
-            let ipc_segment = CustomIpcSegment::new(CustomIpcData::ImportCharacter {
-                service_account_id,
-                path: "temp.zip".to_string(),
-            });
+        match name.as_str() {
+            "service_account" => {
+                let Some(chosen_id) = std::str::from_utf8(&data)
+                    .ok()
+                    .and_then(|id| id.parse::<u64>().ok())
+                else {
+                    continue;
+                };
+
+                if !state
+                    .database
+                    .lock()
+                    .owns_service_account(user_id, chosen_id)
+                {
+                    tracing::warn!(
+                        "User {user_id} tried to restore into service account {chosen_id}, which isn't theirs!"
+                    );
+                    return restore_backup_with_message(
+                        state,
+                        jar,
+                        Some("Unknown service account.".to_string()),
+                    )
+                    .await
+                    .into_response();
+                }
 
-            if let Some(response) = send_custom_world_packet(ipc_segment).await
-                && let CustomIpcData::CharacterImported { message } = response.data
-            {
-                return restore_backup_with_message(message).await.into_response();
+                service_account_id = chosen_id;
             }
+            "charbak" => backup = Some(data),
+            _ => {}
+        }
+    }
+
+    if let Some(backup) = backup {
+        std::fs::write("temp.zip", backup).unwrap();
+
+        let ipc_segment = CustomIpcSegment::new(CustomIpcData::ImportCharacter {
+            service_account_id,
+            path: "temp.zip".to_string(),
+        });
+
+        if let Some(response) = send_custom_world_packet(ipc_segment).await
+            && let CustomIpcData::CharacterImported { message } = response.data
+        {
+            return restore_backup_with_message(state, jar, Some(message))
+                .await
+                .into_response();
         }
     }
 
-    restore_backup_with_message("Unknown Error".to_string())
+    restore_backup_with_message(state, jar, Some("Unknown Error".to_string()))
         .await
         .into_response()
 }
@@ -345,58 +569,272 @@ async fn logout(State(state): State<LoginServerState>, jar: CookieJar) -> (Cooki
     )
 }
 
-async fn change_password() -> Html<String> {
-    // TODO: actually change password
+/// Returns the user logged into the account management page, but only if their session is still valid.
+fn get_managing_user(database: &mut LoginDatabase, jar: &CookieJar) -> Option<u64> {
+    let session_id = jar.get("cis_sessid")?;
+    if !database.is_session_valid(ACCOUNT_MANAGEMENT_SERVICE, session_id.value()) {
+        return None;
+    }
+
+    database.get_user_id(session_id.value())
+}
+
+fn change_password_with_message(
+    database: &mut LoginDatabase,
+    user_id: u64,
+    status_message: Option<&str>,
+) -> Html<String> {
+    let username = database.get_username(user_id);
+
     let environment = setup_default_environment();
     let template = environment.get_template("changepassword.html").unwrap();
-    Html(template.render(context! {}).unwrap())
+    Html(
+        template
+            .render(context! { username => username, status_message => status_message })
+            .unwrap(),
+    )
+}
+
+async fn change_password(State(state): State<LoginServerState>, jar: CookieJar) -> Html<String> {
+    let mut database = state.database.lock();
+
+    if let Some(user_id) = get_managing_user(&mut database, &jar) {
+        return change_password_with_message(&mut database, user_id, None);
+    }
+
+    Html("You need to be logged in!".to_string())
+}
+
+#[derive(Deserialize, Debug)]
+struct ChangePasswordInput {
+    old_password: String,
+    new_password: String,
+}
+
+async fn do_change_password(
+    State(state): State<LoginServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    Form(input): Form<ChangePasswordInput>,
+) -> Response<Body> {
+    let mut database = state.database.lock();
+
+    let Some(user_id) = get_managing_user(&mut database, &jar) else {
+        return Html("You need to be logged in!".to_string()).into_response();
+    };
+
+    if input.new_password.is_empty() {
+        return change_password_with_message(
+            &mut database,
+            user_id,
+            Some("The new password can't be empty."),
+        )
+        .into_response();
+    }
+
+    if let Err(err) = database.change_password(
+        user_id,
+        &input.old_password,
+        &input.new_password,
+        &addr.ip().to_string(),
+    ) {
+        let status_message = match err {
+            LoginError::WrongPassword => "The old password is incorrect.",
+            LoginError::LockedOut => "Too many incorrect attempts, please try again later.",
+            _ => "Failed to change your password, please try again later.",
+        };
+        return change_password_with_message(&mut database, user_id, Some(status_message))
+            .into_response();
+    }
+
+    // Every session was revoked, including this one.
+    (
+        jar.remove("cis_sessid"),
+        Redirect::to("/oauth/oa/oauthlogin"),
+    )
+        .into_response()
 }
 
-async fn cancel_account() -> Html<String> {
+fn cancel_account_with_message(
+    database: &mut LoginDatabase,
+    user_id: u64,
+    status_message: Option<&str>,
+) -> Html<String> {
+    let username = database.get_username(user_id);
+
     let environment = setup_default_environment();
     let template = environment.get_template("cancel.html").unwrap();
-    Html(template.render(context! {}).unwrap())
+    Html(
+        template
+            .render(context! { username => username, status_message => status_message })
+            .unwrap(),
+    )
+}
+
+async fn cancel_account(State(state): State<LoginServerState>, jar: CookieJar) -> Html<String> {
+    let mut database = state.database.lock();
+
+    if let Some(user_id) = get_managing_user(&mut database, &jar) {
+        return cancel_account_with_message(&mut database, user_id, None);
+    }
+
+    Html("You need to be logged in!".to_string())
+}
+
+#[derive(Deserialize, Debug)]
+struct CancelAccountInput {
+    password: String,
 }
 
 async fn cancel_account_perform(
     State(state): State<LoginServerState>,
     jar: CookieJar,
-) -> (CookieJar, Redirect) {
-    let Some(session_id) = jar.get("cis_sessid") else {
-        return (jar.remove("cis_sessid"), Redirect::to("/"));
-    };
+    Form(input): Form<CancelAccountInput>,
+) -> Response<Body> {
+    let service_accounts;
+    {
+        let mut database = state.database.lock();
 
-    let Some(user_id) = state.database.lock().get_user_id(session_id.value()) else {
-        return (jar.remove("cis_sessid"), Redirect::to("/"));
-    };
+        let Some(user_id) = get_managing_user(&mut database, &jar) else {
+            return (jar.remove("cis_sessid"), Redirect::to("/")).into_response();
+        };
 
-    {
-        // TODO: only supports one service account
-        let service_account_id = state.database.lock().get_service_account(user_id);
+        // Make sure it's really them, and not someone who happened upon an unattended browser.
+        if !database.check_password(user_id, &input.password) {
+            return cancel_account_with_message(
+                &mut database,
+                user_id,
+                Some("The password is incorrect."),
+            )
+            .into_response();
+        }
 
-        state.database.lock().delete_user(user_id);
+        service_accounts = database.get_service_accounts(user_id);
+        database.delete_user(user_id);
+    }
 
-        let ipc_segment =
-            CustomIpcSegment::new(CustomIpcData::DeleteServiceAccount { service_account_id });
+    for service_account in service_accounts {
+        let ipc_segment = CustomIpcSegment::new(CustomIpcData::DeleteServiceAccount {
+            service_account_id: service_account.id,
+        });
 
         let _ = send_custom_world_packet(ipc_segment).await; // we don't care about the response, for now.
     }
 
-    (jar.remove("cis_sessid"), Redirect::to("/"))
+    (jar.remove("cis_sessid"), Redirect::to("/")).into_response()
 }
 
-async fn restore_backup() -> Html<String> {
+fn service_accounts_with_message(
+    database: &mut LoginDatabase,
+    user_id: u64,
+    status_message: Option<&str>,
+) -> Html<String> {
+    let username = database.get_username(user_id);
+    let service_accounts = database.get_service_accounts(user_id);
+
     let environment = setup_default_environment();
-    let template = environment.get_template("restore.html").unwrap();
-    Html(template.render(context! {}).unwrap())
+    let template = environment.get_template("serviceaccounts.html").unwrap();
+    Html(
+        template
+            .render(context! {
+                username => username,
+                service_accounts => service_accounts,
+                can_add => service_accounts.len() < MAX_SERVICE_ACCOUNTS,
+                max_service_accounts => MAX_SERVICE_ACCOUNTS,
+                max_name_length => MAX_SERVICE_ACCOUNT_NAME_LENGTH,
+                status_message => status_message,
+            })
+            .unwrap(),
+    )
+}
+
+async fn service_accounts(State(state): State<LoginServerState>, jar: CookieJar) -> Html<String> {
+    let mut database = state.database.lock();
+
+    if let Some(user_id) = get_managing_user(&mut database, &jar) {
+        return service_accounts_with_message(&mut database, user_id, None);
+    }
+
+    Html("You need to be logged in!".to_string())
+}
+
+#[derive(Deserialize, Debug)]
+struct AddServiceAccountInput {
+    name: String,
+}
+
+async fn add_service_account(
+    State(state): State<LoginServerState>,
+    jar: CookieJar,
+    Form(input): Form<AddServiceAccountInput>,
+) -> Html<String> {
+    let mut database = state.database.lock();
+
+    let Some(user_id) = get_managing_user(&mut database, &jar) else {
+        return Html("You need to be logged in!".to_string());
+    };
+
+    let status_message = if database
+        .add_service_account(user_id, input.name.trim())
+        .is_some()
+    {
+        "Created a new service account."
+    } else {
+        "Failed to create a new service account. You may already have the maximum number of them, or the name is too long."
+    };
+
+    service_accounts_with_message(&mut database, user_id, Some(status_message))
+}
+
+#[derive(Deserialize, Debug)]
+struct RenameServiceAccountInput {
+    id: u64,
+    name: String,
 }
 
-async fn restore_backup_with_message(status_message: String) -> Html<String> {
+async fn rename_service_account(
+    State(state): State<LoginServerState>,
+    jar: CookieJar,
+    Form(input): Form<RenameServiceAccountInput>,
+) -> Html<String> {
+    let mut database = state.database.lock();
+
+    let Some(user_id) = get_managing_user(&mut database, &jar) else {
+        return Html("You need to be logged in!".to_string());
+    };
+
+    let status_message = if database.rename_service_account(user_id, input.id, input.name.trim()) {
+        "Renamed the service account."
+    } else {
+        "Failed to rename the service account. The name may be too long."
+    };
+
+    service_accounts_with_message(&mut database, user_id, Some(status_message))
+}
+
+async fn restore_backup(State(state): State<LoginServerState>, jar: CookieJar) -> Html<String> {
+    restore_backup_with_message(state, jar, None).await
+}
+
+async fn restore_backup_with_message(
+    state: LoginServerState,
+    jar: CookieJar,
+    status_message: Option<String>,
+) -> Html<String> {
+    let mut database = state.database.lock();
+
+    let Some(user_id) = get_managing_user(&mut database, &jar) else {
+        return Html("You need to be logged in!".to_string());
+    };
+
+    let username = database.get_username(user_id);
+    let service_accounts = database.get_service_accounts(user_id);
+
     let environment = setup_default_environment();
     let template = environment.get_template("restore.html").unwrap();
     Html(
         template
-            .render(context! { status_message => status_message })
+            .render(context! { username => username, service_accounts => service_accounts, status_message => status_message })
             .unwrap(),
     )
 }
@@ -410,10 +848,11 @@ async fn login_history(State(state): State<LoginServerState>, jar: CookieJar) ->
         let environment = setup_default_environment();
         let template = environment.get_template("loginhistory.html").unwrap();
         let past_logins = database.get_sessions(user_id);
+        let failed_logins = database.get_failed_logins(user_id);
 
         return Html(
             template
-                .render(context! { past_logins => past_logins, game_service_name => GAME_SERVICE})
+                .render(context! { past_logins => past_logins, failed_logins => failed_logins, game_service_name => GAME_SERVICE})
                 .unwrap(),
         );
     }
@@ -435,11 +874,12 @@ async fn login_history_with_sid(
         let environment = setup_default_environment();
         let template = environment.get_template("loginhistory.html").unwrap();
         let past_logins = database.get_sessions(user_id);
+        let failed_logins = database.get_failed_logins(user_id);
         let config = get_config();
 
         return Html(
                 template
-                    .render(context! { past_logins => past_logins, generated_sid => generated_sid, game_service_name => GAME_SERVICE, login_server => config.login.server_name, lobby_port => config.lobby.port, lobby_host => config.lobby.server_name, game_version => SUPPORTED_GAME_VERSION, frontier_host => config.frontier.server_name, login_host => config.login.server_name, server_url => config.web.server_name, enable_registration => config.login.enable_registration  })
+                    .render(context! { past_logins => past_logins, failed_logins => failed_logins, generated_sid => generated_sid, game_service_name => GAME_SERVICE, login_server => config.login.server_name, lobby_port => config.lobby.port, lobby_host => config.lobby.server_name, game_version => SUPPORTED_GAME_VERSION, frontier_host => config.frontier.server_name, login_host => config.login.server_name, server_url => config.web.server_name, enable_registration => config.login.enable_registration  })
                     .unwrap(),
             );
     }
@@ -514,13 +954,23 @@ async fn revoke_sid(
 async fn main() {
     tracing_subscriber::fmt::init();
 
+    if std::env::args().any(|arg| arg == CHECK_MIGRATIONS_ARG) {
+        let success = LoginDatabase::check_migrations();
+        std::process::exit(if success { 0 } else { 1 });
+    }
+
     let config = get_config();
 
     let sqpack_resource = SqPackResource::from_existing(&config.filesystem.game_path);
     let num_expansions = sqpack_resource.repositories.len() - 1; // Base game is always one
 
+    let mut database = LoginDatabase::new(num_expansions);
+    if !config.admin.bootstrap_admin.is_empty() {
+        database.bootstrap_admin(&config.admin.bootstrap_admin);
+    }
+
     let state = LoginServerState {
-        database: Arc::new(Mutex::new(LoginDatabase::new(num_expansions))),
+        database: Arc::new(Mutex::new(database)),
     };
 
     let cors = CorsLayer::new().allow_origin(Any);
@@ -534,6 +984,13 @@ async fn main() {
         .route("/_private/service_accounts", get(check_session))
         .route("/_private/users", get(get_users))
         .route("/_private/max_ex", get(get_max_ex))
+        .route("/_private/clear_lockout", post(clear_lockout))
+        .route("/_private/admin_login", post(admin_login))
+        .route("/_private/admin_session", get(admin_session))
+        .route("/_private/admin_logout", post(admin_logout))
+        .route("/_private/set_role", post(set_role))
+        .route("/_private/audit_log", get(get_audit_log))
+        .route("/_private/audit_log", post(add_audit_log))
         // public website
         .route("/oauth/oa/oauthlogin", get(login))
         .route("/oauth/oa/oauthlogin", post(do_login))
@@ -542,10 +999,20 @@ async fn main() {
         .route("/account/app/svc/manage", get(account))
         .route("/account/app/svc/logout", get(logout))
         .route("/account/app/svc/mbrPasswd", get(change_password))
+        .route("/account/app/svc/mbrPasswd", post(do_change_password))
         .route("/account/app/svc/mbrCancel", get(cancel_account))
         .route(
             "/account/app/svc/mbrCancel/perform",
-            get(cancel_account_perform),
+            post(cancel_account_perform),
+        )
+        .route("/account/app/svc/serviceaccounts", get(service_accounts))
+        .route(
+            "/account/app/svc/serviceaccounts/add",
+            post(add_service_account),
+        )
+        .route(
+            "/account/app/svc/serviceaccounts/rename",
+            post(rename_service_account),
         )
         .route("/account/app/svc/restore", get(restore_backup))
         .route("/account/app/svc/restore", post(upload_character_backup))
@@ -560,5 +1027,10 @@ async fn main() {
     let addr = config.login.get_socketaddr();
     tracing::info!("Server started on {addr}");
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
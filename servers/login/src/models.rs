@@ -9,6 +9,8 @@ pub struct ServiceAccount {
     pub user_id: i64,
     pub max_ex: i32,
     pub legacy: i32,
+    /// Chosen by the user, and may be empty if they never named it.
+    pub name: String,
 }
 
 #[derive(Insertable, Queryable, Selectable)]
@@ -18,6 +20,7 @@ pub struct User {
     pub id: i64,
     pub username: String,
     pub password: String,
+    pub role: i32,
 }
 
 #[derive(Insertable, Queryable, Selectable)]
@@ -31,7 +34,41 @@ pub struct Session {
     pub sid: String,
 }
 
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::login_throttle)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LoginThrottle {
+    pub identifier: String,
+    pub failures: i32,
+    pub locked_until: i64,
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::failed_login)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(belongs_to(User))]
+pub struct FailedLogin {
+    // Fake ID because diesel doesn't support tables without primary IDs
+    pub id: i64,
+    pub user_id: i64,
+    pub time: String,
+    pub service: String,
+    pub address: String,
+}
+
+#[derive(Insertable, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::audit_log)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AuditLog {
+    // Fake ID because diesel doesn't support tables without primary IDs
+    pub id: i64,
+    pub time: String,
+    pub username: String,
+    pub action: String,
+}
+
 #[declare_sql_function]
 extern "SQL" {
     fn datetime() -> diesel::sql_types::Text;
+    fn unixepoch() -> diesel::sql_types::BigInt;
 }
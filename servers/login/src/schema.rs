@@ -3,6 +3,7 @@ diesel::table! {
         id -> BigInt,
         username -> Text,
         password -> Text,
+        role -> Integer,
     }
 }
 
@@ -23,9 +24,46 @@ diesel::table! {
         user_id -> BigInt,
         max_ex -> Integer,
         legacy -> Integer,
+        name -> Text,
     }
 }
 
 diesel::joinable!(service_account -> user (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(user, session, service_account,);
+diesel::table! {
+    login_throttle (identifier) {
+        identifier -> Text,
+        failures -> Integer,
+        locked_until -> BigInt,
+    }
+}
+
+diesel::table! {
+    failed_login (id) {
+        id -> BigInt,
+        user_id -> BigInt,
+        time -> Text,
+        service -> Text,
+        address -> Text,
+    }
+}
+
+diesel::joinable!(failed_login -> user (user_id));
+
+diesel::table! {
+    audit_log (id) {
+        id -> BigInt,
+        time -> Text,
+        username -> Text,
+        action -> Text,
+    }
+}
+
+diesel::allow_tables_to_appear_in_same_query!(
+    user,
+    session,
+    service_account,
+    login_throttle,
+    failed_login,
+    audit_log,
+);
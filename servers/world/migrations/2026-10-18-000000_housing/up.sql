@@ -0,0 +1,27 @@
+CREATE TABLE `house`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`territory_type_id` INTEGER NOT NULL,
+	`ward` INTEGER NOT NULL,
+	`plot` INTEGER NOT NULL,
+	`owner_content_id` BIGINT NOT NULL,
+	`flags` INTEGER NOT NULL,
+	`exterior` TEXT NOT NULL,
+	`interior` TEXT NOT NULL,
+	`storage` TEXT NOT NULL,
+	UNIQUE(`territory_type_id`, `ward`, `plot`),
+	FOREIGN KEY (`owner_content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `furniture`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`house_id` BIGINT NOT NULL,
+	`indoors` BOOL NOT NULL,
+	`container` INTEGER NOT NULL,
+	`slot` INTEGER NOT NULL,
+	`catalog_id` INTEGER NOT NULL,
+	`stain` INTEGER NOT NULL,
+	`rotation` DOUBLE NOT NULL,
+	`position` TEXT NOT NULL,
+	UNIQUE(`house_id`, `container`, `slot`),
+	FOREIGN KEY (`house_id`) REFERENCES `house`(`id`)
+);
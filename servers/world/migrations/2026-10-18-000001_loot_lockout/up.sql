@@ -0,0 +1,7 @@
+CREATE TABLE `loot_lockout`(
+	`content_id` BIGINT NOT NULL,
+	`instance_content_id` INTEGER NOT NULL,
+	`expires_at` BIGINT NOT NULL,
+	PRIMARY KEY(`content_id`, `instance_content_id`),
+	FOREIGN KEY (`content_id`) REFERENCES `character`(`content_id`)
+);
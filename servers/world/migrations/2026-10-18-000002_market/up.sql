@@ -0,0 +1,29 @@
+CREATE TABLE `retainer`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`owner_content_id` BIGINT NOT NULL,
+	`name` TEXT NOT NULL UNIQUE,
+	`gil` INTEGER NOT NULL,
+	`storage` TEXT NOT NULL,
+	FOREIGN KEY (`owner_content_id`) REFERENCES `character`(`content_id`)
+);
+
+CREATE TABLE `market_listing`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`retainer_id` BIGINT NOT NULL,
+	`item_id` INTEGER NOT NULL,
+	`item` TEXT NOT NULL,
+	`price_per_unit` INTEGER NOT NULL,
+	`listed_at` BIGINT NOT NULL,
+	FOREIGN KEY (`retainer_id`) REFERENCES `retainer`(`id`)
+);
+
+CREATE TABLE `market_sale`(
+	`id` BIGINT NOT NULL PRIMARY KEY,
+	`item_id` INTEGER NOT NULL,
+	`quantity` INTEGER NOT NULL,
+	`price_per_unit` INTEGER NOT NULL,
+	`hq` BOOL NOT NULL,
+	`buyer_name` TEXT NOT NULL,
+	`retainer_name` TEXT NOT NULL,
+	`sold_at` BIGINT NOT NULL
+);
@@ -10,7 +10,8 @@ use icarus::Quest::QuestSheet;
 use tokio::sync::mpsc::Sender;
 
 use crate::{
-    StatusEffects,
+    GatheringPointState, StatusEffects,
+    inventory::Item,
     lua::LuaTask,
     server::Party,
     zone_connection::{BaseParameters, TeleportQuery},
@@ -18,18 +19,19 @@ use crate::{
 use kawari::{
     common::{
         CharacterMode, ContainerType, HandlerId, JumpState, LegacyEquipmentModelId, LogMessageType,
-        MoveAnimationState, MoveAnimationType, ObjectId, ObjectTypeId, Position, WarpType,
-        WeaponModelId,
+        LootRoll, MoveAnimationState, MoveAnimationType, ObjectId, ObjectTypeId, Position,
+        TradeOffer, WarpType, WeaponModelId,
     },
+    config::WorldConfig,
     ipc::{
         chat::{CWLinkshellMessage, ChatChannelType, PartyMessage, TellMessage},
         zone::{
             ActionRequest, ActorControlCategory, CWLSLeaveReason, CWLSPermissionRank,
             ClientTrigger, Conditions, Config, CrossworldLinkshellInvite, DutyFinderSetting,
-            InviteReply, InviteType, OnlineStatus, PartyMemberEntry, PartyMemberPositions,
-            PartyUpdateStatus, ReadyCheckReply, ServerZoneIpcSegment, SpawnNpc, SpawnObject,
-            SpawnPlayer, SpawnTreasure, StrategyBoard, StrategyBoardUpdate, WaymarkPlacementMode,
-            WaymarkPosition, WaymarkPreset,
+            GameMasterRank, InviteReply, InviteType, OnlineStatus, PartyMemberEntry,
+            PartyMemberPositions, PartyUpdateStatus, ReadyCheckReply, ServerZoneIpcSegment,
+            SpawnNpc, SpawnObject, SpawnPlayer, SpawnTreasure, StrategyBoard, StrategyBoardUpdate,
+            WaymarkPlacementMode, WaymarkPosition, WaymarkPreset,
         },
     },
 };
@@ -191,6 +193,8 @@ pub enum FromServer {
     FinishEvent(),
     /// When a fish bites.
     FishBite(),
+    /// Inform the client what's left of the gathering points they've gathered from in this instance.
+    GatheringPoints(HashMap<u32, GatheringPointState>),
     /// Inform the client that another player has dismounted.
     ActorDismounted(ObjectId),
     /// Inform the client of the whereabouts of their party members.
@@ -199,6 +203,36 @@ pub enum FromServer {
     CommitParties(HashMap<u64, Party>),
     /// Treasure was spawned.
     TreasureSpawn(SpawnTreasure),
+    /// Inform the client that the duty they're in was cleared, by InstanceContent ID.
+    DutyCompleted(u16),
+    /// Give the client the items they found in a treasure coffer they opened without a party.
+    TreasureOpened(Vec<u32>),
+    /// Inform the client that they can roll on the items from a treasure coffer, by loot ID and item ID.
+    LootOffered(Vec<(u32, u32)>),
+    /// Give the client an item they won a roll for, by item ID.
+    LootWon(u32),
+    /// Inform the client how a party member rolled on an item: their name, the item ID, their roll and the number they got.
+    LootRolled(String, u32, LootRoll, u8),
+    /// Inform the client who won an item, by item ID. Nobody won if there's no name.
+    LootAwarded(u32, Option<String>),
+    /// Inform the client that a party member voted to kick someone from the duty: the voter's name, who they want kicked, and how many votes there are out of how many are needed.
+    KickVoted(String, String, u32, u32),
+    /// Inform the client that another player, by name, wants to trade with them.
+    TradeRequested(String),
+    /// Inform the client that they've started trading with another player, by name.
+    TradeOpened(String),
+    /// Inform the client what their trading partner, by name, is now offering.
+    TradeOffered(String, TradeOffer<Item>),
+    /// Inform the client that someone in their trade, by name, has confirmed it.
+    TradeConfirmed(String),
+    /// Both players confirmed, so the client should exchange what they're giving for what they're receiving.
+    TradeExchange(TradeOffer<Item>, TradeOffer<Item>),
+    /// Inform the client that the exchange succeeded on both sides.
+    TradeCompleted(),
+    /// Inform the client that their trade was cancelled or refused, and why. Anything already exchanged should be given back.
+    TradeCancelled(String),
+    /// Inform the client that their GM rank was changed.
+    GmRankChanged(GameMasterRank),
     /// A chat message from one of the client's cwlses has been received.
     CWLSMessageReceived(CWLinkshellMessage),
     /// Inform the zone and chat connections about their linkshell channels.
@@ -239,6 +273,8 @@ pub enum FromServer {
     FATEComplete,
     /// Instruct the client's zone connection to check if the teleport can be shared.
     CheckTeleportSharingEligibility(u32),
+    /// The world config was reloaded, and the client should pick up any changes.
+    ConfigChanged(WorldConfig),
 }
 
 #[derive(Debug, Clone)]
@@ -417,12 +453,36 @@ pub enum ToServer {
     StartCountdown(u64, ObjectId, u64, u64, String, ObjectId, u16),
     /// The client yields from a GimmickAccessor.
     GimmickAccessor(ObjectId, ObjectId, Vec<i32>),
-    /// The client begins fishing.
-    Fish(ClientId, ObjectId),
+    /// The client opens a treasure coffer.
+    OpenTreasure(ObjectId, ObjectId),
+    /// The client rolls on an item from a treasure coffer, by loot ID.
+    RollLoot(ObjectId, u32, LootRoll),
+    /// The client asks another player to trade, or whoever they're targeting if there's no actor ID.
+    RequestTrade(ObjectId, Option<ObjectId>),
+    /// The client accepts the latest trade request they received.
+    AcceptTrade(ObjectId),
+    /// The client changes what they're offering in their trade.
+    OfferTrade(ObjectId, TradeOffer<Item>),
+    /// The client confirms their trade.
+    ConfirmTrade(ObjectId),
+    /// The client tried to exchange the items in their trade, and whether it worked.
+    TradePrepared(ObjectId, bool),
+    /// The client cancels their trade, or any trade requests they made or received.
+    CancelTrade(ObjectId),
+    /// The client is logging out, so their trade has to end even if the items are being exchanged. They're told whether it completed or was cancelled.
+    LeaveTrade(ObjectId),
+    /// The client begins fishing, and a fish should bite after this many seconds.
+    Fish(ClientId, ObjectId, f32),
+    /// The client gathered from a gathering point, and this is what's left of it.
+    GatheredFromPoint(ObjectId, u32, GatheringPointState),
     /// Warp to a specified pop range in a new territory.
     WarpPopRange(ClientId, ObjectId, u16, u32),
     /// Request the global server state to reload its Lua state.
     ReloadScripts,
+    /// Request the global server state to reload the config, and push it to every client.
+    ReloadConfig,
+    /// Update the GM rank of the character with this name, if they're online. The database should've already been updated.
+    SetGmRank(String, GameMasterRank),
     /// The client dismounted.
     Dismounted(ObjectId, Option<u64>),
     /// Inform the server of this actor's new online status.
@@ -1,4 +1,6 @@
-use crate::{CharaMake, GameData, RemakeMode, WorldDatabase, inventory::Inventory};
+use crate::{
+    CharaMake, GameData, RemakeMode, ServerHandle, ToServer, WorldDatabase, inventory::Inventory,
+};
 use kawari::{
     common::determine_initial_starting_zone,
     config::get_config,
@@ -21,6 +23,7 @@ pub struct CustomIpcConnection {
     pub state: ConnectionState,
     pub database: Arc<Mutex<WorldDatabase>>,
     pub gamedata: Arc<Mutex<GameData>>,
+    pub handle: ServerHandle,
 }
 
 impl CustomIpcConnection {
@@ -262,6 +265,32 @@ impl CustomIpcConnection {
                 })
                 .await;
             }
+            CustomIpcData::ReloadConfig => {
+                self.handle.send(ToServer::ReloadConfig).await;
+            }
+            CustomIpcData::SetGmRank { name, gm_rank } => {
+                let found;
+                {
+                    let mut database = self.database.lock();
+                    found = database.set_gm_rank(name, *gm_rank);
+                }
+
+                // If they're online, their connection needs to know too or it'll overwrite the database later.
+                if found {
+                    self.handle
+                        .send(ToServer::SetGmRank(name.clone(), *gm_rank))
+                        .await;
+                }
+
+                self.send_custom_response(PacketSegment {
+                    segment_type: SegmentType::KawariIpc,
+                    data: SegmentData::KawariIpc(CustomIpcSegment::new(CustomIpcData::GmRankSet {
+                        found,
+                    })),
+                    ..Default::default()
+                })
+                .await;
+            }
             _ => {
                 panic!("The server is recieving a response or unknown custom IPC! {data:#?}")
             }
@@ -389,7 +389,7 @@ impl WorldDatabase {
             content_id: content_id as i64,
             service_account_id: service_account_id as i64,
             actor_id,
-            gm_rank: GameMasterRank::Debug,
+            gm_rank: get_config().world.default_gm_rank,
             name: name.to_string(),
             ..Default::default()
         };
@@ -548,8 +548,33 @@ impl WorldDatabase {
             == 0
     }
 
+    /// Returns the GM rank of the character with this name, if there's such a character.
+    pub fn find_gm_rank(&mut self, for_name: &str) -> Option<GameMasterRank> {
+        use schema::character::dsl::*;
+
+        character
+            .filter(name.eq(for_name))
+            .select(gm_rank)
+            .first::<GameMasterRank>(&mut self.connection)
+            .ok()
+    }
+
+    /// Sets the GM rank of the character with this name. Returns false if there's no such character.
+    pub fn set_gm_rank(&mut self, for_name: &str, rank: GameMasterRank) -> bool {
+        use schema::character::dsl::*;
+
+        diesel::update(character.filter(name.eq(for_name)))
+            .set(gm_rank.eq(rank))
+            .execute(&mut self.connection)
+            .unwrap_or_default()
+            > 0
+    }
+
     /// Deletes a character and all associated data
     pub fn delete_character(&mut self, for_content_id: u64) {
+        self.relinquish_house(for_content_id);
+        self.dismiss_all_retainers(for_content_id);
+
         {
             use schema::unlock::dsl::*;
             diesel::delete(unlock.filter(content_id.eq(for_content_id as i64)))
@@ -757,6 +782,7 @@ impl WorldDatabase {
                 .map(|x| BasicCharacterData {
                     content_id: x.content_id as u64,
                     name: x.name.clone(),
+                    gm_rank: x.gm_rank,
                 })
                 .collect();
 
@@ -0,0 +1,309 @@
+use super::{WorldDatabase, models, schema};
+use crate::inventory::HousingInventory;
+use diesel::prelude::*;
+use kawari::{
+    common::{ContainerType, HousingFlag, Position},
+    ipc::zone::{HouseExterior, HousingInteriorDetails},
+};
+
+/// A plot that's been bought by a player, along with everything built on it.
+#[derive(Debug, Clone)]
+pub struct PlayerHouse {
+    pub id: i64,
+    /// Index into the TerritoryType Excel sheet, for the residential district this plot is in.
+    pub territory_type_id: u16,
+    /// The ward this plot is in, starting from 0.
+    pub ward: u8,
+    /// The plot's index in the ward, starting from 0.
+    pub plot: u8,
+    pub owner_content_id: u64,
+    pub flags: HousingFlag,
+    pub exterior: HouseExterior,
+    pub interior: HousingInteriorDetails,
+    /// Both the placed furniture and the storerooms.
+    pub storage: HousingInventory,
+}
+
+impl From<models::House> for PlayerHouse {
+    fn from(value: models::House) -> Self {
+        Self {
+            id: value.id,
+            territory_type_id: value.territory_type_id as u16,
+            ward: value.ward as u8,
+            plot: value.plot as u8,
+            owner_content_id: value.owner_content_id as u64,
+            flags: HousingFlag::from_bits_truncate(value.flags as u8),
+            exterior: serde_json::from_str(&value.exterior).unwrap_or_default(),
+            interior: serde_json::from_str(&value.interior).unwrap_or_default(),
+            storage: serde_json::from_str(&value.storage).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&PlayerHouse> for models::House {
+    fn from(value: &PlayerHouse) -> Self {
+        Self {
+            id: value.id,
+            territory_type_id: value.territory_type_id as i32,
+            ward: value.ward as i32,
+            plot: value.plot as i32,
+            owner_content_id: value.owner_content_id as i64,
+            flags: value.flags.bits() as i32,
+            exterior: serde_json::to_string(&value.exterior).unwrap(),
+            interior: serde_json::to_string(&value.interior).unwrap(),
+            storage: serde_json::to_string(&value.storage).unwrap(),
+        }
+    }
+}
+
+/// A piece of furniture that's been placed inside or outside of a house.
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedFurniture {
+    pub container: ContainerType,
+    pub slot: u16,
+    /// See `GameData::get_furniture_item_id`.
+    pub catalog_id: u16,
+    /// Index into the Stain Excel sheet.
+    pub stain: u8,
+    pub position: Position,
+    pub rotation: f32,
+}
+
+impl WorldDatabase {
+    /// Returns the house built on this plot, if anyone owns it.
+    pub fn find_house(
+        &mut self,
+        for_territory_type_id: u16,
+        for_ward: u8,
+        for_plot: u8,
+    ) -> Option<PlayerHouse> {
+        use schema::house::dsl::*;
+
+        house
+            .filter(territory_type_id.eq(for_territory_type_id as i32))
+            .filter(ward.eq(for_ward as i32))
+            .filter(plot.eq(for_plot as i32))
+            .select(models::House::as_select())
+            .first(&mut self.connection)
+            .ok()
+            .map(PlayerHouse::from)
+    }
+
+    /// Returns every house that's been bought in this ward.
+    pub fn find_ward_houses(
+        &mut self,
+        for_territory_type_id: u16,
+        for_ward: u8,
+    ) -> Vec<PlayerHouse> {
+        use schema::house::dsl::*;
+
+        house
+            .filter(territory_type_id.eq(for_territory_type_id as i32))
+            .filter(ward.eq(for_ward as i32))
+            .select(models::House::as_select())
+            .load(&mut self.connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(PlayerHouse::from)
+            .collect()
+    }
+
+    /// Returns the house owned by this character, if they have one.
+    pub fn find_owned_house(&mut self, for_content_id: u64) -> Option<PlayerHouse> {
+        use schema::house::dsl::*;
+
+        house
+            .filter(owner_content_id.eq(for_content_id as i64))
+            .select(models::House::as_select())
+            .first(&mut self.connection)
+            .ok()
+            .map(PlayerHouse::from)
+    }
+
+    /// Gives this plot to `for_content_id`, and builds a house with `new_exterior` on it.
+    /// Returns None if the plot is already taken, or if they already own a house somewhere else.
+    pub fn purchase_house(
+        &mut self,
+        for_content_id: u64,
+        for_territory_type_id: u16,
+        for_ward: u8,
+        for_plot: u8,
+        new_exterior: HouseExterior,
+    ) -> Option<PlayerHouse> {
+        use schema::house::dsl::*;
+
+        if self.find_owned_house(for_content_id).is_some()
+            || self
+                .find_house(for_territory_type_id, for_ward, for_plot)
+                .is_some()
+        {
+            return None;
+        }
+
+        let next_id = if let Ok(highest) = house
+            .select(id)
+            .order(id.desc())
+            .first::<i64>(&mut self.connection)
+        {
+            highest + 1
+        } else {
+            1 // Start from a safe default if there are no houses.
+        };
+
+        let new_house = PlayerHouse {
+            id: next_id,
+            territory_type_id: for_territory_type_id,
+            ward: for_ward,
+            plot: for_plot,
+            owner_content_id: for_content_id,
+            flags: HousingFlag::OPEN,
+            exterior: new_exterior,
+            interior: HousingInteriorDetails::default(),
+            storage: HousingInventory::default(),
+        };
+
+        diesel::insert_into(house)
+            .values(models::House::from(&new_house))
+            .execute(&mut self.connection)
+            .ok()?;
+
+        Some(new_house)
+    }
+
+    /// Tears down the house owned by `for_content_id`, along with all of its furniture. Returns false if they didn't own one.
+    pub fn relinquish_house(&mut self, for_content_id: u64) -> bool {
+        let Some(owned_house) = self.find_owned_house(for_content_id) else {
+            return false;
+        };
+
+        {
+            use schema::furniture::dsl::*;
+
+            diesel::delete(furniture.filter(house_id.eq(owned_house.id)))
+                .execute(&mut self.connection)
+                .unwrap();
+        }
+
+        {
+            use schema::house::dsl::*;
+
+            diesel::delete(house.filter(id.eq(owned_house.id)))
+                .execute(&mut self.connection)
+                .unwrap();
+        }
+
+        true
+    }
+
+    /// Saves the appearance and storage of this house.
+    pub fn commit_house(&mut self, data: &PlayerHouse) {
+        use schema::house::dsl::*;
+
+        diesel::update(house.filter(id.eq(data.id)))
+            .set(models::House::from(data))
+            .execute(&mut self.connection)
+            .unwrap();
+    }
+
+    /// Returns all of the furniture placed in this house, either inside or out in the yard.
+    pub fn find_furniture(&mut self, for_house_id: i64, for_indoors: bool) -> Vec<PlacedFurniture> {
+        use schema::furniture::dsl::*;
+
+        furniture
+            .filter(house_id.eq(for_house_id))
+            .filter(indoors.eq(for_indoors))
+            .select(models::Furniture::as_select())
+            .load(&mut self.connection)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|row| {
+                Some(PlacedFurniture {
+                    container: ContainerType::from_repr(row.container as u16)?,
+                    slot: row.slot as u16,
+                    catalog_id: row.catalog_id as u16,
+                    stain: row.stain as u8,
+                    position: row.position,
+                    rotation: row.rotation as f32,
+                })
+            })
+            .collect()
+    }
+
+    /// Places a piece of furniture in this house, replacing whatever was in that slot before.
+    pub fn place_furniture(
+        &mut self,
+        for_house_id: i64,
+        for_indoors: bool,
+        placed: PlacedFurniture,
+    ) {
+        use schema::furniture::dsl::*;
+
+        self.remove_furniture(for_house_id, placed.container, placed.slot);
+
+        let next_id = if let Ok(highest) = furniture
+            .select(id)
+            .order(id.desc())
+            .first::<i64>(&mut self.connection)
+        {
+            highest + 1
+        } else {
+            1 // Start from a safe default if there is no furniture.
+        };
+
+        diesel::insert_into(furniture)
+            .values(models::Furniture {
+                id: next_id,
+                house_id: for_house_id,
+                indoors: for_indoors,
+                container: placed.container as i32,
+                slot: placed.slot as i32,
+                catalog_id: placed.catalog_id as i32,
+                stain: placed.stain as i32,
+                rotation: placed.rotation as f64,
+                position: placed.position,
+            })
+            .execute(&mut self.connection)
+            .unwrap();
+    }
+
+    /// Moves a piece of furniture that's already been placed.
+    pub fn move_furniture(
+        &mut self,
+        for_house_id: i64,
+        for_container: ContainerType,
+        for_slot: u16,
+        new_position: Position,
+        new_rotation: f32,
+    ) {
+        use schema::furniture::dsl::*;
+
+        diesel::update(
+            furniture
+                .filter(house_id.eq(for_house_id))
+                .filter(container.eq(for_container as i32))
+                .filter(slot.eq(for_slot as i32)),
+        )
+        .set((position.eq(new_position), rotation.eq(new_rotation as f64)))
+        .execute(&mut self.connection)
+        .unwrap();
+    }
+
+    /// Removes a piece of furniture from the world, e.g. when it's put back into an inventory.
+    pub fn remove_furniture(
+        &mut self,
+        for_house_id: i64,
+        for_container: ContainerType,
+        for_slot: u16,
+    ) {
+        use schema::furniture::dsl::*;
+
+        diesel::delete(
+            furniture
+                .filter(house_id.eq(for_house_id))
+                .filter(container.eq(for_container as i32))
+                .filter(slot.eq(for_slot as i32)),
+        )
+        .execute(&mut self.connection)
+        .unwrap();
+    }
+}
@@ -0,0 +1,37 @@
+use super::{WorldDatabase, models, schema};
+use diesel::prelude::*;
+
+impl WorldDatabase {
+    /// Returns whether this character can't receive loot from this InstanceContent until a later weekly reset.
+    pub fn is_locked_out(
+        &mut self,
+        for_content_id: u64,
+        for_instance_content_id: u16,
+        now: u64,
+    ) -> bool {
+        use schema::loot_lockout::dsl::*;
+
+        loot_lockout
+            .filter(content_id.eq(for_content_id as i64))
+            .filter(instance_content_id.eq(for_instance_content_id as i32))
+            .filter(expires_at.gt(now as i64))
+            .select(models::LootLockout::as_select())
+            .first(&mut self.connection)
+            .is_ok()
+    }
+
+    /// Stops this character from receiving loot from this InstanceContent until `until`, a UNIX timestamp.
+    pub fn add_lockout(&mut self, for_content_id: u64, for_instance_content_id: u16, until: u64) {
+        use schema::loot_lockout::dsl::*;
+
+        // Any previous lockout has already expired, so it's safe to replace.
+        diesel::replace_into(loot_lockout)
+            .values(models::LootLockout {
+                content_id: for_content_id as i64,
+                instance_content_id: for_instance_content_id as i32,
+                expires_at: until as i64,
+            })
+            .execute(&mut self.connection)
+            .unwrap();
+    }
+}
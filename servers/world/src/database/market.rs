@@ -0,0 +1,412 @@
+use super::{WorldDatabase, models, schema, unixepoch};
+use crate::inventory::{ITEM_FLAG_HQ, Item};
+use diesel::prelude::*;
+use kawari::common::{MAX_RETAINER_GIL, MAX_SALE_HISTORY};
+
+/// A retainer hired by a player, who holds onto items for them and sells them on the market board.
+#[derive(Debug, Clone)]
+pub struct PlayerRetainer {
+    pub id: i64,
+    pub owner_content_id: u64,
+    pub name: String,
+    /// Earned from selling items, and waiting to be collected by the owner.
+    pub gil: u32,
+    /// The items entrusted to this retainer, not counting what they're selling.
+    pub storage: Vec<Item>,
+}
+
+impl From<models::Retainer> for PlayerRetainer {
+    fn from(value: models::Retainer) -> Self {
+        Self {
+            id: value.id,
+            owner_content_id: value.owner_content_id as u64,
+            name: value.name,
+            gil: value.gil as u32,
+            storage: serde_json::from_str(&value.storage).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&PlayerRetainer> for models::Retainer {
+    fn from(value: &PlayerRetainer) -> Self {
+        Self {
+            id: value.id,
+            owner_content_id: value.owner_content_id as i64,
+            name: value.name.clone(),
+            gil: value.gil as i32,
+            storage: serde_json::to_string(&value.storage).unwrap(),
+        }
+    }
+}
+
+/// An item a retainer is selling on the market board.
+#[derive(Debug, Clone)]
+pub struct MarketOffering {
+    pub id: i64,
+    pub retainer_id: i64,
+    pub retainer_name: String,
+    pub owner_content_id: u64,
+    pub item: Item,
+    /// In Gil.
+    pub price_per_unit: u32,
+    /// When this item was put up for sale, as a UNIX timestamp.
+    pub listed_at: i64,
+}
+
+impl MarketOffering {
+    fn new(listing: models::MarketListing, retainer: models::Retainer) -> Self {
+        Self {
+            id: listing.id,
+            retainer_id: retainer.id,
+            retainer_name: retainer.name,
+            owner_content_id: retainer.owner_content_id as u64,
+            item: serde_json::from_str(&listing.item).unwrap_or_default(),
+            price_per_unit: listing.price_per_unit as u32,
+            listed_at: listing.listed_at,
+        }
+    }
+}
+
+/// A past purchase from the market board.
+#[derive(Debug, Clone)]
+pub struct MarketSale {
+    pub item_id: u32,
+    pub quantity: u32,
+    /// In Gil.
+    pub price_per_unit: u32,
+    pub hq: bool,
+    pub buyer_name: String,
+    pub retainer_name: String,
+    /// When this item was bought, as a UNIX timestamp.
+    pub sold_at: i64,
+}
+
+impl From<models::MarketSale> for MarketSale {
+    fn from(value: models::MarketSale) -> Self {
+        Self {
+            item_id: value.item_id as u32,
+            quantity: value.quantity as u32,
+            price_per_unit: value.price_per_unit as u32,
+            hq: value.hq,
+            buyer_name: value.buyer_name,
+            retainer_name: value.retainer_name,
+            sold_at: value.sold_at,
+        }
+    }
+}
+
+impl WorldDatabase {
+    /// Returns every retainer hired by this character, in the order they were hired.
+    pub fn find_retainers(&mut self, for_content_id: u64) -> Vec<PlayerRetainer> {
+        use schema::retainer::dsl::*;
+
+        retainer
+            .filter(owner_content_id.eq(for_content_id as i64))
+            .order(id.asc())
+            .select(models::Retainer::as_select())
+            .load(&mut self.connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(PlayerRetainer::from)
+            .collect()
+    }
+
+    /// Returns the retainer named `for_name`, but only if it belongs to this character.
+    pub fn find_retainer(&mut self, for_content_id: u64, for_name: &str) -> Option<PlayerRetainer> {
+        use schema::retainer::dsl::*;
+
+        retainer
+            .filter(owner_content_id.eq(for_content_id as i64))
+            .filter(name.eq(for_name))
+            .select(models::Retainer::as_select())
+            .first(&mut self.connection)
+            .ok()
+            .map(PlayerRetainer::from)
+    }
+
+    /// Hires a new retainer for this character. Returns None if someone else already has a retainer with this name.
+    pub fn hire_retainer(&mut self, for_content_id: u64, for_name: &str) -> Option<PlayerRetainer> {
+        use schema::retainer::dsl::*;
+
+        let next_id = if let Ok(highest) = retainer
+            .select(id)
+            .order(id.desc())
+            .first::<i64>(&mut self.connection)
+        {
+            highest + 1
+        } else {
+            1 // Start from a safe default if there are no retainers.
+        };
+
+        let new_retainer = PlayerRetainer {
+            id: next_id,
+            owner_content_id: for_content_id,
+            name: for_name.to_string(),
+            gil: 0,
+            storage: Vec::new(),
+        };
+
+        // Names are unique, so this fails if it's already taken.
+        diesel::insert_into(retainer)
+            .values(models::Retainer::from(&new_retainer))
+            .execute(&mut self.connection)
+            .ok()?;
+
+        Some(new_retainer)
+    }
+
+    /// Saves the gil and items held by this retainer.
+    pub fn commit_retainer(&mut self, data: &PlayerRetainer) {
+        use schema::retainer::dsl::*;
+
+        diesel::update(retainer.filter(id.eq(data.id)))
+            .set(models::Retainer::from(data))
+            .execute(&mut self.connection)
+            .unwrap();
+    }
+
+    /// Dismisses this retainer, and takes down anything they were still selling.
+    pub fn dismiss_retainer(&mut self, for_retainer_id: i64) {
+        {
+            use schema::market_listing::dsl::*;
+
+            diesel::delete(market_listing.filter(retainer_id.eq(for_retainer_id)))
+                .execute(&mut self.connection)
+                .unwrap();
+        }
+
+        {
+            use schema::retainer::dsl::*;
+
+            diesel::delete(retainer.filter(id.eq(for_retainer_id)))
+                .execute(&mut self.connection)
+                .unwrap();
+        }
+    }
+
+    /// Dismisses every retainer hired by this character, e.g. when they're deleted.
+    pub fn dismiss_all_retainers(&mut self, for_content_id: u64) {
+        for hired in self.find_retainers(for_content_id) {
+            self.dismiss_retainer(hired.id);
+        }
+    }
+
+    /// Returns everything this retainer is selling.
+    pub fn find_retainer_listings(&mut self, for_retainer_id: i64) -> Vec<MarketOffering> {
+        use schema::{market_listing, retainer};
+
+        market_listing::table
+            .inner_join(retainer::table)
+            .filter(market_listing::retainer_id.eq(for_retainer_id))
+            .order(market_listing::id.asc())
+            .select((
+                models::MarketListing::as_select(),
+                models::Retainer::as_select(),
+            ))
+            .load(&mut self.connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(listing, seller)| MarketOffering::new(listing, seller))
+            .collect()
+    }
+
+    /// Returns everyone selling this item, cheapest first.
+    pub fn find_offerings(&mut self, for_item_id: u32) -> Vec<MarketOffering> {
+        use schema::{market_listing, retainer};
+
+        market_listing::table
+            .inner_join(retainer::table)
+            .filter(market_listing::item_id.eq(for_item_id as i32))
+            .order((
+                market_listing::price_per_unit.asc(),
+                market_listing::listed_at.asc(),
+            ))
+            .select((
+                models::MarketListing::as_select(),
+                models::Retainer::as_select(),
+            ))
+            .load(&mut self.connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(listing, seller)| MarketOffering::new(listing, seller))
+            .collect()
+    }
+
+    /// Returns a single listing, if it's still for sale.
+    pub fn find_offering(&mut self, for_listing_id: i64) -> Option<MarketOffering> {
+        use schema::{market_listing, retainer};
+
+        market_listing::table
+            .inner_join(retainer::table)
+            .filter(market_listing::id.eq(for_listing_id))
+            .select((
+                models::MarketListing::as_select(),
+                models::Retainer::as_select(),
+            ))
+            .first(&mut self.connection)
+            .ok()
+            .map(|(listing, seller)| MarketOffering::new(listing, seller))
+    }
+
+    /// Returns every item currently for sale on the market board, along with how many listings there are for each.
+    pub fn find_listed_items(&mut self) -> Vec<(u32, u32)> {
+        use schema::market_listing::dsl::*;
+
+        market_listing
+            .group_by(item_id)
+            .select((item_id, diesel::dsl::count(id)))
+            .order(item_id.asc())
+            .load::<(i32, i64)>(&mut self.connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(listed_id, count)| (listed_id as u32, count as u32))
+            .collect()
+    }
+
+    /// Puts `for_item` up for sale by this retainer, and returns the new listing's ID.
+    pub fn add_listing(&mut self, for_retainer_id: i64, for_item: Item, for_price: u32) -> i64 {
+        use schema::market_listing::dsl::*;
+
+        let next_id = if let Ok(highest) = market_listing
+            .select(id)
+            .order(id.desc())
+            .first::<i64>(&mut self.connection)
+        {
+            highest + 1
+        } else {
+            1 // Start from a safe default if there are no listings.
+        };
+
+        let time = diesel::select(unixepoch())
+            .get_result::<i64>(&mut self.connection)
+            .unwrap();
+
+        diesel::insert_into(market_listing)
+            .values(models::MarketListing {
+                id: next_id,
+                retainer_id: for_retainer_id,
+                item_id: for_item.item_id as i32,
+                item: serde_json::to_string(&for_item).unwrap(),
+                price_per_unit: for_price as i32,
+                listed_at: time,
+            })
+            .execute(&mut self.connection)
+            .unwrap();
+
+        next_id
+    }
+
+    /// Takes a listing off of the market board, and returns what was being sold.
+    pub fn remove_listing(&mut self, for_listing_id: i64) -> Option<MarketOffering> {
+        use schema::market_listing::dsl::*;
+
+        let offering = self.find_offering(for_listing_id)?;
+
+        diesel::delete(market_listing.filter(id.eq(for_listing_id)))
+            .execute(&mut self.connection)
+            .unwrap();
+
+        Some(offering)
+    }
+
+    /// Sells a listing to `for_buyer_name`: it's taken off of the market board, the proceeds are given to the retainer who was selling it, and the sale is recorded in the item's history.
+    ///
+    /// The buyer's gil should be taken in the same transaction. Returns None if the listing was already sold.
+    pub fn purchase_listing(
+        &mut self,
+        for_listing_id: i64,
+        for_buyer_name: &str,
+        proceeds: u32,
+    ) -> Option<MarketOffering> {
+        let offering = self.remove_listing(for_listing_id)?;
+
+        {
+            use schema::retainer::dsl::*;
+
+            let current_gil = retainer
+                .filter(id.eq(offering.retainer_id))
+                .select(gil)
+                .first::<i32>(&mut self.connection)
+                .unwrap_or_default() as u32;
+
+            diesel::update(retainer.filter(id.eq(offering.retainer_id)))
+                .set(gil.eq(current_gil.saturating_add(proceeds).min(MAX_RETAINER_GIL) as i32))
+                .execute(&mut self.connection)
+                .unwrap();
+        }
+
+        self.add_sale(MarketSale {
+            item_id: offering.item.item_id,
+            quantity: offering.item.quantity,
+            price_per_unit: offering.price_per_unit,
+            hq: offering.item.item_flags & ITEM_FLAG_HQ != 0,
+            buyer_name: for_buyer_name.to_string(),
+            retainer_name: offering.retainer_name.clone(),
+            sold_at: 0, // Filled in by add_sale.
+        });
+
+        Some(offering)
+    }
+
+    /// Records a sale in the item's history, forgetting the oldest ones once there's more than `MAX_SALE_HISTORY`.
+    fn add_sale(&mut self, sale: MarketSale) {
+        use schema::market_sale::dsl::*;
+
+        let next_id = if let Ok(highest) = market_sale
+            .select(id)
+            .order(id.desc())
+            .first::<i64>(&mut self.connection)
+        {
+            highest + 1
+        } else {
+            1 // Start from a safe default if there are no sales.
+        };
+
+        let time = diesel::select(unixepoch())
+            .get_result::<i64>(&mut self.connection)
+            .unwrap();
+
+        diesel::insert_into(market_sale)
+            .values(models::MarketSale {
+                id: next_id,
+                item_id: sale.item_id as i32,
+                quantity: sale.quantity as i32,
+                price_per_unit: sale.price_per_unit as i32,
+                hq: sale.hq,
+                buyer_name: sale.buyer_name,
+                retainer_name: sale.retainer_name,
+                sold_at: time,
+            })
+            .execute(&mut self.connection)
+            .unwrap();
+
+        let expired: Vec<i64> = market_sale
+            .filter(item_id.eq(sale.item_id as i32))
+            .order(id.desc())
+            .offset(MAX_SALE_HISTORY as i64)
+            .select(id)
+            .load(&mut self.connection)
+            .unwrap_or_default();
+        if !expired.is_empty() {
+            diesel::delete(market_sale.filter(id.eq_any(expired)))
+                .execute(&mut self.connection)
+                .unwrap();
+        }
+    }
+
+    /// Returns the most recent sales of this item, newest first.
+    pub fn find_sale_history(&mut self, for_item_id: u32) -> Vec<MarketSale> {
+        use schema::market_sale::dsl::*;
+
+        market_sale
+            .filter(item_id.eq(for_item_id as i32))
+            .order(id.desc())
+            .limit(MAX_SALE_HISTORY as i64)
+            .select(models::MarketSale::as_select())
+            .load(&mut self.connection)
+            .unwrap_or_default()
+            .into_iter()
+            .map(MarketSale::from)
+            .collect()
+    }
+}
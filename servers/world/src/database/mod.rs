@@ -1,7 +1,12 @@
 mod character;
 mod friends;
+mod housing;
+pub use housing::{PlacedFurniture, PlayerHouse};
 mod linkshell;
+mod lockout;
 mod mail;
+mod market;
+pub use market::{MarketOffering, MarketSale, PlayerRetainer};
 
 mod models;
 pub use models::{
@@ -12,8 +17,12 @@ pub use models::{
 mod schema;
 mod social;
 
-use diesel::{Connection, QueryDsl, RunQueryDsl, SqliteConnection, prelude::*};
-use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+use diesel::{
+    Connection, QueryDsl, RunQueryDsl, SqliteConnection,
+    connection::{AnsiTransactionManager, TransactionManager},
+    prelude::*,
+};
+use diesel_migrations::{EmbeddedMigrations, embed_migrations};
 use kawari::common::ObjectId;
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
@@ -33,11 +42,26 @@ impl WorldDatabase {
         let mut connection =
             SqliteConnection::establish("world.db").expect("Failed to open database!");
 
-        connection.run_pending_migrations(MIGRATIONS).unwrap();
+        kawari::database::run_migrations(&mut connection, MIGRATIONS);
 
         Self { connection }
     }
 
+    /// Checks whether the pending migrations would apply cleanly to the database, without changing it.
+    pub fn check_migrations() -> bool {
+        kawari::database::check_database_migrations("world.db", MIGRATIONS)
+    }
+
+    /// Runs `f` as a single transaction, so other connections never see only some of its changes.
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        AnsiTransactionManager::begin_transaction(&mut self.connection)
+            .expect("Failed to begin transaction!");
+        let result = f(self);
+        AnsiTransactionManager::commit_transaction(&mut self.connection)
+            .expect("Failed to commit transaction!");
+        result
+    }
+
     fn generate_content_id() -> u32 {
         fastrand::u32(..)
     }
@@ -101,3 +125,41 @@ extern "SQL" {
 extern "SQL" {
     fn unixepoch() -> diesel::sql_types::BigInt;
 }
+
+#[cfg(test)]
+mod tests {
+    use diesel::connection::SimpleConnection;
+
+    use super::*;
+
+    #[test]
+    fn test_legacy_migration() {
+        let mut connection =
+            SqliteConnection::establish(":memory:").expect("Failed to open database!");
+        connection
+            .batch_execute(
+                &std::fs::read_to_string("../../resources/data/tests/legacy_world.sql").unwrap(),
+            )
+            .unwrap();
+
+        // Checking shouldn't change anything.
+        assert!(kawari::database::check_migrations(
+            &mut connection,
+            MIGRATIONS
+        ));
+        assert_eq!(kawari::database::schema_version(&mut connection), "current");
+
+        // But actually migrating should bring it up to date.
+        assert_eq!(
+            kawari::database::run_migrations(&mut connection, MIGRATIONS),
+            "20261018000002"
+        );
+
+        // Existing characters should still be there, and the new tables usable.
+        let mut database = WorldDatabase { connection };
+        assert_eq!(database.find_service_account(1), 2);
+        assert!(database.find_owned_house(1).is_none());
+        assert!(database.find_retainers(1).is_empty());
+        assert!(!database.is_locked_out(1, 30001, 0));
+    }
+}
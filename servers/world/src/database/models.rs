@@ -445,3 +445,107 @@ pub struct Buddy {
     pub exp: i32,
     pub color: i32,
 }
+
+#[derive(Insertable, Identifiable, Queryable, Selectable, AsChangeset, Debug, Default, Clone)]
+#[diesel(table_name = super::schema::house)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(primary_key(id))]
+pub struct House {
+    pub id: i64,
+    pub territory_type_id: i32,
+    pub ward: i32,
+    pub plot: i32,
+    pub owner_content_id: i64,
+    pub flags: i32,
+    pub exterior: String,
+    pub interior: String,
+    pub storage: String,
+}
+
+#[derive(
+    Insertable,
+    Identifiable,
+    Queryable,
+    Selectable,
+    Associations,
+    AsChangeset,
+    Debug,
+    Default,
+    Clone,
+)]
+#[diesel(table_name = super::schema::furniture)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(belongs_to(House, foreign_key = house_id))]
+#[diesel(primary_key(id))]
+pub struct Furniture {
+    pub id: i64,
+    pub house_id: i64,
+    pub indoors: bool,
+    pub container: i32,
+    pub slot: i32,
+    pub catalog_id: i32,
+    pub stain: i32,
+    pub rotation: f64,
+    pub position: Position,
+}
+
+#[derive(Insertable, Identifiable, Queryable, Selectable, AsChangeset, Debug, Default, Clone)]
+#[diesel(table_name = super::schema::retainer)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(primary_key(id))]
+pub struct Retainer {
+    pub id: i64,
+    pub owner_content_id: i64,
+    pub name: String,
+    pub gil: i32,
+    pub storage: String,
+}
+
+#[derive(
+    Insertable,
+    Identifiable,
+    Queryable,
+    Selectable,
+    Associations,
+    AsChangeset,
+    Debug,
+    Default,
+    Clone,
+)]
+#[diesel(table_name = super::schema::market_listing)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(belongs_to(Retainer, foreign_key = retainer_id))]
+#[diesel(primary_key(id))]
+pub struct MarketListing {
+    pub id: i64,
+    pub retainer_id: i64,
+    pub item_id: i32,
+    pub item: String,
+    pub price_per_unit: i32,
+    pub listed_at: i64,
+}
+
+#[derive(Insertable, Identifiable, Queryable, Selectable, AsChangeset, Debug, Default, Clone)]
+#[diesel(table_name = super::schema::market_sale)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(primary_key(id))]
+pub struct MarketSale {
+    pub id: i64,
+    pub item_id: i32,
+    pub quantity: i32,
+    pub price_per_unit: i32,
+    pub hq: bool,
+    pub buyer_name: String,
+    pub retainer_name: String,
+    pub sold_at: i64,
+}
+
+#[derive(Insertable, Identifiable, Queryable, Selectable, AsChangeset, Debug, Default, Clone)]
+#[diesel(table_name = super::schema::loot_lockout)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(primary_key(content_id, instance_content_id))]
+pub struct LootLockout {
+    pub content_id: i64,
+    pub instance_content_id: i32,
+    pub expires_at: i64,
+}
@@ -251,6 +251,82 @@ diesel::table! {
 
 diesel::joinable!(buddy -> character (content_id));
 
+diesel::table! {
+    house (id) {
+        id -> BigInt,
+        territory_type_id -> Integer,
+        ward -> Integer,
+        plot -> Integer,
+        owner_content_id -> BigInt,
+        flags -> Integer,
+        exterior -> Text,
+        interior -> Text,
+        storage -> Text,
+    }
+}
+
+diesel::table! {
+    furniture (id) {
+        id -> BigInt,
+        house_id -> BigInt,
+        indoors -> Bool,
+        container -> Integer,
+        slot -> Integer,
+        catalog_id -> Integer,
+        stain -> Integer,
+        rotation -> Double,
+        position -> Text,
+    }
+}
+
+diesel::joinable!(furniture -> house (house_id));
+
+diesel::table! {
+    retainer (id) {
+        id -> BigInt,
+        owner_content_id -> BigInt,
+        name -> Text,
+        gil -> Integer,
+        storage -> Text,
+    }
+}
+
+diesel::table! {
+    market_listing (id) {
+        id -> BigInt,
+        retainer_id -> BigInt,
+        item_id -> Integer,
+        item -> Text,
+        price_per_unit -> Integer,
+        listed_at -> BigInt,
+    }
+}
+
+diesel::joinable!(market_listing -> retainer (retainer_id));
+
+diesel::table! {
+    market_sale (id) {
+        id -> BigInt,
+        item_id -> Integer,
+        quantity -> Integer,
+        price_per_unit -> Integer,
+        hq -> Bool,
+        buyer_name -> Text,
+        retainer_name -> Text,
+        sold_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    loot_lockout (content_id, instance_content_id) {
+        content_id -> BigInt,
+        instance_content_id -> Integer,
+        expires_at -> BigInt,
+    }
+}
+
+diesel::joinable!(loot_lockout -> character (content_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     character,
     classjob,
@@ -267,4 +343,9 @@ diesel::allow_tables_to_appear_in_same_query!(
     search_info,
     grand_company,
     buddy,
+    house,
+    furniture,
+    retainer,
+    market_listing,
+    loot_lockout,
 );
@@ -12,6 +12,7 @@ use crate::{
     CraftingEventHandler, FateEventHandler, FishingEventHandler, GameData, GatheringEventHandler,
     GimmickAccessorEventHandler, InclusionShopEventHandler, InstanceContentEventHandler,
     LuaEventHandler, ShopEventHandler, SpecialShopEventHandler, ZoneConnection,
+    gathering_point_attempts,
 };
 
 use super::lua::LuaPlayer;
@@ -137,7 +138,9 @@ pub fn dispatch_event(
                 (_, _, count, _, _) = gamedata.get_gathering_point(handler_id.event_id());
             }
 
-            Some(Box::new(GatheringEventHandler::new(count)))
+            let attempts = gathering_point_attempts(connection, handler_id.event_id(), count);
+
+            Some(Box::new(GatheringEventHandler::new(count, attempts)))
         }
         HandlerType::Aetheryte => {
             // The Aetheryte sheet actually begins at 0, not 327680
@@ -1,14 +1,24 @@
 use async_trait::async_trait;
 use kawari::{
-    common::CharacterMode,
+    common::{
+        CharacterMode, CrafterStats, CraftingAction, CraftingError, CraftingSession, CraftingStep,
+    },
     ipc::zone::{ActorControlCategory, Condition, LiveEventType, SceneFlags},
 };
+use parking_lot::Mutex;
 
-use crate::{Event, EventHandler, ItemInfoQuery, ZoneConnection, inventory::Item, lua::LuaPlayer};
+use crate::{
+    Event, EventHandler, ItemInfoQuery, ZoneConnection,
+    inventory::{ITEM_FLAG_HQ, Item},
+    lua::LuaPlayer,
+};
 
 /// For crafting events.
 #[derive(Debug)]
-pub struct CraftingEventHandler;
+pub struct CraftingEventHandler {
+    /// The craft in progress, created once the player begins crafting.
+    session: Mutex<Option<CraftingSession>>,
+}
 
 impl Default for CraftingEventHandler {
     fn default() -> Self {
@@ -18,10 +28,71 @@ impl Default for CraftingEventHandler {
 
 impl CraftingEventHandler {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            session: Mutex::new(None),
+        }
+    }
+
+    /// Starts a new craft of the current recipe, using the player's current stats.
+    fn begin_crafting(&self, connection: &ZoneConnection) -> CraftingSession {
+        let base_parameters = connection.base_parameters();
+        let level;
+        {
+            let gamedata = connection.gamedata.lock();
+            level = connection.current_level(&gamedata) as u8;
+        }
+
+        let session = CraftingSession::new(
+            CrafterStats {
+                level,
+                craftsmanship: base_parameters.craftmanship,
+                control: base_parameters.control,
+                cp: base_parameters.cp,
+            },
+            connection.recipe.unwrap().crafting,
+        );
+        *self.session.lock() = Some(session.clone());
+
+        session
+    }
+
+    /// Sends the player's remaining CP, which is shown in place of their MP.
+    async fn send_cp(&self, connection: &mut ZoneConnection, cp: u32) {
+        let hp = connection.base_parameters().hp;
+        connection
+            .update_hp_mp(connection.player_data.character.actor_id, hp, cp as u16)
+            .await;
     }
 }
 
+/// Builds the scene parameters that update the crafting window after an action.
+fn crafting_update_params(
+    action_id: u32,
+    session: &CraftingSession,
+    step: &CraftingStep,
+) -> Vec<u32> {
+    vec![
+        9,
+        0,
+        0,
+        0,
+        action_id,
+        0,
+        1,
+        step.progress_increase,
+        session.progress,
+        step.quality_increase,
+        session.quality,
+        session.hq_chance() as u32,
+        session.durability,
+        step.durability_change as u32,
+        session.condition as u32,
+        1,   // ??
+        100, // ??
+        22,  // ??
+    ]
+}
+
 #[async_trait]
 impl EventHandler for CraftingEventHandler {
     async fn on_yield(
@@ -34,6 +105,9 @@ impl EventHandler for CraftingEventHandler {
         player: &mut LuaPlayer,
     ) {
         if results[0] == 0 {
+            let session = self.begin_crafting(connection);
+            self.send_cp(connection, session.cp).await;
+
             connection
                 .broadcast_actor_control(ActorControlCategory::LiveEvent {
                     event: LiveEventType::StartCraft {
@@ -50,61 +124,93 @@ impl EventHandler for CraftingEventHandler {
                 vec![2, connection.recipe.unwrap().id, 0, 1],
             );
         } else if results[0] == 10 {
-            let craft_action_id = results[1];
+            let craft_action_id = results[1] as u32;
+
+            let existing_session = self.session.lock().clone();
+            let mut session = existing_session.unwrap_or_else(|| self.begin_crafting(connection));
+
+            let step = match CraftingAction::from_id(craft_action_id) {
+                Some(action) => session.apply(action, fastrand::u8(..100)),
+                None => {
+                    tracing::warn!("Unsupported crafting action {craft_action_id}!");
+                    Ok(CraftingStep::default())
+                }
+            };
+
+            let step = match step {
+                Ok(step) => step,
+                Err(CraftingError::NotEnoughCp) => {
+                    connection.send_notice("You don't have enough CP.").await;
+                    CraftingStep::default()
+                }
+                Err(CraftingError::Finished) => CraftingStep::default(),
+            };
 
-            let animation_start;
-            let animation_end;
+            *self.session.lock() = Some(session.clone());
+
+            let animations;
             {
                 let mut gamedata = connection.gamedata.lock();
-                (animation_start, animation_end) =
-                    gamedata.get_craft_action_animations(craft_action_id as u32);
+                animations = gamedata.get_craft_action_animations(craft_action_id);
             }
 
-            // Play the basic touch animation and VFX:
-            connection
-                .broadcast_actor_control(ActorControlCategory::LiveEvent {
-                    event: LiveEventType::PlayAnimation {
-                        animation_start: animation_start as u32,
-                        animation_end: animation_end as u32,
-                    },
-                })
-                .await;
+            // Play the action's animation and VFX:
+            if let Some((animation_start, animation_end)) = animations {
+                connection
+                    .broadcast_actor_control(ActorControlCategory::LiveEvent {
+                        event: LiveEventType::PlayAnimation {
+                            animation_start: animation_start as u32,
+                            animation_end: animation_end as u32,
+                        },
+                    })
+                    .await;
+            }
+
+            self.send_cp(connection, session.cp).await;
 
-            // Increase to max
             player.play_scene(
                 0,
                 SceneFlags::NO_DEFAULT_CAMERA,
-                vec![
-                    9, 0, 0, 0, 100045, 0, 1, 19, // Progress
-                    27, 0, 0, 1, 50, 4294967286, 1, 1, 100, 22,
-                ],
+                crafting_update_params(craft_action_id, &session, &step),
             );
         } else if results[0] == 1 {
             player.play_scene(0, SceneFlags::NO_DEFAULT_CAMERA, vec![3, 0, 0, 0]);
         } else if results[0] == 11 {
-            // Add item to their inventory
-            {
-                let mut gamedata = connection.gamedata.lock();
-
-                if let Some(item_info) = gamedata.get_item_info(ItemInfoQuery::ById(
-                    connection.recipe.unwrap().item_id as u32,
-                )) {
-                    connection
-                        .player_data
-                        .inventory
-                        .add_in_next_free_slot(Item::new(&item_info, 1));
+            let session = self.session.lock().take();
+            let recipe = connection.recipe.unwrap();
+
+            if let Some(session) = session.filter(|session| session.is_successful()) {
+                // Add item to their inventory
+                {
+                    let mut gamedata = connection.gamedata.lock();
+
+                    if let Some(item_info) =
+                        gamedata.get_item_info(ItemInfoQuery::ById(recipe.item_id as u32))
+                    {
+                        let mut item = Item::new(&item_info, recipe.amount.max(1) as u32);
+                        if gamedata.can_item_be_hq(recipe.item_id as u32)
+                            && fastrand::u8(..100) < session.hq_chance()
+                        {
+                            item.item_flags |= ITEM_FLAG_HQ;
+                        }
+
+                        connection.player_data.inventory.add_in_next_free_slot(item);
+                    }
                 }
-            }
-
-            connection.send_inventory().await;
 
-            // The item was added to your inventory.
-            connection
-                .actor_control_self(ActorControlCategory::LogMessage {
-                    log_message: 789,
-                    id: connection.recipe.unwrap().item_id as u32,
-                })
-                .await;
+                connection.send_inventory().await;
+
+                // The item was added to your inventory.
+                connection
+                    .actor_control_self(ActorControlCategory::LogMessage {
+                        log_message: 789,
+                        id: recipe.item_id as u32,
+                    })
+                    .await;
+            } else {
+                // TODO: find the log message for a failed synthesis
+                connection.send_notice("Your synthesis fails!").await;
+            }
 
             connection
                 .broadcast_actor_control(ActorControlCategory::LiveEvent {
@@ -1,7 +1,139 @@
 use async_trait::async_trait;
-use kawari::{common::CharacterMode, ipc::zone::Condition};
+use kawari::{
+    common::{CharacterMode, FishConditions, FishTug, Hookset, choose_fish, timestamp_secs},
+    config::get_config,
+    ipc::zone::{ActorControlCategory, Condition},
+};
 
-use crate::{Event, EventHandler, ZoneConnection, lua::LuaPlayer};
+use crate::{Event, EventHandler, ItemInfoQuery, ZoneConnection, inventory::Item, lua::LuaPlayer};
+
+/// The fish on the end of the player's line.
+#[derive(Debug, Clone, Copy)]
+pub struct FishingState {
+    /// The item ID of the fish.
+    pub item_id: u32,
+    /// How hard this fish pulls on the line.
+    pub tug: FishTug,
+    /// Whether the fish has bitten yet. Hooking before then scares it off.
+    pub bitten: bool,
+}
+
+/// Reads the conditions for this fish, which fall back to the defaults if it doesn't have any.
+fn read_fish_conditions(item_id: u32) -> FishConditions {
+    let Some(path) = get_config().filesystem.locate_id_file("fishing", item_id) else {
+        return FishConditions::default();
+    };
+
+    match std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|err| err.to_string()))
+    {
+        Ok(conditions) => conditions,
+        Err(err) => {
+            tracing::warn!("Failed to load fish conditions from {path}: {err}");
+            FishConditions::default()
+        }
+    }
+}
+
+/// Picks which fish is going to bite the player's line, and how many seconds until it does. Returns `None` if nothing will bite here right now.
+pub fn cast_line(connection: &ZoneConnection) -> Option<(FishingState, f32)> {
+    let zone_id = connection.player_data.volatile.zone_id as u16;
+
+    // Only the bait the player selected counts, as long as they still have some.
+    let has_bait = |bait_id: u32| {
+        bait_id == connection.fishing_bait
+            && connection.player_data.inventory.pages.iter().any(|page| {
+                page.slots
+                    .iter()
+                    .any(|slot| slot.item_id == bait_id && slot.quantity > 0)
+            })
+    };
+
+    let now = timestamp_secs();
+    let candidates: Vec<(u32, FishConditions)>;
+    {
+        let mut gamedata = connection.gamedata.lock();
+        let fish =
+            gamedata.get_fishing_spot_fish(zone_id, connection.player_data.volatile.position);
+        let weather_id = gamedata.get_weather(zone_id as u32).unwrap_or_default();
+
+        candidates = fish
+            .into_iter()
+            .map(|item_id| {
+                let conditions = gamedata
+                    .fish_conditions
+                    .entry(item_id)
+                    .or_insert_with(|| read_fish_conditions(item_id))
+                    .clone();
+                (item_id, conditions)
+            })
+            .filter(|(_, conditions)| conditions.can_bite(&has_bait, weather_id, now))
+            .collect();
+    }
+
+    let (item_id, conditions) = choose_fish(&candidates, fastrand::f32())?;
+
+    Some((
+        FishingState {
+            item_id: *item_id,
+            tug: conditions.tug,
+            bitten: false,
+        },
+        conditions.bite_time(fastrand::f32()),
+    ))
+}
+
+/// Tries to land the fish on the player's line using `hookset`, and adds it to their inventory and fish log if successful.
+pub async fn land_fish(connection: &mut ZoneConnection, state: FishingState, hookset: Hookset) {
+    if !state.bitten {
+        connection
+            .send_notice("You reel in your line too early. Nothing bit.")
+            .await;
+        return;
+    }
+
+    if fastrand::u8(..100) >= hookset.hook_chance(state.tug) {
+        connection.send_notice("The fish got away...").await;
+        return;
+    }
+
+    let fish_parameter_id;
+    {
+        let mut gamedata = connection.gamedata.lock();
+
+        let Some(item_info) = gamedata.get_item_info(ItemInfoQuery::ById(state.item_id)) else {
+            return;
+        };
+        connection
+            .player_data
+            .inventory
+            .add_in_next_free_slot(Item::new(&item_info, 1));
+
+        fish_parameter_id = gamedata.get_fish_parameter_id(state.item_id);
+    }
+
+    connection.send_inventory().await;
+
+    // Record it in the fish log, if this is the first time they caught it.
+    if let Some(fish_parameter_id) = fish_parameter_id
+        && !connection
+            .player_data
+            .unlock
+            .caught_fish
+            .contains(fish_parameter_id)
+    {
+        connection.toggle_caught_fish(fish_parameter_id).await;
+    }
+
+    // The item was added to your inventory.
+    connection
+        .actor_control_self(ActorControlCategory::LogMessage {
+            log_message: 789,
+            id: state.item_id,
+        })
+        .await;
+}
 
 /// For fishing events.
 #[derive(Debug)]
@@ -26,12 +158,13 @@ impl EventHandler for FishingEventHandler {
     async fn on_return(
         &self,
         _event: &Event,
-        _connection: &mut ZoneConnection,
+        connection: &mut ZoneConnection,
         scene: u16,
         _results: &[i32],
         player: &mut LuaPlayer,
     ) {
         if scene == Self::SCENE_HIDING_ROD {
+            connection.fishing = None;
             player.finish_event();
         }
     }
@@ -1,24 +1,135 @@
 use async_trait::async_trait;
 use bitflags::bitflags;
 use kawari::{
-    common::{CharacterMode, ObjectTypeId, value_to_flag_byte_index_value_quests},
+    common::{
+        CharacterMode, GATHERING_LOG_EXP_MULTIPLIER, ObjectTypeId, gathering_chance, gathering_exp,
+        gathering_point_respawn_time, hq_chance, timestamp_secs,
+        value_to_flag_byte_index_value_quests,
+    },
     ipc::zone::{
         ActorControlCategory, Condition, LiveEventType, SceneFlags, ServerZoneIpcData,
         ServerZoneIpcSegment,
     },
 };
+use parking_lot::Mutex;
+
+use crate::{
+    Event, EventHandler, GatheringPointItem, ItemInfoQuery, ToServer, ZoneConnection,
+    inventory::{ITEM_FLAG_HQ, Item},
+    lua::LuaPlayer,
+};
+
+/// What's left of a gathering point that the player has gathered from. The instance keeps track of these, so they last until it's closed.
+#[derive(Debug, Clone, Copy)]
+pub struct GatheringPointState {
+    /// How many more times the player can gather from this point.
+    pub attempts: u8,
+    /// When this point grows back as a UNIX timestamp, once it's been depleted.
+    pub respawn_time: Option<u32>,
+}
+
+impl GatheringPointState {
+    /// Returns how many attempts are left at `now`, taking into account whether it's grown back.
+    pub fn attempts_at(&self, now: u32, max_attempts: u8) -> u8 {
+        match self.respawn_time {
+            Some(respawn_time) if respawn_time <= now => max_attempts,
+            _ => self.attempts,
+        }
+    }
+}
+
+/// Returns how many attempts the player has left on the gathering point `id`, which has `max_attempts` when it's fully grown.
+pub fn gathering_point_attempts(connection: &ZoneConnection, id: u32, max_attempts: u8) -> u8 {
+    connection
+        .gathering_points
+        .get(&id)
+        .map(|state| state.attempts_at(timestamp_secs(), max_attempts))
+        .unwrap_or(max_attempts)
+}
 
-use crate::{Event, EventHandler, ItemInfoQuery, ZoneConnection, inventory::Item, lua::LuaPlayer};
+/// An item on a gathering point, along with the player's chances of gathering it.
+#[derive(Debug, Clone, Copy)]
+struct GatheringSlot {
+    item: GatheringPointItem,
+    /// From 0 to 100.
+    chance: u8,
+    /// From 0 to 100.
+    hq_chance: u8,
+}
 
 /// For gathering events.
 #[derive(Debug)]
 pub struct GatheringEventHandler {
-    count: u8,
+    /// How many attempts this gathering point has when it's fully grown.
+    max_attempts: u8,
+    /// How many attempts were left when the player started interacting with it.
+    attempts: u8,
+    /// Filled in once the player begins gathering, so it doesn't change while they're still here.
+    slots: Mutex<Option<[GatheringSlot; 8]>>,
 }
 
 impl GatheringEventHandler {
-    pub fn new(count: u8) -> Self {
-        Self { count }
+    pub fn new(max_attempts: u8, attempts: u8) -> Self {
+        Self {
+            max_attempts,
+            attempts,
+            slots: Mutex::new(None),
+        }
+    }
+
+    /// Returns the items on this gathering point, figuring out the player's chances the first time it's called.
+    fn begin_gathering(&self, event: &Event, connection: &ZoneConnection) -> [GatheringSlot; 8] {
+        let mut slots = self.slots.lock();
+        if let Some(slots) = *slots {
+            return slots;
+        }
+
+        let base_parameters = connection.base_parameters();
+
+        let mut gamedata = connection.gamedata.lock();
+        let items = gamedata.get_gathering_point_items(event.id.event_id());
+        let new_slots = items.map(|item| {
+            let recommended = gamedata.get_gathering_recommended_stats(item.level);
+            GatheringSlot {
+                item,
+                chance: gathering_chance(base_parameters.gathering, recommended),
+                hq_chance: if gamedata.can_item_be_hq(item.item_id as u32) {
+                    hq_chance(base_parameters.perception, recommended)
+                } else {
+                    0
+                },
+            }
+        });
+
+        *slots = Some(new_slots);
+        new_slots
+    }
+
+    /// Returns how many attempts the player has left on this gathering point.
+    fn remaining_attempts(&self, event: &Event, connection: &ZoneConnection) -> u8 {
+        gathering_point_attempts(connection, event.id.event_id(), self.max_attempts)
+    }
+
+    /// Uses up one attempt on this gathering point, and starts the respawn timer if that was the last one.
+    async fn use_attempt(&self, event: &Event, connection: &mut ZoneConnection) {
+        let now = timestamp_secs();
+        let attempts = self.remaining_attempts(event, connection).saturating_sub(1);
+        let state = GatheringPointState {
+            attempts,
+            respawn_time: (attempts == 0).then(|| gathering_point_respawn_time(now)),
+        };
+
+        connection
+            .gathering_points
+            .insert(event.id.event_id(), state);
+        connection
+            .handle
+            .send(ToServer::GatheredFromPoint(
+                connection.player_data.character.actor_id,
+                event.id.event_id(),
+                state,
+            ))
+            .await;
     }
 }
 
@@ -55,7 +166,7 @@ impl EventHandler for GatheringEventHandler {
                 0,
                 event.id.event_id(),
                 2147485320,
-                u32::from_le_bytes([self.count, 0, self.count, 0]), // first: count, second: ??, third: remaining count, fourth: ??
+                u32::from_le_bytes([self.max_attempts, 0, self.attempts, 0]), // first: count, second: ??, third: remaining count, fourth: ??
                 24,
                 1310820,
                 67305316,
@@ -129,46 +240,65 @@ impl EventHandler for GatheringEventHandler {
         results: &[i32],
         player: &mut LuaPlayer,
     ) {
-        // TODO: store this on begin gather
-        let items;
-        {
-            let mut gamedata = connection.gamedata.lock();
-            items = gamedata.get_gathering_point_items(event.id.event_id());
-        }
+        let slots = self.begin_gathering(event, connection);
 
         if results[2] == 2 {
             // gather
             let item_index = results[1];
-            let gather_item_id = items[item_index as usize].item_id;
+            let slot = slots[item_index as usize];
+            let gather_item_id = slot.item.item_id;
+
+            if self.remaining_attempts(event, connection) == 0 {
+                player.finish_event();
+                return;
+            }
+            self.use_attempt(event, connection).await;
 
             // plays the animation
             player.play_scene(1, SceneFlags::NO_DEFAULT_CAMERA, vec![2, 266]);
 
+            // TODO: find the log message for failing to gather
+            if fastrand::u8(..100) >= slot.chance {
+                let item_name = connection.item_name(gather_item_id as u32);
+                connection
+                    .send_notice(&format!("You fail to gather the {item_name}."))
+                    .await;
+                return;
+            }
+
             // Add item to their inventory
+            let base_exp;
+            let player_level;
             {
                 let mut gamedata = connection.gamedata.lock();
 
                 if let Some(item_info) =
                     gamedata.get_item_info(ItemInfoQuery::ById(gather_item_id as u32))
                 {
-                    connection
-                        .player_data
-                        .inventory
-                        .add_in_next_free_slot(Item::new(&item_info, 1));
+                    let mut item = Item::new(&item_info, 1);
+                    if fastrand::u8(..100) < slot.hq_chance {
+                        item.item_flags |= ITEM_FLAG_HQ;
+                    }
+
+                    connection.player_data.inventory.add_in_next_free_slot(item);
                 }
+
+                base_exp = gamedata.get_gathering_exp(slot.item.level);
+                player_level = connection.current_level(&gamedata) as u8;
             }
 
             connection.send_inventory().await;
 
+            let exp = gathering_exp(base_exp, player_level, slot.item.level);
+
             if !player
                 .player_data
                 .quest
                 .gathered_gathering_items
-                .contains(items[item_index as usize].gathering_id as u32)
+                .contains(slot.item.gathering_id as u32)
             {
-                let (value, index) = value_to_flag_byte_index_value_quests(
-                    items[item_index as usize].gathering_id as u32,
-                );
+                let (value, index) =
+                    value_to_flag_byte_index_value_quests(slot.item.gathering_id as u32);
 
                 connection.player_data.quest.gathered_gathering_items.data[index as usize] ^= value;
 
@@ -187,15 +317,12 @@ impl EventHandler for GatheringEventHandler {
                     })
                     .await;
 
-                // Add EXP
-                // TODO: don't use placeholder EXP
                 // TODO: the first time EXP doesn't take into account bonus?
-                connection.add_exp(516).await;
+                connection.add_exp(exp * GATHERING_LOG_EXP_MULTIPLIER).await;
             }
 
             // Add EXP
-            // TODO: don't use placeholder EXP
-            connection.add_exp(96).await;
+            connection.add_exp(exp).await;
 
             // The item was added to your inventory.
             connection
@@ -214,14 +341,28 @@ impl EventHandler for GatheringEventHandler {
             return;
         }
 
+        let attempts = self.remaining_attempts(event, connection);
+        if attempts == 0 {
+            connection
+                .send_notice("This gathering point has been depleted, come back later.")
+                .await;
+            player.finish_event();
+            return;
+        }
+
         let mut params = vec![
             7,
             event.id.event_id(),
             2147485320,
-            u32::from_le_bytes([self.count, 0, self.count, 0]), // first: count, second: ??, third: remaining count, fourth: ??
+            u32::from_le_bytes([self.max_attempts, 0, attempts, 0]), // first: count, second: ??, third: remaining count, fourth: ??
         ];
 
-        for item in items {
+        for GatheringSlot {
+            item,
+            chance,
+            hq_chance,
+        } in slots
+        {
             let mut flags = GatheringItemFlag::default();
             if item.hidden {
                 flags.insert(GatheringItemFlag::HIDDEN);
@@ -238,7 +379,7 @@ impl EventHandler for GatheringEventHandler {
             params.append(&mut vec![
                 item.gathering_id as u32,
                 u32::from_le_bytes([0, 0, item.level, 0]), // first: ??, second: ??, third: displayed level, fourth: ??
-                u32::from_le_bytes([100, 0, 1, flags.0]), // first: gathering chance, second: HQ gathering chance, third: count, fourth: flag (see above)
+                u32::from_le_bytes([chance, hq_chance, 1, flags.0]), // first: gathering chance, second: HQ gathering chance, third: count, fourth: flag (see above)
                 0,
                 0,
                 0,
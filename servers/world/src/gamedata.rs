@@ -1,6 +1,9 @@
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
+use glam::Vec2;
 use icarus::Action::ActionSheet;
 use icarus::AetherCurrentCompFlgSet::AetherCurrentCompFlgSetSheet;
 use icarus::Aetheryte::AetheryteSheet;
@@ -22,7 +25,10 @@ use icarus::EquipSlotCategory::EquipSlotCategorySheet;
 use icarus::Fate::FateSheet;
 use icarus::FateProgressUI::FateProgressUISheet;
 use icarus::FateShop::FateShopSheet;
+use icarus::FishParameter::FishParameterSheet;
+use icarus::FishingSpot::FishingSpotSheet;
 use icarus::FittingShopCategoryItem::FittingShopCategoryItemSheet;
+use icarus::GatheringExp::GatheringExpSheet;
 use icarus::GatheringItem::GatheringItemSheet;
 use icarus::GatheringItemLevelConvertTable::GatheringItemLevelConvertTableSheet;
 use icarus::GatheringPoint::GatheringPointSheet;
@@ -40,6 +46,7 @@ use icarus::ItemLevel::ItemLevelSheet;
 use icarus::Mount::MountSheet;
 use icarus::NpcEquip::NpcEquipSheet;
 use icarus::NpcYell::NpcYellSheet;
+use icarus::Omen::OmenSheet;
 use icarus::OnlineStatus::OnlineStatusSheet;
 use icarus::Opening::OpeningSheet;
 use icarus::ParamGrow::{ParamGrowRow, ParamGrowSheet};
@@ -48,10 +55,13 @@ use icarus::PreHandler::PreHandlerSheet;
 use icarus::PublicContent::PublicContentSheet;
 use icarus::Quest::QuestSheet;
 use icarus::Recipe::RecipeSheet;
+use icarus::RecipeLevelTable::{RecipeLevelTableRow, RecipeLevelTableSheet};
 use icarus::SpecialShop::SpecialShopSheet;
+use icarus::Status::StatusSheet;
 use icarus::SwitchTalkVariation::{SwitchTalkVariationRow, SwitchTalkVariationSheet};
 use icarus::TerritoryType::TerritoryTypeSheet;
 use icarus::TopicSelect::TopicSelectSheet;
+use icarus::Treasure::TreasureSheet;
 use icarus::WarpLogic::WarpLogicSheet;
 use icarus::WeatherRate::WeatherRateSheet;
 use icarus::{Tribe::TribeSheet, Warp::WarpSheet};
@@ -63,13 +73,16 @@ use physis::savedata::chardat::CustomizeData;
 use physis::{Language, TerritoryIntendedUse};
 
 use kawari::common::{
-    FateRule, InstanceContentType, PublicContentType,
-    get_aether_current_comp_flg_set_to_screenimage,
+    ActionAoe, ActionTiming, AggroProfiles, CraftingRecipe, FateRule, FishConditions,
+    InstanceContentType, LevelModifiers, PublicContentType,
+    get_aether_current_comp_flg_set_to_screenimage, level_slopes, main_stat_baseline,
 };
-use kawari::common::{LegacyEquipmentModelId, WeaponModelId, timestamp_secs};
+use kawari::common::{LegacyEquipmentModelId, Position, WeaponModelId, timestamp_secs};
 use kawari::config::get_config;
 use strum::FromRepr;
 
+use crate::StatusInfo;
+
 /// Convenient methods built on top of Physis to access data relevant to the server
 #[derive(Clone)]
 pub struct GameData {
@@ -106,13 +119,29 @@ pub struct GameData {
     pub gathering_point_base_sheet: GatheringPointBaseSheet,
     pub gathering_item_sheet: GatheringItemSheet,
     pub gathering_item_level_convert_table_sheet: GatheringItemLevelConvertTableSheet,
+    pub gathering_exp_sheet: GatheringExpSheet,
+    pub recipe_sheet: RecipeSheet,
+    pub recipe_level_table_sheet: RecipeLevelTableSheet,
+    pub craft_action_sheet: CraftActionSheet,
     pub public_content_sheet: PublicContentSheet,
     pub fate_sheet: FateSheet,
     pub dawn_content_sheet: DawnContentSheet,
     pub fate_progress_ui_sheet: FateProgressUISheet,
+    pub status_sheet: StatusSheet,
+    pub omen_sheet: OmenSheet,
+    pub treasure_sheet: TreasureSheet,
 
     pub gimmick_rect_lookup: HashMap<u32, u32>,
     pub fate_event_range_lookup: HashMap<u32, u32>,
+    /// The FishingSpots in each zone, with their position and the fish that can be caught there.
+    pub fishing_spots: HashMap<u16, Vec<(Vec2, Vec<u32>)>>,
+    /// FishParameter ids, by the item id of the fish.
+    pub fish_parameter_lookup: HashMap<u32, u32>,
+
+    /// These come from our own resources instead of the game, but are shared by every instance.
+    pub aggro_profiles: Arc<AggroProfiles>,
+    /// When each fish can bite, read from our own resources the first time it's needed.
+    pub fish_conditions: HashMap<u32, FishConditions>,
 }
 
 impl Default for GameData {
@@ -156,6 +185,10 @@ pub struct ItemRow {
     pub item_level: u16,
     /// The item's ClassJobCategory.
     pub classjob_category: u8,
+    /// Index into the ItemSearchCategory Excel sheet, for where it's found on the market board. Zero if it can't be sold there.
+    pub item_search_category: u8,
+    /// Whether this item can't be traded or sold to other players.
+    pub is_untradable: bool,
 
     /// Stat modifier stuff
     pub base_param_ids: [u8; 6],
@@ -165,6 +198,10 @@ pub struct ItemRow {
     pub defense: u16,
     /// Magic defense;
     pub magic_defense: u16,
+    /// Physical damage, for weapons.
+    pub physical_damage: u16,
+    /// Magic damage, for weapons.
+    pub magic_damage: u16,
 }
 
 #[derive(Debug)]
@@ -189,6 +226,31 @@ pub struct ItemEquipRestrictions {
 pub struct Recipe {
     pub id: u32,
     pub item_id: i32,
+    /// How many items are made at once.
+    pub amount: u8,
+    pub crafting: CraftingRecipe,
+}
+
+/// What's awarded for clearing an instanced duty.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DutyRewards {
+    pub exp: i32,
+    pub gil: i32,
+    /// Allagan Tomestones of Poetics.
+    pub tomestones: i32,
+    /// Extra EXP awarded the first time this duty is cleared.
+    pub first_clear_exp: i32,
+    /// Extra gil awarded the first time this duty is cleared.
+    pub first_clear_gil: i32,
+}
+
+/// What gear of a given item level provides, from the ItemLevel sheet.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ItemLevelStats {
+    pub physical_damage: u16,
+    pub magic_damage: u16,
+    pub defense: u16,
+    pub magic_defense: u16,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -204,15 +266,22 @@ pub struct Modifiers {
 }
 
 impl Modifiers {
+    /// Returns the modifier for this attribute, as a percentage.
+    pub fn get(&self, index: u8) -> Option<u16> {
+        match index {
+            1 => Some(self.strength),
+            2 => Some(self.dexterity),
+            3 => Some(self.vitality),
+            4 => Some(self.intelligence),
+            5 => Some(self.mind),
+            6 => Some(self.piety),
+            _ => None,
+        }
+    }
+
     pub fn apply_to(&self, index: u8, value: u32) -> u32 {
-        let modifier = match index {
-            1 => self.strength,
-            2 => self.dexterity,
-            3 => self.vitality,
-            4 => self.intelligence,
-            5 => self.mind,
-            6 => self.piety,
-            _ => return value,
+        let Some(modifier) = self.get(index) else {
+            return value;
         };
 
         (value as f32 * (modifier as f32 / 100.0)).floor() as u32
@@ -370,6 +439,17 @@ impl GameData {
             GatheringItemLevelConvertTableSheet::read_from(&mut resource_resolver, Language::None)
                 .unwrap();
 
+        let gathering_exp_sheet =
+            GatheringExpSheet::read_from(&mut resource_resolver, Language::None).unwrap();
+
+        let recipe_sheet = RecipeSheet::read_from(&mut resource_resolver, Language::None).unwrap();
+
+        let recipe_level_table_sheet =
+            RecipeLevelTableSheet::read_from(&mut resource_resolver, Language::None).unwrap();
+
+        let craft_action_sheet =
+            CraftActionSheet::read_from(&mut resource_resolver, config.world.language()).unwrap();
+
         let public_content_sheet =
             PublicContentSheet::read_from(&mut resource_resolver, config.world.language()).unwrap();
 
@@ -382,6 +462,14 @@ impl GameData {
         let fate_progress_ui_sheet =
             FateProgressUISheet::read_from(&mut resource_resolver, Language::None).unwrap();
 
+        let status_sheet =
+            StatusSheet::read_from(&mut resource_resolver, config.world.language()).unwrap();
+
+        let omen_sheet = OmenSheet::read_from(&mut resource_resolver, Language::None).unwrap();
+
+        let treasure_sheet =
+            TreasureSheet::read_from(&mut resource_resolver, Language::None).unwrap();
+
         let mut gimmick_rect_lookup = HashMap::new();
         for (id, row) in gimmick_rect_sheet.into_iter().flatten_subrows() {
             gimmick_rect_lookup.insert(row.LayoutID, id);
@@ -392,6 +480,29 @@ impl GameData {
             fate_event_range_lookup.insert(row.Location, id);
         }
 
+        let mut fishing_spots: HashMap<u16, Vec<(Vec2, Vec<u32>)>> = HashMap::new();
+        let fishing_spot_sheet =
+            FishingSpotSheet::read_from(&mut resource_resolver, Language::None).unwrap();
+        for (_, row) in fishing_spot_sheet.into_iter().flatten_subrows() {
+            let fish = row
+                .Item
+                .iter()
+                .map(|item_id| *item_id as u32)
+                .filter(|item_id| *item_id != 0)
+                .collect();
+            fishing_spots
+                .entry(row.TerritoryType as u16)
+                .or_default()
+                .push((Vec2::new(row.X as f32, row.Z as f32), fish));
+        }
+
+        let mut fish_parameter_lookup = HashMap::new();
+        let fish_parameter_sheet =
+            FishParameterSheet::read_from(&mut resource_resolver, Language::None).unwrap();
+        for (id, row) in fish_parameter_sheet.into_iter().flatten_subrows() {
+            fish_parameter_lookup.entry(row.Item as u32).or_insert(id);
+        }
+
         Self {
             resource: resource_resolver,
             item_sheet,
@@ -424,12 +535,23 @@ impl GameData {
             gathering_point_base_sheet,
             gathering_item_sheet,
             gathering_item_level_convert_table_sheet,
+            gathering_exp_sheet,
+            recipe_sheet,
+            recipe_level_table_sheet,
+            craft_action_sheet,
             public_content_sheet,
             fate_sheet,
             gimmick_rect_lookup,
             fate_event_range_lookup,
+            fishing_spots,
+            fish_parameter_lookup,
             dawn_content_sheet,
             fate_progress_ui_sheet,
+            status_sheet,
+            omen_sheet,
+            treasure_sheet,
+            aggro_profiles: Arc::new(AggroProfiles::load(&config.filesystem)),
+            fish_conditions: HashMap::new(),
         }
     }
 
@@ -489,10 +611,14 @@ impl GameData {
                 stack_size: matched_row.StackSize,
                 item_level: matched_row.LevelItem,
                 classjob_category: matched_row.ClassJobCategory,
+                item_search_category: matched_row.ItemSearchCategory,
+                is_untradable: matched_row.IsUntradable,
                 base_param_ids: matched_row.BaseParam,
                 base_param_values: matched_row.BaseParamValue,
                 defense: matched_row.DefensePhys,
                 magic_defense: matched_row.DefenseMag,
+                physical_damage: matched_row.DamagePhys,
+                magic_damage: matched_row.DamageMag,
                 equip_restrictions: self
                     .get_equipslot_restrictions(matched_row.EquipSlotCategory)
                     .unwrap(),
@@ -696,10 +822,33 @@ impl GameData {
         })
     }
 
-    pub fn get_casttime(&mut self, action_id: u32) -> Option<u16> {
+    /// Returns the cast time, recast time and cooldown group for this action.
+    pub fn get_action_timing(&mut self, action_id: u32) -> Option<ActionTiming> {
         let row = self.action_sheet.row(action_id)?;
 
-        Some(row.Cast100ms)
+        Some(ActionTiming {
+            cast_time: Duration::from_millis(row.Cast100ms as u64 * 100),
+            recast_time: Duration::from_millis(row.Recast100ms as u64 * 100),
+            cooldown_group: row.CooldownGroup,
+            max_charges: row.MaxCharges,
+            category: row.ActionCategory as u8,
+        })
+    }
+
+    /// Returns the area of effect of this action, or None if it only hits a single target.
+    pub fn get_action_aoe(&mut self, action_id: u32) -> Option<ActionAoe> {
+        let row = self.action_sheet.row(action_id)?;
+
+        let omen_path = if row.Omen != 0 {
+            self.omen_sheet
+                .row(row.Omen as u32)
+                .map(|omen| omen.Path)
+                .unwrap_or_default()
+        } else {
+            String::default()
+        };
+
+        ActionAoe::from_action(row.CastType, row.EffectRange, row.XAxisModifier, &omen_path)
     }
 
     /// Calculates the current weather at the current time
@@ -898,6 +1047,33 @@ impl GameData {
         InstanceContentType::from_repr(instance_content_row.InstanceContentType)
     }
 
+    /// Returns what's awarded for clearing this InstanceContent.
+    pub fn get_duty_rewards(&mut self, content_id: u16) -> Option<DutyRewards> {
+        let row = self.instance_content_sheet.row(content_id as u32)?;
+
+        // We don't track when the final boss dies separately, so its rewards are given at the end.
+        Some(DutyRewards {
+            exp: row.InstanceClearExp as i32 + row.FinalBossExp as i32,
+            gil: row.InstanceClearGil as i32,
+            tomestones: row.FinalBossCurrencyA as i32,
+            first_clear_exp: row.NewPlayerBonusExp as i32,
+            first_clear_gil: row.NewPlayerBonusGil as i32,
+        })
+    }
+
+    /// Returns the items that can be found in this treasure coffer.
+    pub fn get_treasure_items(&mut self, base_id: u32) -> Vec<u32> {
+        let Some(row) = self.treasure_sheet.row(base_id) else {
+            return Vec::new();
+        };
+
+        row.Item
+            .iter()
+            .map(|item_id| *item_id as u32)
+            .filter(|item_id| *item_id != 0)
+            .collect()
+    }
+
     /// Gets the order of the mount.
     pub fn find_mount_order(&mut self, mount_id: u32) -> Option<i16> {
         let config = get_config();
@@ -1223,6 +1399,39 @@ impl GameData {
         self.param_grow_sheet.row(level)
     }
 
+    /// Returns the baseline stats used in damage and healing formulas for this level.
+    pub fn get_level_modifiers(&mut self, level: u32) -> Option<LevelModifiers> {
+        let param_grow = self.get_param_grow(level)?;
+
+        // ParamGrow only has the substat baseline, so the main stat one and the slopes come from tables.
+        let (attack_power_slope, healing_power_slope, healing_power_divisor) = level_slopes(level);
+        Some(LevelModifiers {
+            main: main_stat_baseline(level),
+            sub: param_grow.BaseSpeed as u32,
+            div: param_grow.LevelModifier as u32,
+            attack_power_slope,
+            healing_power_slope,
+            healing_power_divisor,
+        })
+    }
+
+    /// Returns how this status effect stacks, and what it prevents the actor from doing.
+    pub fn get_status_info(&mut self, effect_id: u16) -> StatusInfo {
+        let Some(row) = self.status_sheet.row(effect_id as u32) else {
+            return StatusInfo::default();
+        };
+
+        StatusInfo {
+            max_stacks: row.MaxStacks,
+            // Detrimental effects are tracked separately for each caster.
+            per_source: row.StatusCategory == 2,
+            can_dispel: row.CanDispel,
+            is_permanent: row.IsPermanent,
+            lock_movement: row.LockMovement,
+            lock_actions: row.LockActions,
+        }
+    }
+
     /// Returns the ParamGrow for this level.
     pub fn get_class_job_modifiers(&mut self, classjob_id: u32) -> Option<Modifiers> {
         let row = self.classjob_sheet.row(classjob_id)?;
@@ -1426,31 +1635,105 @@ impl GameData {
         new_items
     }
 
+    /// Returns the base EXP for gathering an item of this level.
+    pub fn get_gathering_exp(&mut self, level: u8) -> i32 {
+        self.gathering_exp_sheet
+            .row(level as u32)
+            .map(|row| row.Exp as i32)
+            .unwrap_or_default()
+    }
+
+    /// Returns the Gathering and Perception that are recommended for gathering an item of this level.
+    pub fn get_gathering_recommended_stats(&mut self, level: u8) -> u32 {
+        self.param_grow_sheet
+            .row(level as u32)
+            .map(|row| row.LevelModifier as u32)
+            .unwrap_or_default()
+    }
+
+    /// Returns whether this item has a high quality version.
+    pub fn can_item_be_hq(&mut self, item_id: u32) -> bool {
+        self.item_sheet
+            .row(item_id)
+            .map(|row| row.CanBeHq)
+            .unwrap_or_default()
+    }
+
+    /// Returns the fish that can be caught from the FishingSpot closest to `position` in this zone.
+    pub fn get_fishing_spot_fish(&self, zone_id: u16, position: Position) -> Vec<u32> {
+        let Some(spots) = self.fishing_spots.get(&zone_id) else {
+            return Vec::new();
+        };
+
+        // TODO: The radius of each spot isn't respected yet, we just assume the player is fishing from the closest one.
+        let position = Vec2::new(position.0.x, position.0.z);
+        spots
+            .iter()
+            .min_by(|(a, _), (b, _)| a.distance(position).total_cmp(&b.distance(position)))
+            .map(|(_, fish)| fish.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the FishParameter id for this fish, which is its index in the fish log.
+    pub fn get_fish_parameter_id(&self, item_id: u32) -> Option<u32> {
+        self.fish_parameter_lookup.get(&item_id).copied()
+    }
+
     /// Returns the ClassJobCategory for this item.
     pub fn get_item_classjobcategory(&mut self, item_id: u32) -> u8 {
         let row = self.item_sheet.row(item_id).unwrap();
         row.ClassJobCategory
     }
 
+    /// Whether a player on this classjob is allowed to roll Need on this item. Items without a ClassJobCategory can be needed by anyone.
+    pub fn can_need_item(&mut self, item_id: u32, classjob_id: u8) -> bool {
+        let Some(row) = self.item_sheet.row(item_id) else {
+            return false;
+        };
+
+        let category = row.ClassJobCategory;
+        category == 0
+            || self
+                .get_applicable_classjobs(category as u16)
+                .contains(&classjob_id)
+    }
+
     /// Returns a Recipe.
     pub fn get_recipe(&mut self, id: u32) -> Recipe {
-        let sheet = RecipeSheet::read_from(&mut self.resource, Language::None).unwrap();
-        let row = sheet.row(id).unwrap();
+        let row = self.recipe_sheet.row(id).unwrap();
+
+        let level_row = self
+            .get_recipe_level_table(row.RecipeLevelTable as u32)
+            .unwrap();
 
         Recipe {
             id,
             item_id: row.ItemResult,
+            amount: row.AmountResult,
+            crafting: CraftingRecipe {
+                level: level_row.ClassJobLevel,
+                progress: level_row.Difficulty as u32 * row.DifficultyFactor as u32 / 100,
+                quality: level_row.Quality as u32 * row.QualityFactor as u32 / 100,
+                durability: level_row.Durability as u32 * row.DurabilityFactor as u32 / 100,
+                progress_divider: level_row.ProgressDivider as u32,
+                quality_divider: level_row.QualityDivider as u32,
+                progress_modifier: level_row.ProgressModifier as u32,
+                quality_modifier: level_row.QualityModifier as u32,
+                can_hq: row.CanHq,
+            },
         }
     }
 
-    /// Returns a CraftAction's animation start/end.
-    pub fn get_craft_action_animations(&mut self, id: u32) -> (u16, u16) {
-        let config = get_config();
-        let sheet =
-            CraftActionSheet::read_from(&mut self.resource, config.world.language()).unwrap();
-        let row = sheet.row(id).unwrap();
+    /// Returns a RecipeLevelTable row, which has most of the numbers needed to craft a recipe.
+    fn get_recipe_level_table(&mut self, id: u32) -> Option<RecipeLevelTableRow> {
+        self.recipe_level_table_sheet.row(id)
+    }
 
-        (row.AnimationStart, row.AnimationEnd)
+    /// Returns a CraftAction's animation start/end. Buffs like Veneration are regular actions, and don't have any.
+    pub fn get_craft_action_animations(&mut self, id: u32) -> Option<(u16, u16)> {
+        let row = self.craft_action_sheet.row(id)?;
+
+        Some((row.AnimationStart, row.AnimationEnd))
     }
 
     /// Returns a list of priorities for each online status.
@@ -1490,6 +1773,18 @@ impl GameData {
         ]
     }
 
+    /// Returns the weapon damage and defense that gear of this item level has.
+    pub fn get_item_level_stats(&mut self, item_level: u16) -> Option<ItemLevelStats> {
+        let row = self.item_level_sheet.row(item_level as u32)?;
+
+        Some(ItemLevelStats {
+            physical_damage: row.PhysicalDamage,
+            magic_damage: row.MagicalDamage,
+            defense: row.Defense,
+            magic_defense: row.MagicDefense,
+        })
+    }
+
     pub fn get_action_cooldown_group(&mut self, id: u32) -> u8 {
         let row = self.action_sheet.row(id).unwrap();
 
@@ -59,7 +59,10 @@ impl CurrencyStorage {
         match id {
             CurrencyKind::Gil => 0,
             CurrencyKind::WolfMark => 4,
+            CurrencyKind::TomestonePoetics => 6,
+            CurrencyKind::TomestoneMaths => 7,
             CurrencyKind::MGP => 9,
+            CurrencyKind::TomestoneHelio => 10,
             _ => unimplemented!(),
         }
     }
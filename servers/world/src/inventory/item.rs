@@ -4,6 +4,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::ItemRow;
 
+/// Set in `Item::item_flags` when the item is high quality.
+pub const ITEM_FLAG_HQ: u8 = 1;
+
 /// Represents an item, or if the quantity is zero, an empty slot.
 #[derive(Default, Copy, Clone, Serialize, Deserialize, Debug)]
 pub struct Item {
@@ -13,7 +16,7 @@ pub struct Item {
     pub item_id: u32,
     /// The player who crafted this item.
     pub crafter_content_id: u64,
-    /// Unknown flags.
+    /// Mostly unknown flags, see `ITEM_FLAG_HQ`.
     pub item_flags: u8,
     /// The condition of this item from 0 to 30000.
     pub condition: u16,
@@ -43,6 +46,10 @@ pub struct Item {
     pub defense: u16,
     #[serde(skip)]
     pub magic_defense: u16,
+    #[serde(skip)]
+    pub physical_damage: u16,
+    #[serde(skip)]
+    pub magic_damage: u16,
 }
 
 impl Item {
@@ -58,6 +65,8 @@ impl Item {
             base_param_values: item_info.base_param_values,
             defense: item_info.defense,
             magic_defense: item_info.magic_defense,
+            physical_damage: item_info.physical_damage,
+            magic_damage: item_info.magic_damage,
             ..Default::default()
         }
     }
@@ -8,7 +8,7 @@ use diesel::{
 };
 use icarus::{ClassJob::ClassJobSheet, Race::RaceSheet};
 use kawari::{
-    common::{ContainerType, ItemOperationKind, LegacyEquipmentModelId, WeaponModelId},
+    common::{ContainerType, ItemOperationKind, LegacyEquipmentModelId, MAX_GIL, WeaponModelId},
     config::get_config,
     ipc::zone::ItemInfo,
 };
@@ -26,7 +26,7 @@ mod generic;
 pub use generic::GenericStorage;
 
 mod item;
-pub use item::Item;
+pub use item::{ITEM_FLAG_HQ, Item};
 
 mod storage;
 pub use storage::{Storage, get_next_free_slot};
@@ -350,6 +350,7 @@ impl Inventory {
             for page in &mut self.pages {
                 for (slot_index, slot) in page.slots.iter_mut().enumerate() {
                     if slot.item_id == item.item_id
+                        && slot.item_flags == item.item_flags
                         && slot.quantity + item.quantity <= item.stack_size
                     {
                         slot.quantity += item.quantity;
@@ -394,6 +395,60 @@ impl Inventory {
         slot.clone_from(&item);
     }
 
+    /// Takes `item` out of a single stack in the main inventory that holds at least as many. Returns false if there isn't one.
+    fn remove_from_stack(&mut self, item: &Item) -> bool {
+        let Some(slot) = self
+            .pages
+            .iter_mut()
+            .flat_map(|page| page.slots.iter_mut())
+            .find(|slot| {
+                slot.item_id == item.item_id
+                    && slot.item_flags == item.item_flags
+                    && slot.crafter_content_id == item.crafter_content_id
+                    && slot.materia == item.materia
+                    && slot.quantity >= item.quantity
+            })
+        else {
+            return false;
+        };
+
+        slot.quantity -= item.quantity;
+        if slot.quantity == 0 {
+            *slot = Item::default();
+        }
+
+        true
+    }
+
+    /// Exchanges items and gil with another player: `given` is taken out of the main inventory, and `received` is put in.
+    /// If something being given is missing or something received doesn't fit, the inventory is left untouched and false is returned.
+    pub fn exchange(
+        &mut self,
+        given: &[Item],
+        given_gil: u32,
+        received: &[Item],
+        received_gil: u32,
+    ) -> bool {
+        let snapshot = self.clone();
+
+        let gil = self.currency.get_item_for_id(CurrencyKind::Gil);
+        let exchanged = gil.quantity >= given_gil
+            && gil.quantity - given_gil + received_gil <= MAX_GIL
+            && given.iter().all(|item| self.remove_from_stack(item))
+            && received
+                .iter()
+                .all(|item| self.add_in_next_free_slot(*item).is_some());
+
+        if !exchanged {
+            *self = snapshot;
+            return false;
+        }
+
+        let gil = self.currency.get_item_for_id(CurrencyKind::Gil);
+        gil.quantity = gil.quantity - given_gil + received_gil;
+        true
+    }
+
     fn get_container_mut(&mut self, container_type: &ContainerType) -> Option<&mut dyn Storage> {
         match container_type {
             ContainerType::Inventory0 => Some(&mut self.pages[0]),
@@ -867,3 +922,76 @@ pub enum DesiredHousingInventoryPages {
     Interior,
     InteriorStoreroom,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(item_id: u32, quantity: u32) -> Item {
+        Item {
+            item_id,
+            quantity,
+            stack_size: 99,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_exchange() {
+        let mut inventory = Inventory::default();
+        inventory.add_in_next_free_slot(item(1, 10));
+        inventory
+            .currency
+            .get_item_for_id(CurrencyKind::Gil)
+            .quantity = 100;
+
+        assert!(inventory.exchange(&[item(1, 4)], 30, &[item(2, 1)], 5));
+        assert_eq!(inventory.pages[0].slots[0].quantity, 6);
+        assert_eq!(inventory.pages[0].slots[1].item_id, 2);
+        assert_eq!(
+            inventory
+                .currency
+                .get_item_for_id(CurrencyKind::Gil)
+                .quantity,
+            75
+        );
+    }
+
+    #[test]
+    fn test_exchange_rollback() {
+        let mut inventory = Inventory::default();
+        inventory.add_in_next_free_slot(item(1, 10));
+        inventory
+            .currency
+            .get_item_for_id(CurrencyKind::Gil)
+            .quantity = 100;
+
+        // Not enough of the item being given.
+        assert!(!inventory.exchange(&[item(1, 11)], 0, &[], 0));
+
+        // Not enough gil.
+        assert!(!inventory.exchange(&[item(1, 1)], 101, &[], 0));
+
+        // Too much gil to carry.
+        assert!(!inventory.exchange(&[], 0, &[], MAX_GIL));
+
+        // No room for what's being received, after the item being given was already taken out.
+        for page in &mut inventory.pages {
+            for slot in &mut page.slots {
+                if slot.is_empty_slot() {
+                    *slot = item(3, 99);
+                }
+            }
+        }
+        assert!(!inventory.exchange(&[item(1, 10)], 0, &[item(4, 1), item(5, 1)], 0));
+
+        assert_eq!(inventory.pages[0].slots[0].quantity, 10);
+        assert_eq!(
+            inventory
+                .currency
+                .get_item_for_id(CurrencyKind::Gil)
+                .quantity,
+            100
+        );
+    }
+}
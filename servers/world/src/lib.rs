@@ -13,7 +13,7 @@ use serde::{Deserialize, Serialize};
 pub use zone_connection::{ObsfucationData, PlayerData, TeleportReason, ZoneConnection};
 
 mod database;
-pub use database::{Content, Unlock, WorldDatabase};
+pub use database::{Content, PlacedFurniture, PlayerHouse, Unlock, WorldDatabase};
 
 pub mod lua;
 
@@ -21,7 +21,7 @@ mod event;
 pub use event::{Event, EventHandler};
 
 mod status_effects;
-pub use status_effects::StatusEffects;
+pub use status_effects::{EffectOverTime, MAX_STATUS_EFFECTS, StatusEffects, StatusInfo};
 
 mod server;
 pub use server::{Party, server_main_loop};
@@ -45,7 +45,8 @@ pub use bitmask::{Bitmask, QuestBitmask};
 
 mod gamedata;
 pub use gamedata::{
-    GameData, GatheringPointItem, ItemInfoQuery, ItemRow, Recipe, Roulette, TerritoryNameKind,
+    DutyRewards, GameData, GatheringPointItem, ItemInfoQuery, ItemRow, Recipe, Roulette,
+    TerritoryNameKind,
 };
 
 mod chara_make;
@@ -2,9 +2,77 @@ use mlua::{FromLua, Lua, UserData, UserDataMethods, Value};
 
 use kawari::ipc::zone::{DamageElement, DamageKind, DamageType, TargetEffect, TargetEffectKind};
 
+use crate::zone_connection::PotencyKind;
+
+/// Changes to enmity that aren't caused by damage or healing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnmityChange {
+    /// Puts the user at the top of the target's enmity list.
+    Provoke,
+    /// Gives some of the user's enmity to the target party member.
+    Shirk,
+}
+
+/// Damage or healing that's calculated from a potency, once the server knows who it's hitting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PendingPotency {
+    /// Index of the effect whose amount is filled in.
+    pub index: usize,
+    pub kind: PotencyKind,
+    pub potency: u32,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct EffectsBuilder {
     pub effects: Vec<TargetEffect>,
+    /// This isn't sent to the client, but is handled by the server.
+    pub enmity_change: Option<EnmityChange>,
+    /// This isn't sent to the client, but is handled by the server.
+    pub potencies: Vec<PendingPotency>,
+    /// Whether to remove one of the target's dispellable effects. This isn't sent to the client, but is handled by the server.
+    pub dispel: bool,
+}
+
+impl EffectsBuilder {
+    fn damage(&mut self, damage_type: DamageType, amount: u16) {
+        self.effects.push(TargetEffect(TargetEffectKind::Damage {
+            damage_kind: DamageKind::default(), // Will be filled in later, if it's from a potency
+            damage_type,
+            damage_element: DamageElement::Unaspected, // Will be filled in later
+            bonus_percent: 0,
+            unk3: 0,
+            unk4: 0,
+            amount,
+        }));
+    }
+
+    fn heal(&mut self, amount: u16) {
+        self.effects.push(TargetEffect(TargetEffectKind::Heal {
+            unk1: [0; 5],
+            amount,
+        }));
+    }
+
+    fn gain_effect(&mut self, effect_id: u16, param: u16, duration: f32) {
+        self.effects
+            .push(TargetEffect(TargetEffectKind::GainEffect {
+                unk1: 0,
+                unk2: 0,
+                unk3: 0,
+                effect_id,
+                duration,
+                param,
+            }));
+    }
+
+    /// Adds an effect whose amount is calculated later from this potency. For status effects, it's the amount applied every tick.
+    fn potency(&mut self, kind: PotencyKind, potency: u32) {
+        self.potencies.push(PendingPotency {
+            index: self.effects.len() - 1,
+            kind,
+            potency,
+        });
+    }
 }
 
 impl UserData for EffectsBuilder {
@@ -12,30 +80,54 @@ impl UserData for EffectsBuilder {
         methods.add_method_mut(
             "damage",
             |_, this, (damage_type, amount): (DamageType, u16)| {
-                this.effects.push(TargetEffect(TargetEffectKind::Damage {
-                    damage_kind: DamageKind::default(),
-                    damage_type,
-                    damage_element: DamageElement::Unaspected, // Will be filled in later
-                    bonus_percent: 0,
-                    unk3: 0,
-                    unk4: 0,
-                    amount,
-                }));
+                this.damage(damage_type, amount);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "physical_damage",
+            |_, this, (damage_type, potency): (DamageType, u32)| {
+                this.damage(damage_type, 0);
+                this.potency(PotencyKind::Physical, potency);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "magical_damage",
+            |_, this, (damage_type, potency): (DamageType, u32)| {
+                this.damage(damage_type, 0);
+                this.potency(PotencyKind::Magical, potency);
                 Ok(())
             },
         );
         methods.add_method_mut(
             "gain_effect",
             |_, this, (effect_id, param, duration): (u16, u16, f32)| {
-                this.effects
-                    .push(TargetEffect(TargetEffectKind::GainEffect {
-                        unk1: 0,
-                        unk2: 0,
-                        unk3: 0,
-                        effect_id,
-                        duration,
-                        param,
-                    }));
+                this.gain_effect(effect_id, param, duration);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "physical_damage_over_time",
+            |_, this, (effect_id, param, duration, potency): (u16, u16, f32, u32)| {
+                this.gain_effect(effect_id, param, duration);
+                this.potency(PotencyKind::Physical, potency);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "magical_damage_over_time",
+            |_, this, (effect_id, param, duration, potency): (u16, u16, f32, u32)| {
+                this.gain_effect(effect_id, param, duration);
+                this.potency(PotencyKind::Magical, potency);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "heal_over_time",
+            |_, this, (effect_id, param, duration, potency): (u16, u16, f32, u32)| {
+                this.gain_effect(effect_id, param, duration);
+                this.potency(PotencyKind::Healing, potency);
                 Ok(())
             },
         );
@@ -69,10 +161,12 @@ impl UserData for EffectsBuilder {
             },
         );
         methods.add_method_mut("heal", |_, this, amount: u16| {
-            this.effects.push(TargetEffect(TargetEffectKind::Heal {
-                unk1: [0; 5],
-                amount,
-            }));
+            this.heal(amount);
+            Ok(())
+        });
+        methods.add_method_mut("heal_potency", |_, this, potency: u32| {
+            this.heal(0);
+            this.potency(PotencyKind::Healing, potency);
             Ok(())
         });
         methods.add_method_mut("interrupt", |_, this, _: ()| {
@@ -105,6 +199,18 @@ impl UserData for EffectsBuilder {
                 }));
             Ok(())
         });
+        methods.add_method_mut("provoke", |_, this, _: ()| {
+            this.enmity_change = Some(EnmityChange::Provoke);
+            Ok(())
+        });
+        methods.add_method_mut("shirk", |_, this, _: ()| {
+            this.enmity_change = Some(EnmityChange::Shirk);
+            Ok(())
+        });
+        methods.add_method_mut("dispel", |_, this, _: ()| {
+            this.dispel = true;
+            Ok(())
+        });
         methods.add_method_mut("summon_companion", |_, this, _: ()| {
             this.effects
                 .push(TargetEffect(TargetEffectKind::SummonCompanion {
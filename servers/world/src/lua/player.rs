@@ -10,11 +10,11 @@ use crate::{
     zone_connection::BaseParameters,
 };
 use kawari::{
-    common::{HandlerId, ObjectTypeId, ObjectTypeKind, Position},
+    common::{HandlerId, LootRoll, ObjectTypeId, ObjectTypeKind, Position},
     ipc::zone::{
-        ActorControlCategory, ActorControlSelf, ActorSetPos, EventType, GrandCompany, OnlineStatus,
-        SceneFlags, ServerNoticeFlags, ServerNoticeMessage, ServerZoneIpcData,
-        ServerZoneIpcSegment,
+        ActorControlCategory, ActorControlSelf, ActorSetPos, EventType, GameMasterRank,
+        GrandCompany, OnlineStatus, SceneFlags, ServerNoticeFlags, ServerNoticeMessage,
+        ServerZoneIpcData, ServerZoneIpcSegment,
     },
     packet::PacketSegment,
 };
@@ -569,6 +569,135 @@ impl LuaPlayer {
             .push(LuaTask::SetGrandCompanyRank { rank });
     }
 
+    fn purchase_plot(&mut self, plot: u8) {
+        self.queued_tasks.push(LuaTask::PurchasePlot { plot });
+    }
+
+    fn relinquish_house(&mut self) {
+        self.queued_tasks.push(LuaTask::RelinquishHouse);
+    }
+
+    fn change_ward(&mut self, ward: u8) {
+        self.queued_tasks.push(LuaTask::ChangeWard { ward });
+    }
+
+    fn enter_house(&mut self, plot: u8) {
+        self.queued_tasks.push(LuaTask::EnterHouse { plot });
+    }
+
+    fn set_house_exterior(&mut self, roof_id: u16, walls_id: u16, windows_id: u16, door_id: u16) {
+        self.queued_tasks.push(LuaTask::SetHouseExterior {
+            roof_id,
+            walls_id,
+            windows_id,
+            door_id,
+        });
+    }
+
+    /// Rolls on an item from a treasure coffer. Returns false if `name` isn't "need", "greed" or "pass".
+    fn roll_loot(&mut self, id: u32, name: &str) -> bool {
+        let Some(roll) = LootRoll::from_name(name) else {
+            return false;
+        };
+
+        self.queued_tasks.push(LuaTask::RollLoot { id, roll });
+        true
+    }
+
+    /// Changes the GM rank of another character. Returns false if `gm_rank` isn't a valid rank.
+    fn set_gm_rank(&mut self, name: String, gm_rank: u8) -> bool {
+        let Some(gm_rank) = GameMasterRank::from_repr(gm_rank) else {
+            return false;
+        };
+
+        self.queued_tasks.push(LuaTask::SetGmRank { name, gm_rank });
+        true
+    }
+
+    fn list_retainers(&mut self) {
+        self.queued_tasks.push(LuaTask::ListRetainers);
+    }
+
+    fn hire_retainer(&mut self, name: String) {
+        self.queued_tasks.push(LuaTask::HireRetainer { name });
+    }
+
+    fn dismiss_retainer(&mut self, name: String) {
+        self.queued_tasks.push(LuaTask::DismissRetainer { name });
+    }
+
+    /// Gives up to `quantity` of an item from the player's inventory to one of their retainers.
+    fn entrust_item(&mut self, retainer: String, item_id: u32, quantity: u32) {
+        self.queued_tasks.push(LuaTask::EntrustItem {
+            retainer,
+            item_id,
+            quantity,
+        });
+    }
+
+    fn withdraw_item(&mut self, retainer: String, item_id: u32) {
+        self.queued_tasks
+            .push(LuaTask::WithdrawItem { retainer, item_id });
+    }
+
+    fn withdraw_retainer_gil(&mut self, retainer: String) {
+        self.queued_tasks
+            .push(LuaTask::WithdrawRetainerGil { retainer });
+    }
+
+    /// Puts an item held by one of the player's retainers up for sale on the market board.
+    fn sell_item(&mut self, retainer: String, item_id: u32, quantity: u32, price_per_unit: u32) {
+        self.queued_tasks.push(LuaTask::SellItem {
+            retainer,
+            item_id,
+            quantity,
+            price_per_unit,
+        });
+    }
+
+    fn unlist_item(&mut self, id: i64) {
+        self.queued_tasks.push(LuaTask::UnlistItem { id });
+    }
+
+    fn show_market_offerings(&mut self, item_id: u32) {
+        self.queued_tasks
+            .push(LuaTask::ShowMarketOfferings { item_id });
+    }
+
+    fn buy_item(&mut self, id: i64) {
+        self.queued_tasks.push(LuaTask::BuyItem { id });
+    }
+
+    /// Asks whoever the player is targeting to trade.
+    fn request_trade(&mut self) {
+        self.queued_tasks.push(LuaTask::RequestTrade);
+    }
+
+    fn accept_trade(&mut self) {
+        self.queued_tasks.push(LuaTask::AcceptTrade);
+    }
+
+    fn cancel_trade(&mut self) {
+        self.queued_tasks.push(LuaTask::CancelTrade);
+    }
+
+    fn offer_trade_item(&mut self, item_id: u32, quantity: u32) {
+        self.queued_tasks
+            .push(LuaTask::OfferTradeItem { item_id, quantity });
+    }
+
+    fn offer_trade_gil(&mut self, gil: u32) {
+        self.queued_tasks.push(LuaTask::OfferTradeGil { gil });
+    }
+
+    fn clear_trade_offer(&mut self) {
+        self.queued_tasks.push(LuaTask::ClearTradeOffer);
+    }
+
+    fn confirm_trade(&mut self) {
+        self.queued_tasks.push(LuaTask::ConfirmTrade);
+    }
+
     fn jump(&mut self, name: String) {
         self.queued_tasks.push(LuaTask::Jump { name });
     }
@@ -1044,6 +1173,115 @@ impl UserData for LuaPlayer {
             this.set_grand_company_rank(rank);
             Ok(())
         });
+        methods.add_method_mut("purchase_plot", |_, this, plot: u8| {
+            this.purchase_plot(plot);
+            Ok(())
+        });
+        methods.add_method_mut("relinquish_house", |_, this, _: ()| {
+            this.relinquish_house();
+            Ok(())
+        });
+        methods.add_method_mut("change_ward", |_, this, ward: u8| {
+            this.change_ward(ward);
+            Ok(())
+        });
+        methods.add_method_mut("enter_house", |_, this, plot: u8| {
+            this.enter_house(plot);
+            Ok(())
+        });
+        methods.add_method_mut(
+            "set_house_exterior",
+            |_, this, (roof_id, walls_id, windows_id, door_id): (u16, u16, u16, u16)| {
+                this.set_house_exterior(roof_id, walls_id, windows_id, door_id);
+                Ok(())
+            },
+        );
+        methods.add_method_mut("roll_loot", |_, this, (id, name): (u32, String)| {
+            Ok(this.roll_loot(id, &name))
+        });
+        methods.add_method_mut("set_gm_rank", |_, this, (name, gm_rank): (String, u8)| {
+            Ok(this.set_gm_rank(name, gm_rank))
+        });
+        methods.add_method_mut("list_retainers", |_, this, _: ()| {
+            this.list_retainers();
+            Ok(())
+        });
+        methods.add_method_mut("hire_retainer", |_, this, name: String| {
+            this.hire_retainer(name);
+            Ok(())
+        });
+        methods.add_method_mut("dismiss_retainer", |_, this, name: String| {
+            this.dismiss_retainer(name);
+            Ok(())
+        });
+        methods.add_method_mut(
+            "entrust_item",
+            |_, this, (retainer, item_id, quantity): (String, u32, u32)| {
+                this.entrust_item(retainer, item_id, quantity);
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "withdraw_item",
+            |_, this, (retainer, item_id): (String, u32)| {
+                this.withdraw_item(retainer, item_id);
+                Ok(())
+            },
+        );
+        methods.add_method_mut("withdraw_retainer_gil", |_, this, retainer: String| {
+            this.withdraw_retainer_gil(retainer);
+            Ok(())
+        });
+        methods.add_method_mut(
+            "sell_item",
+            |_, this, (retainer, item_id, quantity, price_per_unit): (String, u32, u32, u32)| {
+                this.sell_item(retainer, item_id, quantity, price_per_unit);
+                Ok(())
+            },
+        );
+        methods.add_method_mut("unlist_item", |_, this, id: i64| {
+            this.unlist_item(id);
+            Ok(())
+        });
+        methods.add_method_mut("show_market_offerings", |_, this, item_id: u32| {
+            this.show_market_offerings(item_id);
+            Ok(())
+        });
+        methods.add_method_mut("buy_item", |_, this, id: i64| {
+            this.buy_item(id);
+            Ok(())
+        });
+        methods.add_method_mut("request_trade", |_, this, _: ()| {
+            this.request_trade();
+            Ok(())
+        });
+        methods.add_method_mut("accept_trade", |_, this, _: ()| {
+            this.accept_trade();
+            Ok(())
+        });
+        methods.add_method_mut("cancel_trade", |_, this, _: ()| {
+            this.cancel_trade();
+            Ok(())
+        });
+        methods.add_method_mut(
+            "offer_trade_item",
+            |_, this, (item_id, quantity): (u32, u32)| {
+                this.offer_trade_item(item_id, quantity);
+                Ok(())
+            },
+        );
+        methods.add_method_mut("offer_trade_gil", |_, this, gil: u32| {
+            this.offer_trade_gil(gil);
+            Ok(())
+        });
+        methods.add_method_mut("clear_trade_offer", |_, this, _: ()| {
+            this.clear_trade_offer();
+            Ok(())
+        });
+        methods.add_method_mut("confirm_trade", |_, this, _: ()| {
+            this.confirm_trade();
+            Ok(())
+        });
         methods.add_method_mut("jump", |_, this, name: String| {
             this.jump(name);
             Ok(())
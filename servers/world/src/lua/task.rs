@@ -3,8 +3,8 @@ use crate::{
     inventory::{CrystalKind, CurrencyKind},
 };
 use kawari::{
-    common::Position,
-    ipc::zone::{EventType, GrandCompany, SceneFlags, ServerZoneIpcSegment},
+    common::{LootRoll, Position},
+    ipc::zone::{EventType, GameMasterRank, GrandCompany, SceneFlags, ServerZoneIpcSegment},
     packet::PacketSegment,
 };
 
@@ -222,6 +222,76 @@ pub enum LuaTask {
     SetGrandCompanyRank {
         rank: u8,
     },
+    PurchasePlot {
+        plot: u8,
+    },
+    RelinquishHouse,
+    ChangeWard {
+        ward: u8,
+    },
+    EnterHouse {
+        plot: u8,
+    },
+    SetHouseExterior {
+        roof_id: u16,
+        walls_id: u16,
+        windows_id: u16,
+        door_id: u16,
+    },
+    RollLoot {
+        id: u32,
+        roll: LootRoll,
+    },
+    SetGmRank {
+        name: String,
+        gm_rank: GameMasterRank,
+    },
+    ListRetainers,
+    HireRetainer {
+        name: String,
+    },
+    DismissRetainer {
+        name: String,
+    },
+    EntrustItem {
+        retainer: String,
+        item_id: u32,
+        quantity: u32,
+    },
+    WithdrawItem {
+        retainer: String,
+        item_id: u32,
+    },
+    WithdrawRetainerGil {
+        retainer: String,
+    },
+    SellItem {
+        retainer: String,
+        item_id: u32,
+        quantity: u32,
+        price_per_unit: u32,
+    },
+    UnlistItem {
+        id: i64,
+    },
+    ShowMarketOfferings {
+        item_id: u32,
+    },
+    BuyItem {
+        id: i64,
+    },
+    RequestTrade,
+    AcceptTrade,
+    CancelTrade,
+    OfferTradeItem {
+        item_id: u32,
+        quantity: u32,
+    },
+    OfferTradeGil {
+        gil: u32,
+    },
+    ClearTradeOffer,
+    ConfirmTrade,
     Jump {
         name: String,
     },
@@ -1,16 +1,17 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Instant, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use axum::Router;
 use axum::routing::get;
 use kawari::common::{
-    ContainerType, DEBUG_COMMAND_TRIGGER, FestivalId, HandlerId, HandlerType, InstanceContentType,
-    ItemOperationKind, LogMessageType, MaxEx, ObjectId, ObjectTypeId, ObjectTypeKind,
-    PlayerStateFlags1, PlayerStateFlags2, PlayerStateFlags3, Position, QuestSpecialFlags,
-    TAB_SHARED_FATE_COUNT, WarpType, calculate_max_level,
+    ContainerType, DEBUG_COMMAND_TRIGGER, FestivalId, HandlerId, HandlerType, Hookset,
+    InstanceContentType, ItemOperationKind, LogMessageType, MaxEx, ObjectId, ObjectTypeId,
+    ObjectTypeKind, PlayerStateFlags1, PlayerStateFlags2, PlayerStateFlags3, Position,
+    QuestSpecialFlags, TAB_SHARED_FATE_COUNT, WarpType, calculate_max_level,
 };
-use kawari::config::get_config;
+use kawari::config::{ConfigWatcher, get_config};
+use kawari::database::CHECK_MIGRATIONS_ARG;
 use kawari_world::inventory::{Item, MAX_LARGE_STORAGE, Storage, get_next_free_slot};
 use physis::{TerritoryIntendedUse, equipment::EquipSlot};
 
@@ -19,9 +20,9 @@ use kawari::ipc::chat::ClientChatIpcData;
 use kawari::ipc::zone::{
     ActorControlCategory, CWLSLeaveReason, Conditions, ContentFinderUserAction, CrossRealmListing,
     CrossRealmListings, DutyFinderSetting, DutySupportInformation, EventType,
-    FurnitureTranslatedForObserver, ItemInfo, LinkshellInviteResponse, MarketBoardHistory,
-    MarketBoardHistoryEntry, MarketBoardItem, OnlineStatus, OnlineStatusMask, PlayerSetup,
-    SceneFlags, SearchInfo, SocialListRequestType, TrustContent, TrustInformation,
+    FurnitureTranslatedForObserver, ItemInfo, LinkshellInviteResponse, OnlineStatus,
+    OnlineStatusMask, PlayerSetup, SceneFlags, SearchInfo, SocialListRequestType, TrustContent,
+    TrustInformation,
 };
 
 use kawari::ipc::zone::{
@@ -36,11 +37,11 @@ use kawari::packet::{ConnectionState, ConnectionType, SegmentData, parse_packet_
 use kawari_world::lua::{KawariLua, KawariLuaState, LuaPlayer};
 use kawari_world::{
     ChatConnection, CustomIpcConnection, Event, EventHandler, GameData, ObsfucationData, Roulette,
-    TeleportReason, ZoneConnection,
+    TeleportReason, ZoneConnection, cast_line, land_fish,
 };
 use kawari_world::{
     ChatConnectionChannels, ChatPlayerData, ClientHandle, ClientId, FromServer, MessageInfo,
-    PlayerData, ServerHandle, ToServer, WorldDatabase, server_main_loop,
+    PlacedFurniture, PlayerData, ServerHandle, ToServer, WorldDatabase, server_main_loop,
 };
 
 use mlua::Function;
@@ -123,6 +124,7 @@ async fn initial_setup(
                     state: ConnectionState::None,
                     database: database.clone(),
                     gamedata: game_data.clone(),
+                    handle: handle.clone(),
                 };
                 // Handle the first batch of segments before handing off control to the loop proper.
                 let segments = connection.parse_packet(&buf[..n]);
@@ -185,11 +187,16 @@ async fn initial_setup(
                     spawned_in: false,
                     offered_teleport: None,
                     is_trading: false,
+                    trade_offer: None,
+                    trade_snapshot: None,
                     dyeing_information: None,
                     marketboard_request_item_id: 0,
                     hide_spectator_ui: false,
                     initial_login: true,
                     fate_motivation_npcs: HashMap::new(),
+                    gathering_points: HashMap::new(),
+                    fishing: None,
+                    fishing_bait: 0,
                     content_handler_id: None,
                     can_share_teleport: false,
                 };
@@ -446,6 +453,7 @@ async fn client_chat_loop(
                     FromServer::PartyMessageReceived(message_data) => connection.party_message_received(message_data).await,
                     FromServer::MustRefreshChatChannels() => connection.refresh_chatchannels().await,
                     FromServer::CWLSMessageReceived(message_info) => connection.cwls_message_received(message_info).await,
+                    FromServer::ConfigChanged(config) => connection.config = config,
                     _ => tracing::error!("ChatConnection {:#?} received a FromServer message we don't care about: {:#?}, ensure you're using the right client network or that you've implemented a handler for it if we actually care about it!", client_handle.id, msg),
                 },
                 None => break,
@@ -1194,6 +1202,9 @@ async fn process_packet(
                                     // Ditto.
                                     connection.glamour_information = Some(trigger.trigger.clone());
                                 }
+                                ClientTriggerCommand::SelectBait { item_id, .. } => {
+                                    connection.fishing_bait = *item_id;
+                                }
                                 ClientTriggerCommand::BeginOrEndFishing { end } => {
                                     let handler_id = HandlerId::new(HandlerType::Fishing, 1);
                                     if !end {
@@ -1237,13 +1248,22 @@ async fn process_packet(
                                         );
                                         connection.send_ipc_self(ipc).await;
 
-                                        connection
-                                            .handle
-                                            .send(ToServer::Fish(
-                                                connection.id,
-                                                connection.player_data.character.actor_id,
-                                            ))
-                                            .await;
+                                        if let Some((state, bite_time)) = cast_line(connection) {
+                                            connection.fishing = Some(state);
+                                            connection
+                                                .handle
+                                                .send(ToServer::Fish(
+                                                    connection.id,
+                                                    connection.player_data.character.actor_id,
+                                                    bite_time,
+                                                ))
+                                                .await;
+                                        } else {
+                                            connection.fishing = None;
+                                            connection
+                                                .send_notice("Nothing seems to be biting here.")
+                                                .await;
+                                        }
                                     } else {
                                         let event = &events.last().unwrap().1;
 
@@ -1426,6 +1446,11 @@ async fn process_packet(
                                         continue;
                                     }
 
+                                    if let Some(house) = &mut connection.player_data.house {
+                                        house.interior.light_level = level as u8;
+                                    }
+                                    connection.commit_house();
+
                                     connection
                                         .actor_control_self(
                                             ActorControlCategory::InteriorLightLevel {
@@ -1547,6 +1572,8 @@ async fn process_packet(
 
                                     *transfer_item = Item::default();
 
+                                    connection.save_removed_furniture(storage_id, slot);
+
                                     // Next, update the client's inventory.
                                     let src_container_type = storage_id;
                                     let dst_container_type = item_info.container;
@@ -2023,6 +2050,25 @@ async fn process_packet(
                                 .await;
                         }
                         ClientZoneIpcData::ActionRequest(request) => {
+                            if let Some(hookset) = Hookset::from_action_id(request.action_id)
+                                && let Some(state) = connection.fishing.take()
+                            {
+                                land_fish(connection, state, hookset).await;
+
+                                // Reel in the line, whether or not anything was caught.
+                                // TODO: Retail lets you cast again right away, but we don't know which scene does that yet so the rod is put away.
+                                if let Some((_, event)) = events.last() {
+                                    connection
+                                        .event_scene(
+                                            event,
+                                            3,
+                                            SceneFlags::NO_DEFAULT_CAMERA,
+                                            vec![273],
+                                        )
+                                        .await;
+                                }
+                            }
+
                             connection
                                 .handle
                                 .send(ToServer::ActionRequest(
@@ -2858,12 +2904,17 @@ async fn process_packet(
                                 event.0.on_enter_territory(&event.1, lua_player).await;
                             }
                         }
-                        ClientZoneIpcData::Trade { sequence, .. } => {
+                        ClientZoneIpcData::Trade {
+                            sequence,
+                            target_actor_id,
+                            ..
+                        } => {
                             // TODO: This needs a lot more research, but it's good enough for now to act as a stub to prevent client softlocks
                             // When trading, the client sends the trade opcode twice for unknown (at this time) reasons, so we need to keep track of where we're at in the sequence
                             let unk1;
                             if !connection.is_trading {
-                                tracing::info!("Trading is unimplemented");
+                                // The trade window itself isn't understood yet, so the rest of the trade happens through !trade.
+                                connection.request_trade(Some(*target_actor_id)).await;
                                 unk1 = 22; // Some ack to let the client proceed with the trade sequence
                                 connection.is_trading = true;
                             } else {
@@ -2995,98 +3046,7 @@ async fn process_packet(
                                 .await;
                         }
                         ClientZoneIpcData::RequestMarketBoardItems { sequence, .. } => {
-                            // TODO: placeholder, of course
-                            let ipc =
-                                ServerZoneIpcSegment::new(ServerZoneIpcData::MarketBoardItems {
-                                    sequence: *sequence,
-                                    items: vec![
-                                        MarketBoardItem {
-                                            item_id: 1659,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1649,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1621,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 31542,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1657,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1642,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1613,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1650,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1648,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1643,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1616,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1639,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1635,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1606,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1637,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1633,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1627,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1625,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1622,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 1614,
-                                            count: 1,
-                                        },
-                                        MarketBoardItem {
-                                            item_id: 92,
-                                            count: 1,
-                                        },
-                                    ],
-                                });
-                            connection.send_ipc_self(ipc).await;
+                            connection.send_market_search(*sequence).await;
                         }
                         ClientZoneIpcData::SetFriendGroupIcon(icon_info) => {
                             connection.set_friend_group_icon(icon_info).await;
@@ -3102,8 +3062,14 @@ async fn process_packet(
                                 .send_cwlinkshell_members(*linkshell_id, *sequence)
                                 .await;
                         }
-                        ClientZoneIpcData::OpenTreasure { .. } => {
-                            tracing::warn!("Opening treasure chests is unimplemented");
+                        ClientZoneIpcData::OpenTreasure { entity_id } => {
+                            connection
+                                .handle
+                                .send(ToServer::OpenTreasure(
+                                    connection.player_data.character.actor_id,
+                                    *entity_id,
+                                ))
+                                .await;
                         }
                         ClientZoneIpcData::CrossRealmListingsRequest1 { max_results, .. } => {
                             let results_aligned = max_results.div_ceil(4) * 4; // each packet holds 4
@@ -3474,15 +3440,27 @@ async fn process_packet(
 
                             // If the client opted to move furniture to the storeroom, there's nothing further to do here.
                             if !spawn_furniture {
+                                connection.commit_house();
                                 continue;
                             }
 
                             // Finally, acknowledge the placement.
-                            // TODO: We need to store the coordinates when things are persistent
                             let indoors = intended_use == TerritoryIntendedUse::HousingIndoor;
                             // TODO: implement dyes...
                             let stain = 0;
 
+                            connection.save_placed_furniture(
+                                indoors,
+                                PlacedFurniture {
+                                    container: result.container,
+                                    slot: result.slot,
+                                    catalog_id: item_id,
+                                    stain,
+                                    position: *position,
+                                    rotation: *rotation,
+                                },
+                            );
+
                             connection
                                 .handle
                                 .send(ToServer::PlaceFurniture(
@@ -3536,7 +3514,6 @@ async fn process_packet(
                                 unk3
                             );
 
-                            // TODO: We need to store the new coordinates and rotation when making everything persistent!
                             let indoors = intended_use == TerritoryIntendedUse::HousingIndoor;
 
                             // Determine which container the moved item belongs to.
@@ -3576,6 +3553,13 @@ async fn process_packet(
                                 }
                             };
 
+                            connection.save_moved_furniture(
+                                storage_id,
+                                *slot % MAX_LARGE_STORAGE as u16,
+                                *position,
+                                *rotation,
+                            );
+
                             connection
                                 .handle
                                 .send(ToServer::TranslateFurniture(
@@ -3660,34 +3644,7 @@ async fn process_packet(
                             connection.send_ipc_self(ipc).await;
                         }
                         ClientZoneIpcData::UnkMarketBoardRequest1 { item_id, .. } => {
-                            connection.marketboard_request_item_id = *item_id;
-
-                            // Send hits information
-                            let ipc =
-                                ServerZoneIpcSegment::new(ServerZoneIpcData::MarketBoardHits {
-                                    unk1: 0,
-                                    hits: 1,
-                                });
-                            connection.send_ipc_self(ipc).await;
-
-                            // Send dummy information so the list is populated and Dalamud self-tests work
-                            let ipc = ServerZoneIpcSegment::new(
-                                ServerZoneIpcData::MarketBoardHistory(MarketBoardHistory {
-                                    item_id: *item_id as u16,
-                                    entries: vec![
-                                        MarketBoardHistoryEntry {
-                                            unk1: 0,
-                                            price: 1,
-                                            timestamp: 0,
-                                            quantity: 1,
-                                            unk3: 0,
-                                            name: "Test Retainer".to_string()
-                                        };
-                                        20
-                                    ],
-                                }),
-                            );
-                            connection.send_ipc_self(ipc).await;
+                            connection.send_market_item(*item_id).await;
                         }
                         ClientZoneIpcData::UnkMarketBoardRequest2 { unk1, request_id } => {
                             tracing::info!("{unk1} {request_id}");
@@ -4031,7 +3988,17 @@ async fn process_server_msg(
             FromServer::FinishEvent() => {
                 connection.event_finish(events).await;
             }
+            FromServer::GatheringPoints(gathering_points) => {
+                connection.gathering_points = gathering_points;
+            }
             FromServer::FishBite() => {
+                // The player might have already reeled in or put away their rod.
+                let Some(state) = connection.fishing.as_mut() else {
+                    return;
+                };
+                state.bitten = true;
+                let tug = state.tug;
+
                 let handler_id = HandlerId::new(HandlerType::Fishing, 1).0;
 
                 let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::LogMessage {
@@ -4048,7 +4015,8 @@ async fn process_server_msg(
                         &events.last().unwrap().1,
                         4,
                         SceneFlags::NO_DEFAULT_CAMERA,
-                        vec![271, 0, 0],
+                        // TODO: Confirm that the second parameter is really the tug type.
+                        vec![271, tug as u32, 0],
                     )
                     .await;
             }
@@ -4089,6 +4057,33 @@ async fn process_server_msg(
                 database.commit_parties(parties);
             }
             FromServer::TreasureSpawn(treasure) => connection.spawn_treasure(treasure).await,
+            FromServer::DutyCompleted(content_id) => connection.complete_duty(content_id).await,
+            FromServer::TreasureOpened(item_ids) => connection.open_treasure(&item_ids).await,
+            FromServer::LootOffered(loot) => connection.offer_loot(&loot).await,
+            FromServer::LootWon(item_id) => connection.receive_treasure(&[item_id]).await,
+            FromServer::LootRolled(name, item_id, roll, number) => {
+                connection
+                    .inform_loot_roll(&name, item_id, roll, number)
+                    .await
+            }
+            FromServer::LootAwarded(item_id, winner) => {
+                connection.inform_loot_winner(item_id, winner).await
+            }
+            FromServer::KickVoted(voter, target, votes, needed) => {
+                connection
+                    .inform_kick_vote(&voter, &target, votes, needed)
+                    .await
+            }
+            FromServer::TradeRequested(name) => connection.trade_requested(&name).await,
+            FromServer::TradeOpened(name) => connection.trade_opened(&name).await,
+            FromServer::TradeOffered(name, offer) => connection.trade_offered(&name, &offer).await,
+            FromServer::TradeConfirmed(name) => connection.trade_confirmed(&name).await,
+            FromServer::TradeExchange(given, received) => {
+                connection.exchange_trade(&given, &received).await
+            }
+            FromServer::TradeCompleted() => connection.complete_trade().await,
+            FromServer::TradeCancelled(reason) => connection.trade_cancelled(&reason).await,
+            FromServer::GmRankChanged(gm_rank) => connection.gm_rank_changed(gm_rank).await,
             FromServer::LinkshellDisbanded(linkshell_id, linkshell_name) => {
                 connection
                     .crossworld_linkshell_disbanded(linkshell_id, linkshell_name)
@@ -4298,6 +4293,7 @@ async fn process_server_msg(
             FromServer::CheckTeleportSharingEligibility(aetheryte_id) => {
                 connection.check_tele_sharing_eligibility(aetheryte_id);
             }
+            FromServer::ConfigChanged(config) => connection.apply_config(config).await,
             _ => {
                 tracing::error!(
                     "ZoneConnection {:#?} received a FromServer message we don't care about: {:#?}, ensure you're using the right client network or that you've implemented a handler for it if we actually care about it!",
@@ -4356,12 +4352,18 @@ async fn client_loop(
 
     // forcefully log out the player if they weren't logging out but force D/C'd
     if connection.player_data.character.actor_id.is_valid() {
+        // A trade could still be settling, so find out which inventory the player should be left with before saving it.
+        let was_trading = connection.leave_trade(&mut internal_recv).await;
+
         if !connection.gracefully_logged_out {
             tracing::info!(
                 "Forcefully logging out connection {:#?}...",
                 client_handle.id
             );
             connection.begin_log_out().await;
+        } else if was_trading {
+            let mut database = connection.database.lock();
+            database.commit_player_data(&connection.player_data);
         }
         connection
             .handle
@@ -4394,6 +4396,11 @@ async fn root() -> String {
 async fn main() {
     tracing_subscriber::fmt::init();
 
+    if std::env::args().any(|arg| arg == CHECK_MIGRATIONS_ARG) {
+        let success = WorldDatabase::check_migrations();
+        std::process::exit(if success { 0 } else { 1 });
+    }
+
     let config = get_config();
 
     let addr = config.world.get_socketaddr();
@@ -4433,6 +4440,21 @@ async fn main() {
         axum::serve(healthcheck_listener, app).await.unwrap();
     });
 
+    // Pick up changes to the config file, so things like the login message don't need a restart.
+    {
+        let mut handle = handle.clone();
+        tokio::spawn(async move {
+            let mut watcher = ConfigWatcher::new();
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                if watcher.poll().is_some() {
+                    handle.send(ToServer::ReloadConfig).await;
+                }
+            }
+        });
+    }
+
     loop {
         if let Ok((socket, addr)) = listener.accept().await {
             let id = handle.next_id();
@@ -1,32 +1,105 @@
 //! Executing actions and other related functions.
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use mlua::Function;
 use parking_lot::Mutex;
 
 use crate::{
-    ClientId, FromServer, GameData, PlayerData, StatusEffects, ToServer,
-    lua::{EffectsBuilder, KawariLua, KawariLuaState, LuaPlayer, LuaZone},
+    ClientId, EffectOverTime, FromServer, GameData, PlayerData, StatusEffects, ToServer,
+    lua::{EffectsBuilder, EnmityChange, KawariLua, KawariLuaState, LuaPlayer, LuaZone},
     server::{
         WorldServer,
         actor::{NetworkedActor, create_npc_common_spawn, update_actor_hp_mp},
-        effect::gain_effect,
+        effect::{gain_effect, remove_effect, set_effect_over_time},
+        enmity::{generate_action_enmity, provoke, shirk},
         instance::{Instance, QueuedTaskData},
         network::{DestinationNetwork, NetworkState},
     },
     zone_connection::{BaseParameters, TeleportQuery},
 };
 use kawari::{
-    common::{ANIMATION_LOCK_TIME, COMBO_TIMEOUT, CharacterMode, ObjectId, STRIKING_DUMMY_NAME_ID},
+    common::{
+        ANIMATION_LOCK_TIME, ActionAoe, ActionTiming, COMBO_TIMEOUT, CharacterMode, DamageRolls,
+        LevelModifiers, ObjectId, ObjectTypeId, ObjectTypeKind, Position, STRIKING_DUMMY_NAME_ID,
+        calculate_damage, calculate_healing, speed_adjusted,
+    },
     config::get_config,
     ipc::zone::{
         ActionEffect, ActionRequest, ActionType, ActorControlCategory, BattleNpcSubKind,
-        CommonSpawn, EffectEntry, EffectResult, ObjectKind, ServerZoneIpcData,
+        CommonSpawn, DamageKind, EffectEntry, EffectResult, ObjectKind, ServerZoneIpcData,
         ServerZoneIpcSegment, SpawnNpc, TargetEffect, TargetEffectKind,
     },
 };
 
+/// Set in the second parameter of a heal effect when it's a critical heal.
+const CRITICAL_HEAL: u8 = 0x20;
+
+/// Fills in the amounts of any damage or healing the script gave as a potency, and whether they were critical or direct hits.
+/// Without level modifiers, the potency itself is used as a flat amount.
+fn apply_potencies(
+    effects_builder: &mut EffectsBuilder,
+    attacker: &BaseParameters,
+    target: &BaseParameters,
+    level_modifiers: Option<&LevelModifiers>,
+) {
+    for pending in &effects_builder.potencies {
+        let Some(effect) = effects_builder.effects.get_mut(pending.index) else {
+            continue;
+        };
+
+        let Some(level_modifiers) = level_modifiers else {
+            if let TargetEffectKind::Damage { amount, .. } | TargetEffectKind::Heal { amount, .. } =
+                &mut effect.0
+            {
+                *amount = pending.potency.min(u16::MAX as u32) as u16;
+            }
+            continue;
+        };
+
+        let rolls = DamageRolls {
+            critical: fastrand::f32(),
+            direct_hit: fastrand::f32(),
+            variance: fastrand::f32(),
+        };
+
+        match &mut effect.0 {
+            TargetEffectKind::Damage {
+                damage_kind,
+                amount,
+                ..
+            } => {
+                let hit = calculate_damage(
+                    pending.potency,
+                    &attacker.combat_stats(pending.kind),
+                    &target.combat_stats(pending.kind),
+                    level_modifiers,
+                    &rolls,
+                );
+                *amount = hit.amount.min(u16::MAX as u32) as u16;
+                damage_kind.set(DamageKind::CRITICAL, hit.critical);
+                damage_kind.set(DamageKind::DIRECT_HIT, hit.direct_hit);
+            }
+            TargetEffectKind::Heal { unk1, amount } => {
+                let hit = calculate_healing(
+                    pending.potency,
+                    &attacker.combat_stats(pending.kind),
+                    level_modifiers,
+                    &rolls,
+                );
+                *amount = hit.amount.min(u16::MAX as u32) as u16;
+                if hit.critical {
+                    unk1[1] |= CRITICAL_HEAL;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 /// Process action-related messages.
 pub fn handle_action_messages(
     data: Arc<Mutex<WorldServer>>,
@@ -35,20 +108,91 @@ pub fn handle_action_messages(
     msg: &ToServer,
 ) -> bool {
     if let ToServer::ActionRequest(from_id, from_actor_id, request) = msg {
-        let cast_time;
-        {
-            let mut game_data = game_data.lock();
-            cast_time = game_data.get_casttime(request.action_id).unwrap(); // TODO: take into account the haste stat like the client does
-        }
-
-        let delay_milliseconds = cast_time as u64 * 100;
+        // Only actions are in the Action sheet, the IDs of items, mounts and such mean something else.
+        let timing = if request.action_type == ActionType::Action {
+            game_data
+                .lock()
+                .get_action_timing(request.action_id)
+                .unwrap_or_default()
+        } else {
+            ActionTiming::default()
+        };
 
         let mut data = data.lock();
         let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
             return true;
         };
 
-        if cast_time > 0 {
+        let Some(actor) = instance.find_actor_mut(*from_actor_id) else {
+            return true;
+        };
+
+        // Things like stuns prevent using any actions.
+        if actor
+            .status_effects()
+            .is_some_and(|status_effects| status_effects.are_actions_locked())
+        {
+            cancel_action(network.clone(), *from_id, request.action_id);
+            return true;
+        }
+
+        // Speed shortens cast times, and the recast of anything on the GCD.
+        let parameters = actor.base_parameters();
+        let level_modifiers = game_data
+            .lock()
+            .get_level_modifiers(actor.get_common_spawn().level as u32);
+        let (cast_time, recast_time) = match level_modifiers {
+            Some(level_modifiers) => {
+                let speed = if timing.is_spell() {
+                    parameters.spell_speed
+                } else {
+                    parameters.skill_speed
+                };
+                let adjust = |time| speed_adjusted(time, speed, parameters.haste, &level_modifiers);
+
+                let recast_time = if timing.is_gcd() {
+                    adjust(timing.recast_time)
+                } else {
+                    timing.recast_time
+                };
+                (adjust(timing.cast_time), recast_time)
+            }
+            None => (timing.cast_time, timing.recast_time),
+        };
+
+        // Don't trust the client to tell us when the action is ready again.
+        if let NetworkedActor::Player {
+            remove_cooldowns,
+            recasts,
+            ..
+        } = actor
+            && !*remove_cooldowns
+            && timing.cooldown_group != 0
+        {
+            let now = Instant::now();
+            if !recasts.is_ready(timing.cooldown_group, timing.max_charges, now) {
+                tracing::warn!(
+                    "{from_actor_id} tried to use action {} while it's still recasting!",
+                    request.action_id
+                );
+                cancel_action(network.clone(), *from_id, request.action_id);
+                return true;
+            }
+            recasts.start(timing.cooldown_group, recast_time, now);
+
+            // The client doesn't know about our speed calculations, so make sure it agrees.
+            network.lock().send_to(
+                *from_id,
+                FromServer::ActorControlSelf(ActorControlCategory::SetCooldownTimerMax {
+                    cooldown_group: timing.cooldown_group as u32 - 1,
+                    action_id: request.action_id,
+                    milliseconds: recast_time.as_millis() as u32 / 10,
+                }),
+                DestinationNetwork::ZoneClients,
+            );
+        }
+
+        if !cast_time.is_zero() {
             let Some(actor) = instance.find_actor(*from_actor_id) else {
                 return true;
             };
@@ -58,7 +202,7 @@ pub fn handle_action_messages(
                 action_id: request.action_id,
                 action_type: request.action_type,
                 omen_delay: 0,
-                cast_time: delay_milliseconds as f32 / 1000.0,
+                cast_time: cast_time.as_secs_f32(),
                 target: request.target.object_id,
                 rotation: request.rotation1,
                 interruptible: false,
@@ -74,13 +218,14 @@ pub fn handle_action_messages(
             );
         }
 
+        // Players can't be interrupted by others, but moving cancels their cast.
         instance.insert_task(
             *from_id,
             *from_actor_id,
-            Duration::from_millis(delay_milliseconds),
+            cast_time,
             QueuedTaskData::CastAction {
                 request: request.clone(),
-                interruptible: delay_milliseconds > 0,
+                interruptible: !cast_time.is_zero(),
             },
         );
 
@@ -90,6 +235,65 @@ pub fn handle_action_messages(
     false
 }
 
+/// Resolves a telegraphed action once its cast finishes, using it on everyone standing inside of the area of effect.
+pub fn execute_telegraph(
+    network: Arc<Mutex<NetworkState>>,
+    data: Arc<Mutex<WorldServer>>,
+    game_data: Arc<Mutex<GameData>>,
+    lua: Arc<Mutex<KawariLua>>,
+    from_id: ClientId,
+    from_actor_id: ObjectId,
+    request: ActionRequest,
+    aoe: ActionAoe,
+    position: Position,
+    rotation: f32,
+) {
+    let hit_actor_ids: Vec<ObjectId>;
+    {
+        let data = data.lock();
+        let Some(instance) = data.find_actor_instance(from_actor_id) else {
+            return;
+        };
+
+        let Some(caster) = instance.find_actor(from_actor_id) else {
+            return;
+        };
+
+        let mut game_data = game_data.lock();
+        let enemies = game_data.get_battalion_enemies(caster.get_common_spawn().battalion as u32);
+
+        hit_actor_ids = instance
+            .find_possible_enemies()
+            .into_iter()
+            .filter(|(actor_id, actor_position, battalion, _)| {
+                *actor_id != from_actor_id
+                    && enemies[*battalion as usize]
+                    && aoe.shape.contains(position.0, rotation, actor_position.0)
+            })
+            .map(|(actor_id, ..)| actor_id)
+            .collect();
+    }
+
+    // TODO: the animation should still play if nobody was hit
+    for actor_id in hit_actor_ids {
+        execute_action(
+            network.clone(),
+            data.clone(),
+            game_data.clone(),
+            lua.clone(),
+            from_id,
+            from_actor_id,
+            ActionRequest {
+                target: ObjectTypeId {
+                    object_id: actor_id,
+                    object_type: ObjectTypeKind::None,
+                },
+                ..request.clone()
+            },
+        );
+    }
+}
+
 /// Executes an action, and returns a list of Tasks that must be executed by the client.
 pub fn execute_action(
     network: Arc<Mutex<NetworkState>>,
@@ -150,8 +354,7 @@ pub fn execute_action(
     let in_combo;
     let combo_action_id;
     {
-        let mut game_data = game_data.lock();
-        combo_action_id = game_data.get_combo_action(request.action_id);
+        combo_action_id = game_data.lock().get_combo_action(request.action_id);
 
         let data = data.lock();
         let Some(instance) = data.find_actor_instance(from_actor_id) else {
@@ -189,10 +392,7 @@ pub fn execute_action(
             NetworkedActor::Player { teleport_query, .. } => teleport_query.clone(),
             _ => TeleportQuery::default(),
         };
-        lua_player.base_parameters = match actor {
-            NetworkedActor::Player { parameters, .. } => parameters.clone(),
-            _ => BaseParameters::default(), // TODO: fill for other actors!
-        };
+        lua_player.base_parameters = actor.base_parameters();
 
         common_spawn = actor.get_common_spawn().clone();
 
@@ -224,18 +424,29 @@ pub fn execute_action(
                 return;
             };
 
-            // aggro any NPCs
-            {
-                let Some(actor) = instance.find_actor_mut(request.target.object_id) else {
-                    return;
-                };
-
-                if let NetworkedActor::Npc {
-                    newly_hated_actor, ..
-                } = actor
-                {
-                    *newly_hated_actor = Some(from_actor_id);
+            // Calculate damage and healing, now that we know who it's for
+            if !effects_builder.potencies.is_empty() {
+                let target_parameters = instance
+                    .find_actor(request.target.object_id)
+                    .map(|actor| actor.base_parameters())
+                    .unwrap_or_default();
+
+                let level_modifiers = game_data
+                    .lock()
+                    .get_level_modifiers(common_spawn.level as u32);
+                if level_modifiers.is_none() {
+                    tracing::warn!(
+                        "No level modifiers for level {}, using potencies as flat amounts for action {}!",
+                        common_spawn.level,
+                        request.action_id
+                    );
                 }
+                apply_potencies(
+                    &mut effects_builder,
+                    &lua_player.base_parameters,
+                    &target_parameters,
+                    level_modifiers.as_ref(),
+                );
             }
 
             // Handle invulnerability
@@ -263,6 +474,37 @@ pub fn execute_action(
                 }
             }
 
+            // Generate enmity
+            {
+                let mut damage = 0;
+                let mut healing = 0;
+                for effect in &effects_builder.effects {
+                    match effect.0 {
+                        TargetEffectKind::Damage { amount, .. } => damage += amount as u32,
+                        TargetEffectKind::Heal { amount, .. } => healing += amount as u32,
+                        _ => {}
+                    }
+                }
+
+                generate_action_enmity(
+                    instance,
+                    from_actor_id,
+                    request.target.object_id,
+                    damage,
+                    healing,
+                );
+
+                match effects_builder.enmity_change {
+                    Some(EnmityChange::Provoke) => {
+                        provoke(instance, from_actor_id, request.target.object_id)
+                    }
+                    Some(EnmityChange::Shirk) => {
+                        shirk(instance, from_actor_id, request.target.object_id)
+                    }
+                    None => {}
+                }
+            }
+
             // Handle combos
             {
                 // TODO: don't send this for auto-attacks. it should be harmless in the mean time
@@ -336,9 +578,29 @@ pub fn execute_action(
                         *damage_element = game_data.get_action_damage_element(request.action_id);
                     }
                     TargetEffectKind::InterruptAction => {
-                        // TODO: this could cancel more than just casting, so we need to be more specific eventually
-                        // TODO: also cancel the cast visually
-                        instance.cancel_actor_tasks(request.target.object_id);
+                        // Only casts that can be interrupted are stopped, and nothing else the target has queued.
+                        for task in instance.find_tasks(request.target.object_id) {
+                            let QueuedTaskData::CastAction {
+                                request: cast_request,
+                                interruptible: true,
+                            } = &task.data
+                            else {
+                                continue;
+                            };
+
+                            instance.cancel_task(network.clone(), &task);
+
+                            let mut network = network.lock();
+                            network.send_ac_in_range_inclusive_instance(
+                                instance,
+                                request.target.object_id,
+                                ActorControlCategory::CancelCast {
+                                    unk1: 538,
+                                    unk2: 1,
+                                    action_id: cast_request.action_id,
+                                },
+                            );
+                        }
                     }
                     TargetEffectKind::SummonPet { .. } => {
                         let Some(actor) = instance.find_actor(from_actor_id) else {
@@ -363,7 +625,7 @@ pub fn execute_action(
 
                         let config = get_config();
                         let mut game_data = game_data.lock();
-                        let base_npc = create_npc_common_spawn(
+                        let (base_npc, parameters) = create_npc_common_spawn(
                             &mut game_data,
                             13498,
                             10261,
@@ -384,6 +646,7 @@ pub fn execute_action(
                                 },
                                 ..base_npc
                             },
+                            parameters,
                             &config,
                         );
 
@@ -422,7 +685,7 @@ pub fn execute_action(
 
                         let config = get_config();
                         let mut game_data = game_data.lock();
-                        let base_npc = create_npc_common_spawn(
+                        let (base_npc, parameters) = create_npc_common_spawn(
                             &mut game_data,
                             952,
                             0,
@@ -443,6 +706,7 @@ pub fn execute_action(
                                 },
                                 ..base_npc
                             },
+                            parameters,
                             &config,
                         );
 
@@ -466,8 +730,6 @@ pub fn execute_action(
             update_actor_hp_mp(network.clone(), instance, request.target.object_id);
         }
 
-        // TODO: send Cooldown ActorControlSelf
-
         {
             let mut network = network.lock();
 
@@ -533,7 +795,7 @@ pub fn execute_action(
             let mut num_target_entries = 0u8;
             let mut target_entries = [EffectEntry::default(); 4];
 
-            for effect in &effects_builder.effects {
+            for (i, effect) in effects_builder.effects.iter().enumerate() {
                 if let TargetEffectKind::GainEffect {
                     effect_id,
                     duration,
@@ -544,6 +806,7 @@ pub fn execute_action(
                     let index = gain_effect(
                         network.clone(),
                         data.clone(),
+                        game_data.clone(),
                         ClientId::default(),
                         request.target.object_id,
                         effect_id,
@@ -553,6 +816,36 @@ pub fn execute_action(
                         false,         // EffectsResult will show it for us
                     );
 
+                    // Snapshot the player's stats, if this effect deals damage or heals over time
+                    if let Some(pending) = effects_builder
+                        .potencies
+                        .iter()
+                        .find(|pending| pending.index == i)
+                    {
+                        let level_modifiers = game_data
+                            .lock()
+                            .get_level_modifiers(common_spawn.level as u32);
+                        if let Some(level_modifiers) = level_modifiers {
+                            set_effect_over_time(
+                                data.clone(),
+                                request.target.object_id,
+                                effect_id,
+                                EffectOverTime {
+                                    kind: pending.kind,
+                                    potency: pending.potency,
+                                    source_actor_id: from_actor_id,
+                                    stats: lua_player.base_parameters.combat_stats(pending.kind),
+                                    level_modifiers,
+                                },
+                            );
+                        } else {
+                            tracing::warn!(
+                                "No level modifiers for level {}, status effect {effect_id} won't deal damage or heal over time!",
+                                common_spawn.level
+                            );
+                        }
+                    }
+
                     target_entries[num_target_entries as usize] = EffectEntry {
                         index,
                         id: effect_id,
@@ -574,6 +867,7 @@ pub fn execute_action(
                     let index = gain_effect(
                         network.clone(),
                         data.clone(),
+                        game_data.clone(),
                         from_id,
                         from_actor_id,
                         effect_id,
@@ -668,6 +962,35 @@ pub fn execute_action(
                 );
             }
         }
+
+        // Remove one of the target's dispellable effects, if it has any
+        if effects_builder.dispel {
+            let dispellable = {
+                let data = data.lock();
+                data.find_actor_instance(request.target.object_id)
+                    .and_then(|instance| instance.find_actor(request.target.object_id))
+                    .and_then(|actor| actor.status_effects())
+                    .and_then(|status_effects| status_effects.dispellable())
+            };
+
+            if let Some(effect) = dispellable {
+                let target_client_id = network
+                    .lock()
+                    .find_by_actor(request.target.object_id)
+                    .unwrap_or_default();
+
+                remove_effect(
+                    network.clone(),
+                    data.clone(),
+                    lua.clone(),
+                    target_client_id,
+                    request.target.object_id,
+                    effect.effect_id,
+                    effect.param,
+                    effect.source_actor_id,
+                );
+            }
+        }
     }
 
     let mut network = network.lock();
@@ -4,6 +4,7 @@ use crate::{
     ClientId, FromServer, GameData, StatusEffects,
     server::{
         WorldServer,
+        enmity::EnmityTable,
         instance::{Instance, QueuedTaskData},
         network::{DestinationNetwork, NetworkState},
     },
@@ -12,8 +13,8 @@ use crate::{
 use glam::Vec3A;
 use kawari::{
     common::{
-        CharacterMode, DEAD_FADE_OUT_TIME, DistanceRange, ObjectId, Position,
-        SharedGroupTimelineState, Timeline, TimepointData, should_respawn_mobs,
+        AggroProfile, CharacterMode, DEAD_FADE_OUT_TIME, DistanceRange, ObjectId, Position,
+        Recasts, SharedGroupTimelineState, Timeline, TimepointData, should_respawn_mobs,
     },
     config::get_config,
     ipc::zone::{
@@ -32,6 +33,8 @@ pub enum NpcState {
     Follow,
     /// Actively targetting another actor.
     Hate,
+    /// Giving up on the fight and returning home, where it fully resets.
+    Evade,
     /// DEAD!
     Dead,
 }
@@ -73,6 +76,8 @@ pub enum NetworkedActor {
         dueling_opponent_id: ObjectId,
         /// Whether or not cooldowns should be cheatily removed.
         remove_cooldowns: bool,
+        /// When each of their cooldown groups are ready again.
+        recasts: Recasts,
         /// Whether the player can execute a combo action. If so, contains a Some of the last action used.
         last_combo_action: u16,
         /// Sequence into the current combo.
@@ -88,16 +93,24 @@ pub enum NetworkedActor {
         navmesh_path_lerp: f32,
         navmesh_target: Option<NpcTarget>,
         last_position: Option<Vec3A>,
+        /// Where this NPC spawned, and returns to when evading.
+        home_position: Vec3A,
         spawn: SpawnNpc,
         timeline: Timeline,
-        /// In half-seconds (the current server logic tick.)
+        /// In half-seconds (the current server logic tick.) This is relative to the start of the current phase.
         timeline_position: i64,
-        /// Used for aggros outside of the server logic loop (such as regular attacks.)
-        newly_hated_actor: Option<ObjectId>,
+        /// Which phase of the timeline is playing, or None for the opening timeline points.
+        timeline_phase: Option<usize>,
+        /// How this NPC notices potential targets.
+        aggro: AggroProfile,
+        /// How much this NPC hates everyone it's fighting. The top of this list is its target.
+        enmity: EnmityTable,
         /// Whether this NPC is currently invulnerable to all attacks.
         currently_invulnerable: bool,
         /// This actor's status effects.
         status_effects: StatusEffects,
+        /// This NPC's parameters, which are derived once when it's created.
+        parameters: BaseParameters,
         /// The last time the mob wandered.
         last_wander_timestamp: Instant,
     },
@@ -186,6 +199,15 @@ impl NetworkedActor {
         }
     }
 
+    /// Returns the parameters used in this actor's damage and healing calculations. For NPCs, these are derived from their level when they're created.
+    pub fn base_parameters(&self) -> BaseParameters {
+        match self {
+            NetworkedActor::Player { parameters, .. } => parameters.clone(),
+            NetworkedActor::Npc { parameters, .. } => parameters.clone(),
+            _ => BaseParameters::default(),
+        }
+    }
+
     /// Returns this actor's status effects list.
     pub fn status_effects(&self) -> Option<&StatusEffects> {
         match self {
@@ -475,7 +497,7 @@ pub fn spawn_custom_bnpc(
             return;
         };
 
-        let base_npc = create_npc_common_spawn(game_data, base_id, name_id, None, 1);
+        let (base_npc, parameters) = create_npc_common_spawn(game_data, base_id, name_id, None, 1);
         let npc_spawn = SpawnNpc {
             common: CommonSpawn {
                 position: spawn.common.position,
@@ -485,49 +507,58 @@ pub fn spawn_custom_bnpc(
         };
 
         let config = get_config();
-        instance.insert_npc(actor_id, npc_spawn.clone(), &config);
+        instance.insert_npc(actor_id, npc_spawn.clone(), parameters, &config);
+    }
+}
+
+/// Derives an NPC's parameters for the given level.
+///
+/// Enemies don't have gear, so they're given the stats of a classjob-less character wearing gear of the level's item level.
+/// None of the BNpc sheets (including BNpcParts and ModelChara) have any stats, so their rank isn't taken into account.
+fn npc_base_parameters(game_data: &mut GameData, level: u32) -> BaseParameters {
+    let classjob_id = 0; // Pretty sure it's this for all enemies
+    let mut base_parameters = BaseParameters::default();
+
+    let (Some(modifiers), Some(attributes), Some(param_grow)) = (
+        game_data.get_class_job_modifiers(classjob_id as u32),
+        game_data.get_racial_base_attributes(classjob_id),
+        game_data.get_param_grow(level),
+    ) else {
+        return base_parameters;
+    };
+
+    let primary_stat = game_data
+        .get_job_primary_stat(classjob_id as u16)
+        .unwrap_or(1);
+    base_parameters.perform_calculations(&attributes, &param_grow, &modifiers);
+
+    if let Some(gear) = game_data.get_item_level_stats(param_grow.ItemLevelSync) {
+        base_parameters.physical_damage = gear.physical_damage as u32;
+        base_parameters.magic_damage = gear.magic_damage as u32;
+        base_parameters.defense = gear.defense as u32;
+        base_parameters.magic_defense = gear.magic_defense as u32;
     }
+
+    base_parameters.calculate_potencies(primary_stat, &param_grow, None); // TODO: If NPCs have classjob modifiers and such, change that None!
+
+    base_parameters
 }
 
-/// Creates the NPC spawn data based off of game data and whatever parameters you require.
+/// Creates the NPC spawn data based off of game data and whatever parameters you require, along with the NPC's parameters.
 pub fn create_npc_common_spawn(
     game_data: &mut GameData,
     base_id: u32,
     name_id: u32,
     hp: Option<u32>,
     level: u32,
-) -> SpawnNpc {
+) -> (SpawnNpc, BaseParameters) {
     let (model_chara, battalion, customize, rank, equip, behavior) =
         game_data.find_bnpc(base_id).unwrap();
 
-    let usable_hp;
-    if let Some(hp) = hp {
-        usable_hp = hp;
-    } else {
-        let classjob_id = 0; // Pretty sure it's this for all enemies
-        let modifiers = game_data
-            .get_class_job_modifiers(classjob_id as u32)
-            .expect("Failed to read param grow");
-
-        let attributes = game_data
-            .get_racial_base_attributes(classjob_id)
-            .expect("Failed to read racial attributes");
-
-        let param_grow = game_data
-            .get_param_grow(level)
-            .expect("Failed to read param grow");
-
-        let mut base_parameters = BaseParameters::default();
-        let primary_stat = game_data
-            .get_job_primary_stat(classjob_id as u16)
-            .unwrap_or(1);
-        base_parameters.perform_calculations(primary_stat, &attributes, &param_grow, &modifiers);
-        base_parameters.calculate_potencies(&param_grow, None); // TODO: If NPCs have classjob modifiers and such, change that None!
-
-        usable_hp = base_parameters.hp;
-    }
+    let parameters = npc_base_parameters(game_data, level);
+    let usable_hp = hp.unwrap_or(parameters.hp);
 
-    SpawnNpc {
+    let spawn = SpawnNpc {
         character_data_icon: rank,
         common: CommonSpawn {
             base_id,
@@ -543,5 +574,7 @@ pub fn create_npc_common_spawn(
             ..game_data.get_npc_equip(equip as u32).unwrap_or_default()
         },
         ..Default::default()
-    }
+    };
+
+    (spawn, parameters)
 }
@@ -23,6 +23,7 @@ use crate::{
         effect::gain_effect_instance,
         instance::{Instance, QueuedTaskData},
         network::{DestinationNetwork, NetworkState},
+        party::duty_votes_needed,
     },
 };
 
@@ -466,6 +467,37 @@ impl DirectorData {
         }
     }
 
+    pub fn on_timeline_event(&mut self, bnpc_id: u32, event: &str) {
+        // Skip if the function isn't defined
+        if !self
+            .lua
+            .0
+            .globals()
+            .contains_key("onTimelineEvent")
+            .unwrap_or_default()
+        {
+            return;
+        }
+
+        let mut run_script = || {
+            let mut lua_director = self.create_lua_director();
+            let err = self.lua.0.scope(|scope| {
+                let data = scope.create_userdata_ref_mut(&mut lua_director)?;
+
+                let func: Function = self.lua.0.globals().get("onTimelineEvent")?;
+
+                func.call::<()>((data, bnpc_id, event))?;
+
+                Ok(())
+            });
+            self.apply_lua_director(lua_director);
+            err
+        };
+        if let Err(err) = run_script() {
+            tracing::warn!("Syntax error during onTimelineEvent: {err:?}");
+        }
+    }
+
     pub fn get_debug_shortcut(&self, id: u32) -> u32 {
         let run_script = || {
             self.lua.0.scope(|_| {
@@ -506,7 +538,11 @@ impl DirectorData {
 }
 
 /// Perform any queued director tasks
-pub fn director_tick(network: Arc<Mutex<NetworkState>>, instance: &mut Instance) {
+pub fn director_tick(
+    network: Arc<Mutex<NetworkState>>,
+    gamedata: Arc<Mutex<GameData>>,
+    instance: &mut Instance,
+) {
     let tasks = if let Some(director) = &instance.directors.first() {
         director.tasks.clone()
     } else {
@@ -673,10 +709,10 @@ pub fn director_tick(network: Arc<Mutex<NetworkState>>, instance: &mut Instance)
                 );
             }
             LuaDirectorTask::SpawnBattleNpc { id } => {
-                if let Some(mut npc) = instance.zone.get_battle_npc(*id) {
+                if let Some((mut npc, parameters)) = instance.zone.get_battle_npc(*id) {
                     npc.common.handler_id = director_id;
                     let config = get_config();
-                    instance.insert_npc(ObjectId(fastrand::u32(..)), npc, &config);
+                    instance.insert_npc(ObjectId(fastrand::u32(..)), npc, parameters, &config);
                 } else {
                     tracing::warn!("Failed to find bnpc {id} for SpawnBattleNpc, it won't spawn!");
                 }
@@ -689,6 +725,7 @@ pub fn director_tick(network: Arc<Mutex<NetworkState>>, instance: &mut Instance)
             } => {
                 gain_effect_instance(
                     network.clone(),
+                    gamedata.clone(),
                     ClientId::default(),
                     instance,
                     *actor_id,
@@ -739,12 +776,12 @@ pub fn director_tick(network: Arc<Mutex<NetworkState>>, instance: &mut Instance)
                 line_id,
                 place_name,
             } => {
-                if let Some(mut npc) = instance.zone.get_battle_npc(*bnpc_id) {
+                if let Some((mut npc, parameters)) = instance.zone.get_battle_npc(*bnpc_id) {
                     npc.common.handler_id = director_id;
 
                     let actor_id = ObjectId(fastrand::u32(..));
                     let config = get_config();
-                    instance.insert_npc(actor_id, npc, &config);
+                    instance.insert_npc(actor_id, npc, parameters, &config);
                     bosses.insert(
                         *bnpc_id,
                         DirectorBoss {
@@ -772,19 +809,19 @@ pub fn director_tick(network: Arc<Mutex<NetworkState>>, instance: &mut Instance)
                 }
             }
             LuaDirectorTask::VariantVoteRoute { npc_route } => {
+                let mut network = network.lock();
                 let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::ActorControlSelf(
                     ActorControlSelf {
                         category: ActorControlCategory::DirectorEvent {
                             handler_id: director_id,
                             event: DirectorEvent::VariantVoteRoute {
-                                votes_needed: 1, // TODO: set to the number of players in the instance
+                                votes_needed: duty_votes_needed(&network, instance) as u32,
                                 npc_route: *npc_route,
                             },
                         },
                     },
                 ));
 
-                let mut network = network.lock();
                 network.send_to_instance(
                     ObjectId::default(),
                     instance,
@@ -843,7 +880,12 @@ pub fn director_tick(network: Arc<Mutex<NetworkState>>, instance: &mut Instance)
                     DestinationNetwork::ZoneClients,
                 );
 
-                // TODO: mark duty as completed for each player
+                network.send_to_instance(
+                    ObjectId::default(),
+                    instance,
+                    FromServer::DutyCompleted(instance.content_id),
+                    DestinationNetwork::ZoneClients,
+                );
             }
             LuaDirectorTask::MapEffect { index, timeline_id } => {
                 let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::DirectorMapEffect {
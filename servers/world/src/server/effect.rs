@@ -1,22 +1,25 @@
 //! Executing status effect related functions.
 
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use mlua::Function;
 use parking_lot::Mutex;
 
 use crate::{
-    ClientId, FromServer, PlayerData, StatusEffects, ToServer,
+    ClientId, EffectOverTime, FromServer, GameData, MAX_STATUS_EFFECTS, PlayerData, StatusEffects,
+    ToServer,
     lua::{KawariLua, KawariLuaState, LuaPlayer, LuaZone},
     server::{
         WorldServer,
+        actor::update_actor_hp_mp,
+        enmity::{STATUS_EFFECT_ENMITY, enmity_table_mut, generate_action_enmity},
         instance::{Instance, QueuedTaskData},
         network::{DestinationNetwork, NetworkState},
     },
-    zone_connection::BaseParameters,
+    zone_connection::{BaseParameters, PotencyKind},
 };
 use kawari::{
-    common::ObjectId,
+    common::{DamageRolls, ObjectId, STRIKING_DUMMY_NAME_ID, calculate_damage, calculate_healing},
     ipc::zone::{
         ActorControlCategory, ServerZoneIpcData, ServerZoneIpcSegment, StatusEffect,
         StatusEffectList,
@@ -27,6 +30,7 @@ use kawari::{
 pub fn handle_effect_messages(
     data: Arc<Mutex<WorldServer>>,
     network: Arc<Mutex<NetworkState>>,
+    game_data: Arc<Mutex<GameData>>,
     lua: Arc<Mutex<KawariLua>>,
     msg: &ToServer,
 ) -> bool {
@@ -42,6 +46,7 @@ pub fn handle_effect_messages(
             gain_effect(
                 network.clone(),
                 data.clone(),
+                game_data.clone(),
                 *from_id,
                 *from_actor_id,
                 *effect_id,
@@ -91,9 +96,9 @@ pub fn send_effects_list(
     };
     let common_spawn = actor.get_common_spawn();
 
-    let mut statuses = [StatusEffect::default(); 30];
+    let mut statuses = [StatusEffect::default(); MAX_STATUS_EFFECTS];
     let status_data = status_effects.data();
-    statuses[..status_data.len()].copy_from_slice(status_data);
+    statuses[..status_data.len()].copy_from_slice(&status_data);
 
     let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::StatusEffectList(StatusEffectList {
         statuses,
@@ -150,6 +155,7 @@ fn process_effects_list(
 pub fn gain_effect(
     network: Arc<Mutex<NetworkState>>,
     data: Arc<Mutex<WorldServer>>,
+    game_data: Arc<Mutex<GameData>>,
     from_id: ClientId,
     from_actor_id: ObjectId,
     effect_id: u16,
@@ -165,6 +171,7 @@ pub fn gain_effect(
 
     gain_effect_instance(
         network,
+        game_data,
         from_id,
         instance,
         from_actor_id,
@@ -177,8 +184,11 @@ pub fn gain_effect(
 }
 
 /// Gives the actor a new effect. You can also optionally send an ACS, if needed.
+///
+/// How it stacks and who it belongs to is decided by the Status sheet. If the actor already has too many effects, nothing happens.
 pub fn gain_effect_instance(
     network: Arc<Mutex<NetworkState>>,
+    game_data: Arc<Mutex<GameData>>,
     from_id: ClientId,
     instance: &mut Instance,
     from_actor_id: ObjectId,
@@ -192,12 +202,55 @@ pub fn gain_effect_instance(
         return 0;
     };
 
+    // Enemies don't like being debuffed.
+    if effect_source_actor_id.is_valid()
+        && effect_source_actor_id != from_actor_id
+        && let Some(enmity) = enmity_table_mut(actor)
+    {
+        enmity.add(effect_source_actor_id, STATUS_EFFECT_ENMITY);
+    }
+
+    let info = game_data.lock().get_status_info(effect_id);
+
     let Some(status_effects) = actor.status_effects_mut() else {
         return 0;
     };
 
-    let index = status_effects.len() as u8;
-    status_effects.add(effect_id, effect_param, effect_duration);
+    let Some(index) = status_effects.apply(
+        effect_id,
+        effect_param,
+        effect_duration,
+        effect_source_actor_id,
+        info,
+    ) else {
+        tracing::warn!(
+            "{from_actor_id} already has too many status effects, not adding {effect_id}!"
+        );
+        return 0;
+    };
+    let index = index as u8;
+
+    // Stacks are what the client needs to see, not what was requested.
+    let effect_param = status_effects
+        .get_from(effect_id, effect_source_actor_id)
+        .map(|effect| effect.param)
+        .unwrap_or(effect_param);
+
+    // If this refreshed an existing effect, it shouldn't wear off at the old time.
+    instance.retain_tasks(|task| {
+        let QueuedTaskData::LoseStatusEffect {
+            effect_id: task_effect_id,
+            effect_source_actor_id: task_source_actor_id,
+            ..
+        } = task.data
+        else {
+            return true;
+        };
+
+        !(task.from_actor_id == from_actor_id
+            && task_effect_id == effect_id
+            && (!info.per_source || task_source_actor_id == effect_source_actor_id))
+    });
 
     if inform_players {
         {
@@ -264,11 +317,9 @@ pub fn remove_effect(
         };
 
         // If we don't have the status effect, just do nothing
-        if status_effects.get(effect_id).is_none() {
+        if !status_effects.remove_from(effect_id, effect_source_actor_id) {
             return;
         }
-
-        status_effects.remove(effect_id);
     }
 
     // Then send the actor control to lose the effect
@@ -339,3 +390,217 @@ pub fn remove_effect(
         network.send_to(from_id, msg, DestinationNetwork::ZoneClients);
     }
 }
+
+/// Makes one of the actor's status effects apply damage or healing every tick.
+pub fn set_effect_over_time(
+    data: Arc<Mutex<WorldServer>>,
+    actor_id: ObjectId,
+    effect_id: u16,
+    over_time: EffectOverTime,
+) {
+    let mut data = data.lock();
+    let Some(status_effects) = data
+        .find_actor_instance_mut(actor_id)
+        .and_then(|instance| instance.find_actor_mut(actor_id))
+        .and_then(|actor| actor.status_effects_mut())
+    else {
+        return;
+    };
+
+    status_effects.set_over_time(effect_id, over_time);
+}
+
+/// Applies any damage or healing over time, then lets each effect's script run `onTick`. This should be called every `STATUS_EFFECT_TICK`.
+/// `defines_on_tick` remembers which effect scripts have an `onTick`, so the ones that don't aren't loaded every tick.
+pub fn tick_status_effects(
+    network: Arc<Mutex<NetworkState>>,
+    data: Arc<Mutex<WorldServer>>,
+    lua: Arc<Mutex<KawariLua>>,
+    defines_on_tick: &mut HashMap<u32, bool>,
+) {
+    // Every status effect that ticked, along with who has it and their parameters for the script.
+    let mut ticked = Vec::new();
+
+    {
+        let mut data = data.lock();
+        for instance in &mut data.instances {
+            let mut actors_to_update_hp_mp = Vec::new();
+
+            let actor_ids: Vec<ObjectId> = instance.actors.keys().copied().collect();
+            for actor_id in actor_ids {
+                let Some(actor) = instance.find_actor(actor_id) else {
+                    continue;
+                };
+
+                // Dead actors don't tick.
+                if actor.get_common_spawn().health_points == 0 {
+                    continue;
+                }
+
+                let Some(status_effects) = actor.status_effects() else {
+                    continue;
+                };
+
+                let parameters = actor.base_parameters();
+
+                let effects: Vec<(StatusEffect, Option<EffectOverTime>)> = status_effects
+                    .data()
+                    .into_iter()
+                    .map(|effect| {
+                        let over_time =
+                            status_effects.over_time(effect.effect_id, effect.source_actor_id);
+                        (effect, over_time)
+                    })
+                    .collect();
+
+                for (effect, over_time) in effects {
+                    ticked.push((actor_id, effect, parameters.clone()));
+
+                    let Some(over_time) = over_time else {
+                        continue;
+                    };
+
+                    let rolls = DamageRolls {
+                        critical: fastrand::f32(),
+                        direct_hit: fastrand::f32(),
+                        variance: fastrand::f32(),
+                    };
+
+                    let amount;
+                    {
+                        let Some(actor) = instance.find_actor_mut(actor_id) else {
+                            continue;
+                        };
+                        let common_spawn = actor.get_common_spawn_mut();
+
+                        if over_time.kind == PotencyKind::Healing {
+                            amount = calculate_healing(
+                                over_time.potency,
+                                &over_time.stats,
+                                &over_time.level_modifiers,
+                                &rolls,
+                            )
+                            .amount;
+                            common_spawn.health_points = common_spawn
+                                .health_points
+                                .saturating_add(amount)
+                                .min(common_spawn.max_health_points);
+                        } else {
+                            amount = calculate_damage(
+                                over_time.potency,
+                                &over_time.stats,
+                                &parameters.combat_stats(over_time.kind),
+                                &over_time.level_modifiers,
+                                &rolls,
+                            )
+                            .amount;
+                            if common_spawn.name_id != STRIKING_DUMMY_NAME_ID {
+                                common_spawn.health_points =
+                                    common_spawn.health_points.saturating_sub(amount);
+                            }
+                        }
+                    }
+
+                    if over_time.kind == PotencyKind::Healing {
+                        generate_action_enmity(
+                            instance,
+                            over_time.source_actor_id,
+                            actor_id,
+                            0,
+                            amount,
+                        );
+                    } else {
+                        generate_action_enmity(
+                            instance,
+                            over_time.source_actor_id,
+                            actor_id,
+                            amount,
+                            0,
+                        );
+                    }
+
+                    {
+                        let mut network = network.lock();
+                        network.send_ac_in_range_inclusive_instance(
+                            instance,
+                            actor_id,
+                            ActorControlCategory::HotDot {
+                                status_id: effect.effect_id as u32,
+                                amount,
+                                source_actor_id: over_time.source_actor_id,
+                            },
+                        );
+                    }
+
+                    if !actors_to_update_hp_mp.contains(&actor_id) {
+                        actors_to_update_hp_mp.push(actor_id);
+                    }
+                }
+            }
+
+            for actor_id in actors_to_update_hp_mp {
+                update_actor_hp_mp(network.clone(), instance, actor_id);
+            }
+        }
+    }
+
+    // Finally, run the scripts for every effect that ticked
+    let lua = lua.lock();
+    let state = lua.0.app_data_ref::<KawariLuaState>().unwrap();
+
+    for (actor_id, effect, base_parameters) in ticked {
+        let key = effect.effect_id as u32;
+        if defines_on_tick.get(&key) == Some(&false) {
+            continue;
+        }
+
+        let Some(effect_script) = state.effect_scripts.get(&key) else {
+            continue;
+        };
+
+        let mut lua_player = LuaPlayer {
+            player_data: PlayerData::default(),
+            status_effects: StatusEffects::default(),
+            queued_tasks: Vec::new(),
+            zone_data: LuaZone::default(),
+            base_parameters,
+        };
+
+        let result = lua.0.scope(|scope| {
+            let connection_data = scope.create_userdata_ref_mut(&mut lua_player)?;
+
+            // Not every effect has an onTick, so make sure we don't call the one from another script.
+            lua.0.globals().set("onTick", mlua::Nil)?;
+
+            lua.0
+                .load(std::fs::read(effect_script).expect("Failed to locate scripts directory!"))
+                .set_name("@".to_string() + effect_script)
+                .exec()?;
+
+            let Ok(func) = lua.0.globals().get::<Function>("onTick") else {
+                return Ok(false);
+            };
+            func.call::<()>((connection_data, effect))?;
+
+            Ok(true)
+        });
+
+        match result {
+            Ok(defined) => {
+                defines_on_tick.insert(key, defined);
+            }
+            Err(err) => {
+                tracing::error!("Error while calling onTick for effect {key}: {:?}", err);
+            }
+        }
+
+        // Inform the client of any new Lua tasks
+        if !lua_player.queued_tasks.is_empty() {
+            let mut network = network.lock();
+            if let Some(from_id) = network.find_by_actor(actor_id) {
+                let msg = FromServer::NewTasks(lua_player.queued_tasks);
+                network.send_to(from_id, msg, DestinationNetwork::ZoneClients);
+            }
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use kawari::common::ObjectId;
+
+use crate::{
+    StatusEffects,
+    server::{
+        actor::{NetworkedActor, NpcState},
+        instance::Instance,
+    },
+};
+
+/// Status effects for tank stances, which multiply the enmity generated by the tank.
+const TANK_STANCE_EFFECT_IDS: [u16; 4] = [
+    79,   // Iron Will
+    91,   // Defiance
+    743,  // Grit
+    1833, // Royal Guard
+];
+
+/// How much more enmity is generated while in a tank stance.
+const TANK_STANCE_ENMITY_MULTIPLIER: u32 = 10;
+
+/// Healing generates half as much enmity as the amount healed, split between every enemy that's fighting the target.
+const HEALING_ENMITY_DIVISOR: u32 = 2;
+
+/// Enmity generated by giving an enemy a status effect.
+pub const STATUS_EFFECT_ENMITY: u32 = 1;
+
+/// How much of the user's enmity is given away by Shirk.
+const SHIRK_ENMITY_PERCENT: u32 = 25;
+
+/// Tracks how much a single NPC hates every actor it's fighting.
+#[derive(Debug, Clone, Default)]
+pub struct EnmityTable {
+    /// Kept in the order actors were first hated, which breaks ties.
+    entries: Vec<(ObjectId, u32)>,
+}
+
+impl EnmityTable {
+    /// Adds `amount` to the enmity of `actor_id`, which starts being hated if it wasn't already.
+    pub fn add(&mut self, actor_id: ObjectId, amount: u32) {
+        if let Some((_, enmity)) = self.entries.iter_mut().find(|(id, _)| *id == actor_id) {
+            *enmity = enmity.saturating_add(amount);
+        } else {
+            self.entries.push((actor_id, amount));
+        }
+    }
+
+    /// Stops hating `actor_id`.
+    pub fn remove(&mut self, actor_id: ObjectId) {
+        self.entries.retain(|(id, _)| *id != actor_id);
+    }
+
+    /// Stops hating any actors where `f` returns false.
+    pub fn retain(&mut self, mut f: impl FnMut(ObjectId) -> bool) {
+        self.entries.retain(|(id, _)| f(*id));
+    }
+
+    /// Forgets about everyone, such as when the NPC resets.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn contains(&self, actor_id: ObjectId) -> bool {
+        self.entries.iter().any(|(id, _)| *id == actor_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the current enmity of `actor_id`.
+    pub fn get(&self, actor_id: ObjectId) -> u32 {
+        self.entries
+            .iter()
+            .find(|(id, _)| *id == actor_id)
+            .map(|(_, enmity)| *enmity)
+            .unwrap_or_default()
+    }
+
+    /// Returns every hated actor, and their enmity.
+    pub fn iter(&self) -> impl Iterator<Item = (ObjectId, u32)> + '_ {
+        self.entries.iter().copied()
+    }
+
+    /// Returns the actor with the most enmity. If there's a tie, whoever was hated first wins.
+    pub fn top(&self) -> Option<ObjectId> {
+        let mut top: Option<(ObjectId, u32)> = None;
+        for (id, enmity) in &self.entries {
+            if top.is_none_or(|(_, top_enmity)| *enmity > top_enmity) {
+                top = Some((*id, *enmity));
+            }
+        }
+
+        top.map(|(id, _)| id)
+    }
+
+    /// Enmity of `actor_id` relative to the top of the list, out of 100. This is what the client displays.
+    pub fn percentage(&self, actor_id: ObjectId) -> u32 {
+        let top_enmity = self.top().map(|id| self.get(id)).unwrap_or_default();
+        if top_enmity == 0 {
+            return 0;
+        }
+
+        ((self.get(actor_id) as u64 * 100) / top_enmity as u64) as u32
+    }
+
+    /// Puts `actor_id` at the top of the list, like Provoke does.
+    pub fn provoke(&mut self, actor_id: ObjectId) {
+        let top_enmity = self.top().map(|id| self.get(id)).unwrap_or_default();
+        let current_enmity = self.get(actor_id);
+        if self.top() != Some(actor_id) {
+            self.add(
+                actor_id,
+                top_enmity.saturating_sub(current_enmity).saturating_add(1),
+            );
+        }
+    }
+
+    /// Gives `percent` of the enmity from `from_actor_id` to `to_actor_id`, like Shirk does.
+    pub fn transfer(&mut self, from_actor_id: ObjectId, to_actor_id: ObjectId, percent: u32) {
+        if !self.contains(from_actor_id) {
+            return;
+        }
+
+        let amount = ((self.get(from_actor_id) as u64 * percent as u64) / 100) as u32;
+        if let Some((_, enmity)) = self.entries.iter_mut().find(|(id, _)| *id == from_actor_id) {
+            *enmity -= amount;
+        }
+        self.add(to_actor_id, amount);
+    }
+}
+
+/// Calculates the enmity generated by dealing `damage`.
+pub fn damage_enmity(damage: u32, status_effects: Option<&StatusEffects>) -> u32 {
+    // Always generate at least *some* enmity, so zero-damage attacks still aggro.
+    damage.max(1) * stance_multiplier(status_effects)
+}
+
+/// Calculates the enmity generated by healing `amount` HP, which is split between `num_enemies`.
+pub fn healing_enmity(
+    amount: u32,
+    num_enemies: usize,
+    status_effects: Option<&StatusEffects>,
+) -> u32 {
+    if num_enemies == 0 {
+        return 0;
+    }
+
+    (amount / HEALING_ENMITY_DIVISOR / num_enemies as u32).max(1)
+        * stance_multiplier(status_effects)
+}
+
+/// Returns the enmity table of `actor`, if it's an NPC that can hate others. Pets and other owned NPCs can't, and neither can NPCs that are evading.
+pub fn enmity_table_mut(actor: &mut NetworkedActor) -> Option<&mut EnmityTable> {
+    match actor {
+        NetworkedActor::Npc {
+            spawn,
+            enmity,
+            state,
+            ..
+        } if !spawn.common.owner_id.is_valid() && *state != NpcState::Evade => Some(enmity),
+        _ => None,
+    }
+}
+
+/// Generates enmity for `from_actor_id` after they used an action on `target_id`.
+pub fn generate_action_enmity(
+    instance: &mut Instance,
+    from_actor_id: ObjectId,
+    target_id: ObjectId,
+    damage: u32,
+    healing: u32,
+) {
+    // Buffing yourself doesn't make anyone angry.
+    if from_actor_id == target_id {
+        return;
+    }
+
+    let status_effects = instance
+        .find_actor(from_actor_id)
+        .and_then(|actor| actor.status_effects())
+        .cloned();
+
+    // Anything done to an enemy directly only angers that enemy.
+    if let Some(enmity) = instance
+        .find_actor_mut(target_id)
+        .and_then(enmity_table_mut)
+    {
+        enmity.add(
+            from_actor_id,
+            damage_enmity(damage, status_effects.as_ref()),
+        );
+        return;
+    }
+
+    // Healing angers every enemy that's fighting the healed actor.
+    if healing > 0 {
+        let mut tables: Vec<&mut EnmityTable> = instance
+            .actors
+            .values_mut()
+            .filter_map(enmity_table_mut)
+            .filter(|enmity| enmity.contains(target_id))
+            .collect();
+
+        let amount = healing_enmity(healing, tables.len(), status_effects.as_ref());
+        for enmity in &mut tables {
+            enmity.add(from_actor_id, amount);
+        }
+    }
+}
+
+/// Puts `from_actor_id` at the top of `target_id`'s enmity list.
+pub fn provoke(instance: &mut Instance, from_actor_id: ObjectId, target_id: ObjectId) {
+    if let Some(enmity) = instance
+        .find_actor_mut(target_id)
+        .and_then(enmity_table_mut)
+    {
+        enmity.provoke(from_actor_id);
+    }
+}
+
+/// Gives some of `from_actor_id`'s enmity on every enemy to `to_actor_id`.
+pub fn shirk(instance: &mut Instance, from_actor_id: ObjectId, to_actor_id: ObjectId) {
+    for enmity in instance.actors.values_mut().filter_map(enmity_table_mut) {
+        enmity.transfer(from_actor_id, to_actor_id, SHIRK_ENMITY_PERCENT);
+    }
+}
+
+fn stance_multiplier(status_effects: Option<&StatusEffects>) -> u32 {
+    let in_tank_stance = status_effects.is_some_and(|status_effects| {
+        TANK_STANCE_EFFECT_IDS
+            .iter()
+            .any(|effect_id| status_effects.get(*effect_id).is_some())
+    });
+
+    if in_tank_stance {
+        TANK_STANCE_ENMITY_MULTIPLIER
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TANK: ObjectId = ObjectId(1);
+    const HEALER: ObjectId = ObjectId(2);
+    const DPS: ObjectId = ObjectId(3);
+
+    #[test]
+    fn test_top() {
+        let mut table = EnmityTable::default();
+        assert_eq!(table.top(), None);
+
+        table.add(TANK, 100);
+        table.add(DPS, 100);
+        // Ties go to whoever was first
+        assert_eq!(table.top(), Some(TANK));
+
+        table.add(DPS, 1);
+        assert_eq!(table.top(), Some(DPS));
+
+        table.remove(DPS);
+        assert_eq!(table.top(), Some(TANK));
+    }
+
+    #[test]
+    fn test_percentage() {
+        let mut table = EnmityTable::default();
+        table.add(TANK, 200);
+        table.add(HEALER, 50);
+
+        assert_eq!(table.percentage(TANK), 100);
+        assert_eq!(table.percentage(HEALER), 25);
+        assert_eq!(table.percentage(DPS), 0);
+    }
+
+    #[test]
+    fn test_provoke() {
+        let mut table = EnmityTable::default();
+        table.add(DPS, 500);
+        table.add(TANK, 100);
+
+        table.provoke(TANK);
+        assert_eq!(table.top(), Some(TANK));
+        assert_eq!(table.get(TANK), 501);
+
+        // Provoking while already on top does nothing
+        table.provoke(TANK);
+        assert_eq!(table.get(TANK), 501);
+
+        // Provoking an enemy that hasn't noticed us yet
+        let mut table = EnmityTable::default();
+        table.provoke(TANK);
+        assert_eq!(table.top(), Some(TANK));
+
+        // Enmity that's already maxed out shouldn't overflow
+        let mut table = EnmityTable::default();
+        table.add(DPS, u32::MAX);
+        table.provoke(TANK);
+        assert_eq!(table.get(TANK), u32::MAX);
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mut table = EnmityTable::default();
+        table.add(DPS, 400);
+        table.add(TANK, 350);
+
+        table.transfer(DPS, TANK, SHIRK_ENMITY_PERCENT);
+        assert_eq!(table.get(DPS), 300);
+        assert_eq!(table.get(TANK), 450);
+        assert_eq!(table.top(), Some(TANK));
+
+        // Can't give away what you don't have
+        table.transfer(HEALER, TANK, SHIRK_ENMITY_PERCENT);
+        assert!(!table.contains(HEALER));
+        assert_eq!(table.get(TANK), 450);
+    }
+
+    #[test]
+    fn test_stance() {
+        let mut status_effects = StatusEffects::default();
+        assert_eq!(damage_enmity(100, Some(&status_effects)), 100);
+        assert_eq!(damage_enmity(0, None), 1);
+
+        status_effects.add(91, 0, 0.0);
+        assert_eq!(damage_enmity(100, Some(&status_effects)), 1000);
+    }
+
+    #[test]
+    fn test_healing() {
+        assert_eq!(healing_enmity(1000, 0, None), 0);
+        assert_eq!(healing_enmity(1000, 1, None), 500);
+        assert_eq!(healing_enmity(1000, 2, None), 250);
+    }
+}
@@ -187,12 +187,12 @@ pub fn fate_tick(network: Arc<Mutex<NetworkState>>, instance: &mut Instance) {
         for task in &tasks {
             match task {
                 LuaFateTask::SpawnBattleNpc { id } => {
-                    if let Some(mut npc) = instance.zone.get_battle_npc(*id) {
+                    if let Some((mut npc, parameters)) = instance.zone.get_battle_npc(*id) {
                         npc.common.fate_id = fate_id as u16;
                         npc.common.handler_id = HandlerId::new(HandlerType::Fate, 65535);
                         npc.common.display_flags = DisplayFlag::FATE_START_NPC;
                         let config = get_config();
-                        instance.insert_npc(ObjectId(fastrand::u32(..)), npc, &config);
+                        instance.insert_npc(ObjectId(fastrand::u32(..)), npc, parameters, &config);
                     } else {
                         tracing::warn!(
                             "Failed to find bnpc {id} for SpawnBattleNpc, it won't spawn!"
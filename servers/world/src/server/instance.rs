@@ -1,27 +1,31 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use crate::{
-    ClientId, FromServer, GameData, Navmesh, StatusEffects,
+    ClientId, FromServer, GameData, GatheringPointState, Navmesh, StatusEffects,
+    inventory::Item,
     server::{
         WorldServer,
         action::cancel_action,
         actor::{NetworkedActor, NpcState},
         director::DirectorData,
+        enmity::EnmityTable,
         fate::FateInstance,
         network::{DestinationNetwork, NetworkState},
+        trade::cancel_trades,
         zone::Zone,
     },
     zone_connection::{BaseParameters, TeleportQuery},
 };
 use kawari::{
     common::{
-        CharacterMode, DistanceRange, ENTRANCE_CIRCLE_IDS, HandlerId, HandlerType, MAXIMUM_FATES,
-        MOB_WANDER_TIME, ObjectId, Position,
+        ActionAoe, AggroProfiles, CharacterMode, DistanceRange, ENTRANCE_CIRCLE_IDS, HandlerId,
+        HandlerType, LootRoll, MAXIMUM_FATES, MOB_WANDER_TIME, ObjectId, Position, Recasts,
+        TradeSession,
     },
     config::{Config, get_config},
     ipc::zone::{
@@ -46,9 +50,17 @@ pub enum NavmeshGenerationStep {
 pub enum QueuedTaskData {
     CastAction {
         request: ActionRequest,
-        /// Currently means if it has a cast bar.
+        /// If this cast can be stopped before it finishes. Players are interrupted by moving, and enemies by actions like Interject.
         interruptible: bool,
     },
+    /// An action with a telegraphed area of effect, which hits everyone inside of it once the cast finishes.
+    CastTelegraph {
+        request: ActionRequest,
+        aoe: ActionAoe,
+        /// Where the area of effect was placed when the cast began.
+        position: Position,
+        rotation: f32,
+    },
     LoseStatusEffect {
         effect_id: u16,
         effect_param: u16,
@@ -80,6 +92,24 @@ pub enum QueuedTaskData {
     RespawnMob { layout_id: u32 },
     /// Ends a FATE.
     EndFate { fate_id: u32 },
+    /// Hands out an item from a treasure coffer once its rolls are over.
+    DistributeLoot { id: u32 },
+}
+
+/// An item from a treasure coffer that party members are rolling on.
+#[derive(Debug, Clone)]
+pub struct PendingLoot {
+    pub id: u32,
+    pub item_id: u32,
+    /// Who can roll on this item, and what they rolled once they've done so.
+    pub rolls: Vec<(ObjectId, Option<(LootRoll, u8)>)>,
+}
+
+impl PendingLoot {
+    /// Whether everyone has made their roll.
+    pub fn is_finished(&self) -> bool {
+        self.rolls.iter().all(|(_, roll)| roll.is_some())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -130,6 +160,20 @@ pub struct Instance {
     pub synced_level: Option<u8>,
     /// How long this content lasts for, if applicable.
     pub duration: Option<Duration>,
+    /// Items from treasure coffers that are still being rolled on.
+    pub loot: Vec<PendingLoot>,
+    /// The ID to give the next item in `loot`.
+    pub next_loot_id: u32,
+    /// Trades that haven't been accepted yet, by who asked and who they asked.
+    pub trade_requests: Vec<(ObjectId, ObjectId)>,
+    /// Trades between players in this instance.
+    pub trades: Vec<TradeSession<Item>>,
+    /// How each kind of BattleNPC notices players.
+    pub aggro_profiles: Arc<AggroProfiles>,
+    /// What's left of the gathering points players have gathered from, by their actor ID and the point's GatheringPoint ID.
+    pub gathering_points: HashMap<(ObjectId, u32), GatheringPointState>,
+    /// Who voted to kick each party member out of this duty, by the actor ID of who they want kicked.
+    pub kick_votes: HashMap<ObjectId, HashSet<ObjectId>>,
 }
 
 impl Instance {
@@ -137,6 +181,7 @@ impl Instance {
         let mut instance = Instance {
             zone: Zone::load(game_data, id),
             weather_id: game_data.get_weather(id as u32).unwrap_or_default() as u16,
+            aggro_profiles: game_data.aggro_profiles.clone(),
             ..Default::default()
         };
 
@@ -177,8 +222,8 @@ impl Instance {
         // Load initial NPCs into instance
         if !explorer_mode {
             let config = get_config();
-            for npc in instance.zone.get_npcs(game_data) {
-                instance.insert_npc(ObjectId(fastrand::u32(..)), npc, &config);
+            for (npc, parameters) in instance.zone.get_npcs(game_data) {
+                instance.insert_npc(ObjectId(fastrand::u32(..)), npc, parameters, &config);
             }
         }
 
@@ -223,7 +268,13 @@ impl Instance {
         self.actors.get_mut(&id)
     }
 
-    pub fn insert_npc(&mut self, id: ObjectId, spawn: SpawnNpc, config: &Config) {
+    pub fn insert_npc(
+        &mut self,
+        id: ObjectId,
+        spawn: SpawnNpc,
+        parameters: BaseParameters,
+        config: &Config,
+    ) {
         // Load drop-ins
         let mut timeline = serde_json::from_str(
             &std::fs::read_to_string(config.filesystem.locate_timeline_file("Default.json"))
@@ -231,39 +282,15 @@ impl Instance {
         )
         .unwrap();
 
-        let mut search_dirs: Vec<String> = config
-            .filesystem
-            .additional_resource_paths
-            .iter()
-            .cloned()
-            .map(|mut x| {
-                x.push_str("/timelines/");
-                x
-            })
-            .collect();
-        search_dirs.push("resources/timelines/".to_string());
-
-        'outer: for search_dir in search_dirs {
-            for entry in std::fs::read_dir(search_dir)
-                .expect("Didn't find timelines directory?")
-                .flatten()
-            {
-                if !entry
-                    .file_name()
-                    .to_str()
-                    .unwrap_or_default()
-                    .ends_with(&format!("_{}.json", spawn.common.base_id))
-                {
-                    continue;
-                }
-
-                if let Ok(contents) = std::fs::read_to_string(entry.path()) {
-                    timeline = serde_json::from_str(&contents).unwrap();
-                    break 'outer;
-                }
-            }
+        if let Some(contents) = find_bnpc_file(config, "timelines", spawn.common.base_id) {
+            timeline = serde_json::from_str(&contents).unwrap();
         }
 
+        let aggro = self
+            .aggro_profiles
+            .get(spawn.common.base_id, spawn.common.model_chara)
+            .clone();
+
         self.actors.insert(
             id,
             NetworkedActor::Npc {
@@ -272,12 +299,16 @@ impl Instance {
                 navmesh_path_lerp: 0.0,
                 navmesh_target: None,
                 last_position: None,
+                home_position: spawn.common.position.0,
                 spawn,
                 timeline,
                 timeline_position: 0,
-                newly_hated_actor: None,
+                timeline_phase: None,
+                aggro,
+                enmity: EnmityTable::default(),
                 currently_invulnerable: false,
                 status_effects: StatusEffects::default(),
+                parameters,
                 last_wander_timestamp: Instant::now()
                     + Duration::from_secs(fastrand::u64(0..MOB_WANDER_TIME.as_secs())),
             },
@@ -289,8 +320,8 @@ impl Instance {
         ObjectId(fastrand::u32(..))
     }
 
-    /// Finds all (alive) players and NPCs. Returns their ids, positions and battalions.
-    pub fn find_possible_enemies(&self) -> Vec<(ObjectId, Position, u8)> {
+    /// Finds all (alive) players and NPCs. Returns their ids, positions, battalions and levels.
+    pub fn find_possible_enemies(&self) -> Vec<(ObjectId, Position, u8, u8)> {
         self.actors
             .iter()
             .filter(|(_, y)| {
@@ -306,11 +337,21 @@ impl Instance {
                     *x,
                     y.get_common_spawn().position,
                     y.get_common_spawn().battalion,
+                    y.get_common_spawn().level,
                 )
             })
             .collect()
     }
 
+    /// Returns what's left of the gathering points this player has gathered from, by their GatheringPoint ID.
+    pub fn gathering_points_for(&self, actor_id: ObjectId) -> HashMap<u32, GatheringPointState> {
+        self.gathering_points
+            .iter()
+            .filter(|((id, _), _)| *id == actor_id)
+            .map(|((_, point_id), state)| (*point_id, *state))
+            .collect()
+    }
+
     pub fn insert_empty_actor(&mut self, actor_id: ObjectId) {
         if self.actors.contains_key(&actor_id) {
             return;
@@ -329,6 +370,7 @@ impl Instance {
                 parameters: BaseParameters::default(),
                 dueling_opponent_id: ObjectId::default(),
                 remove_cooldowns: false,
+                recasts: Recasts::default(),
                 last_combo_action: 0,
                 combo_sequence: 0,
                 autoattack_target: None,
@@ -527,6 +569,7 @@ pub fn remove_actor_from_instance(
 ) {
     if let Some(current_instance) = data.find_actor_instance_mut(actor_id) {
         network.remove_actor(current_instance, actor_id);
+        cancel_trades(network, current_instance, actor_id);
 
         // Terminate any directors
         for director in &current_instance.directors {
@@ -540,3 +583,9 @@ pub fn remove_actor_from_instance(
         }
     }
 }
+
+/// Finds and reads the file in `folder` that's meant for this BNpcBase, e.g. "SastashaOrobon_1040.json".
+fn find_bnpc_file(config: &Config, folder: &str, base_id: u32) -> Option<String> {
+    let path = config.filesystem.locate_id_file(folder, base_id)?;
+    std::fs::read_to_string(path).ok()
+}
@@ -0,0 +1,198 @@
+use std::{sync::Arc, time::Duration};
+
+use kawari::common::{LOOT_ROLL_SECS, LootRoll, ObjectId, loot_winner};
+use parking_lot::Mutex;
+
+use crate::{
+    ClientId, FromServer, GameData, ToServer,
+    server::{
+        WorldServer,
+        actor::NetworkedActor,
+        instance::{Instance, PendingLoot, QueuedTaskData},
+        network::{DestinationNetwork, NetworkState},
+        party::party_members_in_instance,
+    },
+};
+
+/// Hands out an item that's finished being rolled on, and tells everyone who won it. Anyone who didn't roll in time is treated as passing.
+pub fn distribute_loot(network: &mut NetworkState, instance: &mut Instance, loot_id: u32) {
+    let Some(index) = instance.loot.iter().position(|loot| loot.id == loot_id) else {
+        // It was already handed out once everyone rolled.
+        return;
+    };
+    let loot = instance.loot.remove(index);
+
+    let rolls: Vec<(LootRoll, u8)> = loot
+        .rolls
+        .iter()
+        .map(|(_, roll)| roll.unwrap_or((LootRoll::Pass, 0)))
+        .collect();
+    let winner = loot_winner(&rolls).map(|index| loot.rolls[index].0);
+
+    let winner_name = winner.and_then(|actor_id| {
+        instance
+            .find_actor(actor_id)
+            .and_then(|actor| actor.get_player_spawn())
+            .map(|spawn| spawn.common.name.clone())
+    });
+
+    if let Some(winner) = winner {
+        network.send_to_by_actor_id(
+            winner,
+            FromServer::LootWon(loot.item_id),
+            DestinationNetwork::ZoneClients,
+        );
+    }
+
+    for (actor_id, _) in &loot.rolls {
+        network.send_to_by_actor_id(
+            *actor_id,
+            FromServer::LootAwarded(loot.item_id, winner_name.clone()),
+            DestinationNetwork::ZoneClients,
+        );
+    }
+}
+
+/// Process treasure coffer and loot roll messages.
+pub fn handle_loot_messages(
+    data: Arc<Mutex<WorldServer>>,
+    network: Arc<Mutex<NetworkState>>,
+    gamedata: Arc<Mutex<GameData>>,
+    msg: &ToServer,
+) -> bool {
+    match msg {
+        ToServer::OpenTreasure(from_actor_id, treasure_id) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            let Some(NetworkedActor::Treasure { treasure }) = instance.find_actor(*treasure_id)
+            else {
+                tracing::warn!("Somehow failed to find treasure {treasure_id}!");
+                return true;
+            };
+            let base_id = treasure.base_id;
+            let item_count = treasure.item_count;
+
+            let mut item_ids = {
+                let mut gamedata = gamedata.lock();
+                gamedata.get_treasure_items(base_id)
+            };
+            fastrand::shuffle(&mut item_ids);
+            item_ids.truncate(item_count.max(1) as usize);
+
+            // Coffers can only be opened once.
+            let mut network = network.lock();
+            network.remove_actor(instance, *treasure_id);
+
+            let participants = party_members_in_instance(&network, instance, *from_actor_id);
+            if participants.len() <= 1 {
+                // Nobody to share with, so it all goes to the opener.
+                network.send_to_by_actor_id(
+                    *from_actor_id,
+                    FromServer::TreasureOpened(item_ids),
+                    DestinationNetwork::ZoneClients,
+                );
+                return true;
+            }
+
+            let mut offered = Vec::new();
+            for item_id in item_ids {
+                instance.next_loot_id += 1;
+                let loot_id = instance.next_loot_id;
+
+                instance.loot.push(PendingLoot {
+                    id: loot_id,
+                    item_id,
+                    rolls: participants.iter().map(|id| (*id, None)).collect(),
+                });
+                offered.push((loot_id, item_id));
+
+                instance.insert_task(
+                    ClientId::default(),
+                    *from_actor_id,
+                    Duration::from_secs(LOOT_ROLL_SECS),
+                    QueuedTaskData::DistributeLoot { id: loot_id },
+                );
+            }
+
+            // Everything from the coffer is offered at once, so raid lockouts are only applied once per coffer.
+            for actor_id in &participants {
+                network.send_to_by_actor_id(
+                    *actor_id,
+                    FromServer::LootOffered(offered.clone()),
+                    DestinationNetwork::ZoneClients,
+                );
+            }
+
+            true
+        }
+        ToServer::RollLoot(from_actor_id, loot_id, roll) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            let Some(spawn) = instance
+                .find_actor(*from_actor_id)
+                .and_then(|actor| actor.get_player_spawn())
+            else {
+                return true;
+            };
+            let name = spawn.common.name.clone();
+            let classjob_id = spawn.common.class_job;
+
+            let Some(loot) = instance.loot.iter_mut().find(|loot| loot.id == *loot_id) else {
+                tracing::warn!("{name} tried to roll on unknown loot {loot_id}!");
+                return true;
+            };
+            let item_id = loot.item_id;
+
+            let Some((_, entry)) = loot.rolls.iter_mut().find(|(id, _)| id == from_actor_id) else {
+                tracing::warn!("{name} tried to roll on loot {loot_id} they can't have!");
+                return true;
+            };
+            if entry.is_some() {
+                // Everyone only gets one roll.
+                return true;
+            }
+
+            // The client would have greyed out Need for items they can't use, so treat it as Greed.
+            let mut roll = *roll;
+            if roll == LootRoll::Need {
+                let mut gamedata = gamedata.lock();
+                if !gamedata.can_need_item(item_id, classjob_id) {
+                    roll = LootRoll::Greed;
+                }
+            }
+
+            let number = match roll {
+                LootRoll::Pass => 0,
+                _ => fastrand::u8(1..100),
+            };
+            *entry = Some((roll, number));
+
+            let participants: Vec<ObjectId> = loot.rolls.iter().map(|(id, _)| *id).collect();
+            let is_finished = loot.is_finished();
+
+            let mut network = network.lock();
+            for actor_id in participants {
+                network.send_to_by_actor_id(
+                    actor_id,
+                    FromServer::LootRolled(name.clone(), item_id, roll, number),
+                    DestinationNetwork::ZoneClients,
+                );
+            }
+
+            if is_finished {
+                distribute_loot(&mut network, instance, *loot_id);
+            }
+
+            true
+        }
+        _ => false,
+    }
+}
@@ -14,17 +14,18 @@ use crate::{
     GameData, Navmesh,
     lua::KawariLua,
     server::{
-        action::{execute_action, handle_action_messages},
+        action::{execute_action, execute_telegraph, handle_action_messages},
         actor::{
             NetworkedActor, NpcState, kill_actor, set_character_mode, set_player_minion,
             spawn_custom_bnpc, update_actor_hp_mp,
         },
         chat::handle_chat_messages,
         director::{DirectorData, director_tick, handle_director_messages},
-        effect::{handle_effect_messages, remove_effect, send_effects_list},
+        effect::{handle_effect_messages, remove_effect, send_effects_list, tick_status_effects},
         fate::{ended_fate, fate_tick, start_fate, unk10_fate},
         instance::{Instance, NavmeshGenerationStep, QueuedTaskData, remove_actor_from_instance},
         linkshell::handle_linkshell_messages,
+        loot::{distribute_loot, handle_loot_messages},
         network::{DestinationNetwork, NetworkState},
         party::{
             NUM_TARGET_SIGNS, get_party_id_from_actor_id, handle_party_messages,
@@ -33,6 +34,7 @@ use crate::{
         },
         social::handle_social_messages,
         spawn_allocator::SpawnAllocator,
+        trade::handle_trade_messages,
         zone::{
             MapGimmick, change_zone_to_player, change_zone_warp_to_entrance,
             change_zone_warp_to_pop_range, handle_zone_messages,
@@ -43,14 +45,14 @@ use kawari::{
     common::{
         AUTO_ATTACK_RATE, CharacterMode, DEAD_DESPAWN_TIME, DirectorEvent, DirectorTrigger,
         HandlerId, HandlerType, MAX_SPAWNED_ACTORS, MAX_SPAWNED_OBJECTS, MOB_RESPAWN_TIME,
-        ObjectId, ObjectTypeId, ObjectTypeKind, Position, WarpType, determine_initial_pop_range,
-        euler_to_direction, is_private_area,
+        ObjectId, ObjectTypeId, ObjectTypeKind, Position, STATUS_EFFECT_TICK, WarpType,
+        determine_initial_pop_range, euler_to_direction, is_private_area,
     },
-    config::get_config,
+    config::{get_config, load_config},
     ipc::zone::{
-        ActionRequest, ActionType, ActorControlCategory, ClientTriggerCommand, Condition,
-        Conditions, DutyFinderSetting, EnmityList, Hater, HaterList, PlayerEnmity, PrepareZoning,
-        PrepareZoningFlag, ServerZoneIpcData, ServerZoneIpcSegment, WaymarkPreset,
+        ActionRequest, ActionType, ActorControlCategory, ActorSetPos, ClientTriggerCommand,
+        Condition, Conditions, DutyFinderSetting, EnmityList, Hater, HaterList, PlayerEnmity,
+        PrepareZoning, PrepareZoningFlag, ServerZoneIpcData, ServerZoneIpcSegment, WaymarkPreset,
     },
 };
 
@@ -61,8 +63,10 @@ mod actor;
 mod chat;
 mod director;
 mod effect;
+mod enmity;
 mod instance;
 mod linkshell;
+mod loot;
 mod network;
 mod party;
 pub use party::{Party, PartyMember};
@@ -70,6 +74,7 @@ mod fate;
 mod npc_behavior;
 mod social;
 mod spawn_allocator;
+mod trade;
 mod zone;
 
 #[derive(Default, Debug, Clone)]
@@ -91,6 +96,7 @@ struct WorldServer {
     // TODO: Eventually remove these once we can reliably and ergonomically run misc. tasks on slower intervals!
     rested_exp_counter: i32,
     party_positions_counter: i32,
+    status_effect_counter: i32,
 }
 
 impl WorldServer {
@@ -325,9 +331,9 @@ fn server_logic_tick(
                 if let Some(haters) = haters.get(id) {
                     let mut list: Vec<Hater> = haters
                         .iter()
-                        .map(|actor_id| Hater {
+                        .map(|(actor_id, enmity)| Hater {
                             actor_id: *actor_id,
-                            enmity: 100,
+                            enmity: *enmity,
                         })
                         .collect();
                     list.truncate(32);
@@ -351,13 +357,24 @@ fn server_logic_tick(
                     );
                 }
 
-                // TODO: send info for party
+                // Show how much the enemy we're targeting hates everyone it's fighting.
+                let mut list: Vec<PlayerEnmity> = instance
+                    .find_actor(actor.get_common_spawn().target_id.object_id)
+                    .map(|target| match target {
+                        NetworkedActor::Npc { enmity, .. } => enmity
+                            .iter()
+                            .map(|(actor_id, _)| PlayerEnmity {
+                                actor_id,
+                                enmity: enmity.percentage(actor_id),
+                            })
+                            .collect(),
+                        _ => Vec::new(),
+                    })
+                    .unwrap_or_default();
+                list.truncate(8);
                 let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::EnmityList(EnmityList {
-                    count: 1,
-                    list: vec![PlayerEnmity {
-                        actor_id: *id,
-                        enmity: 100,
-                    }],
+                    count: list.len() as u32,
+                    list,
                 }));
                 network.send_to_by_actor_id(
                     *id,
@@ -742,7 +759,7 @@ fn server_logic_tick(
             }
 
             // Process any director tasks for this instance.
-            director_tick(network.clone(), instance);
+            director_tick(network.clone(), gamedata.clone(), instance);
             fate_tick(network.clone(), instance);
         }
         // Ensure the rested EXP counter only happens every 10 seconds.
@@ -814,12 +831,33 @@ pub async fn server_main_loop(
         tokio::task::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_millis(500)); // Be careful when changing this, as the rested EXP may become whacky.
             interval.tick().await;
+            let mut defines_on_tick = HashMap::new();
             loop {
                 interval.tick().await;
 
                 // Execute general server logic
                 server_logic_tick(data.clone(), network.clone(), game_data.clone());
 
+                // Damage and healing over time is applied on its own, slower interval.
+                let status_effect_tick = {
+                    let mut data = data.lock();
+                    data.status_effect_counter += 1;
+                    if data.status_effect_counter as u128 == STATUS_EFFECT_TICK.as_millis() / 500 {
+                        data.status_effect_counter = 0;
+                        true
+                    } else {
+                        false
+                    }
+                };
+                if status_effect_tick {
+                    tick_status_effects(
+                        network.clone(),
+                        data.clone(),
+                        lua.clone(),
+                        &mut defines_on_tick,
+                    );
+                }
+
                 // Execute list of queued tasks
                 {
                     let mut tasks_to_execute = Vec::new();
@@ -851,6 +889,25 @@ pub async fn server_main_loop(
                                     request.clone(),
                                 );
                             }
+                            QueuedTaskData::CastTelegraph {
+                                request,
+                                aoe,
+                                position,
+                                rotation,
+                            } => {
+                                execute_telegraph(
+                                    network.clone(),
+                                    data.clone(),
+                                    game_data.clone(),
+                                    lua.clone(),
+                                    task.from_id,
+                                    task.from_actor_id,
+                                    request.clone(),
+                                    *aoe,
+                                    *position,
+                                    *rotation,
+                                );
+                            }
                             QueuedTaskData::LoseStatusEffect {
                                 effect_id,
                                 effect_param,
@@ -988,11 +1045,19 @@ pub async fn server_main_loop(
                             QueuedTaskData::RespawnMob { layout_id } => {
                                 let mut data = data.lock();
                                 if let Some(instance) = data.instances.get_mut(*instance_index)
-                                    && let Some(npc) = instance.zone.get_battle_npc(*layout_id)
+                                    && let Some((npc, parameters)) =
+                                        instance.zone.get_battle_npc(*layout_id)
                                 {
                                     let actor_id = ObjectId(fastrand::u32(..));
                                     let config = get_config();
-                                    instance.insert_npc(actor_id, npc, &config);
+                                    instance.insert_npc(actor_id, npc, parameters, &config);
+                                }
+                            }
+                            QueuedTaskData::DistributeLoot { id } => {
+                                let mut data = data.lock();
+                                if let Some(instance) = data.instances.get_mut(*instance_index) {
+                                    let mut network = network.lock();
+                                    distribute_loot(&mut network, instance, *id);
                                 }
                             }
                             QueuedTaskData::EndFate { fate_id } => {
@@ -1059,10 +1124,18 @@ pub async fn server_main_loop(
         handled |= handle_social_messages(data.clone(), network.clone(), &msg);
         handled |= handle_zone_messages(data.clone(), network.clone(), game_data.clone(), &msg);
         handled |= handle_action_messages(data.clone(), game_data.clone(), network.clone(), &msg);
-        handled |= handle_effect_messages(data.clone(), network.clone(), lua.clone(), &msg);
+        handled |= handle_effect_messages(
+            data.clone(),
+            network.clone(),
+            game_data.clone(),
+            lua.clone(),
+            &msg,
+        );
         handled |= handle_director_messages(data.clone(), network.clone(), game_data.clone(), &msg);
         handled |= handle_party_messages(data.clone(), network.clone(), &msg);
         handled |= handle_linkshell_messages(network.clone(), &msg);
+        handled |= handle_loot_messages(data.clone(), network.clone(), game_data.clone(), &msg);
+        handled |= handle_trade_messages(data.clone(), network.clone(), &msg);
 
         if !handled {
             match msg {
@@ -1180,6 +1253,11 @@ pub async fn server_main_loop(
                     );
 
                     network.send_to(from_id, msg, DestinationNetwork::ZoneClients);
+                    network.send_to(
+                        from_id,
+                        FromServer::GatheringPoints(instance.gathering_points_for(from_actor_id)),
+                        DestinationNetwork::ZoneClients,
+                    );
                 }
                 ToServer::ActorMoved(
                     actor_id,
@@ -1192,7 +1270,33 @@ pub async fn server_main_loop(
                 ) => {
                     let mut data = data.lock();
 
-                    if let Some(instance) = data.find_actor_instance_mut(actor_id) {
+                    // Things like binds prevent moving, so put them back where they were.
+                    if let Some(actor) = data
+                        .find_actor_instance(actor_id)
+                        .and_then(|instance| instance.find_actor(actor_id))
+                        && actor.get_common_spawn().position != position
+                        && actor
+                            .status_effects()
+                            .is_some_and(|status_effects| status_effects.is_movement_locked())
+                    {
+                        let common = actor.get_common_spawn();
+                        let segment = ServerZoneIpcSegment::new(ServerZoneIpcData::ActorSetPos(
+                            ActorSetPos {
+                                position: common.position,
+                                rotation: common.rotation,
+                                ..Default::default()
+                            },
+                        ));
+
+                        let mut network = network.lock();
+                        if let Some(from_id) = network.find_by_actor(actor_id) {
+                            network.send_to(
+                                from_id,
+                                FromServer::PacketSegment(segment, actor_id),
+                                DestinationNetwork::ZoneClients,
+                            );
+                        }
+                    } else if let Some(instance) = data.find_actor_instance_mut(actor_id) {
                         let mut moved = false;
                         if let Some((_, spawn)) = instance
                             .actors
@@ -1222,10 +1326,26 @@ pub async fn server_main_loop(
                         if moved {
                             // Check if the actor has any in-progress actions, and cancel them if so.
                             for task in instance.find_tasks(actor_id) {
-                                if let QueuedTaskData::CastAction { interruptible, .. } = task.data
-                                    && interruptible
+                                let QueuedTaskData::CastAction {
+                                    request,
+                                    interruptible: true,
+                                } = &task.data
+                                else {
+                                    continue;
+                                };
+
+                                instance.cancel_task(network.clone(), &task);
+
+                                // The action never happened, so it shouldn't be recasting either.
+                                let cooldown_group = game_data
+                                    .lock()
+                                    .get_action_timing(request.action_id)
+                                    .unwrap_or_default()
+                                    .cooldown_group;
+                                if let Some(NetworkedActor::Player { recasts, .. }) =
+                                    instance.find_actor_mut(actor_id)
                                 {
-                                    instance.cancel_task(network.clone(), &task);
+                                    recasts.refund(cooldown_group);
                                 }
                             }
 
@@ -1319,7 +1439,19 @@ pub async fn server_main_loop(
                             );
 
                             let mut network = network.lock();
-                            let data = data.lock();
+                            let mut data = data.lock();
+
+                            // Remember who they're targeting, so we can show them the right enmity list.
+                            if let Some(actor) = data
+                                .find_actor_instance_mut(from_actor_id)
+                                .and_then(|instance| instance.find_actor_mut(from_actor_id))
+                            {
+                                actor.get_common_spawn_mut().target_id = ObjectTypeId {
+                                    object_id: *actor_id,
+                                    object_type: actor_type,
+                                };
+                            }
+
                             network.send_in_range(
                                 from_actor_id,
                                 &data,
@@ -2077,12 +2209,15 @@ pub async fn server_main_loop(
                                         continue;
                                     };
 
-                                    if let Some(mut npc) = instance.zone.get_battle_npc(layout_id) {
+                                    if let Some((mut npc, parameters)) =
+                                        instance.zone.get_battle_npc(layout_id)
+                                    {
                                         npc.common.handler_id = director.id;
                                         let config = get_config();
                                         instance.insert_npc(
                                             ObjectId(fastrand::u32(..)),
                                             npc,
+                                            parameters,
                                             &config,
                                         );
                                     } else {
@@ -2431,6 +2566,11 @@ pub async fn server_main_loop(
                         instance.content_handler_id(),
                     );
                     network.send_to(from_client_id, msg, DestinationNetwork::ZoneClients);
+                    network.send_to(
+                        from_client_id,
+                        FromServer::GatheringPoints(instance.gathering_points_for(from_actor_id)),
+                        DestinationNetwork::ZoneClients,
+                    );
                 }
                 ToServer::UpdateConditions(from_actor_id, new_conditions) => {
                     // update their stored state
@@ -2510,25 +2650,74 @@ pub async fn server_main_loop(
                         send_effects_list(network.clone(), instance, from_actor_id);
                     }
                 }
-                ToServer::Fish(from_client_id, from_actor_id) => {
+                ToServer::Fish(from_client_id, from_actor_id, bite_time) => {
                     let mut data = data.lock();
                     let Some(instance) = data.find_actor_instance_mut(from_actor_id) else {
                         continue;
                     };
 
+                    // Negative or NaN bite times come from broken fish conditions, so the fish bites right away instead.
                     instance.insert_task(
                         from_client_id,
                         from_actor_id,
-                        Duration::from_secs(2),
+                        Duration::try_from_secs_f32(bite_time).unwrap_or_default(),
                         QueuedTaskData::FishBite,
                     );
                 }
+                ToServer::GatheredFromPoint(from_actor_id, point_id, state) => {
+                    let mut data = data.lock();
+                    let Some(instance) = data.find_actor_instance_mut(from_actor_id) else {
+                        continue;
+                    };
+
+                    instance
+                        .gathering_points
+                        .insert((from_actor_id, point_id), state);
+                }
                 ToServer::ReloadScripts => {
                     let mut lua = lua.lock();
                     if let Err(err) = lua.init(game_data.clone()) {
                         tracing::warn!("Failed to load Init.lua: {:?}", err);
                     }
                 }
+                ToServer::ReloadConfig => match load_config() {
+                    Ok(config) => {
+                        tracing::info!("Reloaded config, pushing it to all clients...");
+
+                        let mut network = network.lock();
+                        network.send_to_all(
+                            FromServer::ConfigChanged(config.world.clone()),
+                            DestinationNetwork::ZoneClients,
+                        );
+                        network.send_to_all(
+                            FromServer::ConfigChanged(config.world),
+                            DestinationNetwork::ChatClients,
+                        );
+                    }
+                    Err(err) => tracing::warn!("Not reloading config: {err}"),
+                },
+                ToServer::SetGmRank(name, gm_rank) => {
+                    let mut data = data.lock();
+
+                    let actor_id = data.find_actor_by_name(&name);
+                    let Some(instance) = data.find_actor_instance_mut(actor_id) else {
+                        // They're offline, so the database is all that needed updating.
+                        continue;
+                    };
+
+                    if let Some(NetworkedActor::Player { spawn, .. }) =
+                        instance.find_actor_mut(actor_id)
+                    {
+                        spawn.gm_rank = gm_rank;
+                    }
+
+                    let mut network = network.lock();
+                    network.send_to_by_actor_id(
+                        actor_id,
+                        FromServer::GmRankChanged(gm_rank),
+                        DestinationNetwork::ZoneClients,
+                    );
+                }
                 ToServer::Dismounted(from_actor_id, party_id) => {
                     let mut network = network.lock();
                     let data = data.lock();
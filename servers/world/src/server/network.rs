@@ -242,6 +242,24 @@ impl NetworkState {
         }
     }
 
+    /// Sends the `message` to every connected client.
+    pub fn send_to_all(&mut self, message: FromServer, destination: DestinationNetwork) {
+        let clients = match destination {
+            DestinationNetwork::ZoneClients => &mut self.clients,
+            DestinationNetwork::ChatClients => &mut self.chat_clients,
+        };
+
+        for (id, (handle, _)) in clients {
+            if handle.send(message.clone()).is_err() {
+                if destination == DestinationNetwork::ZoneClients {
+                    self.to_remove.push(*id);
+                } else {
+                    self.to_remove_chat.push(*id);
+                }
+            }
+        }
+    }
+
     /// Sends the `message` to `actor_id`.
     pub fn send_to_by_actor_id(
         &mut self,
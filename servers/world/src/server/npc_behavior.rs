@@ -2,19 +2,20 @@ use std::{
     collections::{HashMap, VecDeque},
     f32::consts::PI,
     sync::Arc,
-    time::{Duration, Instant},
+    time::Instant,
 };
 
 use glam::Vec3A;
 use kawari::{
     common::{
-        AUTO_ATTACK_RATE, JumpState, MINIMUM_PATHFINDING_DISTANCE, MOB_WANDER_TIME,
-        MoveAnimationState, MoveAnimationType, ObjectId, ObjectTypeId, ObjectTypeKind, Position,
-        TimepointData,
+        AUTO_ATTACK_RATE, ActionTiming, AoeOrigin, JumpState, MINIMUM_PATHFINDING_DISTANCE,
+        MOB_WANDER_TIME, MoveAnimationState, MoveAnimationType, ObjectId, ObjectTypeId,
+        ObjectTypeKind, Position, TimelineContext, TimelineTarget, TimepointData,
     },
+    config::get_config,
     ipc::zone::{
         ActionRequest, ActionType, ActorControlCategory, CharacterDataFlag, ServerZoneIpcData,
-        ServerZoneIpcSegment,
+        ServerZoneIpcSegment, SpawnNpc,
     },
 };
 use parking_lot::Mutex;
@@ -22,7 +23,12 @@ use parking_lot::Mutex;
 use crate::{
     ClientId, FromServer, GameData,
     server::{
-        actor::{NetworkedActor, NpcState, NpcTarget, set_shared_group_timeline_state},
+        actor::{
+            NetworkedActor, NpcState, NpcTarget, set_shared_group_timeline_state,
+            update_actor_hp_mp,
+        },
+        effect::send_effects_list,
+        enmity::{EnmityTable, enmity_table_mut},
         instance::{Instance, QueuedTaskData},
         network::{DestinationNetwork, NetworkState},
     },
@@ -33,7 +39,7 @@ pub fn npc_behavior(
     network: Arc<Mutex<NetworkState>>,
     gamedata: Arc<Mutex<GameData>>,
     instance: &mut Instance,
-    haters: &mut HashMap<ObjectId, Vec<ObjectId>>,
+    haters: &mut HashMap<ObjectId, Vec<(ObjectId, u32)>>,
 ) {
     if instance.enemy_ai_disabled || !instance.navmesh.is_available() {
         return;
@@ -52,6 +58,7 @@ pub fn npc_behavior(
             spawn,
             last_position,
             state,
+            status_effects,
             ..
         } = actor
             && let Some(current_target) = current_target
@@ -84,7 +91,7 @@ pub fn npc_behavior(
                     Some(spawn.common.position),
                     Some(rotate(spawn.common.position.0, target_pos)),
                 )
-            } else if !current_path.is_empty() {
+            } else if !current_path.is_empty() && !status_effects.is_movement_locked() {
                 // otherwise, Follow current path
                 let next_position = current_path[0];
 
@@ -125,8 +132,13 @@ pub fn npc_behavior(
     }
 
     let mut newly_acquired_targets = Vec::new();
+    let mut switched_targets = Vec::new();
     let mut new_action_requests = Vec::new();
     let mut new_timeline_states = Vec::new();
+    let mut new_adds = Vec::new();
+    let mut new_director_events = Vec::new();
+    let mut now_evading = Vec::new();
+    let mut finished_evading = Vec::new();
 
     // mut pass
     for (id, actor) in &mut instance.actors {
@@ -137,10 +149,14 @@ pub fn npc_behavior(
             navmesh_target: current_target,
             spawn,
             last_position,
+            home_position,
             timeline_position,
             timeline,
-            newly_hated_actor,
+            timeline_phase,
+            aggro,
+            enmity,
             currently_invulnerable,
+            status_effects,
             last_wander_timestamp,
             ..
         } = actor
@@ -148,7 +164,7 @@ pub fn npc_behavior(
             && spawn.common.health_points > 0
         {
             // NOTE: this is *intentional* as I believe in retail the timing of actions are dependent on when the actor spawned
-            // This doesn't have an effect if you re-aggro them or whatever.
+            // This doesn't have an effect if you re-aggro them or whatever, it only starts over once they evade.
             *timeline_position += 1; // NOTE: change if the length of a server tick changes
 
             // switch to the next node if we passed this one
@@ -159,13 +175,26 @@ pub fn npc_behavior(
                 }
             }
 
-            // Pick up any newly hated actors first.
-            if let Some(actor) = newly_hated_actor.take() {
+            // Forget about anyone who died or left.
+            enmity.retain(|actor_id| enemies.iter().any(|(id, ..)| *id == actor_id));
+
+            // Always go after whoever we hate the most, even if that means switching targets.
+            if let Some(top) = enmity.top()
+                && *state != NpcState::Evade
+                && !matches!(current_target, Some(NpcTarget::Actor(actor)) if *actor == top)
+            {
+                if *state == NpcState::Hate {
+                    switched_targets.push(*id);
+                } else {
+                    newly_acquired_targets.push(*id);
+                }
+
                 *state = NpcState::Hate;
-                *current_target = Some(NpcTarget::Actor(actor));
+                *current_target = Some(NpcTarget::Actor(top));
+                current_path.clear();
+                *current_path_lerp = 0.0;
 
-                spawn.common.target_id.object_id = actor;
-                newly_acquired_targets.push(*id);
+                spawn.common.target_id.object_id = top;
             }
 
             if current_target.is_none() {
@@ -178,21 +207,35 @@ pub fn npc_behavior(
                     let possible_enemies =
                         game_data.get_battalion_enemies(spawn.common.battalion as u32);
 
+                    // Everything aggros in instanced content, as players are level synced anyway.
+                    let check_level = instance.content_finder_condition_id == 0;
+
                     // find a player if in range
-                    for (target_id, position, battalion) in &enemies {
+                    for (target_id, position, battalion, level) in &enemies {
                         if !possible_enemies[*battalion as usize] {
                             continue;
                         }
 
-                        // TODO: hardcoded sensing range
-                        if Vec3A::distance(position.0, spawn.common.position.0) < 15.0 {
-                            *state = NpcState::Hate;
-                            *current_target = Some(NpcTarget::Actor(*target_id));
+                        if check_level && aggro.ignores_level(spawn.common.level, *level) {
+                            continue;
+                        }
 
-                            spawn.common.target_id.object_id = *target_id;
-                            newly_acquired_targets.push(*id);
+                        if aggro.can_sense(
+                            spawn.common.position.0,
+                            spawn.common.rotation,
+                            position.0,
+                        ) {
+                            enmity.add(*target_id, 1);
                         }
                     }
+
+                    if let Some(top) = enmity.top() {
+                        *state = NpcState::Hate;
+                        *current_target = Some(NpcTarget::Actor(top));
+
+                        spawn.common.target_id.object_id = top;
+                        newly_acquired_targets.push(*id);
+                    }
                 } else if *state == NpcState::Follow {
                     *current_target = Some(NpcTarget::Actor(spawn.common.owner_id));
                 }
@@ -209,7 +252,7 @@ pub fn npc_behavior(
                     ));
                     *last_wander_timestamp = Instant::now();
                 }
-            } else if !current_path.is_empty() {
+            } else if !current_path.is_empty() && !status_effects.is_movement_locked() {
                 let next_position = current_path[0];
                 let current_position = last_position.unwrap_or(spawn.common.position.0);
                 let distance = Vec3A::distance(current_position, next_position);
@@ -217,6 +260,11 @@ pub fn npc_behavior(
                 *current_path_lerp = f32::clamp(*current_path_lerp + (2.0 / distance), 0.0, 1.0);
             }
 
+            // Give up if we were dragged too far from home.
+            let mut start_evading = *state == NpcState::Hate
+                && !spawn.common.owner_id.is_valid()
+                && aggro.exceeds_leash(*home_position, spawn.common.position.0);
+
             let mut reset_target = false;
             let can_take_action; // FIXME: this is kind of stupid because enemies can do ranged attacks, etc.
             if let Some(current_target) = current_target {
@@ -224,7 +272,7 @@ pub fn npc_behavior(
                 match &current_target {
                     NpcTarget::Actor(actor) => {
                         // Check if the enemy is still valid
-                        reset_target = !enemies.iter().any(|(id, _, _)| *id == *actor);
+                        reset_target = !enemies.iter().any(|(id, ..)| *id == *actor);
                         if target_actor_pos.contains_key(actor) {
                             target_pos = Some(target_actor_pos[actor]);
                         } else {
@@ -267,32 +315,48 @@ pub fn npc_behavior(
                 can_take_action = false;
             }
 
+            // Things like stuns also stop enemies from acting.
+            let can_take_action = can_take_action && !status_effects.are_actions_locked();
+
+            let context = TimelineContext {
+                hp_percent: hp_percent(spawn),
+                num_targets: enmity.iter().count(),
+            };
+
+            // Move onto the next phase once HP drops low enough, which starts from the beginning.
+            let phase = timeline.phase_at(context.hp_percent);
+            if phase != *timeline_phase {
+                *timeline_phase = phase;
+                *timeline_position = 0;
+            }
+
             // Only update the timeline on exact second marks
-            if can_take_action && (*timeline_position % 2) == 0 {
+            if can_take_action && *state != NpcState::Evade && (*timeline_position % 2) == 0 {
                 // TODO: something worth thinking about is whether to simplify timeline_always_play, and have it always play anyway but skip Action points?
 
-                // NOTE: the "+ 0.5" is a hack to ensure the last timepoint is always counted
                 let timeline_position_seconds = *timeline_position / 2;
-                let real_timeline_position =
-                    timeline_position_seconds as f32 % (timeline.duration() as f32 + 0.5);
-                for timepoint in timeline.points_at(real_timeline_position as i32) {
-                    match &timepoint.data {
-                        TimepointData::Action { action_id, .. } => {
+                let timepoints =
+                    timeline.phase_points_at(*timeline_phase, timeline_position_seconds as i32);
+                for data in timepoints.iter().flat_map(|timepoint| {
+                    timepoint
+                        .data
+                        .resolve(&context, &mut |len| fastrand::usize(..len))
+                }) {
+                    match data {
+                        TimepointData::Action { action_id, target } => {
                             if spawn.common.target_id.object_id.is_valid() && can_take_action {
-                                let cast_time;
-                                {
-                                    let mut game_data = gamedata.lock();
-                                    cast_time = game_data.get_casttime(*action_id).unwrap(); // TODO: take into account the haste stat like the client does
-                                }
-                                let cast_time_seconds = (cast_time as f32 * 100.0) / 1000.0; // TODO: just change how the Duration is interpreted instead of this nonsense
+                                let timing = gamedata
+                                    .lock()
+                                    .get_action_timing(*action_id)
+                                    .unwrap_or_default();
                                 let request = ActionRequest {
                                     action_id: *action_id,
                                     action_type: ActionType::Action,
                                     rotation1: spawn.common.rotation,
-                                    target: spawn.common.target_id,
+                                    target: select_target(*target, spawn, enmity, &enemies),
                                     ..Default::default()
                                 };
-                                new_action_requests.push((*id, request, cast_time_seconds));
+                                new_action_requests.push((*id, request, timing));
                             }
                         }
                         TimepointData::TimelineState { states } => {
@@ -303,6 +367,20 @@ pub fn npc_behavior(
                         TimepointData::Invulnerability { invulnerable } => {
                             *currently_invulnerable = *invulnerable;
                         }
+                        TimepointData::SpawnAdds { layout_ids } => {
+                            for layout_id in layout_ids {
+                                new_adds.push((
+                                    spawn.common.handler_id,
+                                    *layout_id,
+                                    spawn.common.target_id.object_id,
+                                ));
+                            }
+                        }
+                        TimepointData::DirectorEvent { event } => {
+                            new_director_events.push((spawn.common.layout_id, event.clone()));
+                        }
+                        // These were already resolved above.
+                        TimepointData::Random { .. } | TimepointData::Branch { .. } => {}
                     }
                 }
 
@@ -319,17 +397,50 @@ pub fn npc_behavior(
                             target: spawn.common.target_id,
                             ..Default::default()
                         };
-                        new_action_requests.push((*id, request, 0.0));
+                        new_action_requests.push((*id, request, ActionTiming::default()));
                     }
                 }
             }
 
             if reset_target {
+                // Give up on them, and move on to whoever is next on the list (if any.)
+                if let Some(NpcTarget::Actor(actor)) = current_target {
+                    enmity.remove(*actor);
+                }
+
+                if *state == NpcState::Evade {
+                    // We made it home (or can't path there), so start over from scratch.
+                    enmity.clear();
+                    status_effects.clear();
+                    spawn.common.health_points = spawn.common.max_health_points;
+                    *timeline_position = 0;
+                    *timeline_phase = None;
+                    *currently_invulnerable = false;
+                    finished_evading.push(*id);
+                } else if *state == NpcState::Hate
+                    && enmity.is_empty()
+                    && !spawn.common.owner_id.is_valid()
+                {
+                    // Everyone we were fighting is gone.
+                    start_evading = true;
+                }
+
                 *current_target = None;
                 *state = NpcState::natural_state_of(spawn);
                 spawn.common.target_id = ObjectTypeId::default();
             }
 
+            if start_evading {
+                enmity.clear();
+                *state = NpcState::Evade;
+                *current_target = Some(NpcTarget::Position(*home_position));
+                current_path.clear();
+                *current_path_lerp = 0.0;
+                *currently_invulnerable = true;
+                spawn.common.target_id = ObjectTypeId::default();
+                now_evading.push(*id);
+            }
+
             // update common spawn
             for msg in &actor_moves {
                 if let (_, FromServer::ActorMove(msg_id, pos, rotation, ..)) = msg
@@ -342,40 +453,59 @@ pub fn npc_behavior(
         }
     }
 
+    for id in now_evading {
+        let mut network = network.lock();
+        network.send_ac_in_range_instance(
+            instance,
+            id,
+            ActorControlCategory::SetBattle { battle: false },
+        );
+    }
+
+    for id in finished_evading {
+        update_actor_hp_mp(network.clone(), instance, id);
+        send_effects_list(network.clone(), instance, id);
+    }
+
     // inform clients of the NPCs new positions
     for (id, msg) in actor_moves {
         let mut network = network.lock();
         network.send_in_range_instance(id, instance, msg, DestinationNetwork::ZoneClients);
     }
 
-    for (id, request, cast_time) in new_action_requests {
-        if cast_time == 0.0 {
-            instance.insert_task(
-                ClientId::default(),
-                id,
-                Duration::from_secs_f32(0.0),
-                QueuedTaskData::CastAction {
-                    request: request.clone(),
-                    interruptible: false,
-                },
-            );
-        } else {
-            let position;
-            {
-                let actor = instance.find_actor(id).unwrap();
-                position = actor.position();
-            }
+    for (id, request, timing) in new_action_requests {
+        let cast_time = timing.cast_time;
+
+        let aoe;
+        {
+            let mut game_data = gamedata.lock();
+            aoe = game_data.get_action_aoe(request.action_id);
+        }
 
-            // inform players that this enemy is casting
+        // Enemy spells can be interrupted with something like Interject, and their cast bar shows it. Telegraphs can't be stopped.
+        // The Action sheet has no column for this, as retail decides it on the server and only sends it in ActorCast. So this is a guess from the cast type and area of effect.
+        let interruptible = !cast_time.is_zero() && timing.is_spell() && aoe.is_none();
+
+        // Telegraphs are placed when the cast begins, and don't follow anyone around afterwards.
+        let position = match aoe.map(|aoe| aoe.origin) {
+            Some(AoeOrigin::Target) => instance
+                .find_actor(request.target.object_id)
+                .map(|actor| actor.position()),
+            _ => instance.find_actor(id).map(|actor| actor.position()),
+        }
+        .unwrap_or_default();
+
+        if !cast_time.is_zero() {
+            // inform players that this enemy is casting, which also shows the omen (if any) at this position
             let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::ActorCast {
                 spell_id: request.action_id as u16,
                 action_type: request.action_type,
                 omen_delay: 0,
                 action_id: request.action_id,
-                cast_time,
+                cast_time: cast_time.as_secs_f32(),
                 target: request.target.object_id,
                 rotation: request.rotation1,
-                interruptible: false,
+                interruptible,
                 ballista_entity_id: ObjectId::default(),
                 position,
             });
@@ -387,16 +517,46 @@ pub fn npc_behavior(
                 FromServer::PacketSegment(ipc, id),
                 DestinationNetwork::ZoneClients,
             );
+        }
 
-            instance.insert_task(
-                ClientId::default(),
-                id,
-                Duration::from_secs_f32(cast_time),
-                QueuedTaskData::CastAction {
-                    request: request.clone(),
-                    interruptible: true, // TODO: not always true?
-                },
-            );
+        let task = match aoe {
+            Some(aoe) => QueuedTaskData::CastTelegraph {
+                request: request.clone(),
+                aoe,
+                position,
+                rotation: request.rotation1,
+            },
+            None => QueuedTaskData::CastAction {
+                request: request.clone(),
+                interruptible,
+            },
+        };
+
+        instance.insert_task(ClientId::default(), id, cast_time, task);
+    }
+
+    for (handler_id, layout_id, target_id) in new_adds {
+        let Some((mut npc, parameters)) = instance.zone.get_battle_npc(layout_id) else {
+            tracing::warn!("Failed to find bnpc {layout_id} for SpawnAdds, it won't spawn!");
+            continue;
+        };
+        npc.common.handler_id = handler_id;
+
+        let actor_id = Instance::generate_actor_id();
+        let config = get_config();
+        instance.insert_npc(actor_id, npc, parameters, &config);
+
+        // Adds join in on whoever the boss is fighting.
+        if target_id.is_valid()
+            && let Some(enmity) = instance.find_actor_mut(actor_id).and_then(enmity_table_mut)
+        {
+            enmity.add(target_id, 1);
+        }
+    }
+
+    if let Some(director) = &mut instance.directors.first_mut() {
+        for (layout_id, event) in new_director_events {
+            director.on_timeline_event(layout_id, &event);
         }
     }
 
@@ -417,6 +577,7 @@ pub fn npc_behavior(
             state,
             navmesh_target: current_target,
             spawn,
+            enmity,
             ..
         } = actor
         {
@@ -424,10 +585,17 @@ pub fn npc_behavior(
                 continue;
             }
 
+            for (actor, _) in enmity.iter() {
+                haters
+                    .entry(actor)
+                    .or_default()
+                    .push((*id, enmity.percentage(actor)));
+            }
+
             if let Some(current_target) = current_target
                 && let NpcTarget::Actor(actor) = current_target
             {
-                if newly_acquired_targets.contains(id) {
+                if newly_acquired_targets.contains(id) || switched_targets.contains(id) {
                     // Send an ACT for a visual indicator, and stuff.
                     let mut network = network.lock();
                     let target = ObjectTypeId {
@@ -444,6 +612,10 @@ pub fn npc_behavior(
                         ),
                         DestinationNetwork::ZoneClients,
                     );
+                }
+
+                if newly_acquired_targets.contains(id) {
+                    let mut network = network.lock();
 
                     // TODO: does this need to be set somewhere in CommonSpawn too?
                     network.send_ac_in_range_instance(
@@ -456,10 +628,55 @@ pub fn npc_behavior(
                         director.on_actor_aggro(spawn.common.layout_id);
                     }
                 }
-
-                haters.entry(*actor).or_default();
-                haters.get_mut(actor).unwrap().push(*id);
             }
         }
     }
 }
+
+/// Returns this NPC's current HP, out of 100.
+fn hp_percent(spawn: &SpawnNpc) -> u8 {
+    if spawn.common.max_health_points == 0 {
+        return 0;
+    }
+
+    ((spawn.common.health_points as u64 * 100) / spawn.common.max_health_points as u64) as u8
+}
+
+/// Picks who a timeline action should be used on.
+fn select_target(
+    target: TimelineTarget,
+    spawn: &SpawnNpc,
+    enmity: &EnmityTable,
+    enemies: &[(ObjectId, Position, u8, u8)],
+) -> ObjectTypeId {
+    let candidates: Vec<(ObjectId, Position)> = enmity
+        .iter()
+        .filter_map(|(actor_id, _)| {
+            enemies
+                .iter()
+                .find(|(id, ..)| *id == actor_id)
+                .map(|(id, position, ..)| (*id, *position))
+        })
+        .collect();
+
+    let selected = match target {
+        TimelineTarget::Tank => None,
+        TimelineTarget::Random => fastrand::choice(&candidates).map(|(id, _)| *id),
+        TimelineTarget::Furthest => candidates
+            .iter()
+            .max_by(|(_, a), (_, b)| {
+                let a = Vec3A::distance(spawn.common.position.0, a.0);
+                let b = Vec3A::distance(spawn.common.position.0, b.0);
+                a.total_cmp(&b)
+            })
+            .map(|(id, _)| *id),
+    };
+
+    match selected {
+        Some(object_id) => ObjectTypeId {
+            object_id,
+            ..spawn.common.target_id
+        },
+        None => spawn.common.target_id,
+    }
+}
@@ -7,8 +7,8 @@ use crate::{
     ClientId, FromServer, ToServer,
     common::PartyUpdateTargets,
     server::{
-        DestinationNetwork, WorldServer, actor::NetworkedActor, network::NetworkState,
-        set_character_mode,
+        DestinationNetwork, WorldServer, actor::NetworkedActor, instance::Instance,
+        network::NetworkState, set_character_mode,
     },
 };
 use kawari::{
@@ -192,6 +192,37 @@ pub fn get_party_id_from_actor_id(network: &NetworkState, actor_id: ObjectId) ->
     None
 }
 
+/// Returns the members of `actor_id`'s party that are in the same instance, or just themselves if they aren't in a party.
+pub fn party_members_in_instance(
+    network: &NetworkState,
+    instance: &Instance,
+    actor_id: ObjectId,
+) -> Vec<ObjectId> {
+    let Some(party) =
+        get_party_id_from_actor_id(network, actor_id).and_then(|id| network.parties.get(&id))
+    else {
+        return vec![actor_id];
+    };
+
+    party
+        .members
+        .iter()
+        .map(|member| member.actor_id)
+        .filter(|id| instance.find_actor(*id).is_some())
+        .collect()
+}
+
+/// Returns how many votes are needed for a vote in this duty, which is one from every party member still inside.
+pub fn duty_votes_needed(network: &NetworkState, instance: &Instance) -> usize {
+    // Duties only ever have one party in them, so any player is enough to find it.
+    instance
+        .actors
+        .iter()
+        .find(|(_, actor)| matches!(actor, NetworkedActor::Player { .. }))
+        .map(|(id, _)| party_members_in_instance(network, instance, *id).len())
+        .unwrap_or_default()
+}
+
 /// Helper function to send the party's currently marked targets to a specific actor that changed areas or returned from being offline.
 fn send_party_target_signs(network: &mut NetworkState, party_id: u64, execute_actor_id: ObjectId) {
     let target_signs = match network.parties.get(&party_id) {
@@ -798,7 +829,38 @@ pub fn handle_party_messages(
             target_name,
         ) => {
             let mut network = network.lock();
-            let data = data.lock();
+            let mut data = data.lock();
+
+            // Inside a duty, kicking someone only casts a vote. They're removed once most of the other party members there agree.
+            if let Some(party) = network.parties.get(party_id)
+                && let Some(executor) = party.get_member_by_content_id(*execute_content_id)
+                && let Some(target) = party.get_member_by_content_id(*target_content_id)
+                && let Some(instance) = data.find_actor_instance_mut(executor.actor_id)
+                && instance.content_handler_id().is_some()
+            {
+                let others = duty_votes_needed(&network, instance).saturating_sub(1);
+                let needed = others / 2 + 1;
+
+                let voters = instance.kick_votes.entry(target.actor_id).or_default();
+                voters.insert(executor.actor_id);
+                let votes = voters.len();
+                if votes < needed {
+                    network.send_to_party(
+                        *party_id,
+                        None,
+                        FromServer::KickVoted(
+                            execute_name.clone(),
+                            target_name.clone(),
+                            votes as u32,
+                            needed as u32,
+                        ),
+                        DestinationNetwork::ZoneClients,
+                    );
+                    return true;
+                }
+                instance.kick_votes.remove(&target.actor_id);
+            }
+
             let party = network.parties.get_mut(party_id).unwrap();
 
             let Some(member) = party.get_member_by_content_id(*target_content_id) else {
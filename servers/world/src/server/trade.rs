@@ -0,0 +1,401 @@
+use std::sync::Arc;
+
+use glam::Vec3A;
+use kawari::common::{ObjectId, TRADE_DISTANCE, TradeError, TradeSession};
+use parking_lot::Mutex;
+
+use crate::{
+    FromServer, ToServer,
+    server::{
+        WorldServer,
+        instance::Instance,
+        network::{DestinationNetwork, NetworkState},
+    },
+};
+
+/// Returns the name of the player with this actor ID, if they're in `instance`.
+fn player_name(instance: &Instance, actor_id: ObjectId) -> Option<String> {
+    instance
+        .find_actor(actor_id)
+        .and_then(|actor| actor.get_player_spawn())
+        .map(|spawn| spawn.common.name.clone())
+}
+
+/// Whether `actor_id` is already trading with someone.
+fn is_trading(instance: &Instance, actor_id: ObjectId) -> bool {
+    instance
+        .trades
+        .iter()
+        .any(|trade| trade.side(actor_id).is_some())
+}
+
+/// Ends the trade at `index`, and tells both players why. Their connections roll back anything that was already exchanged, see `ZoneConnection::trade_cancelled`.
+fn end_trade(network: &mut NetworkState, instance: &mut Instance, index: usize, reason: &str) {
+    let trade = instance.trades.remove(index);
+    for actor_id in trade.traders {
+        network.send_to_by_actor_id(
+            actor_id,
+            FromServer::TradeCancelled(reason.to_string()),
+            DestinationNetwork::ZoneClients,
+        );
+    }
+}
+
+/// Cancels any trade `actor_id` is part of, and forgets about their trade requests. Used when they leave the instance.
+pub fn cancel_trades(network: &mut NetworkState, instance: &mut Instance, actor_id: ObjectId) {
+    instance
+        .trade_requests
+        .retain(|(requester, target)| *requester != actor_id && *target != actor_id);
+
+    if let Some(index) = instance
+        .trades
+        .iter()
+        .position(|trade| trade.side(actor_id).is_some())
+    {
+        end_trade(
+            network,
+            instance,
+            index,
+            "Your trading partner left, so the trade was cancelled.",
+        );
+    }
+}
+
+/// Process trade messages.
+pub fn handle_trade_messages(
+    data: Arc<Mutex<WorldServer>>,
+    network: Arc<Mutex<NetworkState>>,
+    msg: &ToServer,
+) -> bool {
+    match msg {
+        ToServer::RequestTrade(from_actor_id, target_actor_id) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            let Some(spawn) = instance
+                .find_actor(*from_actor_id)
+                .and_then(|actor| actor.get_player_spawn())
+            else {
+                return true;
+            };
+            let name = spawn.common.name.clone();
+            let position = spawn.common.position;
+            let target_actor_id = target_actor_id.unwrap_or(spawn.common.target_id.object_id);
+
+            let target = instance
+                .find_actor(target_actor_id)
+                .and_then(|actor| actor.get_player_spawn())
+                .map(|spawn| (spawn.common.name.clone(), spawn.common.position));
+
+            let refusal = match target {
+                _ if target_actor_id == *from_actor_id => {
+                    Some("You can't trade with yourself.".to_string())
+                }
+                None => Some("You have to target another player to trade with them.".to_string()),
+                Some((target_name, target_position))
+                    if Vec3A::distance(position.0, target_position.0) > TRADE_DISTANCE =>
+                {
+                    Some(format!("{target_name} is too far away to trade with."))
+                }
+                Some(_) if is_trading(instance, *from_actor_id) => {
+                    Some("You're already trading with someone.".to_string())
+                }
+                Some((target_name, _)) if is_trading(instance, target_actor_id) => {
+                    Some(format!("{target_name} is busy trading with someone else."))
+                }
+                Some(_) => None,
+            };
+
+            let mut network = network.lock();
+            if let Some(refusal) = refusal {
+                network.send_to_by_actor_id(
+                    *from_actor_id,
+                    FromServer::TradeCancelled(refusal),
+                    DestinationNetwork::ZoneClients,
+                );
+                return true;
+            }
+
+            // Only the latest request from someone is kept.
+            instance
+                .trade_requests
+                .retain(|(requester, _)| requester != from_actor_id);
+            instance
+                .trade_requests
+                .push((*from_actor_id, target_actor_id));
+
+            network.send_to_by_actor_id(
+                target_actor_id,
+                FromServer::TradeRequested(name),
+                DestinationNetwork::ZoneClients,
+            );
+
+            true
+        }
+        ToServer::AcceptTrade(from_actor_id) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            let mut network = network.lock();
+            let Some(index) = instance
+                .trade_requests
+                .iter()
+                .rposition(|(_, target)| target == from_actor_id)
+            else {
+                network.send_to_by_actor_id(
+                    *from_actor_id,
+                    FromServer::TradeCancelled("Nobody has asked to trade with you.".to_string()),
+                    DestinationNetwork::ZoneClients,
+                );
+                return true;
+            };
+            let (requester, _) = instance.trade_requests.remove(index);
+
+            let (Some(requester_name), Some(name)) = (
+                player_name(instance, requester),
+                player_name(instance, *from_actor_id),
+            ) else {
+                return true;
+            };
+
+            if is_trading(instance, requester) || is_trading(instance, *from_actor_id) {
+                network.send_to_by_actor_id(
+                    *from_actor_id,
+                    FromServer::TradeCancelled(format!(
+                        "{requester_name} is busy trading with someone else."
+                    )),
+                    DestinationNetwork::ZoneClients,
+                );
+                return true;
+            }
+
+            instance
+                .trades
+                .push(TradeSession::new(requester, *from_actor_id));
+
+            network.send_to_by_actor_id(
+                requester,
+                FromServer::TradeOpened(name),
+                DestinationNetwork::ZoneClients,
+            );
+            network.send_to_by_actor_id(
+                *from_actor_id,
+                FromServer::TradeOpened(requester_name),
+                DestinationNetwork::ZoneClients,
+            );
+
+            true
+        }
+        ToServer::OfferTrade(from_actor_id, offer) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            let Some(name) = player_name(instance, *from_actor_id) else {
+                return true;
+            };
+
+            let Some(trade) = instance
+                .trades
+                .iter_mut()
+                .find(|trade| trade.side(*from_actor_id).is_some())
+            else {
+                return true;
+            };
+
+            match trade.set_offer(*from_actor_id, offer.clone()) {
+                Ok(()) => {}
+                Err(TradeError::Locked) => {
+                    tracing::warn!("{name} tried to change their offer after confirming!");
+                    return true;
+                }
+                Err(error) => {
+                    tracing::warn!("{name} made an invalid offer: {error:?}");
+                    return true;
+                }
+            }
+
+            if let Some(partner) = trade.partner(*from_actor_id) {
+                let mut network = network.lock();
+                network.send_to_by_actor_id(
+                    partner,
+                    FromServer::TradeOffered(name, offer.clone()),
+                    DestinationNetwork::ZoneClients,
+                );
+            }
+
+            true
+        }
+        ToServer::ConfirmTrade(from_actor_id) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            let Some(name) = player_name(instance, *from_actor_id) else {
+                return true;
+            };
+
+            let Some(trade) = instance
+                .trades
+                .iter_mut()
+                .find(|trade| trade.side(*from_actor_id).is_some())
+            else {
+                return true;
+            };
+
+            if trade.is_locked() {
+                // The items are already being exchanged.
+                return true;
+            }
+
+            let is_locked = trade.confirm(*from_actor_id);
+
+            let mut network = network.lock();
+            for actor_id in trade.traders {
+                network.send_to_by_actor_id(
+                    actor_id,
+                    FromServer::TradeConfirmed(name.clone()),
+                    DestinationNetwork::ZoneClients,
+                );
+            }
+
+            if is_locked {
+                for (side, actor_id) in trade.traders.iter().enumerate() {
+                    network.send_to_by_actor_id(
+                        *actor_id,
+                        FromServer::TradeExchange(
+                            trade.offers[side].clone(),
+                            trade.offers[1 - side].clone(),
+                        ),
+                        DestinationNetwork::ZoneClients,
+                    );
+                }
+            }
+
+            true
+        }
+        ToServer::TradePrepared(from_actor_id, success) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            let name = player_name(instance, *from_actor_id).unwrap_or_default();
+
+            let Some(index) = instance
+                .trades
+                .iter()
+                .position(|trade| trade.side(*from_actor_id).is_some())
+            else {
+                return true;
+            };
+
+            let mut network = network.lock();
+            match instance.trades[index].prepare(*from_actor_id, *success) {
+                Some(true) => {
+                    let trade = instance.trades.remove(index);
+                    for actor_id in trade.traders {
+                        network.send_to_by_actor_id(
+                            actor_id,
+                            FromServer::TradeCompleted(),
+                            DestinationNetwork::ZoneClients,
+                        );
+                    }
+                }
+                Some(false) => end_trade(
+                    &mut network,
+                    instance,
+                    index,
+                    &format!(
+                        "{name} doesn't have room for the items, or no longer has what they offered. The trade was cancelled."
+                    ),
+                ),
+                None => {}
+            }
+
+            true
+        }
+        ToServer::LeaveTrade(from_actor_id) => {
+            let mut data = data.lock();
+            let mut network = network.lock();
+            let index = data
+                .find_actor_instance_mut(*from_actor_id)
+                .and_then(|instance| {
+                    instance
+                        .trades
+                        .iter()
+                        .position(|trade| trade.side(*from_actor_id).is_some())
+                        .map(|index| (instance, index))
+                });
+
+            match index {
+                Some((instance, index)) => {
+                    let name = player_name(instance, *from_actor_id).unwrap_or_default();
+                    end_trade(
+                        &mut network,
+                        instance,
+                        index,
+                        &format!("{name} logged out, so the trade was cancelled."),
+                    );
+                }
+                None => {
+                    // The trade already ended, and they were told how before this.
+                    network.send_to_by_actor_id(
+                        *from_actor_id,
+                        FromServer::TradeCancelled(String::new()),
+                        DestinationNetwork::ZoneClients,
+                    );
+                }
+            }
+
+            true
+        }
+        ToServer::CancelTrade(from_actor_id) => {
+            let mut data = data.lock();
+            let Some(instance) = data.find_actor_instance_mut(*from_actor_id) else {
+                tracing::warn!("Somehow failed to find an instance for actor?");
+                return true;
+            };
+
+            instance.trade_requests.retain(|(requester, target)| {
+                requester != from_actor_id && target != from_actor_id
+            });
+
+            let Some(index) = instance
+                .trades
+                .iter()
+                .position(|trade| trade.side(*from_actor_id).is_some())
+            else {
+                return true;
+            };
+
+            if instance.trades[index].is_locked() {
+                // It's too late to back out once the items are being exchanged.
+                return true;
+            }
+
+            let name = player_name(instance, *from_actor_id).unwrap_or_default();
+            let mut network = network.lock();
+            end_trade(
+                &mut network,
+                instance,
+                index,
+                &format!("{name} cancelled the trade."),
+            );
+
+            true
+        }
+        _ => false,
+    }
+}
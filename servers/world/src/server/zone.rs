@@ -28,7 +28,7 @@ use kawari::{
     common::{
         CharacterMode, DistanceRange, DropIn, DropInLayer, DropInObjectData, ENTRANCE_CIRCLE_IDS,
         EOBJ_EXIT, EOBJ_HOUSING_ENTRANCE, EOBJ_SHORTCUT, EOBJ_SHORTCUT_EXPLORER_MODE, EventState,
-        HandlerType, ObjectId, Position, WARP_DELAY, WarpType, euler_to_direction,
+        HandlerType, ObjectId, Position, Recasts, WARP_DELAY, WarpType, euler_to_direction,
         internal_housing_row,
     },
     config::get_config,
@@ -104,7 +104,7 @@ pub struct Zone {
     pub map_ranges: Vec<MapRange>,
     dropin_layers: Vec<DropInLayer>,
     cached_objects: HashMap<u32, SpawnObject>,
-    cached_npcs: HashMap<u32, SpawnNpc>,
+    cached_npcs: HashMap<u32, (SpawnNpc, BaseParameters)>,
     cached_treasure: HashMap<u32, SpawnTreasure>,
     layer_set: i32,
     bg_path: String,
@@ -636,8 +636,8 @@ impl Zone {
         self.cached_objects.get(&base_id).cloned()
     }
 
-    /// Returns an SpawnNpc for the given instance ID.
-    pub fn get_battle_npc(&self, instance_id: u32) -> Option<SpawnNpc> {
+    /// Returns an SpawnNpc for the given instance ID, along with its parameters.
+    pub fn get_battle_npc(&self, instance_id: u32) -> Option<(SpawnNpc, BaseParameters)> {
         self.cached_npcs.get(&instance_id).cloned()
     }
 
@@ -646,8 +646,8 @@ impl Zone {
         self.cached_treasure.get(&base_id).cloned()
     }
 
-    /// Returns a list of battle NPCs to spawn.
-    pub fn get_npcs(&mut self, game_data: &mut GameData) -> Vec<SpawnNpc> {
+    /// Returns a list of battle NPCs to spawn, along with their parameters.
+    pub fn get_npcs(&mut self, game_data: &mut GameData) -> Vec<(SpawnNpc, BaseParameters)> {
         let mut npc_spawns = Vec::new();
 
         // Only dropins are checked for battle npcs, because they strip that from retail LGBs.
@@ -666,7 +666,8 @@ impl Zone {
                     link_range,
                 } = object.data
                 {
-                    let base_npc = create_npc_common_spawn(game_data, base_id, name_id, hp, level);
+                    let (base_npc, parameters) =
+                        create_npc_common_spawn(game_data, base_id, name_id, hp, level);
                     let spawn = SpawnNpc {
                         gimmick_id,
                         character_data_flags: if hostile {
@@ -686,9 +687,10 @@ impl Zone {
                         ..base_npc
                     };
 
-                    self.cached_npcs.insert(object.layout_id, spawn.clone());
+                    self.cached_npcs
+                        .insert(object.layout_id, (spawn.clone(), parameters.clone()));
                     if !nonpop {
-                        npc_spawns.push(spawn);
+                        npc_spawns.push((spawn, parameters));
                     }
                 }
                 if let DropInObjectData::EventNpc { base_id } = object.data {
@@ -711,8 +713,9 @@ impl Zone {
                         ..Default::default()
                     };
 
-                    self.cached_npcs.insert(object.layout_id, spawn.clone());
-                    npc_spawns.push(spawn);
+                    self.cached_npcs
+                        .insert(object.layout_id, (spawn.clone(), BaseParameters::default()));
+                    npc_spawns.push((spawn, BaseParameters::default()));
                 }
             }
         }
@@ -1060,6 +1063,11 @@ fn do_change_zone(
             target_instance.content_handler_id(),
         );
         network.send_to(from_id, msg, DestinationNetwork::ZoneClients);
+        network.send_to(
+            from_id,
+            FromServer::GatheringPoints(target_instance.gathering_points_for(actor_id)),
+            DestinationNetwork::ZoneClients,
+        );
     } else {
         // We want to delay sending this to give time for the client to fade out.
         let segment = ServerZoneIpcSegment::new(ServerZoneIpcData::ActorSetPos(ActorSetPos {
@@ -1106,6 +1114,7 @@ pub fn handle_zone_messages(
                 parameters: BaseParameters::default(),
                 dueling_opponent_id: ObjectId::default(),
                 remove_cooldowns: false,
+                recasts: Recasts::default(),
                 last_combo_action: 0,
                 combo_sequence: 0,
                 autoattack_target: None,
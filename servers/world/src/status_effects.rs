@@ -1,59 +1,216 @@
-use kawari::ipc::zone::StatusEffect;
+use kawari::{
+    common::{CombatStats, LevelModifiers, ObjectId},
+    ipc::zone::StatusEffect,
+};
+
+use crate::zone_connection::PotencyKind;
+
+/// The most status effects an actor can have at once, as the client only has room for this many.
+pub const MAX_STATUS_EFFECTS: usize = 30;
+
+/// Damage or healing that a status effect applies every tick. Like on retail, the source's stats are snapshotted when it's applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EffectOverTime {
+    pub kind: PotencyKind,
+    pub potency: u32,
+    /// Who applied the effect.
+    pub source_actor_id: ObjectId,
+    pub stats: CombatStats,
+    pub level_modifiers: LevelModifiers,
+}
+
+/// How a status effect behaves, from the Status sheet.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StatusInfo {
+    /// If above zero, the param is used as a stack count capped to this.
+    pub max_stacks: u8,
+    /// If each source gets their own instance of this effect, e.g. two players putting their DoT on the same enemy.
+    pub per_source: bool,
+    /// If this effect can be removed by something like Esuna.
+    pub can_dispel: bool,
+    /// If this effect isn't removed when the list is cleared, e.g. on death.
+    pub is_permanent: bool,
+    /// If the actor can't move while this effect is active.
+    pub lock_movement: bool,
+    /// If the actor can't use actions while this effect is active.
+    pub lock_actions: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ActiveEffect {
+    effect: StatusEffect,
+    info: StatusInfo,
+    /// This is only used by the server.
+    over_time: Option<EffectOverTime>,
+}
 
 #[derive(Debug, Default, Clone)]
 pub struct StatusEffects {
-    status_effects: Vec<StatusEffect>,
+    status_effects: Vec<ActiveEffect>,
     dirty: bool,
 }
 
 impl StatusEffects {
+    /// Adds a new status effect with no source and default behavior, or refreshes an existing one.
     pub fn add(&mut self, effect_id: u16, effect_param: u16, duration: f32) {
-        let status_effect = self.find_or_create_status_effect(effect_id, effect_param);
-        status_effect.duration = duration;
-        self.dirty = true
+        self.apply(
+            effect_id,
+            effect_param,
+            duration,
+            ObjectId::default(),
+            StatusInfo::default(),
+        );
     }
 
-    fn find_or_create_status_effect(
+    /// Adds a new status effect, or refreshes an existing one. Any damage or healing over time from before is forgotten.
+    ///
+    /// Stackable effects gain `effect_param` stacks (at least one) each time they're applied, up to their maximum.
+    /// Returns the index of the effect in the list, or None if the actor already has too many.
+    pub fn apply(
         &mut self,
         effect_id: u16,
         effect_param: u16,
-    ) -> &mut StatusEffect {
-        if let Some(i) = self
-            .status_effects
-            .iter()
-            .position(|effect| effect.effect_id == effect_id)
-        {
-            &mut self.status_effects[i]
+        duration: f32,
+        source_actor_id: ObjectId,
+        info: StatusInfo,
+    ) -> Option<usize> {
+        let stacks = effect_param.max(1);
+        let max_stacks = info.max_stacks as u16;
+
+        let index = if let Some(i) = self.position(effect_id, source_actor_id) {
+            let active = &mut self.status_effects[i];
+            if max_stacks > 0 {
+                active.effect.param = active.effect.param.saturating_add(stacks).min(max_stacks);
+            } else {
+                active.effect.param = effect_param;
+            }
+            i
         } else {
-            self.status_effects.push(StatusEffect {
-                effect_id,
-                param: effect_param,
-                ..Default::default()
+            if self.status_effects.len() >= MAX_STATUS_EFFECTS {
+                return None;
+            }
+
+            let param = if max_stacks > 0 {
+                stacks.min(max_stacks)
+            } else {
+                effect_param
+            };
+            self.status_effects.push(ActiveEffect {
+                effect: StatusEffect {
+                    effect_id,
+                    param,
+                    ..Default::default()
+                },
+                info,
+                over_time: None,
             });
-            self.status_effects.last_mut().unwrap()
+            self.status_effects.len() - 1
+        };
+
+        let active = &mut self.status_effects[index];
+        active.effect.duration = duration;
+        active.effect.source_actor_id = source_actor_id;
+        active.info = info;
+        active.over_time = None;
+        self.dirty = true;
+
+        Some(index)
+    }
+
+    /// Makes an existing status effect apply damage or healing every tick.
+    pub fn set_over_time(&mut self, effect_id: u16, over_time: EffectOverTime) {
+        if let Some(i) = self.position(effect_id, over_time.source_actor_id) {
+            self.status_effects[i].over_time = Some(over_time);
         }
     }
 
+    /// Returns the damage or healing this status effect applies every tick, if any.
+    pub fn over_time(&self, effect_id: u16, source_actor_id: ObjectId) -> Option<EffectOverTime> {
+        self.position(effect_id, source_actor_id)
+            .and_then(|i| self.status_effects[i].over_time)
+    }
+
+    /// Finds the instance of this effect, only matching the source if each source gets their own.
+    fn position(&self, effect_id: u16, source_actor_id: ObjectId) -> Option<usize> {
+        self.status_effects.iter().position(|active| {
+            active.effect.effect_id == effect_id
+                && (!active.info.per_source || active.effect.source_actor_id == source_actor_id)
+        })
+    }
+
+    /// Returns the first instance of this effect, regardless of who applied it.
     pub fn get(&self, effect_id: u16) -> Option<StatusEffect> {
         self.status_effects
             .iter()
-            .position(|effect| effect.effect_id == effect_id)
-            .map(|i| self.status_effects[i])
+            .find(|active| active.effect.effect_id == effect_id)
+            .map(|active| active.effect)
     }
 
+    /// Returns the instance of this effect that `source_actor_id` would refresh.
+    pub fn get_from(&self, effect_id: u16, source_actor_id: ObjectId) -> Option<StatusEffect> {
+        self.position(effect_id, source_actor_id)
+            .map(|i| self.status_effects[i].effect)
+    }
+
+    /// Removes every instance of this effect.
     pub fn remove(&mut self, effect_id: u16) {
-        if let Some(i) = self
-            .status_effects
-            .iter()
-            .position(|effect| effect.effect_id == effect_id)
-        {
+        let len = self.status_effects.len();
+        self.status_effects
+            .retain(|active| active.effect.effect_id != effect_id);
+        if self.status_effects.len() != len {
+            self.dirty = true;
+        }
+    }
+
+    /// Removes the instance of this effect that `source_actor_id` would refresh. Returns true if one was removed.
+    pub fn remove_from(&mut self, effect_id: u16, source_actor_id: ObjectId) -> bool {
+        if let Some(i) = self.position(effect_id, source_actor_id) {
             self.status_effects.remove(i);
             self.dirty = true;
+            true
+        } else {
+            false
         }
     }
 
-    pub fn data(&self) -> &[StatusEffect] {
-        &self.status_effects
+    /// Returns the most recently applied effect that can be dispelled, if any.
+    pub fn dispellable(&self) -> Option<StatusEffect> {
+        self.status_effects
+            .iter()
+            .rev()
+            .find(|active| active.info.can_dispel)
+            .map(|active| active.effect)
+    }
+
+    /// Removes every status effect, except for permanent ones.
+    pub fn clear(&mut self) {
+        let len = self.status_effects.len();
+        self.status_effects
+            .retain(|active| active.info.is_permanent);
+        if self.status_effects.len() != len {
+            self.dirty = true;
+        }
+    }
+
+    /// If any effect prevents the actor from moving.
+    pub fn is_movement_locked(&self) -> bool {
+        self.status_effects
+            .iter()
+            .any(|active| active.info.lock_movement)
+    }
+
+    /// If any effect prevents the actor from using actions.
+    pub fn are_actions_locked(&self) -> bool {
+        self.status_effects
+            .iter()
+            .any(|active| active.info.lock_actions)
+    }
+
+    pub fn data(&self) -> Vec<StatusEffect> {
+        self.status_effects
+            .iter()
+            .map(|active| active.effect)
+            .collect()
     }
 
     /// If the list is dirty and must be propagated to the client
@@ -107,5 +264,203 @@ mod tests {
         status_effects.remove(0);
         assert_eq!(status_effects.get(0), None);
         assert_eq!(status_effects.is_dirty(), true);
+
+        // Clearing should remove everything:
+        status_effects.add(0, 0, 0.0);
+        status_effects.add(1, 0, 0.0);
+        status_effects.reset_dirty();
+        status_effects.clear();
+        assert!(status_effects.is_empty());
+        assert_eq!(status_effects.is_dirty(), true);
+
+        // ...except for permanent effects:
+        let permanent = StatusInfo {
+            is_permanent: true,
+            ..Default::default()
+        };
+        status_effects.add(0, 0, 0.0);
+        status_effects.apply(1, 0, 0.0, ObjectId::default(), permanent);
+        status_effects.clear();
+        assert_eq!(status_effects.get(0), None);
+        assert!(status_effects.get(1).is_some());
+
+        // Nothing to clear shouldn't dirty the list:
+        status_effects.reset_dirty();
+        status_effects.clear();
+        assert!(!status_effects.is_dirty());
+    }
+
+    #[test]
+    fn test_status_effect_stacks() {
+        let mut status_effects = StatusEffects::default();
+        let stackable = StatusInfo {
+            max_stacks: 3,
+            ..Default::default()
+        };
+
+        // Each application adds a stack, up to the maximum.
+        status_effects.apply(0, 0, 10.0, ObjectId(1), stackable);
+        assert_eq!(status_effects.get(0).unwrap().param, 1);
+        status_effects.apply(0, 0, 10.0, ObjectId(1), stackable);
+        assert_eq!(status_effects.get(0).unwrap().param, 2);
+        status_effects.apply(0, 5, 10.0, ObjectId(1), stackable);
+        assert_eq!(status_effects.get(0).unwrap().param, 3);
+
+        // Effects that don't stack simply have their param replaced.
+        status_effects.add(1, 5, 10.0);
+        status_effects.add(1, 2, 20.0);
+        let effect = status_effects.get(1).unwrap();
+        assert_eq!(effect.param, 2);
+        assert_eq!(effect.duration, 20.0);
+        assert_eq!(status_effects.len(), 2);
+    }
+
+    #[test]
+    fn test_status_effect_sources() {
+        let mut status_effects = StatusEffects::default();
+        let per_source = StatusInfo {
+            per_source: true,
+            ..Default::default()
+        };
+
+        // Each source gets their own instance.
+        status_effects.apply(0, 0, 10.0, ObjectId(1), per_source);
+        status_effects.apply(0, 0, 20.0, ObjectId(2), per_source);
+        assert_eq!(status_effects.len(), 2);
+        assert_eq!(
+            status_effects.get_from(0, ObjectId(2)).unwrap().duration,
+            20.0
+        );
+
+        // Removing one source's instance leaves the other.
+        assert!(status_effects.remove_from(0, ObjectId(1)));
+        assert!(!status_effects.remove_from(0, ObjectId(1)));
+        assert_eq!(status_effects.get_from(0, ObjectId(1)), None);
+        assert!(status_effects.get_from(0, ObjectId(2)).is_some());
+
+        // Shared effects are taken over by whoever applied them last.
+        status_effects.apply(1, 0, 10.0, ObjectId(1), StatusInfo::default());
+        status_effects.apply(1, 0, 10.0, ObjectId(2), StatusInfo::default());
+        assert_eq!(status_effects.len(), 2);
+        assert_eq!(status_effects.get(1).unwrap().source_actor_id, ObjectId(2));
+        assert!(status_effects.get_from(1, ObjectId(1)).is_some());
+    }
+
+    #[test]
+    fn test_status_effect_limit() {
+        let mut status_effects = StatusEffects::default();
+        for i in 0..MAX_STATUS_EFFECTS {
+            assert_eq!(
+                status_effects.apply(i as u16, 0, 0.0, ObjectId(1), StatusInfo::default()),
+                Some(i)
+            );
+        }
+
+        // No room for a new effect...
+        assert_eq!(
+            status_effects.apply(100, 0, 0.0, ObjectId(1), StatusInfo::default()),
+            None
+        );
+        assert_eq!(status_effects.get(100), None);
+
+        // ...but existing ones can still be refreshed.
+        assert_eq!(
+            status_effects.apply(5, 0, 30.0, ObjectId(1), StatusInfo::default()),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn test_status_effect_flags() {
+        let mut status_effects = StatusEffects::default();
+        assert!(!status_effects.is_movement_locked());
+        assert!(!status_effects.are_actions_locked());
+        assert_eq!(status_effects.dispellable(), None);
+
+        let bind = StatusInfo {
+            can_dispel: true,
+            lock_movement: true,
+            ..Default::default()
+        };
+        let stun = StatusInfo {
+            lock_movement: true,
+            lock_actions: true,
+            ..Default::default()
+        };
+
+        status_effects.apply(13, 0, 10.0, ObjectId(1), bind);
+        assert!(status_effects.is_movement_locked());
+        assert!(!status_effects.are_actions_locked());
+
+        status_effects.apply(2, 0, 10.0, ObjectId(1), stun);
+        assert!(status_effects.are_actions_locked());
+
+        // Only the bind can be dispelled, and the stun still locks movement afterwards.
+        assert_eq!(status_effects.dispellable().unwrap().effect_id, 13);
+        status_effects.remove(13);
+        assert_eq!(status_effects.dispellable(), None);
+        assert!(status_effects.is_movement_locked());
+
+        status_effects.remove(2);
+        assert!(!status_effects.is_movement_locked());
+        assert!(!status_effects.are_actions_locked());
+    }
+
+    #[test]
+    fn test_effects_over_time() {
+        let over_time = EffectOverTime {
+            kind: PotencyKind::Physical,
+            potency: 40,
+            source_actor_id: ObjectId(1),
+            stats: CombatStats::default(),
+            level_modifiers: LevelModifiers {
+                main: 390,
+                sub: 400,
+                div: 1900,
+                attack_power_slope: 195,
+                healing_power_slope: 569,
+                healing_power_divisor: 1522,
+            },
+        };
+
+        // Only effects the actor has can tick.
+        let mut status_effects = StatusEffects::default();
+        status_effects.set_over_time(0, over_time);
+        assert_eq!(status_effects.over_time(0, ObjectId(1)), None);
+
+        status_effects.add(0, 0, 30.0);
+        status_effects.set_over_time(0, over_time);
+        assert_eq!(status_effects.over_time(0, ObjectId(1)), Some(over_time));
+
+        // Refreshing the effect forgets the old snapshot, as it's replaced by the new one.
+        status_effects.add(0, 0, 30.0);
+        assert_eq!(status_effects.over_time(0, ObjectId(1)), None);
+
+        // It stops ticking once the effect is gone.
+        status_effects.set_over_time(0, over_time);
+        status_effects.remove(0);
+        assert_eq!(status_effects.over_time(0, ObjectId(1)), None);
+
+        status_effects.add(1, 0, 30.0);
+        status_effects.set_over_time(1, over_time);
+        status_effects.clear();
+        assert_eq!(status_effects.over_time(1, ObjectId(1)), None);
+
+        // Each source's snapshot is kept separately.
+        let per_source = StatusInfo {
+            per_source: true,
+            ..Default::default()
+        };
+        let other = EffectOverTime {
+            source_actor_id: ObjectId(2),
+            potency: 80,
+            ..over_time
+        };
+        status_effects.apply(2, 0, 30.0, ObjectId(1), per_source);
+        status_effects.apply(2, 0, 30.0, ObjectId(2), per_source);
+        status_effects.set_over_time(2, over_time);
+        status_effects.set_over_time(2, other);
+        assert_eq!(status_effects.over_time(2, ObjectId(1)), Some(over_time));
+        assert_eq!(status_effects.over_time(2, ObjectId(2)), Some(other));
     }
 }
@@ -7,7 +7,7 @@ use mlua::Function;
 use physis::equipment::EquipSlot;
 
 use crate::{
-    Event, EventHandler, ItemInfoQuery, MessageInfo, ZoneConnection,
+    Event, EventHandler, ItemInfoQuery, MessageInfo, ToServer, ZoneConnection,
     inventory::{Item, Storage},
     lua::{KawariLuaState, LuaPlayer},
 };
@@ -364,4 +364,57 @@ impl ZoneConnection {
             _ => false,
         }
     }
+
+    /// Changes the GM rank of the character with this name, which may also be the player themselves. Players can't give out a higher rank than their own, or change the rank of someone who's already at or above it.
+    pub async fn set_gm_rank(&mut self, name: &str, gm_rank: GameMasterRank) {
+        let own_rank = self.player_data.character.gm_rank;
+        if gm_rank as u8 > own_rank as u8 {
+            self.send_notice("You can't give out a higher GM rank than your own.")
+                .await;
+            return;
+        }
+
+        let target_rank;
+        {
+            let mut database = self.database.lock();
+            target_rank = database.find_gm_rank(name);
+        }
+
+        let Some(target_rank) = target_rank else {
+            self.send_notice(&format!("There's no character named {name}."))
+                .await;
+            return;
+        };
+
+        let is_self = name == self.player_data.character.name;
+        if !is_self && target_rank as u8 >= own_rank as u8 {
+            self.send_notice(&format!(
+                "You can't change the GM rank of {name}, since theirs isn't lower than your own."
+            ))
+            .await;
+            return;
+        }
+
+        {
+            let mut database = self.database.lock();
+            database.set_gm_rank(name, gm_rank);
+        }
+
+        self.handle
+            .send(ToServer::SetGmRank(name.to_string(), gm_rank))
+            .await;
+        self.send_notice(&format!("{name} is now {gm_rank:?}."))
+            .await;
+    }
+
+    /// Called when someone changed this player's GM rank.
+    pub async fn gm_rank_changed(&mut self, gm_rank: GameMasterRank) {
+        self.player_data.character.gm_rank = gm_rank;
+
+        // The client only learns about its rank when it spawns, so the //gm commands won't unlock until then.
+        self.send_notice(&format!(
+            "Your GM rank is now {gm_rank:?}. Change zones for it to fully take effect."
+        ))
+        .await;
+    }
 }
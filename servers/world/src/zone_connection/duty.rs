@@ -0,0 +1,121 @@
+//! Completing instanced duties and handing out their rewards.
+
+use kawari::common::{HandlerType, InstanceContentType, next_weekly_reset, timestamp_secs};
+
+use crate::{Bitmask, ZoneConnection, inventory::CurrencyKind};
+
+/// Sets `index` in `bitmask`, and returns whether it wasn't already set.
+fn mark_cleared<const N: usize>(bitmask: &mut Bitmask<N>, index: u32) -> bool {
+    let first_clear = !bitmask.contains(index);
+    bitmask.set(index);
+    first_clear
+}
+
+impl ZoneConnection {
+    /// Records that the player cleared this InstanceContent, and gives them its rewards.
+    pub async fn complete_duty(&mut self, content_id: u16) {
+        let content_type;
+        let rewards;
+        {
+            let mut game_data = self.gamedata.lock();
+            content_type = game_data.find_type_for_content(content_id);
+            rewards = game_data.get_duty_rewards(content_id).unwrap_or_default();
+        }
+
+        let Some(content_type) = content_type else {
+            tracing::warn!("Unknown content {content_id}!");
+            return;
+        };
+
+        // Like unlocking content, each id has to be subtracted by it's offset in the InstanceContent Excel sheet.
+        let offset = match content_type {
+            InstanceContentType::Dungeon => 1,
+            InstanceContentType::Raid => 30001,
+            InstanceContentType::Guildhests => 10001,
+            InstanceContentType::Trial => 20001,
+            _ => 0,
+        };
+        let first_clear = match (content_id as u32).checked_sub(offset) {
+            Some(index) => {
+                let content = &mut self.player_data.content;
+                match content_type {
+                    InstanceContentType::Dungeon => {
+                        mark_cleared(&mut content.cleared_dungeons, index)
+                    }
+                    InstanceContentType::Raid => mark_cleared(&mut content.cleared_raids, index),
+                    InstanceContentType::Guildhests => {
+                        mark_cleared(&mut content.cleared_guildhests, index)
+                    }
+                    InstanceContentType::Trial => mark_cleared(&mut content.cleared_trials, index),
+                    _ => {
+                        tracing::warn!(
+                            "Not sure how to mark {content_type:?} {content_id} as cleared!"
+                        );
+                        false
+                    }
+                }
+            }
+            None => {
+                tracing::warn!(
+                    "{content_type:?} {content_id} is before its offset, not marking it as cleared!"
+                );
+                false
+            }
+        };
+
+        // NOTE: The Duty Finder shows first clear bonuses based on the cleared content sent in PlayerSetup.
+        let mut exp = rewards.exp;
+        let mut gil = rewards.gil;
+        if first_clear {
+            exp += rewards.first_clear_exp;
+            gil += rewards.first_clear_gil;
+        }
+
+        if exp > 0 {
+            self.add_exp(exp).await;
+        }
+        if gil > 0 {
+            self.modify_currency(CurrencyKind::Gil, gil, true).await;
+        }
+        if rewards.tomestones > 0 {
+            self.modify_currency(CurrencyKind::TomestonePoetics, rewards.tomestones, true)
+                .await;
+        }
+    }
+
+    /// Returns the InstanceContent ID of the raid the player is in, if they're in one.
+    pub fn current_raid(&mut self) -> Option<u16> {
+        let handler_id = self.content_handler_id?;
+        if handler_id.handler_type() != HandlerType::InstanceContent {
+            return None;
+        }
+
+        let content_id = handler_id.event_id() as u16;
+        let mut game_data = self.gamedata.lock();
+        matches!(
+            game_data.find_type_for_content(content_id),
+            Some(InstanceContentType::Raid)
+        )
+        .then_some(content_id)
+    }
+
+    /// Returns whether the player already obtained loot from this raid since the last weekly reset.
+    pub fn is_locked_out(&mut self, raid_id: u16) -> bool {
+        let mut db = self.database.lock();
+        db.is_locked_out(
+            self.player_data.character.content_id as u64,
+            raid_id,
+            timestamp_secs() as u64,
+        )
+    }
+
+    /// Stops the player from obtaining more loot from this raid until the next weekly reset.
+    pub fn lock_out(&mut self, raid_id: u16) {
+        let mut db = self.database.lock();
+        db.add_lockout(
+            self.player_data.character.content_id as u64,
+            raid_id,
+            next_weekly_reset(timestamp_secs() as u64),
+        );
+    }
+}
@@ -0,0 +1,652 @@
+//! Housing plots, their exteriors and the furniture placed in and around them.
+
+use crate::{
+    PlacedFurniture, PlayerHouse, ZoneConnection,
+    inventory::{CurrencyKind, HousingInventory, MAX_LARGE_STORAGE},
+};
+use kawari::{
+    common::{
+        ContainerType, HouseId, HouseUnit, HousingFlag, Position, WarpType, internal_housing_row,
+    },
+    ipc::zone::{
+        Furniture, FurnitureList, House, HouseExterior, HouseList, HouseStatus,
+        HousingInteriorDetails, PlotSize, ServerZoneIpcData, ServerZoneIpcSegment,
+    },
+};
+use physis::TerritoryIntendedUse;
+
+/// The number of plots in a ward.
+const PLOTS_PER_WARD: usize = 30;
+
+/// The number of wards in a residential district.
+const WARDS_PER_DISTRICT: u8 = 30;
+
+/// The number of FurnitureLists sent for a ward's yards. Each one covers two plots.
+const OUTDOOR_FURNITURE_LISTS: u8 = 15;
+
+/// The number of FurnitureLists sent for an interior.
+// TODO: Limit this by interior size
+const INDOOR_FURNITURE_LISTS: u8 = 6;
+
+/// The number of interior containers that furniture can be placed from.
+const INDOOR_FURNITURE_CONTAINERS: u16 = 8;
+
+/// Returns the prefab exterior that's built when a plot of this size is bought.
+fn default_exterior(plot_size: PlotSize) -> HouseExterior {
+    match plot_size {
+        // Highland cottage (Wood)
+        PlotSize::Small => HouseExterior {
+            roof_id: 1136,
+            walls_id: 3687,
+            windows_id: 2598,
+            door_id: 550,
+            ..Default::default()
+        },
+        // Glade house (Wood)
+        PlotSize::Medium => HouseExterior {
+            roof_id: 1029,
+            walls_id: 3589,
+            windows_id: 2562,
+            door_id: 514,
+            ..Default::default()
+        },
+        // Hingan mansion (Mokuzo)
+        PlotSize::Large => HouseExterior {
+            roof_id: 1081,
+            walls_id: 3632,
+            windows_id: 2579,
+            door_id: 531,
+            ..Default::default()
+        },
+    }
+}
+
+/// Returns the price of a plot of this size, before it starts to depreciate.
+// TODO: Plots should get cheaper the longer they go unsold
+fn plot_price(plot_size: PlotSize) -> u32 {
+    match plot_size {
+        PlotSize::Small => 3_750_000,
+        PlotSize::Medium => 20_000_000,
+        PlotSize::Large => 50_000_000,
+    }
+}
+
+/// Returns the interior zone of a house of this size, in this residential district.
+fn interior_zone(zone_id: u16, plot_size: PlotSize) -> Option<u16> {
+    let first_interior = match zone_id {
+        339 => 282, // Mist
+        340 => 342, // The Lavender Beds
+        341 => 345, // The Goblet
+        641 => 649, // Shirogane
+        979 => 980, // Empyreum
+        _ => return None,
+    };
+
+    Some(first_interior + plot_size as u16)
+}
+
+/// Returns which FurnitureList a piece of furniture belongs in, and its index in that list.
+///
+/// Interiors are laid out container after container. Outdoors, each list covers two plots, which are given one exterior container each.
+fn furniture_list_position(
+    indoors: bool,
+    plot: u8,
+    container: ContainerType,
+    slot: u16,
+) -> Option<(usize, usize)> {
+    if slot as usize >= MAX_LARGE_STORAGE {
+        return None;
+    }
+
+    if indoors {
+        let page =
+            (container as u16).checked_sub(ContainerType::HousingInteriorPlacedItems1 as u16)?;
+        if page >= INDOOR_FURNITURE_CONTAINERS {
+            return None;
+        }
+
+        let index = page as usize * MAX_LARGE_STORAGE + slot as usize;
+        Some((index / Furniture::COUNT, index % Furniture::COUNT))
+    } else {
+        if container != ContainerType::HousingExteriorPlacedItems {
+            return None;
+        }
+
+        let plot = plot as usize;
+        Some((plot / 2, (plot % 2) * MAX_LARGE_STORAGE + slot as usize))
+    }
+}
+
+/// Lays out the placed furniture into `count` FurnitureLists worth of entries.
+fn build_furniture_lists(
+    indoors: bool,
+    count: u8,
+    placed: &[(u8, PlacedFurniture)],
+) -> Vec<Vec<Furniture>> {
+    let mut lists = vec![vec![Furniture::default(); Furniture::COUNT]; count as usize];
+
+    for (plot, furniture) in placed {
+        let Some((list, index)) =
+            furniture_list_position(indoors, *plot, furniture.container, furniture.slot)
+        else {
+            tracing::warn!("Furniture {furniture:?} is out of bounds, it won't be shown!");
+            continue;
+        };
+
+        if let Some(list) = lists.get_mut(list) {
+            list[index] = Furniture {
+                id: furniture.catalog_id,
+                id2: 0,
+                stain: furniture.stain,
+                rotation: furniture.rotation,
+                position: furniture.position,
+            };
+        }
+    }
+
+    lists
+}
+
+impl ZoneConnection {
+    /// Loads the house the player can decorate in their current zone, if any.
+    pub fn load_current_house(&mut self, zone_id: u16, intended_use: TerritoryIntendedUse) {
+        let owned_house;
+        {
+            let mut db = self.database.lock();
+            owned_house = db.find_owned_house(self.player_data.character.content_id as u64);
+        }
+
+        if intended_use != TerritoryIntendedUse::HousingIndoor {
+            self.player_data.entered_house = None;
+        }
+
+        let ward = self.player_data.ward;
+        let entered_house = self.player_data.entered_house;
+        let house = owned_house.filter(|house| match intended_use {
+            TerritoryIntendedUse::HousingOutdoor => {
+                house.territory_type_id == zone_id && house.ward == ward
+            }
+            TerritoryIntendedUse::HousingIndoor => entered_house == Some(house.id),
+            _ => false,
+        });
+
+        self.player_data.house_inventory = house
+            .as_ref()
+            .map(|house| house.storage.clone())
+            .unwrap_or_default();
+        self.player_data.house = house;
+    }
+
+    /// Saves the current house's appearance and storage.
+    pub fn commit_house(&mut self) {
+        let Some(house) = &mut self.player_data.house else {
+            return;
+        };
+
+        house.storage = self.player_data.house_inventory.clone();
+
+        let mut db = self.database.lock();
+        db.commit_house(house);
+    }
+
+    /// Returns the HouseId the client uses to refer to this house.
+    fn house_id(&self, house: &PlayerHouse) -> HouseId {
+        HouseId {
+            unit: HouseUnit {
+                apartment_division_plot_index: house.plot,
+                apartment_flag: false,
+            },
+            unk1: 0,
+            ward_index: house.ward,
+            room_number: 0,
+            territory_type_id: house.territory_type_id,
+            world_id: self.config.world_id,
+        }
+    }
+
+    /// Returns the size of this plot in the residential district.
+    fn plot_size(&self, zone_id: u16, plot: u8) -> PlotSize {
+        let mut gamedata = self.gamedata.lock();
+        internal_housing_row(zone_id)
+            .and_then(|row| gamedata.get_land_sets(row))
+            .and_then(|land_sets| land_sets.get(plot as usize).map(|x| x.PlotSize))
+            .and_then(PlotSize::from_repr)
+            .unwrap_or_default()
+    }
+
+    /// Sends the plots in this ward, along with the furniture placed in their yards.
+    pub async fn send_ward(&mut self, zone_id: u16) {
+        let ward = self.player_data.ward;
+        let mut houses = [House::default(); PLOTS_PER_WARD];
+
+        // Plot sizes are fixed per district.
+        {
+            let mut gamedata = self.gamedata.lock();
+            if let Some(land_sets) =
+                internal_housing_row(zone_id).and_then(|row| gamedata.get_land_sets(row))
+            {
+                for (house, land_set) in houses.iter_mut().zip(land_sets) {
+                    house.plot_size = PlotSize::from_repr(land_set.PlotSize).unwrap_or_default();
+                }
+            }
+        }
+
+        let mut placed = Vec::new();
+        {
+            let mut db = self.database.lock();
+            for owned_house in db.find_ward_houses(zone_id, ward) {
+                let Some(house) = houses.get_mut(owned_house.plot as usize) else {
+                    continue;
+                };
+
+                house.status = HouseStatus::HouseBuilt;
+                house.flags = owned_house.flags;
+                house.exterior = owned_house.exterior;
+
+                placed.extend(
+                    db.find_furniture(owned_house.id, false)
+                        .into_iter()
+                        .map(|furniture| (owned_house.plot, furniture)),
+                );
+            }
+        }
+
+        self.send_ipc_self(ServerZoneIpcSegment::new(ServerZoneIpcData::HouseList(
+            HouseList {
+                land_id: 0,
+                ward: ward as u16,
+                territory_type_id: zone_id,
+                world_id: self.config.world_id,
+                subdivision: 257, // TODO: Figure out more about subdivisions
+                houses,
+            },
+        )))
+        .await;
+
+        let lists = build_furniture_lists(false, OUTDOOR_FURNITURE_LISTS, &placed);
+        for (index, furniture) in lists.into_iter().enumerate() {
+            self.send_ipc_self(ServerZoneIpcSegment::new(ServerZoneIpcData::FurnitureList(
+                FurnitureList {
+                    count: OUTDOOR_FURNITURE_LISTS,
+                    index: index as u8,
+                    furniture,
+                    ..Default::default()
+                },
+            )))
+            .await;
+        }
+    }
+
+    /// Sends the appearance of the current house's interior, along with the furniture placed in it.
+    pub async fn send_interior(&mut self) {
+        let (id, details, placed) = if let Some(house) = &self.player_data.house {
+            let placed: Vec<(u8, PlacedFurniture)>;
+            {
+                let mut db = self.database.lock();
+                placed = db
+                    .find_furniture(house.id, true)
+                    .into_iter()
+                    .map(|furniture| (house.plot, furniture))
+                    .collect();
+            }
+
+            (self.house_id(house), house.interior, placed)
+        } else {
+            // The LandId is set so that plugins like HousingPos/Buildingway can plop stuff down, even if it won't be saved.
+            let id = HouseId {
+                unit: HouseUnit {
+                    apartment_division_plot_index: 0,
+                    apartment_flag: true,
+                },
+                unk1: 0,
+                room_number: 1,
+                ward_index: 0,
+                territory_type_id: 340,
+                world_id: self.config.world_id,
+            };
+
+            (id, HousingInteriorDetails::default(), Vec::new())
+        };
+
+        self.send_ipc_self(ServerZoneIpcSegment::new(
+            ServerZoneIpcData::HousingInteriorDetails(details),
+        ))
+        .await;
+
+        let lists = build_furniture_lists(true, INDOOR_FURNITURE_LISTS, &placed);
+        for (index, furniture) in lists.into_iter().enumerate() {
+            self.send_ipc_self(ServerZoneIpcSegment::new(ServerZoneIpcData::FurnitureList(
+                FurnitureList {
+                    id,
+                    count: INDOOR_FURNITURE_LISTS,
+                    index: index as u8,
+                    unk2: 100, // Indoors
+                    furniture,
+                    ..Default::default()
+                },
+            )))
+            .await;
+        }
+    }
+
+    /// Saves furniture that was just placed in the current house.
+    pub fn save_placed_furniture(&mut self, indoors: bool, placed: PlacedFurniture) {
+        let Some(house) = &self.player_data.house else {
+            return;
+        };
+
+        {
+            let mut db = self.database.lock();
+            db.place_furniture(house.id, indoors, placed);
+        }
+
+        self.commit_house();
+    }
+
+    /// Saves the new position of furniture that was moved in the current house.
+    pub fn save_moved_furniture(
+        &mut self,
+        container: ContainerType,
+        slot: u16,
+        position: Position,
+        rotation: f32,
+    ) {
+        let Some(house) = &self.player_data.house else {
+            return;
+        };
+
+        let mut db = self.database.lock();
+        db.move_furniture(house.id, container, slot, position, rotation);
+    }
+
+    /// Saves furniture that was taken out of the current house.
+    pub fn save_removed_furniture(&mut self, container: ContainerType, slot: u16) {
+        let Some(house) = &self.player_data.house else {
+            return;
+        };
+
+        {
+            let mut db = self.database.lock();
+            db.remove_furniture(house.id, container, slot);
+        }
+
+        self.commit_house();
+    }
+
+    /// Buys this plot in the current ward for the player, and builds a house on it.
+    pub async fn purchase_plot(&mut self, plot: u8) {
+        let zone_id = self.player_data.volatile.zone_id as u16;
+        if self.get_zone_intended_use() != TerritoryIntendedUse::HousingOutdoor {
+            self.send_notice("You must be in a residential district to buy a plot.")
+                .await;
+            return;
+        }
+
+        if plot as usize >= PLOTS_PER_WARD {
+            self.send_notice(&format!("There are only {PLOTS_PER_WARD} plots in a ward."))
+                .await;
+            return;
+        }
+
+        let plot_size = self.plot_size(zone_id, plot);
+        let price = plot_price(plot_size);
+        if self.player_data.inventory.currency.gil.quantity < price {
+            self.send_notice(&format!("You need {price} gil to buy this plot."))
+                .await;
+            return;
+        }
+
+        let house;
+        {
+            // The house and its payment are saved together, so there's no way to get one without the other.
+            let mut db = self.database.lock();
+            house = db.transaction(|db| {
+                let house = db.purchase_house(
+                    self.player_data.character.content_id as u64,
+                    zone_id,
+                    self.player_data.ward,
+                    plot,
+                    default_exterior(plot_size),
+                )?;
+
+                let gil = &mut self.player_data.inventory.currency.gil.quantity;
+                *gil = gil.saturating_sub(price);
+                db.commit_player_data(&self.player_data);
+
+                Some(house)
+            });
+        }
+
+        let Some(house) = house else {
+            self.send_notice("That plot is already taken, or you already own a house.")
+                .await;
+            return;
+        };
+
+        self.send_currency(CurrencyKind::Gil).await;
+
+        self.player_data.house_inventory = house.storage.clone();
+        self.player_data.house = Some(house);
+
+        // TODO: Players already in this ward won't see the new house until they zone in again.
+        self.send_ward(zone_id).await;
+    }
+
+    /// Moves the player to another ward in their current residential district.
+    pub async fn change_ward(&mut self, ward: u8) {
+        if self.get_zone_intended_use() != TerritoryIntendedUse::HousingOutdoor {
+            self.send_notice("You must be in a residential district to change wards.")
+                .await;
+            return;
+        }
+
+        if ward >= WARDS_PER_DISTRICT {
+            self.send_notice(&format!(
+                "There are only {WARDS_PER_DISTRICT} wards in a residential district."
+            ))
+            .await;
+            return;
+        }
+
+        let zone_id = self.player_data.volatile.zone_id as u16;
+        self.player_data.ward = ward;
+        self.load_current_house(zone_id, TerritoryIntendedUse::HousingOutdoor);
+        self.send_ward(zone_id).await;
+    }
+
+    /// Enters the house built on this plot in the current ward, if it's open to the player.
+    pub async fn enter_house(&mut self, plot: u8) {
+        let zone_id = self.player_data.volatile.zone_id as u16;
+        if self.get_zone_intended_use() != TerritoryIntendedUse::HousingOutdoor {
+            self.send_notice("You must be in a residential district to enter a house.")
+                .await;
+            return;
+        }
+
+        let house;
+        {
+            let mut db = self.database.lock();
+            house = db.find_house(zone_id, self.player_data.ward, plot);
+        }
+
+        let Some(house) = house else {
+            self.send_notice("There's no house on that plot.").await;
+            return;
+        };
+
+        if !house.flags.contains(HousingFlag::OPEN)
+            && house.owner_content_id != self.player_data.character.content_id as u64
+        {
+            self.send_notice("That house is locked.").await;
+            return;
+        }
+
+        let Some(interior_zone_id) = interior_zone(zone_id, self.plot_size(zone_id, plot)) else {
+            self.send_notice("This residential district has no interiors.")
+                .await;
+            return;
+        };
+
+        self.player_data.entered_house = Some(house.id);
+        self.change_zone(interior_zone_id, None, None, WarpType::Normal, 0)
+            .await;
+    }
+
+    /// Tears down the player's house, if they own one.
+    pub async fn relinquish_house(&mut self) {
+        let relinquished;
+        {
+            let mut db = self.database.lock();
+            relinquished = db.relinquish_house(self.player_data.character.content_id as u64);
+        }
+
+        if !relinquished {
+            self.send_notice("You don't own a house.").await;
+            return;
+        }
+
+        self.player_data.house = None;
+        self.player_data.house_inventory = HousingInventory::default();
+
+        if self.get_zone_intended_use() == TerritoryIntendedUse::HousingOutdoor {
+            self.send_ward(self.player_data.volatile.zone_id as u16)
+                .await;
+        }
+    }
+
+    /// Changes the style of the player's house, if they own one. Fixtures and dyes are left alone.
+    pub async fn set_house_exterior(
+        &mut self,
+        roof_id: u16,
+        walls_id: u16,
+        windows_id: u16,
+        door_id: u16,
+    ) {
+        let owned_house;
+        {
+            let mut db = self.database.lock();
+            owned_house = db.find_owned_house(self.player_data.character.content_id as u64);
+        }
+
+        let Some(mut owned_house) = owned_house else {
+            self.send_notice("You don't own a house.").await;
+            return;
+        };
+
+        owned_house.exterior.roof_id = roof_id;
+        owned_house.exterior.walls_id = walls_id;
+        owned_house.exterior.windows_id = windows_id;
+        owned_house.exterior.door_id = door_id;
+        let exterior = owned_house.exterior;
+        {
+            let mut db = self.database.lock();
+            db.commit_house(&owned_house);
+        }
+
+        if let Some(house) = &mut self.player_data.house {
+            if house.id == owned_house.id {
+                house.exterior = exterior;
+            }
+        }
+
+        let zone_id = self.player_data.volatile.zone_id as u16;
+        if owned_house.territory_type_id == zone_id {
+            self.send_ward(zone_id).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn furniture(container: ContainerType, slot: u16, catalog_id: u16) -> PlacedFurniture {
+        PlacedFurniture {
+            container,
+            slot,
+            catalog_id,
+            stain: 0,
+            position: Position::default(),
+            rotation: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_interior_zone() {
+        assert_eq!(interior_zone(339, PlotSize::Small), Some(282));
+        assert_eq!(interior_zone(341, PlotSize::Medium), Some(346));
+        assert_eq!(interior_zone(979, PlotSize::Large), Some(982));
+        assert_eq!(interior_zone(129, PlotSize::Small), None);
+    }
+
+    #[test]
+    fn test_furniture_list_position() {
+        // Interiors
+        assert_eq!(
+            furniture_list_position(true, 0, ContainerType::HousingInteriorPlacedItems1, 3),
+            Some((0, 3))
+        );
+        assert_eq!(
+            furniture_list_position(true, 0, ContainerType::HousingInteriorPlacedItems2, 3),
+            Some((0, 53))
+        );
+        assert_eq!(
+            furniture_list_position(true, 0, ContainerType::HousingInteriorPlacedItems3, 0),
+            Some((1, 0))
+        );
+        assert_eq!(
+            furniture_list_position(true, 0, ContainerType::HousingInteriorStoreroom1, 0),
+            None
+        );
+
+        // Yards
+        assert_eq!(
+            furniture_list_position(false, 4, ContainerType::HousingExteriorPlacedItems, 2),
+            Some((2, 2))
+        );
+        assert_eq!(
+            furniture_list_position(false, 5, ContainerType::HousingExteriorPlacedItems, 2),
+            Some((2, 52))
+        );
+        assert_eq!(
+            furniture_list_position(false, 5, ContainerType::HousingExteriorPlacedItems, 50),
+            None
+        );
+    }
+
+    #[test]
+    fn test_build_furniture_lists() {
+        let lists = build_furniture_lists(
+            false,
+            OUTDOOR_FURNITURE_LISTS,
+            &[
+                (
+                    0,
+                    furniture(ContainerType::HousingExteriorPlacedItems, 0, 10),
+                ),
+                (
+                    29,
+                    furniture(ContainerType::HousingExteriorPlacedItems, 1, 20),
+                ),
+                // Out of bounds, so it's skipped
+                (
+                    0,
+                    furniture(ContainerType::HousingInteriorPlacedItems1, 0, 30),
+                ),
+            ],
+        );
+
+        assert_eq!(lists.len(), OUTDOOR_FURNITURE_LISTS as usize);
+        assert!(lists.iter().all(|list| list.len() == Furniture::COUNT));
+        assert_eq!(lists[0][0].id, 10);
+        assert_eq!(lists[14][51].id, 20);
+        assert_eq!(
+            lists
+                .iter()
+                .flatten()
+                .filter(|furniture| furniture.id != 0)
+                .count(),
+            2
+        );
+    }
+}
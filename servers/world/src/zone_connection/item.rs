@@ -2,7 +2,9 @@
 
 use crate::{
     ItemInfoQuery, ToServer, ZoneConnection,
-    inventory::{DesiredHousingInventoryPages, EQUIP_RESTRICTED, Storage},
+    inventory::{
+        CurrencyKind, CurrencyStorage, DesiredHousingInventoryPages, EQUIP_RESTRICTED, Storage,
+    },
 };
 use kawari::{
     common::{ContainerType, ItemOperationKind, LegacyEquipmentModelId, ObjectId, WeaponModelId},
@@ -16,6 +18,48 @@ use physis::equipment::EquipSlot;
 use strum::IntoEnumIterator;
 
 impl ZoneConnection {
+    /// Returns the name of this item, for showing to the player.
+    pub fn item_name(&self, item_id: u32) -> String {
+        let mut game_data = self.gamedata.lock();
+        game_data
+            .get_item_info(ItemInfoQuery::ById(item_id))
+            .map(|item_info| item_info.name)
+            .unwrap_or_else(|| format!("item {item_id}"))
+    }
+
+    /// Adds (or removes, if `amount` is negative) some of this currency.
+    pub async fn modify_currency(
+        &mut self,
+        id: CurrencyKind,
+        amount: i32,
+        send_client_update: bool,
+    ) {
+        let slot = self.player_data.inventory.currency.get_item_for_id(id);
+
+        if amount > 0 {
+            slot.quantity = slot.quantity.saturating_add(amount as u32);
+        } else {
+            slot.quantity = slot.quantity.saturating_sub(-amount as u32);
+        }
+
+        if send_client_update {
+            self.send_currency(id).await;
+        }
+    }
+
+    /// Tells the client how much of this currency the player has.
+    pub async fn send_currency(&mut self, id: CurrencyKind) {
+        let slot = *self.player_data.inventory.currency.get_item_for_id(id);
+
+        let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::UpdateInventorySlot(ItemInfo {
+            sequence: self.player_data.item_sequence,
+            container: ContainerType::Currency,
+            slot: CurrencyStorage::get_slot_for_id(id),
+            ..slot.into()
+        }));
+        self.send_ipc_self(ipc).await;
+    }
+
     /// Inform other clients (including yourself) that you changed your equipped model ids.
     pub async fn inform_equip(&mut self) {
         let main_weapon_id;
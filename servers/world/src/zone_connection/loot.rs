@@ -0,0 +1,135 @@
+//! Receiving treasure, and rolling on it with the rest of the party.
+
+use kawari::{
+    common::{LOOT_ROLL_SECS, LootRoll},
+    ipc::zone::ActorControlCategory,
+};
+
+use crate::{ItemInfoQuery, ToServer, ZoneConnection, inventory::Item};
+
+impl ZoneConnection {
+    /// Receives everything in a treasure coffer the player opened without a party.
+    ///
+    /// Raids only give loot once a week, so nothing is received if the player is locked out.
+    pub async fn open_treasure(&mut self, item_ids: &[u32]) {
+        if let Some(raid_id) = self.current_raid() {
+            if self.is_locked_out(raid_id) {
+                self.send_notice("You have already obtained treasure from this duty this week.")
+                    .await;
+                return;
+            }
+            self.lock_out(raid_id);
+        }
+
+        self.receive_treasure(item_ids).await;
+    }
+
+    /// Adds treasure to the player's inventory. Anything that doesn't fit is sent to them by mail.
+    pub async fn receive_treasure(&mut self, item_ids: &[u32]) {
+        let mut overflow = Vec::new();
+        for item_id in item_ids {
+            let item_info;
+            {
+                let mut game_data = self.gamedata.lock();
+                item_info = game_data.get_item_info(ItemInfoQuery::ById(*item_id));
+            }
+
+            let Some(item_info) = item_info else {
+                tracing::warn!("Unknown treasure item {item_id}!");
+                continue;
+            };
+
+            let item = Item::new(&item_info, 1);
+            if self
+                .player_data
+                .inventory
+                .add_in_next_free_slot(item)
+                .is_none()
+            {
+                overflow.push(item);
+                continue;
+            }
+
+            // The item was added to your inventory.
+            self.actor_control_self(ActorControlCategory::LogMessage {
+                log_message: 789,
+                id: *item_id,
+            })
+            .await;
+        }
+
+        self.send_inventory().await;
+
+        if !overflow.is_empty() {
+            self.send_items_by_mail(
+                "Your inventory was full, so the treasure you obtained has been delivered here.",
+                &overflow,
+            )
+            .await;
+            self.send_notice("Your inventory is full. The rest of the treasure was sent by mail.")
+                .await;
+        }
+    }
+
+    /// Tells the player that they can roll on the items from a treasure coffer, by loot ID and item ID.
+    ///
+    /// Raids only give loot once a week, so a player who's locked out passes on everything. Otherwise being offered the loot starts their lockout.
+    ///
+    /// TODO: This is an interim chat version, until the loot window's packets are known.
+    pub async fn offer_loot(&mut self, loot: &[(u32, u32)]) {
+        if let Some(raid_id) = self.current_raid() {
+            if self.is_locked_out(raid_id) {
+                self.send_notice(
+                    "You have already obtained treasure from this duty this week, so you pass on it.",
+                )
+                .await;
+                for (loot_id, _) in loot {
+                    self.roll_loot(*loot_id, LootRoll::Pass).await;
+                }
+                return;
+            }
+            self.lock_out(raid_id);
+        }
+
+        for (loot_id, item_id) in loot {
+            let name = self.item_name(*item_id);
+            self.send_notice(&format!(
+                "[{loot_id}] {name} is up for grabs! Use !loot need|greed|pass {loot_id} within {} minutes.",
+                LOOT_ROLL_SECS / 60
+            ))
+            .await;
+        }
+    }
+
+    /// Rolls on an item from a treasure coffer.
+    pub async fn roll_loot(&mut self, loot_id: u32, roll: LootRoll) {
+        self.handle
+            .send(ToServer::RollLoot(
+                self.player_data.character.actor_id,
+                loot_id,
+                roll,
+            ))
+            .await;
+    }
+
+    /// Tells the player how someone in their party rolled.
+    pub async fn inform_loot_roll(&mut self, name: &str, item_id: u32, roll: LootRoll, number: u8) {
+        let item_name = self.item_name(item_id);
+        let message = match roll {
+            LootRoll::Need => format!("{name} rolls Need on {item_name}. {number}!"),
+            LootRoll::Greed => format!("{name} rolls Greed on {item_name}. {number}!"),
+            LootRoll::Pass => format!("{name} passes on {item_name}."),
+        };
+        self.send_notice(&message).await;
+    }
+
+    /// Tells the player who won an item, or that nobody did.
+    pub async fn inform_loot_winner(&mut self, item_id: u32, winner: Option<String>) {
+        let item_name = self.item_name(item_id);
+        let message = match winner {
+            Some(winner) => format!("{winner} obtains {item_name}."),
+            None => format!("Nobody rolled on {item_name}, so it was lost."),
+        };
+        self.send_notice(&message).await;
+    }
+}
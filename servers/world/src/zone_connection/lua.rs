@@ -3,7 +3,7 @@
 use crate::{
     Event, ItemInfoQuery, ToServer, ZoneConnection,
     event::EventHandler,
-    inventory::{CrystalsStorage, CurrencyStorage, Item},
+    inventory::{CrystalsStorage, Item},
     lua::{LuaPlayer, LuaTask},
 };
 use icarus::AetherCurrent::AetherCurrentSheet;
@@ -189,27 +189,8 @@ impl ZoneConnection {
                     amount,
                     send_client_update,
                 } => {
-                    let slot = self.player_data.inventory.currency.get_item_for_id(*id);
-
-                    if *amount > 0 {
-                        slot.quantity = slot.quantity.saturating_add(*amount as u32);
-                    } else {
-                        slot.quantity = slot.quantity.saturating_sub(-(*amount) as u32);
-                    }
-
-                    if *send_client_update {
-                        let slot = *slot;
-
-                        let ipc = ServerZoneIpcSegment::new(
-                            ServerZoneIpcData::UpdateInventorySlot(ItemInfo {
-                                sequence: self.player_data.item_sequence,
-                                container: ContainerType::Currency,
-                                slot: CurrencyStorage::get_slot_for_id(*id),
-                                ..slot.into()
-                            }),
-                        );
-                        self.send_ipc_self(ipc).await;
-                    }
+                    self.modify_currency(*id, *amount, *send_client_update)
+                        .await;
                 }
                 LuaTask::ModifyCrystal {
                     id,
@@ -797,6 +778,94 @@ impl ZoneConnection {
                     self.set_grand_company_rank(*rank);
                     self.send_grand_company_info().await;
                 }
+                LuaTask::PurchasePlot { plot } => {
+                    self.purchase_plot(*plot).await;
+                }
+                LuaTask::RelinquishHouse => {
+                    self.relinquish_house().await;
+                }
+                LuaTask::ChangeWard { ward } => {
+                    self.change_ward(*ward).await;
+                }
+                LuaTask::EnterHouse { plot } => {
+                    self.enter_house(*plot).await;
+                }
+                LuaTask::SetHouseExterior {
+                    roof_id,
+                    walls_id,
+                    windows_id,
+                    door_id,
+                } => {
+                    self.set_house_exterior(*roof_id, *walls_id, *windows_id, *door_id)
+                        .await;
+                }
+                LuaTask::RollLoot { id, roll } => {
+                    self.roll_loot(*id, *roll).await;
+                }
+                LuaTask::SetGmRank { name, gm_rank } => {
+                    self.set_gm_rank(name, *gm_rank).await;
+                }
+                LuaTask::ListRetainers => {
+                    self.list_retainers().await;
+                }
+                LuaTask::HireRetainer { name } => {
+                    self.hire_retainer(name).await;
+                }
+                LuaTask::DismissRetainer { name } => {
+                    self.dismiss_retainer(name).await;
+                }
+                LuaTask::EntrustItem {
+                    retainer,
+                    item_id,
+                    quantity,
+                } => {
+                    self.entrust_item(retainer, *item_id, *quantity).await;
+                }
+                LuaTask::WithdrawItem { retainer, item_id } => {
+                    self.withdraw_item(retainer, *item_id).await;
+                }
+                LuaTask::WithdrawRetainerGil { retainer } => {
+                    self.withdraw_retainer_gil(retainer).await;
+                }
+                LuaTask::SellItem {
+                    retainer,
+                    item_id,
+                    quantity,
+                    price_per_unit,
+                } => {
+                    self.sell_item(retainer, *item_id, *quantity, *price_per_unit)
+                        .await;
+                }
+                LuaTask::UnlistItem { id } => {
+                    self.unlist_item(*id).await;
+                }
+                LuaTask::ShowMarketOfferings { item_id } => {
+                    self.show_market_offerings(*item_id).await;
+                }
+                LuaTask::BuyItem { id } => {
+                    self.buy_item(*id).await;
+                }
+                LuaTask::RequestTrade => {
+                    self.request_trade(None).await;
+                }
+                LuaTask::AcceptTrade => {
+                    self.accept_trade().await;
+                }
+                LuaTask::CancelTrade => {
+                    self.cancel_trade().await;
+                }
+                LuaTask::OfferTradeItem { item_id, quantity } => {
+                    self.offer_trade_item(*item_id, *quantity).await;
+                }
+                LuaTask::OfferTradeGil { gil } => {
+                    self.offer_trade_gil(*gil).await;
+                }
+                LuaTask::ClearTradeOffer => {
+                    self.clear_trade_offer().await;
+                }
+                LuaTask::ConfirmTrade => {
+                    self.confirm_trade().await;
+                }
                 LuaTask::Jump { name } => {
                     self.handle
                         .send(ToServer::Jump(self.id, name.clone()))
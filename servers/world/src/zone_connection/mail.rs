@@ -241,6 +241,31 @@ impl ZoneConnection {
         }
     }
 
+    /// Mails these items to the player as a reward letter, for when they don't fit in their inventory.
+    pub async fn send_items_by_mail(&mut self, message: &str, items: &[Item]) {
+        let content_id = self.player_data.character.content_id as u64;
+        {
+            let mut db = self.database.lock();
+            for chunk in items.chunks(MAX_MAIL_ATTACHMENTS_STORAGE) {
+                let mut attachments =
+                    crate::inventory::GenericStorage::<{ MAX_MAIL_ATTACHMENTS_STORAGE }>::default();
+                for (slot, item) in attachments.slots.iter_mut().zip(chunk) {
+                    *slot = *item;
+                }
+
+                db.add_letter_to_mailbox(
+                    content_id,
+                    content_id,
+                    LetterType::Reward,
+                    BString::from(message),
+                    attachments,
+                );
+            }
+        }
+
+        self.send_mailbox_status().await;
+    }
+
     pub async fn view_letter(&mut self, sender_content_id: u64, timestamp: u32) {
         let letter;
         {
@@ -0,0 +1,569 @@
+//! Retainers, and buying and selling items on the market board.
+
+use kawari::{
+    common::{
+        MAX_GIL, MAX_RETAINER_ITEMS, MAX_RETAINER_LISTINGS, MAX_RETAINERS, MarketPrice,
+        is_valid_retainer_name,
+    },
+    ipc::zone::{
+        MarketBoardHistory, MarketBoardHistoryEntry, MarketBoardItem, ServerZoneIpcData,
+        ServerZoneIpcSegment,
+    },
+};
+
+use crate::{
+    ItemInfoQuery, ZoneConnection,
+    database::PlayerRetainer,
+    inventory::{CurrencyKind, ITEM_FLAG_HQ, Item},
+};
+
+/// How many items fit in a single MarketBoardItems.
+const SEARCH_RESULTS_PER_PAGE: usize = 21;
+
+impl ZoneConnection {
+    /// Sends the items for sale, along with how many listings there are for each.
+    ///
+    /// We don't know where RequestMarketBoardItems holds the category or page yet, so this always sends the first page of everything for sale.
+    pub async fn send_market_search(&mut self, sequence: u16) {
+        let listed_items;
+        {
+            let mut db = self.database.lock();
+            listed_items = db.find_listed_items();
+        }
+
+        let items: Vec<MarketBoardItem> = listed_items
+            .into_iter()
+            .take(SEARCH_RESULTS_PER_PAGE)
+            .map(|(item_id, count)| MarketBoardItem { item_id, count })
+            .collect();
+
+        let ipc =
+            ServerZoneIpcSegment::new(ServerZoneIpcData::MarketBoardItems { sequence, items });
+        self.send_ipc_self(ipc).await;
+    }
+
+    /// Sends how many listings there are for this item, and its sale history.
+    ///
+    /// The listings themselves aren't sent, because we don't know the layout of MarketBoardOfferings yet. Until then, `!market offers` lists them instead.
+    pub async fn send_market_item(&mut self, item_id: u32) {
+        self.marketboard_request_item_id = item_id;
+
+        let hits;
+        let history;
+        {
+            let mut db = self.database.lock();
+            hits = db.find_offerings(item_id).len() as u32;
+            history = db.find_sale_history(item_id);
+        }
+
+        let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::MarketBoardHits { unk1: 0, hits });
+        self.send_ipc_self(ipc).await;
+
+        let ipc =
+            ServerZoneIpcSegment::new(ServerZoneIpcData::MarketBoardHistory(MarketBoardHistory {
+                item_id: item_id as u16,
+                entries: history
+                    .into_iter()
+                    .map(|sale| MarketBoardHistoryEntry {
+                        unk1: 0,
+                        price: sale.price_per_unit,
+                        timestamp: sale.sold_at as u32,
+                        quantity: sale.quantity,
+                        unk3: 0,
+                        name: sale.buyer_name,
+                    })
+                    .collect(),
+            }));
+        self.send_ipc_self(ipc).await;
+    }
+
+    /// Looks up one of the player's retainers by name, and tells them if they don't have one.
+    async fn find_own_retainer(&mut self, name: &str) -> Option<PlayerRetainer> {
+        let retainer;
+        {
+            let mut db = self.database.lock();
+            retainer = db.find_retainer(self.player_data.character.content_id as u64, name);
+        }
+
+        if retainer.is_none() {
+            self.send_notice(&format!("You don't have a retainer named {name}."))
+                .await;
+        }
+
+        retainer
+    }
+
+    /// Tells the player about each of their retainers.
+    pub async fn list_retainers(&mut self) {
+        let retainers;
+        let mut listing_counts = Vec::new();
+        {
+            let mut db = self.database.lock();
+            retainers = db.find_retainers(self.player_data.character.content_id as u64);
+            for retainer in &retainers {
+                listing_counts.push(db.find_retainer_listings(retainer.id).len());
+            }
+        }
+
+        if retainers.is_empty() {
+            self.send_notice("You haven't hired any retainers.").await;
+            return;
+        }
+
+        for (retainer, listing_count) in retainers.iter().zip(listing_counts) {
+            self.send_notice(&format!(
+                "{}: holding {}/{MAX_RETAINER_ITEMS} items, selling {listing_count}/{MAX_RETAINER_LISTINGS} items, {} gil.",
+                retainer.name,
+                retainer.storage.len(),
+                retainer.gil
+            ))
+            .await;
+        }
+    }
+
+    /// Hires a new retainer with this name.
+    pub async fn hire_retainer(&mut self, name: &str) {
+        if !is_valid_retainer_name(name) {
+            self.send_notice(&format!("{name} can't be used as a retainer's name."))
+                .await;
+            return;
+        }
+
+        let hired;
+        {
+            let mut db = self.database.lock();
+            let content_id = self.player_data.character.content_id as u64;
+            if db.find_retainers(content_id).len() >= MAX_RETAINERS {
+                hired = None;
+            } else {
+                hired = Some(db.hire_retainer(content_id, name));
+            }
+        }
+
+        let message = match hired {
+            None => format!("You can't hire more than {MAX_RETAINERS} retainers."),
+            Some(None) => format!("The name {name} is already taken."),
+            Some(Some(_)) => format!("You hired {name}!"),
+        };
+        self.send_notice(&message).await;
+    }
+
+    /// Dismisses one of the player's retainers, but only once they aren't holding anything.
+    pub async fn dismiss_retainer(&mut self, name: &str) {
+        let Some(retainer) = self.find_own_retainer(name).await else {
+            return;
+        };
+
+        let listing_count;
+        {
+            let mut db = self.database.lock();
+            listing_count = db.find_retainer_listings(retainer.id).len();
+        }
+
+        if !retainer.storage.is_empty() || listing_count > 0 || retainer.gil > 0 {
+            self.send_notice(&format!(
+                "{name} is still holding onto items or gil. Withdraw everything before dismissing them."
+            ))
+            .await;
+            return;
+        }
+
+        {
+            let mut db = self.database.lock();
+            db.dismiss_retainer(retainer.id);
+        }
+
+        self.send_notice(&format!("You dismissed {name}.")).await;
+    }
+
+    /// Moves up to `quantity` of an item from the player's inventory to their retainer.
+    pub async fn entrust_item(&mut self, retainer_name: &str, item_id: u32, quantity: u32) {
+        let Some(mut retainer) = self.find_own_retainer(retainer_name).await else {
+            return;
+        };
+
+        if retainer.storage.len() >= MAX_RETAINER_ITEMS {
+            self.send_notice(&format!("{retainer_name} can't hold any more items."))
+                .await;
+            return;
+        }
+
+        let Some(slot) = self
+            .player_data
+            .inventory
+            .pages
+            .iter_mut()
+            .flat_map(|page| page.slots.iter_mut())
+            .find(|slot| slot.item_id == item_id && slot.quantity > 0)
+        else {
+            let item_name = self.item_name(item_id);
+            self.send_notice(&format!("You don't have any {item_name}."))
+                .await;
+            return;
+        };
+
+        let mut item = *slot;
+        item.quantity = quantity.clamp(1, slot.quantity);
+        slot.quantity -= item.quantity;
+        if slot.quantity == 0 {
+            *slot = Item::default();
+        }
+
+        retainer.storage.push(item);
+        {
+            let mut db = self.database.lock();
+            db.transaction(|db| {
+                db.commit_retainer(&retainer);
+                db.commit_classjob_and_inventory(&self.player_data);
+            });
+        }
+
+        self.send_inventory().await;
+
+        let item_name = self.item_name(item_id);
+        self.send_notice(&format!(
+            "You entrusted {} {item_name} to {retainer_name}.",
+            item.quantity
+        ))
+        .await;
+    }
+
+    /// Moves an item held by a retainer back into the player's inventory.
+    pub async fn withdraw_item(&mut self, retainer_name: &str, item_id: u32) {
+        let Some(mut retainer) = self.find_own_retainer(retainer_name).await else {
+            return;
+        };
+
+        let item_name = self.item_name(item_id);
+        let Some(index) = retainer
+            .storage
+            .iter()
+            .position(|item| item.item_id == item_id)
+        else {
+            self.send_notice(&format!("{retainer_name} isn't holding any {item_name}."))
+                .await;
+            return;
+        };
+
+        let mut item = retainer.storage[index];
+        {
+            let mut game_data = self.gamedata.lock();
+            item.stack_size = game_data
+                .get_item_info(ItemInfoQuery::ById(item_id))
+                .map(|item_info| item_info.stack_size)
+                .unwrap_or(1);
+        }
+
+        if self
+            .player_data
+            .inventory
+            .add_in_next_free_slot(item)
+            .is_none()
+        {
+            self.send_notice("Your inventory is full.").await;
+            return;
+        }
+
+        retainer.storage.remove(index);
+        {
+            let mut db = self.database.lock();
+            db.transaction(|db| {
+                db.commit_retainer(&retainer);
+                db.commit_classjob_and_inventory(&self.player_data);
+            });
+        }
+
+        self.send_inventory().await;
+        self.send_notice(&format!(
+            "You withdrew {} {item_name} from {retainer_name}.",
+            item.quantity
+        ))
+        .await;
+    }
+
+    /// Collects the gil a retainer earned from selling items.
+    pub async fn withdraw_retainer_gil(&mut self, retainer_name: &str) {
+        let Some(mut retainer) = self.find_own_retainer(retainer_name).await else {
+            return;
+        };
+
+        let current_gil = self
+            .player_data
+            .inventory
+            .currency
+            .get_item_for_id(CurrencyKind::Gil)
+            .quantity;
+        let amount = retainer.gil.min(MAX_GIL.saturating_sub(current_gil));
+        if amount == 0 {
+            self.send_notice(&format!("{retainer_name} has no gil for you to collect."))
+                .await;
+            return;
+        }
+
+        retainer.gil -= amount;
+        self.player_data
+            .inventory
+            .currency
+            .get_item_for_id(CurrencyKind::Gil)
+            .quantity += amount;
+        {
+            let mut db = self.database.lock();
+            db.transaction(|db| {
+                db.commit_retainer(&retainer);
+                db.commit_classjob_and_inventory(&self.player_data);
+            });
+        }
+
+        self.send_currency(CurrencyKind::Gil).await;
+        self.send_notice(&format!("You collected {amount} gil from {retainer_name}."))
+            .await;
+    }
+
+    /// Puts an item held by a retainer up for sale on the market board.
+    pub async fn sell_item(
+        &mut self,
+        retainer_name: &str,
+        item_id: u32,
+        quantity: u32,
+        price_per_unit: u32,
+    ) {
+        let Some(mut retainer) = self.find_own_retainer(retainer_name).await else {
+            return;
+        };
+
+        let item_name = self.item_name(item_id);
+        let Some(index) = retainer
+            .storage
+            .iter()
+            .position(|item| item.item_id == item_id)
+        else {
+            self.send_notice(&format!("{retainer_name} isn't holding any {item_name}."))
+                .await;
+            return;
+        };
+
+        let can_be_sold = {
+            let mut game_data = self.gamedata.lock();
+            game_data
+                .get_item_info(ItemInfoQuery::ById(item_id))
+                .is_some_and(|item_info| {
+                    !item_info.is_untradable && item_info.item_search_category != 0
+                })
+        };
+        if !can_be_sold {
+            self.send_notice(&format!("{item_name} can't be sold on the market board."))
+                .await;
+            return;
+        }
+
+        let held_quantity = retainer.storage[index].quantity;
+        let quantity = quantity.clamp(1, held_quantity);
+        if price_per_unit == 0 || MarketPrice::new(price_per_unit, quantity).is_none() {
+            self.send_notice("That price isn't allowed.").await;
+            return;
+        }
+
+        let listing_id;
+        {
+            let mut db = self.database.lock();
+            if db.find_retainer_listings(retainer.id).len() >= MAX_RETAINER_LISTINGS {
+                listing_id = None;
+            } else {
+                let mut item = retainer.storage[index];
+                item.quantity = quantity;
+                if quantity == held_quantity {
+                    retainer.storage.remove(index);
+                } else {
+                    retainer.storage[index].quantity -= quantity;
+                }
+
+                listing_id = Some(db.transaction(|db| {
+                    db.commit_retainer(&retainer);
+                    db.add_listing(retainer.id, item, price_per_unit)
+                }));
+            }
+        }
+
+        let Some(listing_id) = listing_id else {
+            self.send_notice(&format!(
+                "{retainer_name} can't sell more than {MAX_RETAINER_LISTINGS} items at once."
+            ))
+            .await;
+            return;
+        };
+
+        self.send_notice(&format!(
+            "[{listing_id}] {retainer_name} is now selling {quantity} {item_name} for {price_per_unit} gil each."
+        ))
+        .await;
+    }
+
+    /// Takes one of the player's listings off of the market board, and gives the item back to their retainer.
+    pub async fn unlist_item(&mut self, listing_id: i64) {
+        let content_id = self.player_data.character.content_id as u64;
+
+        let message;
+        {
+            let mut db = self.database.lock();
+            match db.find_offering(listing_id) {
+                Some(offering) if offering.owner_content_id == content_id => {
+                    let mut retainer = db
+                        .find_retainer(content_id, &offering.retainer_name)
+                        .expect("The retainer selling this should exist!");
+                    if retainer.storage.len() >= MAX_RETAINER_ITEMS {
+                        message = format!("{} can't hold any more items.", offering.retainer_name);
+                    } else {
+                        retainer.storage.push(offering.item);
+                        db.transaction(|db| {
+                            db.remove_listing(listing_id);
+                            db.commit_retainer(&retainer);
+                        });
+                        message = format!(
+                            "{} is no longer selling listing {listing_id}.",
+                            offering.retainer_name
+                        );
+                    }
+                }
+                _ => message = format!("You aren't selling anything under listing {listing_id}."),
+            }
+        }
+
+        self.send_notice(&message).await;
+    }
+
+    /// Tells the player who's selling this item, and for how much.
+    pub async fn show_market_offerings(&mut self, item_id: u32) {
+        let offerings;
+        {
+            let mut db = self.database.lock();
+            offerings = db.find_offerings(item_id);
+        }
+
+        let item_name = self.item_name(item_id);
+        if offerings.is_empty() {
+            self.send_notice(&format!("Nobody is selling {item_name}."))
+                .await;
+            return;
+        }
+
+        for offering in offerings {
+            let hq = if offering.item.item_flags & ITEM_FLAG_HQ != 0 {
+                " (HQ)"
+            } else {
+                ""
+            };
+            self.send_notice(&format!(
+                "[{}] {} {item_name}{hq} for {} gil each, sold by {}.",
+                offering.id,
+                offering.item.quantity,
+                offering.price_per_unit,
+                offering.retainer_name
+            ))
+            .await;
+        }
+    }
+
+    /// Buys a listing from the market board. The item is put in the player's inventory, or sent by mail if it's full.
+    pub async fn buy_item(&mut self, listing_id: i64) {
+        let content_id = self.player_data.character.content_id as u64;
+
+        let item_id;
+        {
+            let mut db = self.database.lock();
+            item_id = db
+                .find_offering(listing_id)
+                .map(|offering| offering.item.item_id);
+        }
+        let Some(item_id) = item_id else {
+            self.send_notice(&format!("Listing {listing_id} is no longer for sale."))
+                .await;
+            return;
+        };
+
+        let stack_size;
+        {
+            let mut game_data = self.gamedata.lock();
+            stack_size = game_data
+                .get_item_info(ItemInfoQuery::ById(item_id))
+                .map(|item_info| item_info.stack_size)
+                .unwrap_or(1);
+        }
+
+        let result;
+        {
+            // The database stays locked the whole time, so two people can't buy the same listing. The buyer's gil and items are saved in the same transaction as the sale, so neither side can be lost.
+            let mut db = self.database.lock();
+            let player_data = &mut self.player_data;
+            result = db.transaction(|db| {
+                let current_gil = player_data
+                    .inventory
+                    .currency
+                    .get_item_for_id(CurrencyKind::Gil)
+                    .quantity;
+
+                let offering = match db.find_offering(listing_id) {
+                    None => return Err(format!("Listing {listing_id} is no longer for sale.")),
+                    Some(offering) if offering.owner_content_id == content_id => {
+                        return Err("You can't buy your own items.".to_string());
+                    }
+                    Some(offering) => offering,
+                };
+
+                let price = match MarketPrice::new(offering.price_per_unit, offering.item.quantity)
+                {
+                    Some(price) if price.total() <= current_gil => price,
+                    _ => return Err("You don't have enough gil.".to_string()),
+                };
+
+                let offering = db
+                    .purchase_listing(listing_id, &player_data.character.name, price.proceeds)
+                    .ok_or_else(|| format!("Listing {listing_id} is no longer for sale."))?;
+
+                player_data
+                    .inventory
+                    .currency
+                    .get_item_for_id(CurrencyKind::Gil)
+                    .quantity -= price.total();
+
+                let mut item = offering.item;
+                item.stack_size = stack_size;
+                let added = player_data.inventory.add_in_next_free_slot(item).is_some();
+
+                db.commit_classjob_and_inventory(player_data);
+
+                Ok((item, price, added))
+            });
+        }
+
+        let (item, price, added) = match result {
+            Ok(result) => result,
+            Err(message) => {
+                self.send_notice(&message).await;
+                return;
+            }
+        };
+
+        self.send_currency(CurrencyKind::Gil).await;
+
+        let item_name = self.item_name(item.item_id);
+        self.send_notice(&format!(
+            "You bought {} {item_name} for {} gil ({} gil tax).",
+            item.quantity,
+            price.total(),
+            price.tax
+        ))
+        .await;
+
+        if added {
+            self.send_inventory().await;
+        } else {
+            self.send_items_by_mail(
+                "Your inventory was full, so the item you bought from the market board has been delivered here.",
+                &[item],
+            )
+            .await;
+            self.send_notice("Your inventory is full. The item was sent by mail.")
+                .await;
+        }
+    }
+}
@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use tokio::net::TcpStream;
 
 use crate::{
-    Content, GameData, Recipe, Unlock,
+    Content, FishingState, GameData, GatheringPointState, Recipe, Unlock,
     database::{
         AetherCurrent, Aetheryte, Buddy, Character, ClassJob, Companion, Friends, GrandCompany,
         Mentor, Quest, SearchInfo, Volatile,
@@ -17,7 +17,7 @@ use crate::{
     lua::{KawariLua, LuaTask},
 };
 use kawari::{
-    common::{HandlerId, ObjectId, Position, timestamp_secs},
+    common::{HandlerId, ObjectId, Position, TradeOffer, timestamp_secs},
     config::WorldConfig,
     ipc::zone::{
         ApartmentList, ApartmentListEntry, CWLSMemberListEntry, ClientTriggerCommand,
@@ -34,25 +34,30 @@ use kawari::{
 };
 
 use super::{
-    WorldDatabase,
+    PlayerHouse, WorldDatabase,
     common::{ClientId, ServerHandle},
-    inventory::{BuyBackList, HousingInventory, Inventory},
+    inventory::{BuyBackList, HousingInventory, Inventory, Item},
 };
 
 mod actor;
 mod chat;
+mod duty;
 mod effect;
 mod event;
 mod friends;
+mod housing;
 mod item;
 mod linkshell;
+mod loot;
 mod lua;
 mod mail;
+mod market;
 mod party;
 mod quest;
 mod social;
 mod stats;
-pub use stats::BaseParameters;
+pub use stats::{BaseParameters, PotencyKind};
+mod trade;
 mod unlock;
 mod zone;
 
@@ -107,7 +112,13 @@ pub struct PlayerData {
     pub saw_inn_wakeup: bool,
     pub friends: Friends,
     pub grand_company: GrandCompany,
-    // TODO: These inventories need to be made persistent and also vary per property, this is just for initial support
+    /// The ward the player is visiting, when they're in a residential district.
+    pub ward: u8,
+    /// The id of the house the player last entered. Only their own house can be decorated from the inside.
+    pub entered_house: Option<i64>,
+    /// The house the player can decorate in their current zone, if any.
+    pub house: Option<PlayerHouse>,
+    /// The inventory of `house`. If there's no house, changes to this aren't saved.
     pub house_inventory: HousingInventory,
     pub buddy: Buddy,
     pub equipped_glasses_ids: [u16; 2],
@@ -194,6 +205,10 @@ pub struct ZoneConnection {
     pub offered_teleport: Option<TeleportQuery>,
     /// Whether the player is trading with another player or not.
     pub is_trading: bool,
+    /// What the player is offering in their trade, if they've started one with another player.
+    pub trade_offer: Option<TradeOffer<Item>>,
+    /// The player's inventory from before their trade's items were exchanged, kept in case the other side fails and it has to be rolled back.
+    pub trade_snapshot: Option<Inventory>,
     /// Current dye action information.
     pub dyeing_information: Option<DyeInformation>,
     /// Temporary data for marketboard requests.
@@ -204,6 +219,12 @@ pub struct ZoneConnection {
     pub initial_login: bool,
     /// Mapping of motivation NPCs to their FATE ids for easier lookup. Used in the FATE event handler.
     pub fate_motivation_npcs: HashMap<ObjectId, u16>,
+    /// Gathering points the player has gathered from, keyed by their GatheringPoint id. This is a copy of what the instance keeps.
+    pub gathering_points: HashMap<u32, GatheringPointState>,
+    /// The fish on the end of the player's line, if they're fishing.
+    pub fishing: Option<FishingState>,
+    /// The item ID of the bait the player selected for fishing.
+    pub fishing_bait: u32,
     /// Used only for the local player's PlayerSpawn.
     pub content_handler_id: Option<HandlerId>,
     /// Whether the player can offer this teleport to their party.
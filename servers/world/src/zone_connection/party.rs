@@ -147,6 +147,14 @@ impl ZoneConnection {
         self.party_id != 0
     }
 
+    /// Tells the player that someone in their party voted to kick another member out of the duty.
+    pub async fn inform_kick_vote(&mut self, voter: &str, target: &str, votes: u32, needed: u32) {
+        self.send_notice(&format!(
+            "{voter} has voted to dismiss {target} from the party. ({votes}/{needed} votes)"
+        ))
+        .await;
+    }
+
     pub async fn received_strategy_board(&mut self, content_id: u64, board_data: StrategyBoard) {
         // TODO: Figure out what all these mean!
         let ipc = ServerZoneIpcSegment::new(ServerZoneIpcData::BeginStrategyBoardSession {
@@ -7,12 +7,23 @@ use crate::{
 };
 use icarus::ParamGrow::ParamGrowRow;
 use kawari::{
-    common::{MAXIMUM_RESTED_EXP, ObjectId},
+    common::{CombatStats, MAXIMUM_RESTED_EXP, ObjectId},
     ipc::zone::{
         ActorControlCategory, PlayerStats, ServerZoneIpcData, ServerZoneIpcSegment, UpdateClassInfo,
     },
 };
-use mlua::{UserData, UserDataMethods};
+use mlua::UserData;
+
+/// Which set of stats an action's potency is scaled by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PotencyKind {
+    /// Scaled by attack power and physical weapon damage, and mitigated by defense.
+    Physical,
+    /// Scaled by attack magic potency and magic weapon damage, and mitigated by magic defense.
+    Magical,
+    /// Scaled by healing magic potency and magic weapon damage.
+    Healing,
+}
 
 /// Every BaseParam row, some of them may be useless.
 #[derive(Default, Debug, Clone)]
@@ -90,6 +101,8 @@ pub struct BaseParameters {
     pub control: u32,
     pub gathering: u32,
     pub perception: u32,
+    /// Not a BaseParam, but the classjob's modifier for its primary stat as a percentage. Used to scale weapon damage.
+    pub attribute_modifier: u32,
 }
 
 impl BaseParameters {
@@ -176,7 +189,6 @@ impl BaseParameters {
     /// Calculates a set of attributes based on the level and class modifiers.
     pub fn perform_calculations(
         &mut self,
-        primary_stat: u8,
         attributes: &Attributes,
         param_grow: &ParamGrowRow,
         modifiers: &Modifiers,
@@ -203,7 +215,9 @@ impl BaseParameters {
 
         self.spell_speed = param_grow.BaseSpeed as u32;
         self.tenacity = param_grow.BaseSpeed as u32;
-        self.attack_power = *self.get_mut(primary_stat);
+        self.critical_hit = param_grow.BaseSpeed as u32;
+        self.direct_hit_rate = param_grow.BaseSpeed as u32;
+        self.determination = param_grow.BaseSpeed as u32;
         self.skill_speed = self.tenacity;
         self.haste = 100; // Controls cast times
 
@@ -214,12 +228,16 @@ impl BaseParameters {
     // This should be called after item stat calculations.
     pub fn calculate_potencies(
         &mut self,
+        primary_stat: u8,
         param_grow: &ParamGrowRow,
         modifiers: Option<&Modifiers>,
     ) {
-        self.physical_damage = self.strength;
+        self.attack_power = *self.get_mut(primary_stat);
         self.attack_magic_potency = self.intelligence;
         self.healing_magic_potency = self.mind;
+        self.attribute_modifier = modifiers
+            .and_then(|modifiers| modifiers.get(primary_stat))
+            .unwrap_or(100) as u32;
 
         // To calculate HP, we use a formula loosely inspired by Akh Morning and take some liberties to keep it fairly simple, at least for now.
         // TODO: This formula isn't the greatest for 1-50, as near the end of that range it's fairly low compared to retail. For level 80+ though it's pretty close.
@@ -245,25 +263,31 @@ impl BaseParameters {
             .round() as u32;
     }
 
-    /// Calculates amount of physical damage to apply based on potency.
-    fn calc_physical_damage(&self, potency: u32) -> u16 {
-        let normalized_potency = potency as f32 / 100.0;
-        (normalized_potency * self.physical_damage as f32).floor() as u16
-    }
-
-    /// Calculates amount of magic damage to apply based on potency.
-    fn calc_magical_damage(&self, potency: u32) -> u16 {
-        let normalized_potency = potency as f32 / 100.0;
-        (normalized_potency * self.attack_magic_potency as f32).floor() as u16
-    }
+    /// The stats used when dealing or receiving damage (or healing) of this kind.
+    pub fn combat_stats(&self, kind: PotencyKind) -> CombatStats {
+        let (attack_power, weapon_damage, defense) = match kind {
+            PotencyKind::Physical => (self.attack_power, self.physical_damage, self.defense),
+            PotencyKind::Magical => (
+                self.attack_magic_potency,
+                self.magic_damage,
+                self.magic_defense,
+            ),
+            PotencyKind::Healing => (self.healing_magic_potency, self.magic_damage, 0),
+        };
 
-    /// Calculates amount of healing to apply based on potency.
-    fn calc_heal_amount(&self, potency: u32) -> u16 {
-        let normalized_potency = potency as f32 / 100.0;
-        (normalized_potency * self.healing_magic_potency as f32).floor() as u16
+        CombatStats {
+            attack_power,
+            weapon_damage,
+            determination: self.determination,
+            tenacity: self.tenacity,
+            critical_hit: self.critical_hit,
+            direct_hit_rate: self.direct_hit_rate,
+            defense,
+            attribute_modifier: self.attribute_modifier,
+        }
     }
 
-    /// Iterates over the given equipped items and calculates defense, along with any stat bonuses.
+    /// Iterates over the given equipped items and calculates defense and weapon damage, along with any stat bonuses.
     pub fn calculate_stat_across_all_items(
         &mut self,
         equipped: &EquippedStorage,
@@ -274,6 +298,8 @@ impl BaseParameters {
             if slot.quantity > 0 {
                 self.defense += slot.defense as u32;
                 self.magic_defense += slot.magic_defense as u32;
+                self.physical_damage += slot.physical_damage as u32;
+                self.magic_damage += slot.magic_damage as u32;
 
                 for (i, param_id) in slot.base_param_ids.iter().enumerate() {
                     if *param_id != 0 {
@@ -293,19 +319,7 @@ impl BaseParameters {
     }
 }
 
-impl UserData for BaseParameters {
-    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
-        methods.add_method("calc_physical_damage", |_, this, potency: u32| {
-            Ok(this.calc_physical_damage(potency))
-        });
-        methods.add_method("calc_magical_damage", |_, this, potency: u32| {
-            Ok(this.calc_magical_damage(potency))
-        });
-        methods.add_method("calc_heal_amount", |_, this, potency: u32| {
-            Ok(this.calc_heal_amount(potency))
-        });
-    }
-}
+impl UserData for BaseParameters {}
 
 impl ZoneConnection {
     pub async fn update_class_info(&mut self) {
@@ -406,7 +420,7 @@ impl ZoneConnection {
             .unwrap_or(1);
 
         let mut base_parameters = BaseParameters::default();
-        base_parameters.perform_calculations(primary_stat, &attributes, &param_grow, &modifiers);
+        base_parameters.perform_calculations(&attributes, &param_grow, &modifiers);
         base_parameters.calculate_stat_across_all_items(
             &self.player_data.inventory.equipped,
             if item_level_sync.is_some() {
@@ -415,7 +429,7 @@ impl ZoneConnection {
                 None
             },
         );
-        base_parameters.calculate_potencies(&param_grow, Some(&modifiers));
+        base_parameters.calculate_potencies(primary_stat, &param_grow, Some(&modifiers));
 
         base_parameters
     }
@@ -443,9 +457,9 @@ impl ZoneConnection {
             .unwrap_or(1);
 
         let mut base_parameters = BaseParameters::default();
-        base_parameters.perform_calculations(primary_stat, &attributes, &param_grow, &modifiers);
+        base_parameters.perform_calculations(&attributes, &param_grow, &modifiers);
         base_parameters.calculate_stat_across_all_items(&self.player_data.inventory.equipped, None);
-        base_parameters.calculate_potencies(&param_grow, Some(&modifiers));
+        base_parameters.calculate_potencies(primary_stat, &param_grow, Some(&modifiers));
 
         base_parameters
     }
@@ -0,0 +1,291 @@
+//! Trading items and gil with another player.
+//!
+//! TODO: This is an interim chat version using `!trade`, until the trade window's packets are known.
+
+use std::time::Duration;
+
+use kawari::common::{MAX_TRADE_ITEMS, ObjectId, TradeError, TradeOffer};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::{
+    FromServer, ItemInfoQuery, ToServer, ZoneConnection,
+    inventory::{CurrencyKind, Item},
+};
+
+/// How long to wait for the server to settle a trade when logging out, before giving up and rolling it back.
+const LEAVE_TRADE_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl ZoneConnection {
+    /// Describes what's in an offer, e.g. "2 Potion, 100 gil".
+    fn describe_trade_offer(&self, offer: &TradeOffer<Item>) -> String {
+        if offer.is_empty() {
+            return "nothing".to_string();
+        }
+
+        let mut parts: Vec<String> = offer
+            .items
+            .iter()
+            .map(|item| format!("{} {}", item.quantity, self.item_name(item.item_id)))
+            .collect();
+        if offer.gil > 0 {
+            parts.push(format!("{} gil", offer.gil));
+        }
+
+        parts.join(", ")
+    }
+
+    /// Sends the player's new offer to their trading partner.
+    async fn update_trade_offer(&mut self, offer: TradeOffer<Item>) {
+        self.trade_offer = Some(offer.clone());
+        self.handle
+            .send(ToServer::OfferTrade(
+                self.player_data.character.actor_id,
+                offer,
+            ))
+            .await;
+    }
+
+    /// Returns what the player is offering, and tells them if they aren't trading with anyone.
+    async fn current_trade_offer(&mut self) -> Option<TradeOffer<Item>> {
+        if self.trade_offer.is_none() {
+            self.send_notice("You aren't trading with anyone.").await;
+        }
+
+        self.trade_offer.clone()
+    }
+
+    /// Asks another player to trade. If there's no actor ID, it's whoever the player is targeting.
+    pub async fn request_trade(&mut self, target_actor_id: Option<ObjectId>) {
+        self.handle
+            .send(ToServer::RequestTrade(
+                self.player_data.character.actor_id,
+                target_actor_id,
+            ))
+            .await;
+    }
+
+    /// Accepts the latest trade request the player received.
+    pub async fn accept_trade(&mut self) {
+        self.handle
+            .send(ToServer::AcceptTrade(self.player_data.character.actor_id))
+            .await;
+    }
+
+    /// Cancels the player's trade, along with any trade requests.
+    pub async fn cancel_trade(&mut self) {
+        self.handle
+            .send(ToServer::CancelTrade(self.player_data.character.actor_id))
+            .await;
+    }
+
+    /// Adds up to `quantity` of an item from a single stack in the player's inventory to their offer.
+    pub async fn offer_trade_item(&mut self, item_id: u32, quantity: u32) {
+        let Some(mut offer) = self.current_trade_offer().await else {
+            return;
+        };
+
+        let item_name = self.item_name(item_id);
+        let is_tradable = {
+            let mut game_data = self.gamedata.lock();
+            game_data
+                .get_item_info(ItemInfoQuery::ById(item_id))
+                .is_some_and(|item_info| !item_info.is_untradable)
+        };
+        if !is_tradable {
+            self.send_notice(&format!("{item_name} can't be traded."))
+                .await;
+            return;
+        }
+
+        if offer.items.iter().any(|item| item.item_id == item_id) {
+            self.send_notice(&format!("You're already offering {item_name}."))
+                .await;
+            return;
+        }
+
+        let Some(slot) = self
+            .player_data
+            .inventory
+            .pages
+            .iter()
+            .flat_map(|page| page.slots.iter())
+            .find(|slot| slot.item_id == item_id && slot.quantity > 0)
+        else {
+            self.send_notice(&format!("You don't have any {item_name}."))
+                .await;
+            return;
+        };
+
+        let mut item = *slot;
+        item.quantity = quantity.clamp(1, slot.quantity);
+
+        if offer.add_item(item) == Err(TradeError::TooManyItems) {
+            self.send_notice(&format!(
+                "You can't offer more than {MAX_TRADE_ITEMS} items at once."
+            ))
+            .await;
+            return;
+        }
+
+        self.update_trade_offer(offer).await;
+        self.send_notice(&format!("You offered {} {item_name}.", item.quantity))
+            .await;
+    }
+
+    /// Changes how much gil the player is offering.
+    pub async fn offer_trade_gil(&mut self, gil: u32) {
+        let Some(mut offer) = self.current_trade_offer().await else {
+            return;
+        };
+
+        let current_gil = self
+            .player_data
+            .inventory
+            .currency
+            .get_item_for_id(CurrencyKind::Gil)
+            .quantity;
+        if gil > current_gil {
+            self.send_notice("You don't have that much gil.").await;
+            return;
+        }
+
+        if let Err(TradeError::TooMuchGil) = offer.set_gil(gil) {
+            self.send_notice("You can't offer that much gil at once.")
+                .await;
+            return;
+        }
+
+        self.update_trade_offer(offer).await;
+        self.send_notice(&format!("You offered {gil} gil.")).await;
+    }
+
+    /// Takes back everything the player was offering.
+    pub async fn clear_trade_offer(&mut self) {
+        if self.current_trade_offer().await.is_none() {
+            return;
+        }
+
+        self.update_trade_offer(TradeOffer::default()).await;
+        self.send_notice("You took back your offer.").await;
+    }
+
+    /// Confirms the player's trade. The items are exchanged once both sides have confirmed.
+    pub async fn confirm_trade(&mut self) {
+        if self.current_trade_offer().await.is_none() {
+            return;
+        }
+
+        self.handle
+            .send(ToServer::ConfirmTrade(self.player_data.character.actor_id))
+            .await;
+    }
+
+    /// Tells the player that someone wants to trade with them.
+    pub async fn trade_requested(&mut self, name: &str) {
+        self.send_notice(&format!(
+            "{name} wants to trade with you. Use !trade accept to start trading."
+        ))
+        .await;
+    }
+
+    /// Starts a trade with another player.
+    pub async fn trade_opened(&mut self, name: &str) {
+        self.trade_offer = Some(TradeOffer::default());
+        self.trade_snapshot = None;
+        self.send_notice(&format!(
+            "You're now trading with {name}. Use !trade item and !trade gil to make your offer, then !trade confirm."
+        ))
+        .await;
+    }
+
+    /// Tells the player what their trading partner is now offering.
+    pub async fn trade_offered(&mut self, name: &str, offer: &TradeOffer<Item>) {
+        let description = self.describe_trade_offer(offer);
+        self.send_notice(&format!(
+            "{name} is now offering {description}. The trade will need to be confirmed again."
+        ))
+        .await;
+    }
+
+    /// Tells the player that someone confirmed the trade.
+    pub async fn trade_confirmed(&mut self, name: &str) {
+        self.send_notice(&format!("{name} confirmed the trade."))
+            .await;
+    }
+
+    /// Exchanges what the player is giving for what they're receiving, and tells the server whether it worked.
+    /// Their old inventory is kept until the other side has done the same, in case it has to be rolled back.
+    pub async fn exchange_trade(&mut self, given: &TradeOffer<Item>, received: &TradeOffer<Item>) {
+        let snapshot = self.player_data.inventory.clone();
+        let success = self.player_data.inventory.exchange(
+            &given.items,
+            given.gil,
+            &received.items,
+            received.gil,
+        );
+        if success {
+            self.trade_snapshot = Some(snapshot);
+        }
+
+        self.handle
+            .send(ToServer::TradePrepared(
+                self.player_data.character.actor_id,
+                success,
+            ))
+            .await;
+    }
+
+    /// Finishes the trade, once both sides exchanged their items.
+    pub async fn complete_trade(&mut self) {
+        self.trade_offer = None;
+        self.trade_snapshot = None;
+
+        self.send_inventory().await;
+        self.send_notice("The trade is complete.").await;
+    }
+
+    /// Ends the trade without exchanging anything, and gives back anything that was already exchanged.
+    pub async fn trade_cancelled(&mut self, reason: &str) {
+        self.trade_offer = None;
+        if let Some(snapshot) = self.trade_snapshot.take() {
+            self.player_data.inventory = snapshot;
+            self.send_inventory().await;
+        }
+
+        self.send_notice(reason).await;
+    }
+
+    /// Ends the player's trade when they log out, and returns whether they were trading.
+    ///
+    /// If they already exchanged their items, this waits for the server to say whether the trade completed. Otherwise their inventory is rolled back, the same as their partner's.
+    pub async fn leave_trade(&mut self, internal_recv: &mut UnboundedReceiver<FromServer>) -> bool {
+        if self.trade_offer.take().is_none() {
+            return false;
+        }
+
+        self.handle
+            .send(ToServer::LeaveTrade(self.player_data.character.actor_id))
+            .await;
+
+        if self.trade_snapshot.is_none() {
+            return true;
+        }
+
+        // Messages from the server arrive in order, so whichever of these comes first is how the trade ended.
+        let completed = loop {
+            match tokio::time::timeout(LEAVE_TRADE_TIMEOUT, internal_recv.recv()).await {
+                Ok(Some(FromServer::TradeCompleted())) => break true,
+                Ok(Some(FromServer::TradeCancelled(_))) | Ok(None) | Err(_) => break false,
+                Ok(Some(_)) => {}
+            }
+        };
+
+        if let Some(snapshot) = self.trade_snapshot.take()
+            && !completed
+        {
+            self.player_data.inventory = snapshot;
+        }
+
+        true
+    }
+}
@@ -6,14 +6,12 @@ use crate::{
 };
 use kawari::{
     common::{
-        FestivalId, HandlerId, HouseId, HouseUnit, HousingFlag, LandData, Position, WarpType,
-        timestamp_secs,
+        FestivalId, HandlerId, HouseId, HouseUnit, LandData, Position, WarpType, timestamp_secs,
     },
-    config::get_config,
+    config::{WorldConfig, get_config},
     constants::OBFUSCATION_ENABLED_MODE,
     ipc::zone::{
-        ActorControlCategory, Condition, DutyFinderSetting, FurnitureList, House, HouseExterior,
-        HouseList, HouseStatus, HousingInteriorDetails, PlotSize, ServerZoneIpcData,
+        ActorControlCategory, Condition, DutyFinderSetting, ServerZoneIpcData,
         ServerZoneIpcSegment, WeatherChange, ZoneInit, ZoneInitFlags,
     },
     packet::{ConnectionState, PacketSegment, ScramblerKeyGenerator, SegmentData, SegmentType},
@@ -286,115 +284,16 @@ impl ZoneConnection {
             self.send_crafting_gathering_information().await;
         }
 
-        if lua_zone.intended_use == TerritoryIntendedUse::HousingOutdoor as u8 {
-            let mut houses = [House::default(); 30];
-
-            // First, populate the houses in this ward. Note that for now, we treat every ward the same.
-            // TODO: For now, we hardcode 3 prefab houses as demonstration units until we implement more of the system. One cottage, one house and one mansion, all set to be individually owned and locked. Plots 5, 6, and 12 were chosen due to their close proximity to each other.
-            // Glade house (Wood)
-            houses[4] = House {
-                plot_size: PlotSize::Medium,
-                status: HouseStatus::HouseBuilt,
-                flags: HousingFlag::OPEN,
-                exterior: HouseExterior {
-                    roof_id: 1029,
-                    walls_id: 3589,
-                    windows_id: 2562,
-                    door_id: 514,
-                    ..Default::default()
-                },
-                ..Default::default()
-            };
-
-            // Hingan mansion (Mokuzo)
-            houses[5] = House {
-                plot_size: PlotSize::Large,
-                status: HouseStatus::HouseBuilt,
-                flags: HousingFlag::OPEN,
-                exterior: HouseExterior {
-                    roof_id: 1081,
-                    walls_id: 3632,
-                    windows_id: 2579,
-                    door_id: 531,
-                    ..Default::default()
-                },
-                ..Default::default()
-            };
-
-            // Highland cottage (Wood)
-            houses[11] = House {
-                plot_size: PlotSize::Small,
-                status: HouseStatus::HouseBuilt,
-                flags: HousingFlag::OPEN | HousingFlag::OWNED_BY_FC,
-                exterior: HouseExterior {
-                    roof_id: 1136,
-                    walls_id: 3687,
-                    windows_id: 2598,
-                    door_id: 550,
-                    ..Default::default()
-                },
-                ..Default::default()
-            };
-
-            let config = get_config();
-            self.send_ipc_self(ServerZoneIpcSegment::new(ServerZoneIpcData::HouseList(
-                HouseList {
-                    land_id: 0,
-                    ward: 0,
-                    territory_type_id: lua_zone.zone_id,
-                    world_id: config.world.world_id,
-                    subdivision: 257, // TODO: Figure out more about subdivisions
-                    houses,
-                },
-            )))
-            .await;
+        if let Some(intended_use) = TerritoryIntendedUse::from_repr(lua_zone.intended_use) {
+            self.load_current_house(lua_zone.zone_id, intended_use);
+        }
 
-            // Finally, populate the exterior furniture.
-            // TODO: Actually send some real furniture, once we can do that!
-            for index in 0..15 {
-                self.send_ipc_self(ServerZoneIpcSegment::new(ServerZoneIpcData::FurnitureList(
-                    FurnitureList {
-                        count: 15,
-                        index,
-                        ..Default::default()
-                    },
-                )))
-                .await;
-            }
+        if lua_zone.intended_use == TerritoryIntendedUse::HousingOutdoor as u8 {
+            self.send_ward(lua_zone.zone_id).await;
         }
 
         if lua_zone.intended_use == TerritoryIntendedUse::HousingIndoor as u8 {
-            let config = get_config();
-            // Bare minimum stuff to make housing interiors load
-            self.send_ipc_self(ServerZoneIpcSegment::new(
-                ServerZoneIpcData::HousingInteriorDetails(HousingInteriorDetails::default()),
-            ))
-            .await;
-
-            // TODO: Limit this by interior size once we have furniture persistence
-            for index in 0..6 {
-                // The LandId is currently set so that plugins like HousingPos/Buildingway can plop stuff down
-                self.send_ipc_self(ServerZoneIpcSegment::new(ServerZoneIpcData::FurnitureList(
-                    FurnitureList {
-                        id: HouseId {
-                            unit: HouseUnit {
-                                apartment_division_plot_index: 0,
-                                apartment_flag: true,
-                            },
-                            unk1: 0,
-                            room_number: 1,
-                            ward_index: 0,
-                            territory_type_id: 340,
-                            world_id: config.world.world_id,
-                        },
-                        count: 6,
-                        index,
-                        unk2: 100, // Indoors
-                        ..Default::default()
-                    },
-                )))
-                .await;
-            }
+            self.send_interior().await;
         }
 
         self.conditions
@@ -529,6 +428,29 @@ impl ZoneConnection {
         .await;
     }
 
+    /// Replaces our copy of the world config, and pushes anything the player can see to the client.
+    pub async fn apply_config(&mut self, config: WorldConfig) {
+        // Instanced content (like ocean fishing) uses its own festivals, so leave those alone until the player leaves.
+        if config.active_festivals != self.config.active_festivals
+            && self.content_handler_id.is_none()
+        {
+            let festivals = config.active_festivals;
+            self.actor_control_self(ActorControlCategory::SetFestival {
+                festival1: festivals[0] as u32,
+                festival2: festivals[1] as u32,
+                festival3: festivals[2] as u32,
+                festival4: festivals[3] as u32,
+            })
+            .await;
+        }
+
+        if config.login_message != self.config.login_message {
+            self.send_notice(&config.login_message).await;
+        }
+
+        self.config = config;
+    }
+
     /// Returns the intended usage of the zone the player's currently in.
     pub fn get_zone_intended_use(&self) -> TerritoryIntendedUse {
         let mut gamedata = self.gamedata.lock();